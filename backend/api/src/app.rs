@@ -22,8 +22,9 @@ use tracing::{info_span, Level};
 use crate::{
     config::Erc20TokenConfig,
     routes::{
-        alerts as alert_routes, auth as auth_routes, health, portfolio as portfolio_routes, secure,
-        strategies as strategy_routes, wallets as wallet_routes,
+        alerts as alert_routes, auth as auth_routes, datasets as dataset_routes, health,
+        portfolio as portfolio_routes, secure, strategies as strategy_routes,
+        wallets as wallet_routes,
     },
     state::AppState,
 };
@@ -99,6 +100,7 @@ pub fn build_router(state: AppState, allowed_origins: Vec<HeaderValue>) -> Route
                 .merge(wallet_routes::router())
                 .merge(portfolio_routes::router())
                 .merge(strategy_routes::router())
+                .merge(dataset_routes::router())
                 .merge(alert_routes::router())
                 .merge(secure::router())
                 .route("/config/tokens", get(get_public_tokens)),
@@ -11,6 +11,8 @@ async fn main() -> anyhow::Result<()> {
     let mut config = AppConfig::from_env()?;
     // 避免在工具模式下啟動多餘背景任務
     config.enable_alert_worker = false;
+    config.enable_backtest_worker = false;
+    config.enable_paper_worker = false;
     let state = build_state(&config).await?;
 
     let mut args = env::args().skip(1);
@@ -10,6 +10,9 @@ async fn main() -> anyhow::Result<()> {
     // 確保 worker 模式會啟用 alert evaluator
     let mut config = AppConfig::from_env()?;
     config.enable_alert_worker = true;
+    // 回測 job 由 API 行程處理
+    config.enable_backtest_worker = false;
+    config.enable_paper_worker = false;
     let _state = build_state(&config).await?;
     tracing::info!("alert worker started; polling every 60s");
 
@@ -9,9 +9,9 @@ use crate::{
         PostgresTransactionRepository, PostgresUserRepository, PostgresWalletRepository,
     },
     services::{
-        AlertEvaluator, CachedPriceOracle, CoingeckoPriceOracle, DbPortfolioService,
-        FallbackPriceOracle, PriceRefresher, RecordingPriceOracle, SimulationConfig,
-        StaticPriceOracle, TokenConfig,
+        AlertEvaluator, BacktestWorkerPool, CachedPriceOracle, CoingeckoPriceOracle,
+        DbPortfolioService, FallbackPriceOracle, LogFetcher, PaperTrader, PriceRefresher,
+        RecordingPriceOracle, SimulationConfig, StaticPriceOracle, TokenConfig,
     },
     state::AppState,
 };
@@ -130,13 +130,18 @@ pub async fn build_state(config: &AppConfig) -> Result<AppState> {
         config.portfolio_sync_retries as u32,
         Duration::from_millis(500),
         chrono_duration(config.portfolio_sync_interval),
-    ));
+    )
+    .with_log_fetcher(LogFetcher::new(
+        config.logs_chunk_blocks,
+        config.chain_logs_chunk_blocks.clone(),
+    )));
+    let notifier = Arc::new(LoggingNotifier);
     let alert_evaluator = Arc::new(AlertEvaluator::new(
         alert_repo.clone(),
         portfolio_repo.clone(),
         wallet_repo.clone(),
         transaction_repo.clone(),
-        Arc::new(LoggingNotifier),
+        notifier.clone(),
         default_provider.clone(),
         tokens_for_alert,
     ));
@@ -146,6 +151,11 @@ pub async fn build_state(config: &AppConfig) -> Result<AppState> {
     portfolio_service
         .clone()
         .spawn_indexer(config.portfolio_sync_interval);
+    if config.enable_backfill_worker {
+        portfolio_service
+            .clone()
+            .spawn_backfill(price_history_repo.clone(), config.backfill_interval);
+    }
     if config.ws_trigger_enabled {
         portfolio_service.clone().spawn_ws_listeners();
     }
@@ -172,7 +182,12 @@ pub async fn build_state(config: &AppConfig) -> Result<AppState> {
     ));
     refresher.spawn();
 
-    Ok(AppState {
+    let backtest_jobs = Arc::new(
+        BacktestWorkerPool::new(config.backtest_workers, Duration::from_secs(5))
+            .with_lease(config.backtest_lease),
+    );
+
+    let state = AppState {
         config: config.clone(),
         db: pool,
         provider: default_provider,
@@ -190,7 +205,15 @@ pub async fn build_state(config: &AppConfig) -> Result<AppState> {
         price_history_repo,
         transaction_repo,
         nonce_limiter,
-    })
+        backtest_jobs,
+    };
+    if config.enable_backtest_worker {
+        state.backtest_jobs.clone().spawn(state.clone());
+    }
+    if config.enable_paper_worker {
+        Arc::new(PaperTrader::new(notifier, config.paper_interval)).spawn(state.clone());
+    }
+    Ok(state)
 }
 
 fn chrono_duration(value: Duration) -> ChronoDuration {
@@ -47,6 +47,20 @@ pub struct AppConfig {
     pub port: u16,
     pub portfolio_simulation: bool,
     pub enable_alert_worker: bool,
+    pub enable_backtest_worker: bool,
+    pub backtest_workers: usize,
+    /// Lease a worker holds on a running backtest job between renewals.
+    pub backtest_lease: Duration,
+    pub enable_paper_worker: bool,
+    pub paper_interval: Duration,
+    pub enable_backfill_worker: bool,
+    pub backfill_interval: Duration,
+    /// Block a wallet's historical backfill starts from unless the request names one.
+    pub backfill_start_block: u64,
+    /// Blocks per `eth_getLogs` call unless the chain has its own limit.
+    pub logs_chunk_blocks: u64,
+    /// Per-chain `eth_getLogs` block limits of the configured providers.
+    pub chain_logs_chunk_blocks: HashMap<u64, u64>,
 }
 
 impl AppConfig {
@@ -66,6 +80,19 @@ impl AppConfig {
         let token_prices = parse_token_prices("TOKEN_PRICES");
         let token_price_ids = parse_token_price_ids("TOKEN_PRICE_IDS");
         let enable_alert_worker = parse_bool("ENABLE_ALERT_WORKER", true);
+        let enable_backtest_worker = parse_bool("ENABLE_BACKTEST_WORKER", true);
+        let backtest_workers = parse_usize("BACKTEST_WORKERS", 2);
+        let backtest_lease = parse_duration_seconds("BACKTEST_LEASE_SECS", 60);
+        let enable_paper_worker = parse_bool("ENABLE_PAPER_WORKER", true);
+        let paper_interval = parse_duration_seconds("PAPER_INTERVAL_SECS", 60);
+        let enable_backfill_worker = parse_bool("ENABLE_BACKFILL_WORKER", true);
+        let backfill_interval = parse_duration_seconds("BACKFILL_INTERVAL_SECS", 60);
+        let backfill_start_block = env::var("BACKFILL_START_BLOCK")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        let logs_chunk_blocks = parse_usize("LOGS_CHUNK_BLOCKS", 2_000) as u64;
+        let chain_logs_chunk_blocks = parse_chain_blocks("CHAIN_LOGS_CHUNK_BLOCKS");
 
         // 讀取 JWT secret 和 cookie 配置
         let jwt_secret = env::var("JWT_SECRET").unwrap_or_else(|_| "dev-secret".to_string());
@@ -138,6 +165,16 @@ impl AppConfig {
                 .context("PORT must be a valid u16")?,
             portfolio_simulation: parse_bool("PORTFOLIO_SIMULATION", false),
             enable_alert_worker,
+            enable_backtest_worker,
+            backtest_workers,
+            backtest_lease,
+            enable_paper_worker,
+            paper_interval,
+            enable_backfill_worker,
+            backfill_interval,
+            backfill_start_block,
+            logs_chunk_blocks,
+            chain_logs_chunk_blocks,
         })
     }
 }
@@ -222,6 +259,22 @@ fn parse_chain_ttls(key: &str) -> HashMap<u64, Duration> {
         .collect()
 }
 
+fn parse_chain_blocks(key: &str) -> HashMap<u64, u64> {
+    let raw = match env::var(key) {
+        Ok(v) => v,
+        Err(_) => return HashMap::new(),
+    };
+
+    raw.split(',')
+        .filter_map(|item| {
+            let (chain, blocks) = item.trim().split_once('=')?;
+            let chain_id = chain.trim().parse::<u64>().ok()?;
+            let blocks = blocks.trim().parse::<u64>().ok().filter(|b| *b > 0)?;
+            Some((chain_id, blocks))
+        })
+        .collect()
+}
+
 fn parse_erc20_tokens(key: &str) -> Vec<Erc20TokenConfig> {
     let raw = match env::var(key) {
         Ok(v) => v,
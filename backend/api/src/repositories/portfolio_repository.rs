@@ -1,7 +1,7 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, NaiveDate, Utc};
-use domain::{PortfolioSnapshot, Position};
+use domain::{DailySnapshot, PortfolioSnapshot, Position};
 use sqlx::{PgPool, Row};
 use uuid::Uuid;
 
@@ -32,6 +32,21 @@ pub trait PortfolioSnapshotRepository: Send + Sync {
         total_usd_value: f64,
         positions: &[Position],
     ) -> Result<()>;
+    /// Writes a daily snapshot unless the day already has one; `true` if written.
+    async fn fill_daily_snapshot(
+        &self,
+        wallet_id: Uuid,
+        day: NaiveDate,
+        total_usd_value: f64,
+        positions: &[Position],
+    ) -> Result<bool>;
+    /// Daily snapshots between `from` and `to` (inclusive, either open), oldest first.
+    async fn daily_snapshots(
+        &self,
+        wallet_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<DailySnapshot>>;
 }
 
 #[derive(Clone)]
@@ -196,4 +211,60 @@ impl PortfolioSnapshotRepository for PostgresPortfolioSnapshotRepository {
         .await?;
         Ok(())
     }
+
+    async fn fill_daily_snapshot(
+        &self,
+        wallet_id: Uuid,
+        day: NaiveDate,
+        total_usd_value: f64,
+        positions: &[Position],
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO portfolio_daily_snapshots (id, wallet_id, day, total_usd_value, positions)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (wallet_id, day) DO NOTHING",
+        )
+        .bind(Uuid::new_v4())
+        .bind(wallet_id)
+        .bind(day)
+        .bind(total_usd_value)
+        .bind(serde_json::to_value(positions)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn daily_snapshots(
+        &self,
+        wallet_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<DailySnapshot>> {
+        let rows = sqlx::query(
+            "SELECT day, total_usd_value::float8 AS total_usd_value, positions
+             FROM portfolio_daily_snapshots
+             WHERE wallet_id = $1
+               AND ($2::date IS NULL OR day >= $2)
+               AND ($3::date IS NULL OR day <= $3)
+             ORDER BY day ASC",
+        )
+        .bind(wallet_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let positions: Vec<Position> =
+                    serde_json::from_value(row.try_get("positions")?).unwrap_or_default();
+                Ok(DailySnapshot {
+                    wallet_id,
+                    day: row.try_get("day")?,
+                    total_usd_value: row.try_get("total_usd_value")?,
+                    positions,
+                })
+            })
+            .collect()
+    }
 }
@@ -1,10 +1,16 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use domain::PriceHistoryPoint;
-use sqlx::{PgPool, Row};
+use domain::{PriceDataset, PriceHistoryPoint};
+use sqlx::{postgres::PgRow, PgPool, Row};
 use uuid::Uuid;
 
+/// Rows per bulk insert when storing a dataset.
+const DATASET_INSERT_CHUNK: usize = 5_000;
+
+const DATASET_COLUMNS: &str = "id, user_id, name, symbol, source, series_key, point_count, \
+     first_ts, last_ts, created_at";
+
 #[async_trait]
 pub trait PriceHistoryRepository: Send + Sync {
     async fn upsert_points(&self, points: &[PriceHistoryPoint]) -> Result<()>;
@@ -15,11 +21,91 @@ pub trait PriceHistoryRepository: Send + Sync {
         from: DateTime<Utc>,
         to: DateTime<Utc>,
     ) -> Result<Vec<PriceHistoryPoint>>;
+    /// The latest `limit` points of `symbol`, oldest first.
+    async fn fetch_latest(
+        &self,
+        symbol: &str,
+        chain_id: Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<PriceHistoryPoint>>;
+    /// The last `limit` points of `symbol` before `before`, oldest first.
+    async fn fetch_before(
+        &self,
+        symbol: &str,
+        chain_id: Option<u64>,
+        before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<PriceHistoryPoint>>;
+    /// The first `limit` points of `symbol` at or after `from`, oldest first.
+    async fn fetch_since(
+        &self,
+        symbol: &str,
+        chain_id: Option<u64>,
+        from: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<PriceHistoryPoint>>;
     async fn latest_timestamp(
         &self,
         symbol: &str,
         chain_id: Option<u64>,
     ) -> Result<Option<DateTime<Utc>>>;
+    /// The last price of `symbol` at or before `at`.
+    async fn price_at(
+        &self,
+        symbol: &str,
+        chain_id: Option<u64>,
+        at: DateTime<Utc>,
+    ) -> Result<Option<f64>>;
+    /// Stores `points` under `dataset.series_key`, replacing any dataset of the
+    /// same user and name, and returns the stored dataset row.
+    async fn save_dataset(
+        &self,
+        dataset: &PriceDataset,
+        points: &[PriceHistoryPoint],
+    ) -> Result<PriceDataset>;
+    async fn find_dataset(&self, user_id: Uuid, name: &str) -> Result<Option<PriceDataset>>;
+    async fn list_datasets(&self, user_id: Uuid) -> Result<Vec<PriceDataset>>;
+    /// Removes the dataset and its points; `false` if it did not exist.
+    async fn delete_dataset(&self, user_id: Uuid, name: &str) -> Result<bool>;
+    /// All points of a dataset in time order.
+    async fn fetch_dataset_points(&self, dataset: &PriceDataset) -> Result<Vec<PriceHistoryPoint>>;
+}
+
+fn point_from_row(row: &PgRow) -> PriceHistoryPoint {
+    PriceHistoryPoint {
+        id: row.try_get("id").unwrap_or_else(|_| Uuid::new_v4()),
+        symbol: row.try_get("symbol").unwrap_or_default(),
+        price: row.try_get::<f64, _>("price").unwrap_or(0.0),
+        price_ts: row
+            .try_get("price_ts")
+            .unwrap_or_else(|_| DateTime::<Utc>::MIN_UTC),
+        source: row
+            .try_get("source")
+            .unwrap_or_else(|_| "unknown".to_string()),
+        chain_id: row
+            .try_get::<i64, _>("chain_id")
+            .ok()
+            .and_then(|v| v.try_into().ok()),
+        open: row.try_get("open").ok().flatten(),
+        high: row.try_get("high").ok().flatten(),
+        low: row.try_get("low").ok().flatten(),
+        volume: row.try_get("volume").ok().flatten(),
+    }
+}
+
+fn dataset_from_row(row: &PgRow) -> Result<PriceDataset> {
+    Ok(PriceDataset {
+        id: row.try_get("id")?,
+        user_id: row.try_get("user_id")?,
+        name: row.try_get("name")?,
+        symbol: row.try_get("symbol")?,
+        source: row.try_get("source")?,
+        series_key: row.try_get("series_key")?,
+        point_count: row.try_get("point_count")?,
+        first_ts: row.try_get("first_ts")?,
+        last_ts: row.try_get("last_ts")?,
+        created_at: row.try_get("created_at")?,
+    })
 }
 
 #[derive(Clone)]
@@ -68,7 +154,7 @@ impl PriceHistoryRepository for PostgresPriceHistoryRepository {
         to: DateTime<Utc>,
     ) -> Result<Vec<PriceHistoryPoint>> {
         let rows = sqlx::query(
-            "SELECT id, symbol, price, price_ts, source, chain_id
+            "SELECT id, symbol, price::float8 AS price, price_ts, source, chain_id, open, high, low, volume
              FROM price_history
              WHERE symbol = $1 AND price_ts BETWEEN $2 AND $3 AND chain_id = $4
              ORDER BY price_ts ASC",
@@ -80,24 +166,80 @@ impl PriceHistoryRepository for PostgresPriceHistoryRepository {
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(rows
-            .into_iter()
-            .map(|row| PriceHistoryPoint {
-                id: row.try_get("id").unwrap_or_else(|_| Uuid::new_v4()),
-                symbol: row.try_get("symbol").unwrap_or_default(),
-                price: row.try_get::<f64, _>("price").unwrap_or(0.0),
-                price_ts: row
-                    .try_get("price_ts")
-                    .unwrap_or_else(|_| DateTime::<Utc>::MIN_UTC),
-                source: row
-                    .try_get("source")
-                    .unwrap_or_else(|_| "unknown".to_string()),
-                chain_id: row
-                    .try_get::<i64, _>("chain_id")
-                    .ok()
-                    .and_then(|v| v.try_into().ok()),
-            })
-            .collect())
+        Ok(rows.iter().map(point_from_row).collect())
+    }
+
+    async fn fetch_latest(
+        &self,
+        symbol: &str,
+        chain_id: Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<PriceHistoryPoint>> {
+        let rows = sqlx::query(
+            "SELECT * FROM (
+                 SELECT id, symbol, price::float8 AS price, price_ts, source, chain_id, open, high, low, volume
+                 FROM price_history
+                 WHERE symbol = $1 AND chain_id = $2
+                 ORDER BY price_ts DESC
+                 LIMIT $3
+             ) latest
+             ORDER BY price_ts ASC",
+        )
+        .bind(symbol)
+        .bind(i64::try_from(chain_id.unwrap_or(0)).unwrap_or(0))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().map(point_from_row).collect())
+    }
+
+    async fn fetch_before(
+        &self,
+        symbol: &str,
+        chain_id: Option<u64>,
+        before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<PriceHistoryPoint>> {
+        let rows = sqlx::query(
+            "SELECT * FROM (
+                 SELECT id, symbol, price::float8 AS price, price_ts, source, chain_id, open, high, low, volume
+                 FROM price_history
+                 WHERE symbol = $1 AND chain_id = $2 AND price_ts < $3
+                 ORDER BY price_ts DESC
+                 LIMIT $4
+             ) earlier
+             ORDER BY price_ts ASC",
+        )
+        .bind(symbol)
+        .bind(i64::try_from(chain_id.unwrap_or(0)).unwrap_or(0))
+        .bind(before)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().map(point_from_row).collect())
+    }
+
+    async fn fetch_since(
+        &self,
+        symbol: &str,
+        chain_id: Option<u64>,
+        from: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<PriceHistoryPoint>> {
+        let rows = sqlx::query(
+            "SELECT id, symbol, price::float8 AS price, price_ts, source, chain_id, open, high, low, volume
+             FROM price_history
+             WHERE symbol = $1 AND chain_id = $2 AND price_ts >= $3
+             ORDER BY price_ts ASC
+             LIMIT $4",
+        )
+        .bind(symbol)
+        .bind(i64::try_from(chain_id.unwrap_or(0)).unwrap_or(0))
+        .bind(from)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().map(point_from_row).collect())
     }
 
     async fn latest_timestamp(
@@ -114,4 +256,155 @@ impl PriceHistoryRepository for PostgresPriceHistoryRepository {
         .await?;
         Ok(row.map(|r| r.try_get("price_ts").unwrap()))
     }
+
+    async fn price_at(
+        &self,
+        symbol: &str,
+        chain_id: Option<u64>,
+        at: DateTime<Utc>,
+    ) -> Result<Option<f64>> {
+        let row = sqlx::query(
+            "SELECT price::float8 AS price FROM price_history
+             WHERE symbol = $1 AND chain_id = $2 AND price_ts <= $3
+             ORDER BY price_ts DESC LIMIT 1",
+        )
+        .bind(symbol)
+        .bind(i64::try_from(chain_id.unwrap_or(0)).unwrap_or(0))
+        .bind(at)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| r.try_get("price")).transpose()?)
+    }
+
+    async fn save_dataset(
+        &self,
+        dataset: &PriceDataset,
+        points: &[PriceHistoryPoint],
+    ) -> Result<PriceDataset> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM price_history WHERE symbol = $1")
+            .bind(&dataset.series_key)
+            .execute(&mut *tx)
+            .await?;
+        for chunk in points.chunks(DATASET_INSERT_CHUNK) {
+            let ids: Vec<Uuid> = chunk.iter().map(|p| p.id).collect();
+            let prices: Vec<f64> = chunk.iter().map(|p| p.price).collect();
+            let timestamps: Vec<DateTime<Utc>> = chunk.iter().map(|p| p.price_ts).collect();
+            let opens: Vec<Option<f64>> = chunk.iter().map(|p| p.open).collect();
+            let highs: Vec<Option<f64>> = chunk.iter().map(|p| p.high).collect();
+            let lows: Vec<Option<f64>> = chunk.iter().map(|p| p.low).collect();
+            let volumes: Vec<Option<f64>> = chunk.iter().map(|p| p.volume).collect();
+            sqlx::query(
+                "INSERT INTO price_history
+                     (id, symbol, price, price_ts, source, chain_id, open, high, low, volume)
+                 SELECT id, $2, price, price_ts, $3, 0, open, high, low, volume
+                 FROM UNNEST($1::uuid[], $4::float8[], $5::timestamptz[], $6::float8[],
+                             $7::float8[], $8::float8[], $9::float8[])
+                     AS t(id, price, price_ts, open, high, low, volume)",
+            )
+            .bind(&ids)
+            .bind(&dataset.series_key)
+            .bind(&dataset.source)
+            .bind(&prices)
+            .bind(&timestamps)
+            .bind(&opens)
+            .bind(&highs)
+            .bind(&lows)
+            .bind(&volumes)
+            .execute(&mut *tx)
+            .await?;
+        }
+        let row = sqlx::query(&format!(
+            "INSERT INTO price_datasets
+                 (id, user_id, name, symbol, source, series_key, point_count, first_ts, last_ts)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             ON CONFLICT (user_id, name) DO UPDATE
+             SET symbol = EXCLUDED.symbol, source = EXCLUDED.source,
+                 point_count = EXCLUDED.point_count, first_ts = EXCLUDED.first_ts,
+                 last_ts = EXCLUDED.last_ts, created_at = NOW()
+             RETURNING {DATASET_COLUMNS}"
+        ))
+        .bind(dataset.id)
+        .bind(dataset.user_id)
+        .bind(&dataset.name)
+        .bind(&dataset.symbol)
+        .bind(&dataset.source)
+        .bind(&dataset.series_key)
+        .bind(dataset.point_count)
+        .bind(dataset.first_ts)
+        .bind(dataset.last_ts)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        dataset_from_row(&row)
+    }
+
+    async fn find_dataset(&self, user_id: Uuid, name: &str) -> Result<Option<PriceDataset>> {
+        let row = sqlx::query(&format!(
+            "SELECT {DATASET_COLUMNS} FROM price_datasets WHERE user_id = $1 AND name = $2"
+        ))
+        .bind(user_id)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(dataset_from_row).transpose()
+    }
+
+    async fn list_datasets(&self, user_id: Uuid) -> Result<Vec<PriceDataset>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {DATASET_COLUMNS} FROM price_datasets WHERE user_id = $1 ORDER BY name"
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(dataset_from_row).collect()
+    }
+
+    async fn delete_dataset(&self, user_id: Uuid, name: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let key: Option<String> = sqlx::query_scalar(
+            "DELETE FROM price_datasets WHERE user_id = $1 AND name = $2 RETURNING series_key",
+        )
+        .bind(user_id)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(key) = key else {
+            return Ok(false);
+        };
+        sqlx::query("DELETE FROM price_history WHERE symbol = $1")
+            .bind(&key)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn fetch_dataset_points(&self, dataset: &PriceDataset) -> Result<Vec<PriceHistoryPoint>> {
+        let rows = sqlx::query(
+            "SELECT id, price::float8 AS price, price_ts, open, high, low, volume
+             FROM price_history
+             WHERE symbol = $1
+             ORDER BY price_ts ASC",
+        )
+        .bind(&dataset.series_key)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(PriceHistoryPoint {
+                    id: row.try_get("id")?,
+                    symbol: dataset.symbol.clone(),
+                    price: row.try_get("price")?,
+                    price_ts: row.try_get("price_ts")?,
+                    source: dataset.source.clone(),
+                    chain_id: None,
+                    open: row.try_get("open")?,
+                    high: row.try_get("high")?,
+                    low: row.try_get("low")?,
+                    volume: row.try_get("volume")?,
+                })
+            })
+            .collect()
+    }
 }
@@ -1,23 +1,206 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use async_trait::async_trait;
-use domain::{BacktestResult, Strategy};
-use sqlx::{PgPool, Row};
+use chrono::{DateTime, Utc};
+use domain::{
+    BacktestJob, BacktestJobKind, BacktestJobStatus, BacktestResult, PaperAccount,
+    PaperEquityPoint, Strategy, StrategyVersion,
+};
+use sqlx::{PgPool, Row, postgres::PgRow};
 use tracing::warn;
 use uuid::Uuid;
 
 #[async_trait]
 pub trait StrategyRepository: Send + Sync {
     async fn create(&self, strategy: &Strategy) -> Result<()>;
+    /// Stores `strategy`'s name, type and params as a new version. Returns the
+    /// stored strategy (unchanged when nothing differs), or `None` if it does
+    /// not exist.
+    async fn update(&self, strategy: &Strategy) -> Result<Option<Strategy>>;
     async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<Strategy>>;
     async fn find_by_id(&self, id: Uuid, user_id: Uuid) -> Result<Option<Strategy>>;
-    async fn save_backtest(&self, result: &BacktestResult) -> Result<()>;
+    /// Versions of a strategy, newest first.
+    async fn list_versions(&self, strategy_id: Uuid, user_id: Uuid)
+        -> Result<Vec<StrategyVersion>>;
+    async fn find_version(
+        &self,
+        strategy_id: Uuid,
+        user_id: Uuid,
+        version: i32,
+    ) -> Result<Option<StrategyVersion>>;
+    /// Stores a finished synchronous backtest and returns its id.
+    async fn save_backtest(&self, result: &BacktestResult) -> Result<Uuid>;
+    /// Finished backtests, newest first, optionally only those of one version.
     async fn list_backtests(
         &self,
         strategy_id: Uuid,
         user_id: Uuid,
+        version: Option<i32>,
         limit: usize,
     ) -> Result<Vec<BacktestResult>>;
+    /// A finished backtest of the strategy, with its id set.
+    async fn find_backtest(
+        &self,
+        backtest_id: Uuid,
+        strategy_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<BacktestResult>>;
     async fn delete(&self, id: Uuid, user_id: Uuid) -> Result<bool>;
+    async fn enqueue_backtest(
+        &self,
+        strategy_id: Uuid,
+        kind: BacktestJobKind,
+        request: &serde_json::Value,
+    ) -> Result<BacktestJob>;
+    /// Moves the oldest queued job to `running` under a lease of `lease` and
+    /// returns it with its strategy.
+    async fn claim_backtest(&self, lease: Duration) -> Result<Option<(BacktestJob, Strategy)>>;
+    /// Extends the lease of the run of `job_id` that started at `started_at`;
+    /// `false` once that run is over (cancelled, finished or requeued).
+    async fn renew_backtest_lease(
+        &self,
+        job_id: Uuid,
+        started_at: DateTime<Utc>,
+        lease: Duration,
+    ) -> Result<bool>;
+    /// Returns `false` once the job is no longer running (e.g. it was cancelled).
+    async fn update_backtest_progress(&self, job_id: Uuid, progress: f64) -> Result<bool>;
+    async fn complete_backtest(&self, job_id: Uuid, result: &BacktestResult) -> Result<bool>;
+    /// Stores the ranked trials of a finished `optimize` job.
+    async fn complete_optimization(
+        &self,
+        job_id: Uuid,
+        result: &serde_json::Value,
+    ) -> Result<bool>;
+    async fn fail_backtest(&self, job_id: Uuid, error: &str) -> Result<bool>;
+    /// Cancels a queued or running job; `false` if it was not found or already finished.
+    async fn cancel_backtest(&self, job_id: Uuid, user_id: Uuid) -> Result<bool>;
+    async fn find_backtest_job(&self, job_id: Uuid, user_id: Uuid) -> Result<Option<BacktestJob>>;
+    async fn list_backtest_jobs(
+        &self,
+        user_id: Uuid,
+        strategy_id: Option<Uuid>,
+        status: Option<BacktestJobStatus>,
+        limit: usize,
+    ) -> Result<Vec<BacktestJob>>;
+    /// Puts `running` jobs whose lease expired, i.e. whose worker stopped
+    /// renewing it, back in the queue.
+    async fn requeue_expired_backtests(&self) -> Result<u64>;
+    /// Starts paper trading, replacing a stopped account and its equity;
+    /// `None` if the strategy is already active.
+    async fn activate_paper(&self, account: &PaperAccount) -> Result<Option<PaperAccount>>;
+    async fn find_paper(&self, strategy_id: Uuid, user_id: Uuid) -> Result<Option<PaperAccount>>;
+    /// Stops an active account; `None` if there is none or it already stopped.
+    async fn stop_paper(&self, strategy_id: Uuid, user_id: Uuid) -> Result<Option<PaperAccount>>;
+    async fn list_active_paper(&self) -> Result<Vec<(PaperAccount, Strategy)>>;
+    /// Saves the account state with the equity points that led to it;
+    /// `false` if the account was stopped in the meantime.
+    async fn record_paper(
+        &self,
+        account: &PaperAccount,
+        points: &[PaperEquityPoint],
+    ) -> Result<bool>;
+    /// The latest `limit` equity points, oldest first.
+    async fn paper_equity(&self, strategy_id: Uuid, limit: usize) -> Result<Vec<PaperEquityPoint>>;
+}
+
+const STRATEGY_COLUMNS: &str = "id, user_id, name, type, params, version";
+
+fn strategy_from_row(row: &PgRow) -> Result<Strategy> {
+    Ok(Strategy {
+        id: row.try_get("id")?,
+        user_id: row.try_get("user_id")?,
+        name: row.try_get("name")?,
+        r#type: row.try_get("type")?,
+        params: row.try_get("params")?,
+        version: row.try_get("version")?,
+    })
+}
+
+fn version_from_row(row: &PgRow) -> Result<StrategyVersion> {
+    Ok(StrategyVersion {
+        strategy_id: row.try_get("strategy_id")?,
+        version: row.try_get("version")?,
+        name: row.try_get("name")?,
+        r#type: row.try_get("type")?,
+        params: row.try_get("params")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+const JOB_COLUMNS: &str = "b.id, b.strategy_id, b.kind, b.status, b.progress, b.error, \
+     b.request, b.created_at, b.started_at, b.completed_at, b.result";
+
+fn job_from_row(row: &PgRow, with_result: bool) -> Result<BacktestJob> {
+    let status: String = row.try_get("status")?;
+    let status = BacktestJobStatus::parse(&status)
+        .ok_or_else(|| anyhow::anyhow!("unknown backtest job status {status}"))?;
+    let kind: String = row.try_get("kind")?;
+    let kind = BacktestJobKind::parse(&kind)
+        .ok_or_else(|| anyhow::anyhow!("unknown backtest job kind {kind}"))?;
+    let completed_at = row.try_get("completed_at")?;
+    let finished = with_result && status == BacktestJobStatus::Succeeded;
+    let optimization = if finished && kind == BacktestJobKind::Optimize {
+        row.try_get("result")?
+    } else {
+        None
+    };
+    let result = if finished && kind == BacktestJobKind::Backtest {
+        let value: serde_json::Value = row.try_get("result")?;
+        match serde_json::from_value::<BacktestResult>(value) {
+            Ok(mut parsed) => {
+                parsed.id = Some(row.try_get("id")?);
+                parsed.completed_at = completed_at;
+                Some(parsed)
+            }
+            Err(err) => {
+                warn!(error = %err, "invalid backtest result json; omitting");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    Ok(BacktestJob {
+        id: row.try_get("id")?,
+        strategy_id: row.try_get("strategy_id")?,
+        kind,
+        status,
+        progress: row.try_get("progress")?,
+        error: row.try_get("error")?,
+        request: row.try_get("request")?,
+        created_at: row.try_get("created_at")?,
+        started_at: row.try_get("started_at")?,
+        completed_at,
+        result,
+        optimization,
+    })
+}
+
+const PAPER_COLUMNS: &str = "p.strategy_id, p.status, p.symbol, p.chain_id, p.strategy_version, \
+     p.wallet_id, p.alert_rule_id, p.initial_equity, p.equity, p.position, p.last_price, \
+     p.last_price_ts, p.replay_from, p.activated_at, p.stopped_at, p.updated_at";
+
+fn paper_from_row(row: &PgRow) -> Result<PaperAccount> {
+    Ok(PaperAccount {
+        strategy_id: row.try_get("strategy_id")?,
+        status: row.try_get("status")?,
+        symbol: row.try_get("symbol")?,
+        chain_id: u64::try_from(row.try_get::<i64, _>("chain_id")?)?,
+        strategy_version: row.try_get("strategy_version")?,
+        wallet_id: row.try_get("wallet_id")?,
+        alert_rule_id: row.try_get("alert_rule_id")?,
+        initial_equity: row.try_get("initial_equity")?,
+        equity: row.try_get("equity")?,
+        position: row.try_get("position")?,
+        last_price: row.try_get("last_price")?,
+        last_price_ts: row.try_get("last_price_ts")?,
+        replay_from: row.try_get("replay_from")?,
+        activated_at: row.try_get("activated_at")?,
+        stopped_at: row.try_get("stopped_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
 }
 
 #[derive(Clone)]
@@ -34,90 +217,190 @@ impl PostgresStrategyRepository {
 #[async_trait]
 impl StrategyRepository for PostgresStrategyRepository {
     async fn create(&self, strategy: &Strategy) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
         sqlx::query(
-            "INSERT INTO strategies (id, user_id, name, type, params) VALUES ($1, $2, $3, $4, $5)",
+            "INSERT INTO strategies (id, user_id, name, type, params, version)
+             VALUES ($1, $2, $3, $4, $5, 1)",
         )
         .bind(strategy.id)
         .bind(strategy.user_id)
         .bind(&strategy.name)
         .bind(&strategy.r#type)
         .bind(&strategy.params)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
+        sqlx::query(
+            "INSERT INTO strategy_versions (strategy_id, version, name, type, params)
+             VALUES ($1, 1, $2, $3, $4)",
+        )
+        .bind(strategy.id)
+        .bind(&strategy.name)
+        .bind(&strategy.r#type)
+        .bind(&strategy.params)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
         Ok(())
     }
 
-    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<Strategy>> {
-        let rows = sqlx::query(
-            "SELECT id, user_id, name, type, params FROM strategies WHERE user_id = $1 ORDER BY created_at DESC",
+    async fn update(&self, strategy: &Strategy) -> Result<Option<Strategy>> {
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query(&format!(
+            "SELECT {STRATEGY_COLUMNS} FROM strategies WHERE id = $1 AND user_id = $2 FOR UPDATE"
+        ))
+        .bind(strategy.id)
+        .bind(strategy.user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let current = strategy_from_row(&row)?;
+        if current.name == strategy.name
+            && current.r#type == strategy.r#type
+            && current.params == strategy.params
+        {
+            return Ok(Some(current));
+        }
+
+        // Strategies inserted without a version row (seed data) keep their
+        // current settings as the version being replaced.
+        sqlx::query(
+            "INSERT INTO strategy_versions (strategy_id, version, name, type, params)
+             SELECT id, version, name, type, params FROM strategies WHERE id = $1
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(current.id)
+        .execute(&mut *tx)
+        .await?;
+        let version = current.version + 1;
+        sqlx::query(
+            "UPDATE strategies SET name = $2, type = $3, params = $4, version = $5, updated_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(current.id)
+        .bind(&strategy.name)
+        .bind(&strategy.r#type)
+        .bind(&strategy.params)
+        .bind(version)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO strategy_versions (strategy_id, version, name, type, params)
+             VALUES ($1, $2, $3, $4, $5)",
         )
+        .bind(current.id)
+        .bind(version)
+        .bind(&strategy.name)
+        .bind(&strategy.r#type)
+        .bind(&strategy.params)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(Strategy {
+            version,
+            ..strategy.clone()
+        }))
+    }
+
+    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<Strategy>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {STRATEGY_COLUMNS} FROM strategies WHERE user_id = $1 ORDER BY created_at DESC"
+        ))
         .bind(user_id)
         .fetch_all(&self.pool)
         .await?;
 
-        rows.into_iter()
-            .map(|row| {
-                Ok(Strategy {
-                    id: row.try_get("id")?,
-                    user_id: row.try_get("user_id")?,
-                    name: row.try_get("name")?,
-                    r#type: row.try_get("type")?,
-                    params: row.try_get("params")?,
-                })
-            })
-            .collect()
+        rows.iter().map(strategy_from_row).collect()
     }
 
     async fn find_by_id(&self, id: Uuid, user_id: Uuid) -> Result<Option<Strategy>> {
-        let row = sqlx::query(
-            "SELECT id, user_id, name, type, params FROM strategies WHERE id = $1 AND user_id = $2 LIMIT 1",
-        )
+        let row = sqlx::query(&format!(
+            "SELECT {STRATEGY_COLUMNS} FROM strategies WHERE id = $1 AND user_id = $2 LIMIT 1"
+        ))
         .bind(id)
         .bind(user_id)
         .fetch_optional(&self.pool)
         .await?;
 
-        if let Some(row) = row {
-            Ok(Some(Strategy {
-                id: row.try_get("id")?,
-                user_id: row.try_get("user_id")?,
-                name: row.try_get("name")?,
-                r#type: row.try_get("type")?,
-                params: row.try_get("params")?,
-            }))
-        } else {
-            Ok(None)
-        }
+        row.map(|row| strategy_from_row(&row)).transpose()
+    }
+
+    async fn list_versions(
+        &self,
+        strategy_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<StrategyVersion>> {
+        let rows = sqlx::query(
+            "SELECT v.strategy_id, v.version, v.name, v.type, v.params, v.created_at
+             FROM strategy_versions v
+             JOIN strategies s ON s.id = v.strategy_id
+             WHERE v.strategy_id = $1 AND s.user_id = $2
+             ORDER BY v.version DESC",
+        )
+        .bind(strategy_id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(version_from_row).collect()
+    }
+
+    async fn find_version(
+        &self,
+        strategy_id: Uuid,
+        user_id: Uuid,
+        version: i32,
+    ) -> Result<Option<StrategyVersion>> {
+        let row = sqlx::query(
+            "SELECT v.strategy_id, v.version, v.name, v.type, v.params, v.created_at
+             FROM strategy_versions v
+             JOIN strategies s ON s.id = v.strategy_id
+             WHERE v.strategy_id = $1 AND s.user_id = $2 AND v.version = $3",
+        )
+        .bind(strategy_id)
+        .bind(user_id)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| version_from_row(&row)).transpose()
     }
 
-    async fn save_backtest(&self, result: &BacktestResult) -> Result<()> {
+    async fn save_backtest(&self, result: &BacktestResult) -> Result<Uuid> {
+        let id = Uuid::new_v4();
         sqlx::query(
-            "INSERT INTO strategy_backtests (id, strategy_id, started_at, completed_at, result)
-             VALUES ($1, $2, NOW(), NOW(), $3)",
+            "INSERT INTO strategy_backtests
+                 (id, strategy_id, started_at, completed_at, result, strategy_version, overrides)
+             VALUES ($1, $2, NOW(), NOW(), $3, $4, $5)",
         )
-        .bind(Uuid::new_v4())
+        .bind(id)
         .bind(result.strategy_id)
-        .bind(serde_json::to_value(&result)?)
+        .bind(serde_json::to_value(result)?)
+        .bind(result.strategy_version)
+        .bind(serde_json::Value::Object(result.overrides.clone()))
         .execute(&self.pool)
         .await?;
-        Ok(())
+        Ok(id)
     }
 
     async fn list_backtests(
         &self,
         strategy_id: Uuid,
         user_id: Uuid,
+        version: Option<i32>,
         limit: usize,
     ) -> Result<Vec<BacktestResult>> {
         let rows = sqlx::query(
-            "SELECT b.result, b.completed_at FROM strategy_backtests b
+            "SELECT b.id, b.result, b.completed_at FROM strategy_backtests b
              JOIN strategies s ON s.id = b.strategy_id
-             WHERE b.strategy_id = $1 AND s.user_id = $2
+             WHERE b.strategy_id = $1 AND s.user_id = $2 AND b.status = 'succeeded'
+               AND b.kind = 'backtest'
+               AND ($3::int IS NULL OR b.strategy_version = $3)
              ORDER BY b.completed_at DESC
-             LIMIT $3",
+             LIMIT $4",
         )
         .bind(strategy_id)
         .bind(user_id)
+        .bind(version)
         .bind(limit as i64)
         .fetch_all(&self.pool)
         .await?;
@@ -127,6 +410,7 @@ impl StrategyRepository for PostgresStrategyRepository {
                 let value: serde_json::Value = row.try_get("result").ok()?;
                 match serde_json::from_value::<BacktestResult>(value) {
                     Ok(mut parsed) => {
+                        parsed.id = row.try_get("id").ok();
                         let completed_at: chrono::DateTime<chrono::Utc> =
                             row.try_get("completed_at").unwrap_or_else(|_| chrono::Utc::now());
                         parsed.completed_at = Some(completed_at);
@@ -141,6 +425,32 @@ impl StrategyRepository for PostgresStrategyRepository {
             .collect()
     }
 
+    async fn find_backtest(
+        &self,
+        backtest_id: Uuid,
+        strategy_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<BacktestResult>> {
+        let row = sqlx::query(
+            "SELECT b.result, b.completed_at FROM strategy_backtests b
+             JOIN strategies s ON s.id = b.strategy_id
+             WHERE b.id = $1 AND b.strategy_id = $2 AND s.user_id = $3
+               AND b.status = 'succeeded' AND b.kind = 'backtest'",
+        )
+        .bind(backtest_id)
+        .bind(strategy_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let mut result: BacktestResult = serde_json::from_value(row.try_get("result")?)?;
+        result.id = Some(backtest_id);
+        result.completed_at = row.try_get("completed_at")?;
+        Ok(Some(result))
+    }
+
     async fn delete(&self, id: Uuid, user_id: Uuid) -> Result<bool> {
         // Remove backtests first, then the strategy.
         let mut tx = self.pool.begin().await?;
@@ -157,4 +467,364 @@ impl StrategyRepository for PostgresStrategyRepository {
         tx.commit().await?;
         Ok(res.rows_affected() > 0)
     }
+
+    async fn enqueue_backtest(
+        &self,
+        strategy_id: Uuid,
+        kind: BacktestJobKind,
+        request: &serde_json::Value,
+    ) -> Result<BacktestJob> {
+        let row = sqlx::query(&format!(
+            "INSERT INTO strategy_backtests AS b (id, strategy_id, kind, status, progress, request)
+             VALUES ($1, $2, $3, 'queued', 0, $4)
+             RETURNING {JOB_COLUMNS}"
+        ))
+        .bind(Uuid::new_v4())
+        .bind(strategy_id)
+        .bind(kind.as_str())
+        .bind(request)
+        .fetch_one(&self.pool)
+        .await?;
+        job_from_row(&row, false)
+    }
+
+    async fn claim_backtest(&self, lease: Duration) -> Result<Option<(BacktestJob, Strategy)>> {
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query(&format!(
+            "UPDATE strategy_backtests AS b
+             SET status = 'running', progress = 0, started_at = NOW(),
+                 lease_until = NOW() + make_interval(secs => $1)
+             WHERE b.id = (
+                 SELECT id FROM strategy_backtests
+                 WHERE status = 'queued'
+                 ORDER BY created_at
+                 LIMIT 1
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING {JOB_COLUMNS}"
+        ))
+        .bind(lease.as_secs_f64())
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(row) = row else {
+            tx.commit().await?;
+            return Ok(None);
+        };
+        let job = job_from_row(&row, false)?;
+        let row = sqlx::query(&format!(
+            "SELECT {STRATEGY_COLUMNS} FROM strategies WHERE id = $1"
+        ))
+        .bind(job.strategy_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let strategy = strategy_from_row(&row)?;
+        tx.commit().await?;
+        Ok(Some((job, strategy)))
+    }
+
+    async fn renew_backtest_lease(
+        &self,
+        job_id: Uuid,
+        started_at: DateTime<Utc>,
+        lease: Duration,
+    ) -> Result<bool> {
+        let res = sqlx::query(
+            "UPDATE strategy_backtests SET lease_until = NOW() + make_interval(secs => $3)
+             WHERE id = $1 AND started_at = $2 AND status = 'running'",
+        )
+        .bind(job_id)
+        .bind(started_at)
+        .bind(lease.as_secs_f64())
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn update_backtest_progress(&self, job_id: Uuid, progress: f64) -> Result<bool> {
+        let res = sqlx::query(
+            "UPDATE strategy_backtests SET progress = $2 WHERE id = $1 AND status = 'running'",
+        )
+        .bind(job_id)
+        .bind(progress.clamp(0.0, 1.0))
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn complete_backtest(&self, job_id: Uuid, result: &BacktestResult) -> Result<bool> {
+        let res = sqlx::query(
+            "UPDATE strategy_backtests
+             SET status = 'succeeded', progress = 1, result = $2, completed_at = NOW(),
+                 strategy_version = $3, overrides = $4
+             WHERE id = $1 AND status = 'running'",
+        )
+        .bind(job_id)
+        .bind(serde_json::to_value(result)?)
+        .bind(result.strategy_version)
+        .bind(serde_json::Value::Object(result.overrides.clone()))
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn complete_optimization(
+        &self,
+        job_id: Uuid,
+        result: &serde_json::Value,
+    ) -> Result<bool> {
+        let res = sqlx::query(
+            "UPDATE strategy_backtests
+             SET status = 'succeeded', progress = 1, result = $2, completed_at = NOW()
+             WHERE id = $1 AND status = 'running' AND kind = 'optimize'",
+        )
+        .bind(job_id)
+        .bind(result)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn fail_backtest(&self, job_id: Uuid, error: &str) -> Result<bool> {
+        let res = sqlx::query(
+            "UPDATE strategy_backtests
+             SET status = 'failed', error = $2, completed_at = NOW()
+             WHERE id = $1 AND status = 'running'",
+        )
+        .bind(job_id)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn cancel_backtest(&self, job_id: Uuid, user_id: Uuid) -> Result<bool> {
+        let res = sqlx::query(
+            "UPDATE strategy_backtests AS b
+             SET status = 'cancelled', completed_at = NOW()
+             FROM strategies s
+             WHERE b.id = $1 AND s.id = b.strategy_id AND s.user_id = $2
+               AND b.status IN ('queued', 'running')",
+        )
+        .bind(job_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn find_backtest_job(
+        &self,
+        job_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<BacktestJob>> {
+        let row = sqlx::query(&format!(
+            "SELECT {JOB_COLUMNS} FROM strategy_backtests b
+             JOIN strategies s ON s.id = b.strategy_id
+             WHERE b.id = $1 AND s.user_id = $2
+             LIMIT 1"
+        ))
+        .bind(job_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| job_from_row(&row, true)).transpose()
+    }
+
+    async fn list_backtest_jobs(
+        &self,
+        user_id: Uuid,
+        strategy_id: Option<Uuid>,
+        status: Option<BacktestJobStatus>,
+        limit: usize,
+    ) -> Result<Vec<BacktestJob>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {JOB_COLUMNS} FROM strategy_backtests b
+             JOIN strategies s ON s.id = b.strategy_id
+             WHERE s.user_id = $1
+               AND ($2::uuid IS NULL OR b.strategy_id = $2)
+               AND ($3::text IS NULL OR b.status = $3)
+             ORDER BY b.created_at DESC
+             LIMIT $4"
+        ))
+        .bind(user_id)
+        .bind(strategy_id)
+        .bind(status.map(|s| s.as_str()))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(|row| job_from_row(row, false)).collect()
+    }
+
+    async fn requeue_expired_backtests(&self) -> Result<u64> {
+        let res = sqlx::query(
+            "UPDATE strategy_backtests
+             SET status = 'queued', progress = 0, started_at = NULL, lease_until = NULL
+             WHERE status = 'running' AND (lease_until IS NULL OR lease_until < NOW())",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    async fn activate_paper(&self, account: &PaperAccount) -> Result<Option<PaperAccount>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "DELETE FROM paper_equity
+             WHERE strategy_id = $1
+               AND EXISTS (
+                   SELECT 1 FROM paper_accounts
+                   WHERE strategy_id = $1 AND status <> 'active'
+               )",
+        )
+        .bind(account.strategy_id)
+        .execute(&mut *tx)
+        .await?;
+        let row = sqlx::query(&format!(
+            "INSERT INTO paper_accounts AS p
+                 (strategy_id, status, symbol, chain_id, strategy_version, wallet_id,
+                  alert_rule_id, initial_equity, equity, position, last_price, last_price_ts,
+                  activated_at, stopped_at, updated_at)
+             VALUES ($1, 'active', $2, $3, $4, $5, $6, $7, $7, 0, NULL, NULL, $8, NULL, NOW())
+             ON CONFLICT (strategy_id) DO UPDATE
+             SET status = 'active', symbol = EXCLUDED.symbol, chain_id = EXCLUDED.chain_id,
+                 strategy_version = EXCLUDED.strategy_version,
+                 wallet_id = EXCLUDED.wallet_id, alert_rule_id = EXCLUDED.alert_rule_id,
+                 initial_equity = EXCLUDED.initial_equity, equity = EXCLUDED.equity,
+                 position = 0, last_price = NULL, last_price_ts = NULL, replay_from = NULL,
+                 activated_at = EXCLUDED.activated_at, stopped_at = NULL, updated_at = NOW()
+             WHERE p.status <> 'active'
+             RETURNING {PAPER_COLUMNS}"
+        ))
+        .bind(account.strategy_id)
+        .bind(&account.symbol)
+        .bind(i64::try_from(account.chain_id)?)
+        .bind(account.strategy_version)
+        .bind(account.wallet_id)
+        .bind(account.alert_rule_id)
+        .bind(account.initial_equity)
+        .bind(account.activated_at)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(row) = row else {
+            tx.rollback().await?;
+            return Ok(None);
+        };
+        let account = paper_from_row(&row)?;
+        tx.commit().await?;
+        Ok(Some(account))
+    }
+
+    async fn find_paper(&self, strategy_id: Uuid, user_id: Uuid) -> Result<Option<PaperAccount>> {
+        let row = sqlx::query(&format!(
+            "SELECT {PAPER_COLUMNS}
+             FROM paper_accounts p
+             JOIN strategies s ON s.id = p.strategy_id
+             WHERE p.strategy_id = $1 AND s.user_id = $2"
+        ))
+        .bind(strategy_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(paper_from_row).transpose()
+    }
+
+    async fn stop_paper(&self, strategy_id: Uuid, user_id: Uuid) -> Result<Option<PaperAccount>> {
+        let row = sqlx::query(&format!(
+            "UPDATE paper_accounts AS p
+             SET status = 'stopped', stopped_at = NOW(), updated_at = NOW()
+             FROM strategies s
+             WHERE s.id = p.strategy_id AND p.strategy_id = $1 AND s.user_id = $2
+               AND p.status = 'active'
+             RETURNING {PAPER_COLUMNS}"
+        ))
+        .bind(strategy_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(paper_from_row).transpose()
+    }
+
+    async fn list_active_paper(&self) -> Result<Vec<(PaperAccount, Strategy)>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {PAPER_COLUMNS}, s.id, s.user_id, s.name, s.type, s.params, s.version
+             FROM paper_accounts p
+             JOIN strategies s ON s.id = p.strategy_id
+             WHERE p.status = 'active'
+             ORDER BY p.activated_at"
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| Ok((paper_from_row(row)?, strategy_from_row(row)?)))
+            .collect()
+    }
+
+    async fn record_paper(
+        &self,
+        account: &PaperAccount,
+        points: &[PaperEquityPoint],
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let res = sqlx::query(
+            "UPDATE paper_accounts
+             SET equity = $2, position = $3, last_price = $4, last_price_ts = $5,
+                 replay_from = $6, updated_at = NOW()
+             WHERE strategy_id = $1 AND status = 'active'",
+        )
+        .bind(account.strategy_id)
+        .bind(account.equity)
+        .bind(account.position)
+        .bind(account.last_price)
+        .bind(account.last_price_ts)
+        .bind(account.replay_from)
+        .execute(&mut *tx)
+        .await?;
+        if res.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+        for point in points {
+            sqlx::query(
+                "INSERT INTO paper_equity (strategy_id, ts, price, position, equity)
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (strategy_id, ts) DO NOTHING",
+            )
+            .bind(account.strategy_id)
+            .bind(point.timestamp)
+            .bind(point.price)
+            .bind(point.position)
+            .bind(point.equity)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn paper_equity(&self, strategy_id: Uuid, limit: usize) -> Result<Vec<PaperEquityPoint>> {
+        let rows = sqlx::query(
+            "SELECT * FROM (
+                 SELECT ts, price, position, equity
+                 FROM paper_equity
+                 WHERE strategy_id = $1
+                 ORDER BY ts DESC
+                 LIMIT $2
+             ) latest
+             ORDER BY ts ASC",
+        )
+        .bind(strategy_id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(PaperEquityPoint {
+                    timestamp: row.try_get("ts")?,
+                    price: row.try_get("price")?,
+                    position: row.try_get("position")?,
+                    equity: row.try_get("equity")?,
+                })
+            })
+            .collect()
+    }
 }
@@ -2,10 +2,14 @@ use anyhow::Result;
 use async_trait::async_trait;
 use chrono::NaiveDate;
 use chrono::Utc;
-use domain::WalletTransaction;
-use sqlx::{PgPool, Row};
+use domain::{WalletBackfill, WalletTransaction};
+use sqlx::{postgres::PgRow, PgPool, Row};
 use uuid::Uuid;
 
+const BACKFILL_COLUMNS: &str = "wallet_id, chain_id, backfill_status, backfill_start_block, \
+     backfill_target_block, backfill_block, backfill_days, backfill_error, \
+     backfill_requested_at, backfill_completed_at";
+
 #[async_trait]
 pub trait TransactionRepository: Send + Sync {
     async fn insert_transactions(&self, txs: &[WalletTransaction]) -> Result<()>;
@@ -18,6 +22,44 @@ pub trait TransactionRepository: Send + Sync {
         day: NaiveDate,
     ) -> Result<()>;
     async fn net_flow_since(&self, wallet_id: Uuid, since: chrono::DateTime<Utc>) -> Result<f64>;
+    /// Every stored transfer of the wallet in chain order.
+    async fn list_transactions(&self, wallet_id: Uuid) -> Result<Vec<WalletTransaction>>;
+    /// Restarts the wallet's backfill from `start_block` as `pending`.
+    async fn request_backfill(
+        &self,
+        wallet_id: Uuid,
+        chain_id: u64,
+        start_block: i64,
+    ) -> Result<WalletBackfill>;
+    async fn find_backfill(&self, wallet_id: Uuid) -> Result<Option<WalletBackfill>>;
+    /// Backfills still `pending` or `running`, oldest request first.
+    async fn active_backfills(&self) -> Result<Vec<WalletBackfill>>;
+    /// Marks the backfill `running` with Transfer logs stored up to `scanned_block`.
+    async fn update_backfill_progress(
+        &self,
+        wallet_id: Uuid,
+        target_block: i64,
+        scanned_block: Option<i64>,
+    ) -> Result<()>;
+    async fn record_backfill_error(&self, wallet_id: Uuid, error: &str) -> Result<()>;
+    async fn complete_backfill(&self, wallet_id: Uuid, days_filled: i32) -> Result<()>;
+}
+
+fn backfill_from_row(row: &PgRow) -> Result<WalletBackfill> {
+    Ok(WalletBackfill {
+        wallet_id: row.try_get("wallet_id")?,
+        chain_id: u64::try_from(row.try_get::<i64, _>("chain_id")?).unwrap_or(0),
+        status: row.try_get("backfill_status")?,
+        start_block: row
+            .try_get::<Option<i64>, _>("backfill_start_block")?
+            .unwrap_or(0),
+        target_block: row.try_get("backfill_target_block")?,
+        scanned_block: row.try_get("backfill_block")?,
+        days_filled: row.try_get("backfill_days")?,
+        error: row.try_get("backfill_error")?,
+        requested_at: row.try_get("backfill_requested_at")?,
+        completed_at: row.try_get("backfill_completed_at")?,
+    })
 }
 
 #[derive(Clone)]
@@ -130,4 +172,143 @@ impl TransactionRepository for PostgresTransactionRepository {
         let inflow: f64 = row.try_get("inflow").unwrap_or(0.0);
         Ok(outflow - inflow)
     }
+
+    async fn list_transactions(&self, wallet_id: Uuid) -> Result<Vec<WalletTransaction>> {
+        let rows = sqlx::query(
+            "SELECT id, wallet_id, chain_id, tx_hash, block_number, log_index, asset_symbol,
+                    amount::float8 AS amount, usd_value::float8 AS usd_value, direction,
+                    from_address, to_address, block_timestamp
+             FROM wallet_transactions
+             WHERE wallet_id = $1
+             ORDER BY block_number ASC, log_index ASC",
+        )
+        .bind(wallet_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(WalletTransaction {
+                    id: row.try_get("id")?,
+                    wallet_id: row.try_get("wallet_id")?,
+                    chain_id: u64::try_from(row.try_get::<i64, _>("chain_id")?).unwrap_or(0),
+                    tx_hash: row.try_get("tx_hash")?,
+                    block_number: row.try_get("block_number")?,
+                    log_index: row.try_get("log_index")?,
+                    asset_symbol: row.try_get("asset_symbol")?,
+                    amount: row.try_get("amount")?,
+                    usd_value: row.try_get("usd_value")?,
+                    direction: row.try_get("direction")?,
+                    from_address: row.try_get("from_address")?,
+                    to_address: row.try_get("to_address")?,
+                    block_timestamp: row.try_get("block_timestamp")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn request_backfill(
+        &self,
+        wallet_id: Uuid,
+        chain_id: u64,
+        start_block: i64,
+    ) -> Result<WalletBackfill> {
+        let row = sqlx::query(&format!(
+            "INSERT INTO wallet_sync_cursors
+             (wallet_id, chain_id, backfill_status, backfill_start_block, backfill_requested_at, updated_at)
+             VALUES ($1, $2, 'pending', $3, NOW(), NOW())
+             ON CONFLICT (wallet_id) DO UPDATE
+             SET backfill_status = 'pending',
+                 backfill_start_block = EXCLUDED.backfill_start_block,
+                 backfill_target_block = NULL,
+                 backfill_block = NULL,
+                 backfill_days = 0,
+                 backfill_error = NULL,
+                 backfill_requested_at = NOW(),
+                 backfill_completed_at = NULL,
+                 updated_at = NOW()
+             RETURNING {BACKFILL_COLUMNS}"
+        ))
+        .bind(wallet_id)
+        .bind(i64::try_from(chain_id).unwrap_or(0))
+        .bind(start_block)
+        .fetch_one(&self.pool)
+        .await?;
+        backfill_from_row(&row)
+    }
+
+    async fn find_backfill(&self, wallet_id: Uuid) -> Result<Option<WalletBackfill>> {
+        let row = sqlx::query(&format!(
+            "SELECT {BACKFILL_COLUMNS} FROM wallet_sync_cursors
+             WHERE wallet_id = $1 AND backfill_status IS NOT NULL"
+        ))
+        .bind(wallet_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(backfill_from_row).transpose()
+    }
+
+    async fn active_backfills(&self) -> Result<Vec<WalletBackfill>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {BACKFILL_COLUMNS} FROM wallet_sync_cursors
+             WHERE backfill_status IN ('pending', 'running')
+             ORDER BY backfill_requested_at ASC"
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(backfill_from_row).collect()
+    }
+
+    async fn update_backfill_progress(
+        &self,
+        wallet_id: Uuid,
+        target_block: i64,
+        scanned_block: Option<i64>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE wallet_sync_cursors
+             SET backfill_status = 'running',
+                 backfill_target_block = $2,
+                 backfill_block = $3,
+                 backfill_error = NULL,
+                 updated_at = NOW()
+             WHERE wallet_id = $1",
+        )
+        .bind(wallet_id)
+        .bind(target_block)
+        .bind(scanned_block)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_backfill_error(&self, wallet_id: Uuid, error: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE wallet_sync_cursors
+             SET backfill_error = $2, updated_at = NOW()
+             WHERE wallet_id = $1",
+        )
+        .bind(wallet_id)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn complete_backfill(&self, wallet_id: Uuid, days_filled: i32) -> Result<()> {
+        sqlx::query(
+            "UPDATE wallet_sync_cursors
+             SET backfill_status = 'completed',
+                 backfill_days = $2,
+                 backfill_error = NULL,
+                 backfill_completed_at = NOW(),
+                 updated_at = NOW()
+             WHERE wallet_id = $1",
+        )
+        .bind(wallet_id)
+        .bind(days_filled)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
 }
@@ -0,0 +1,302 @@
+use axum::{
+    Json, Router,
+    extract::{DefaultBodyLimit, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_extra::extract::Multipart;
+use chrono::Utc;
+use domain::{PriceDataset, PriceHistoryPoint};
+use serde::Serialize;
+use strategy_engine::FieldError;
+use uuid::Uuid;
+
+use crate::{
+    auth_middleware::CurrentUser,
+    services::datasets::{self, ParseOptions, UploadFormat},
+    state::AppState,
+};
+
+/// Largest accepted upload; around a million minute bars of OHLCV CSV.
+const MAX_UPLOAD_BYTES: usize = 64 * 1024 * 1024;
+const MAX_NAME_LEN: usize = 64;
+/// Sources written by the price fetchers; uploads may not pose as them.
+const RESERVED_SOURCES: [&str; 2] = ["coingecko", "oracle"];
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/datasets",
+            post(upload_dataset)
+                .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES))
+                .get(list_datasets),
+        )
+        .route("/datasets/:name", get(get_dataset).delete(delete_dataset))
+}
+
+/// Errors from dataset endpoints: a bare status, or 422 with the offending
+/// options or rows.
+#[derive(Debug)]
+enum DatasetApiError {
+    Status(StatusCode),
+    Invalid(Vec<FieldError>),
+}
+
+#[derive(Debug, Serialize)]
+struct InvalidDatasetBody {
+    error: &'static str,
+    fields: Vec<FieldError>,
+}
+
+impl From<StatusCode> for DatasetApiError {
+    fn from(status: StatusCode) -> Self {
+        DatasetApiError::Status(status)
+    }
+}
+
+impl IntoResponse for DatasetApiError {
+    fn into_response(self) -> Response {
+        match self {
+            DatasetApiError::Status(status) => status.into_response(),
+            DatasetApiError::Invalid(fields) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(InvalidDatasetBody {
+                    error: "invalid dataset",
+                    fields,
+                }),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// Form fields of an upload besides the file itself.
+#[derive(Debug, Default)]
+struct UploadForm {
+    name: Option<String>,
+    symbol: Option<String>,
+    source: Option<String>,
+    format: Option<String>,
+    timezone: Option<String>,
+    timestamp_format: Option<String>,
+    columns: Option<String>,
+    delimiter: Option<String>,
+    file: Option<(Option<String>, String)>,
+}
+
+impl UploadForm {
+    async fn read(mut multipart: Multipart) -> Result<Self, DatasetApiError> {
+        let mut form = UploadForm::default();
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|err| DatasetApiError::Status(err.status()))?
+        {
+            let name = field.name().unwrap_or_default().to_string();
+            let file_name = field.file_name().map(str::to_string);
+            let text = field.text().await.map_err(|err| match err.status() {
+                StatusCode::PAYLOAD_TOO_LARGE => DatasetApiError::Status(err.status()),
+                _ => DatasetApiError::Invalid(vec![FieldError::new(
+                    name.clone(),
+                    "must be UTF-8 text",
+                )]),
+            })?;
+            let slot = match name.as_str() {
+                "file" => {
+                    form.file = Some((file_name, text));
+                    continue;
+                }
+                "name" => &mut form.name,
+                "symbol" => &mut form.symbol,
+                "source" => &mut form.source,
+                "format" => &mut form.format,
+                "timezone" => &mut form.timezone,
+                "timestamp_format" => &mut form.timestamp_format,
+                "columns" => &mut form.columns,
+                "delimiter" => &mut form.delimiter,
+                _ => continue,
+            };
+            *slot = Some(text.trim().to_string()).filter(|value| !value.is_empty());
+        }
+        Ok(form)
+    }
+
+    /// Checks the options and returns the dataset name, symbol, source and
+    /// parser settings.
+    fn options(&self) -> Result<(String, String, String, ParseOptions), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let name = self.name.clone().unwrap_or_default();
+        if let Err(message) = check_name(&name) {
+            errors.push(FieldError::new("name", message));
+        }
+        let symbol = self
+            .symbol
+            .clone()
+            .unwrap_or_else(|| name.clone())
+            .to_uppercase();
+        let source = self
+            .source
+            .clone()
+            .unwrap_or_else(|| "upload".to_string())
+            .to_lowercase();
+        if RESERVED_SOURCES.contains(&source.as_str()) {
+            errors.push(FieldError::new("source", "is reserved for fetched prices"));
+        } else if let Err(message) = check_name(&source) {
+            errors.push(FieldError::new("source", message));
+        }
+
+        let mut options = ParseOptions::default();
+        if let Some(format) = &self.format {
+            match UploadFormat::parse(format) {
+                Some(format) => options.format = Some(format),
+                None => errors.push(FieldError::new("format", "must be csv or json")),
+            }
+        }
+        if let Some(timezone) = &self.timezone {
+            match datasets::parse_timezone(timezone) {
+                Some(offset) => options.timezone = offset,
+                None => errors.push(FieldError::new(
+                    "timezone",
+                    "must be UTC or an offset such as +08:00",
+                )),
+            }
+        }
+        options.timestamp_format = self.timestamp_format.clone();
+        if let Some(columns) = &self.columns {
+            match serde_json::from_str(columns) {
+                Ok(columns) => options.columns = columns,
+                Err(_) => errors.push(FieldError::new(
+                    "columns",
+                    "must be a JSON object of field to column name",
+                )),
+            }
+        }
+        if let Some(delimiter) = &self.delimiter {
+            match delimiter.as_str() {
+                "," | ";" | "|" => options.delimiter = delimiter.chars().next().unwrap_or(','),
+                "tab" | "\\t" => options.delimiter = '\t',
+                _ => errors.push(FieldError::new("delimiter", "must be , ; | or tab")),
+            }
+        }
+        if let (None, Some((file_name, body))) = (options.format, &self.file) {
+            options.format = Some(UploadFormat::detect(file_name.as_deref(), body));
+        }
+        if self.file.is_none() {
+            errors.push(FieldError::new("file", "is required"));
+        }
+        if errors.is_empty() {
+            Ok((name, symbol, source, options))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Names are used in URLs and backtest requests: 1–64 of `[A-Za-z0-9_.-]`.
+fn check_name(name: &str) -> Result<(), String> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if name.is_empty() || name.len() > MAX_NAME_LEN || !valid_chars {
+        return Err(format!(
+            "must be 1-{MAX_NAME_LEN} letters, digits, '_', '.' or '-'"
+        ));
+    }
+    Ok(())
+}
+
+/// Stores an uploaded CSV/JSON price file under `name`, replacing any dataset
+/// of the same name (201).
+async fn upload_dataset(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<PriceDataset>), DatasetApiError> {
+    let user_id = user.claims().user_id;
+    let form = UploadForm::read(multipart).await?;
+    let (name, symbol, source, options) = form.options().map_err(DatasetApiError::Invalid)?;
+    let body = form.file.map(|(_, body)| body).unwrap_or_default();
+    let points = datasets::parse_upload(&body, &options).map_err(DatasetApiError::Invalid)?;
+
+    let series_key = format!("DATASET:{}", Uuid::new_v5(&user_id, name.as_bytes()));
+    let rows: Vec<PriceHistoryPoint> = points
+        .iter()
+        .map(|p| PriceHistoryPoint {
+            id: Uuid::new_v4(),
+            symbol: series_key.clone(),
+            price: p.price,
+            price_ts: p.timestamp,
+            source: source.clone(),
+            chain_id: None,
+            open: p.open,
+            high: p.high,
+            low: p.low,
+            volume: p.volume,
+        })
+        .collect();
+    let dataset = PriceDataset {
+        id: Uuid::new_v4(),
+        user_id,
+        name,
+        symbol,
+        source,
+        series_key,
+        point_count: rows.len() as i64,
+        first_ts: points[0].timestamp,
+        last_ts: points[points.len() - 1].timestamp,
+        created_at: Utc::now(),
+    };
+    let saved = state
+        .price_history_repo
+        .save_dataset(&dataset, &rows)
+        .await
+        .map_err(|err| {
+            tracing::error!(%err, "failed to save price dataset");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok((StatusCode::CREATED, Json(saved)))
+}
+
+async fn list_datasets(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<Vec<PriceDataset>>, StatusCode> {
+    state
+        .price_history_repo
+        .list_datasets(user.claims().user_id)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn get_dataset(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(name): Path<String>,
+) -> Result<Json<PriceDataset>, StatusCode> {
+    state
+        .price_history_repo
+        .find_dataset(user.claims().user_id, &name)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn delete_dataset(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(name): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let deleted = state
+        .price_history_repo
+        .delete_dataset(user.claims().user_id, &name)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
@@ -1,5 +1,6 @@
 pub mod alerts;
 pub mod auth;
+pub mod datasets;
 pub mod health;
 pub mod portfolio;
 pub mod secure;
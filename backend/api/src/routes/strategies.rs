@@ -2,25 +2,121 @@ use axum::{
     Json, Router,
     extract::{Path, Query, State},
     http::StatusCode,
-    routing::{delete, get, post},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
 };
 use chrono::Utc;
-use domain::{BacktestResult, Strategy};
-use serde::Deserialize;
+use domain::{
+    AlertRule, BacktestJob, BacktestJobKind, BacktestJobStatus, BacktestReplay, BacktestResult,
+    PaperAccount, PaperTradingReport, Strategy, StrategyVersion, StrategyVersionDiff, WhatIfReport,
+};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{auth_middleware::CurrentUser, state::AppState};
-use strategy_engine::PricePoint;
-use rand::Rng;
+use strategy_engine::{
+    CancelToken, FieldError, MetricsConfig, StrategyError, StrategyKind, kinds::StrategyKindInfo,
+    metrics,
+};
 
-use crate::services::history::load_prices_from_history;
+use crate::services::{
+    backtest::{self, BacktestRequest, InputError, OptimizeRequest},
+    backtest_jobs::on_blocking_pool,
+    manifest, versions,
+    whatif::{self, WhatIfRequest},
+};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/strategies", get(list_strategies).post(create_strategy))
+        .route("/strategies/kinds", get(list_kinds))
         .route("/strategies/:strategy_id/backtest", post(run_backtest))
+        .route("/strategies/:strategy_id/optimize", post(run_optimization))
         .route("/strategies/:strategy_id/backtests", get(list_backtests))
-        .route("/strategies/:strategy_id", delete(delete_strategy))
+        .route(
+            "/strategies/:strategy_id/backtests/:backtest_id/replay",
+            post(replay_backtest),
+        )
+        .route("/strategies/:strategy_id/backtest-jobs", post(enqueue_backtest))
+        .route("/backtest-jobs", get(list_jobs))
+        .route("/backtest-jobs/:job_id", get(get_job))
+        .route("/backtest-jobs/:job_id/cancel", post(cancel_job))
+        .route(
+            "/strategies/:strategy_id",
+            put(update_strategy).delete(delete_strategy),
+        )
+        .route("/strategies/:strategy_id/versions", get(list_versions))
+        .route(
+            "/strategies/:strategy_id/versions/:version",
+            get(get_version),
+        )
+        .route("/strategies/:strategy_id/diff", get(diff_versions))
+        .route(
+            "/strategies/:strategy_id/paper",
+            get(get_paper).post(activate_paper),
+        )
+        .route("/strategies/:strategy_id/paper/stop", post(stop_paper))
+        .route("/strategies/:strategy_id/what-if", post(run_what_if))
+}
+
+/// Errors from strategy endpoints: a bare status, or 422 with the offending params.
+#[derive(Debug)]
+enum StrategyApiError {
+    Status(StatusCode),
+    InvalidParams(Vec<FieldError>),
+}
+
+#[derive(Debug, Serialize)]
+struct InvalidParamsBody {
+    error: &'static str,
+    fields: Vec<FieldError>,
+}
+
+impl From<StatusCode> for StrategyApiError {
+    fn from(status: StatusCode) -> Self {
+        StrategyApiError::Status(status)
+    }
+}
+
+impl From<StrategyError> for StrategyApiError {
+    fn from(err: StrategyError) -> Self {
+        match err {
+            StrategyError::UnknownType(_) => StrategyApiError::Status(StatusCode::BAD_REQUEST),
+            StrategyError::InvalidParams(fields) => StrategyApiError::InvalidParams(fields),
+            StrategyError::Cancelled => StrategyApiError::Status(StatusCode::SERVICE_UNAVAILABLE),
+        }
+    }
+}
+
+impl From<InputError> for StrategyApiError {
+    fn from(err: InputError) -> Self {
+        match err {
+            InputError::Invalid(err) => err.into(),
+            InputError::Storage(_) | InputError::Engine(_) => {
+                StrategyApiError::Status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+impl IntoResponse for StrategyApiError {
+    fn into_response(self) -> Response {
+        match self {
+            StrategyApiError::Status(status) => status.into_response(),
+            StrategyApiError::InvalidParams(fields) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(InvalidParamsBody {
+                    error: "invalid strategy params",
+                    fields,
+                }),
+            )
+                .into_response(),
+        }
+    }
+}
+
+async fn list_kinds() -> Json<Vec<StrategyKindInfo>> {
+    Json(strategy_engine::kinds::catalog())
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,13 +131,16 @@ async fn create_strategy(
     State(state): State<AppState>,
     user: CurrentUser,
     Json(payload): Json<CreateStrategyRequest>,
-) -> Result<Json<Strategy>, StatusCode> {
+) -> Result<Json<Strategy>, StrategyApiError> {
+    let kind: StrategyKind = payload.r#type.parse()?;
+    kind.validate(&payload.params).map_err(StrategyApiError::InvalidParams)?;
     let strategy = Strategy {
         id: Uuid::new_v4(),
         user_id: user.claims().user_id,
         name: payload.name,
-        r#type: payload.r#type,
+        r#type: kind.as_str().to_string(),
         params: payload.params,
+        version: 1,
     };
     state
         .strategy_repo
@@ -51,6 +150,100 @@ async fn create_strategy(
     Ok(Json(strategy))
 }
 
+/// Replaces a strategy's name, type and params, recording them as a new
+/// version. Earlier versions stay available to backtests and diffs; an
+/// unchanged body adds no version.
+async fn update_strategy(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(strategy_id): Path<Uuid>,
+    Json(payload): Json<CreateStrategyRequest>,
+) -> Result<Json<Strategy>, StrategyApiError> {
+    let kind: StrategyKind = payload.r#type.parse()?;
+    kind.validate(&payload.params).map_err(StrategyApiError::InvalidParams)?;
+    let strategy = Strategy {
+        id: strategy_id,
+        user_id: user.claims().user_id,
+        name: payload.name,
+        r#type: kind.as_str().to_string(),
+        params: payload.params,
+        version: 0,
+    };
+    let updated = state
+        .strategy_repo
+        .update(&strategy)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(updated))
+}
+
+async fn list_versions(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(strategy_id): Path<Uuid>,
+) -> Result<Json<Vec<StrategyVersion>>, StatusCode> {
+    let versions = state
+        .strategy_repo
+        .list_versions(strategy_id, user.claims().user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if versions.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(versions))
+}
+
+async fn get_version(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path((strategy_id, version)): Path<(Uuid, i32)>,
+) -> Result<Json<StrategyVersion>, StatusCode> {
+    state
+        .strategy_repo
+        .find_version(strategy_id, user.claims().user_id, version)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffQuery {
+    from: Option<i32>,
+    to: Option<i32>,
+}
+
+/// Changes between two versions; `to` defaults to the latest and `from` to the
+/// version before `to`.
+async fn diff_versions(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(strategy_id): Path<Uuid>,
+    Query(query): Query<DiffQuery>,
+) -> Result<Json<StrategyVersionDiff>, StatusCode> {
+    let user_id = user.claims().user_id;
+    let strategy = state
+        .strategy_repo
+        .find_by_id(strategy_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let to = query.to.unwrap_or(strategy.version);
+    let from = query.from.unwrap_or(to - 1);
+    let mut pair = Vec::with_capacity(2);
+    for version in [from, to] {
+        let stored = state
+            .strategy_repo
+            .find_version(strategy_id, user_id, version)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+        pair.push(stored);
+    }
+    Ok(Json(versions::diff(&pair[0], &pair[1])))
+}
+
 async fn list_strategies(
     State(state): State<AppState>,
     user: CurrentUser,
@@ -63,98 +256,251 @@ async fn list_strategies(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
-#[derive(Debug, Deserialize)]
-struct BacktestPayload {
-    prices: Option<Vec<PriceInput>>,
-    short_window: Option<usize>,
-    long_window: Option<usize>,
-    symbol: Option<String>,
-    days: Option<u32>,
+async fn run_backtest(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(strategy_id): Path<Uuid>,
+    Json(payload): Json<BacktestRequest>,
+) -> Result<Json<BacktestResult>, StrategyApiError> {
+    let Some(mut strategy) = state
+        .strategy_repo
+        .find_by_id(strategy_id, user.claims().user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    else {
+        return Err(StatusCode::NOT_FOUND.into());
+    };
+    payload.validate()?;
+    let overrides = backtest::prepare_strategy(&state, &mut strategy, &payload).await?;
+
+    let inputs = backtest::resolve_inputs(&state, &strategy, &payload).await?;
+    let run = {
+        let state = state.clone();
+        async move { backtest::run(&state, strategy, inputs, &payload, &CancelToken::new()).await }
+    };
+    let mut result = on_blocking_pool(run)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)??;
+    result.overrides = overrides;
+    let id = state
+        .strategy_repo
+        .save_backtest(&result)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    result.id = Some(id);
+    Ok(Json(result))
 }
 
-#[derive(Debug, Deserialize)]
-struct PriceInput {
-    timestamp: chrono::DateTime<Utc>,
-    price: f64,
+/// Re-runs a stored backtest from its manifest, with the inputs pinned to the
+/// recorded sources, ranges and seed, and reports whether the inputs and the
+/// result came out the same. The re-run is not stored.
+async fn replay_backtest(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path((strategy_id, backtest_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<BacktestReplay>, StrategyApiError> {
+    let user_id = user.claims().user_id;
+    let Some(strategy) = state
+        .strategy_repo
+        .find_by_id(strategy_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    else {
+        return Err(StatusCode::NOT_FOUND.into());
+    };
+    let stored = state
+        .strategy_repo
+        .find_backtest(backtest_id, strategy_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let Some(recorded) = stored.manifest.clone() else {
+        return Err(StrategyApiError::InvalidParams(vec![FieldError::new(
+            "backtest",
+            "has no manifest; it ran before manifests were recorded",
+        )]));
+    };
+    let request: BacktestRequest = serde_json::from_value(recorded.request.clone())
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    // The manifest holds the params as run, overrides applied.
+    let replayed = Strategy {
+        r#type: recorded.strategy_type.clone(),
+        params: recorded.params.clone(),
+        version: stored.strategy_version.unwrap_or(strategy.version),
+        ..strategy
+    };
+    let inputs = backtest::replay_inputs(&state, &replayed, &request, &recorded).await?;
+    let run = {
+        let state = state.clone();
+        async move { backtest::run(&state, replayed, inputs, &request, &CancelToken::new()).await }
+    };
+    let mut result = on_blocking_pool(run)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)??;
+    result.overrides = stored.overrides.clone();
+    let Some(actual) = result.manifest.as_ref() else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    };
+    let mismatches = manifest::compare(&stored, &recorded, &result, actual);
+    Ok(Json(BacktestReplay {
+        backtest_id,
+        reproduced: recorded.input_hash == actual.input_hash
+            && recorded.result_hash == actual.result_hash,
+        recorded_engine_version: recorded.engine_version.clone(),
+        engine_version: actual.engine_version.clone(),
+        mismatches,
+        result,
+    }))
 }
 
-async fn run_backtest(
+/// Queues a backtest for the worker pool and returns the job right away (202).
+async fn enqueue_backtest(
     State(state): State<AppState>,
     user: CurrentUser,
     Path(strategy_id): Path<Uuid>,
-    Json(payload): Json<BacktestPayload>,
-) -> Result<Json<BacktestResult>, StatusCode> {
+    Json(mut payload): Json<BacktestRequest>,
+) -> Result<(StatusCode, Json<BacktestJob>), StrategyApiError> {
     let Some(mut strategy) = state
         .strategy_repo
         .find_by_id(strategy_id, user.claims().user_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     else {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(StatusCode::NOT_FOUND.into());
     };
+    // Reject bad overrides now rather than as a failed job, and pin the
+    // version so later edits do not change what the job runs.
+    payload.validate()?;
+    backtest::prepare_strategy(&state, &mut strategy, &payload).await?;
+    let kind: StrategyKind = strategy.r#type.parse()?;
+    kind.params(&strategy.params)?;
+    payload.version = Some(strategy.version);
 
-    if let Some(short) = payload.short_window {
-        strategy.params["short_window"] = serde_json::json!(short);
-    }
-    if let Some(long) = payload.long_window {
-        strategy.params["long_window"] = serde_json::json!(long);
-    }
+    let request = serde_json::to_value(&payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let job = state
+        .strategy_repo
+        .enqueue_backtest(strategy_id, BacktestJobKind::Backtest, &request)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.backtest_jobs.notify_queued();
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
 
-    let prices: Vec<PricePoint> = if let Some(points) = payload.prices {
-        points
-            .into_iter()
-            .map(|p| PricePoint {
-                timestamp: p.timestamp,
-                price: p.price,
-            })
-            .collect()
-    } else {
-        let symbol = payload.symbol.as_deref().unwrap_or("ETH");
-        let days = payload.days.unwrap_or(30);
-        match load_prices_from_history(&state, symbol, days).await {
-            Ok(points) if !points.is_empty() => points,
-            Ok(_) => synthetic_prices(days),
-            Err(err) => {
-                tracing::warn!(%err, %symbol, days, "price history load failed, fallback to synthetic");
-                synthetic_prices(days)
-            }
-        }
+#[derive(Debug, Deserialize)]
+struct JobQuery {
+    strategy_id: Option<Uuid>,
+    status: Option<String>,
+    limit: Option<i64>,
+}
+
+async fn list_jobs(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Query(params): Query<JobQuery>,
+) -> Result<Json<Vec<BacktestJob>>, StatusCode> {
+    let status = match params.status.as_deref() {
+        Some(value) => Some(BacktestJobStatus::parse(value).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
     };
+    state
+        .strategy_repo
+        .list_backtest_jobs(
+            user.claims().user_id,
+            params.strategy_id,
+            status,
+            params.limit.unwrap_or(20).clamp(1, 100) as usize,
+        )
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
 
-    let mut result = state.strategy.backtest(strategy.clone(), prices).await;
-    if result.completed_at.is_none() {
-        result.completed_at = Some(Utc::now());
-    }
+async fn get_job(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<BacktestJob>, StatusCode> {
     state
         .strategy_repo
-        .save_backtest(&result)
+        .find_backtest_job(job_id, user.claims().user_id)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(Json(result))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
 }
 
-fn synthetic_prices(days: u32) -> Vec<PricePoint> {
-    let days = days.max(7);
-    let mut rng = rand::thread_rng();
-    let mut price = 100.0;
-    let now = Utc::now();
-    let mut points = Vec::with_capacity(days as usize);
-    for i in (0..days).rev() {
-        let ts = now - chrono::Duration::days(i.into());
-        let drift = 0.0015;
-        let noise: f64 = rng.gen_range(-0.01..0.01);
-        price *= 1.0 + drift + noise;
-        points.push(PricePoint {
-            timestamp: ts,
-            price: (price * 100.0).round() / 100.0,
-        });
+/// Cancels a queued or running job; 409 if it already finished.
+async fn cancel_job(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<BacktestJob>, StatusCode> {
+    let user_id = user.claims().user_id;
+    let cancelled = state
+        .strategy_repo
+        .cancel_backtest(job_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if cancelled {
+        state.backtest_jobs.interrupt(job_id).await;
+    }
+    let job = state
+        .strategy_repo
+        .find_backtest_job(job_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if cancelled {
+        Ok(Json(job))
+    } else {
+        Err(StatusCode::CONFLICT)
     }
-    points
+}
+
+/// Queues a parameter search as an `optimize` job; poll it like a backtest
+/// job, the ranked trials arrive in its `optimization`.
+async fn run_optimization(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(strategy_id): Path<Uuid>,
+    Json(mut payload): Json<OptimizeRequest>,
+) -> Result<(StatusCode, Json<BacktestJob>), StrategyApiError> {
+    let Some(mut strategy) = state
+        .strategy_repo
+        .find_by_id(strategy_id, user.claims().user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    else {
+        return Err(StatusCode::NOT_FOUND.into());
+    };
+    // Reject a bad search space now rather than as a failed job, and pin the
+    // version like backtest jobs do.
+    let version = BacktestRequest {
+        version: payload.version,
+        ..BacktestRequest::default()
+    };
+    backtest::prepare_strategy(&state, &mut strategy, &version).await?;
+    let kind: StrategyKind = strategy.r#type.parse()?;
+    payload.search.validate(kind)?;
+    payload.version = Some(strategy.version);
+    payload.seed = Some(payload.seed.unwrap_or_else(rand::random));
+
+    let request = serde_json::to_value(&payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let job = state
+        .strategy_repo
+        .enqueue_backtest(strategy_id, BacktestJobKind::Optimize, &request)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.backtest_jobs.notify_queued();
+    Ok((StatusCode::ACCEPTED, Json(job)))
 }
 
 #[derive(Debug, serde::Deserialize)]
 struct BacktestQuery {
     limit: Option<i64>,
+    /// Only backtests of this strategy version.
+    version: Option<i32>,
 }
 
 async fn list_backtests(
@@ -177,6 +523,7 @@ async fn list_backtests(
         .list_backtests(
             strategy_id,
             user.claims().user_id,
+            params.version,
             params.limit.unwrap_or(5).clamp(1, 20) as usize,
         )
         .await
@@ -200,3 +547,250 @@ async fn delete_strategy(
         Err(StatusCode::NOT_FOUND)
     }
 }
+
+#[derive(Debug, Deserialize)]
+struct ActivatePaperRequest {
+    symbol: Option<String>,
+    chain_id: Option<u64>,
+    /// Version to run; defaults to the latest.
+    version: Option<i32>,
+    initial_equity: Option<f64>,
+}
+
+/// Starts paper trading a single-series strategy on `symbol` (201). Stopped
+/// accounts start over; an active one is a conflict. Signal flips are alerted
+/// through a `strategy_signal` rule created for the account.
+async fn activate_paper(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(strategy_id): Path<Uuid>,
+    Json(payload): Json<ActivatePaperRequest>,
+) -> Result<(StatusCode, Json<PaperAccount>), StrategyApiError> {
+    let claims = user.claims();
+    let Some(mut strategy) = state
+        .strategy_repo
+        .find_by_id(strategy_id, claims.user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    else {
+        return Err(StatusCode::NOT_FOUND.into());
+    };
+    let request = BacktestRequest {
+        version: payload.version,
+        ..BacktestRequest::default()
+    };
+    backtest::prepare_strategy(&state, &mut strategy, &request).await?;
+    let kind: StrategyKind = strategy.r#type.parse()?;
+    if kind.is_multi_asset() {
+        return Err(StrategyApiError::InvalidParams(vec![FieldError::new(
+            "type",
+            format!("{kind} needs one price series per symbol; paper trading follows one"),
+        )]));
+    }
+    let initial_equity = payload.initial_equity.unwrap_or(1.0);
+    if !(initial_equity.is_finite() && initial_equity > 0.0) {
+        return Err(StrategyApiError::InvalidParams(vec![FieldError::new(
+            "initial_equity",
+            "must be positive",
+        )]));
+    }
+
+    let wallets = state
+        .wallet_repo
+        .list_by_user(claims.user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let Some(wallet) = wallets
+        .iter()
+        .find(|w| w.id == claims.wallet_id)
+        .or(wallets.first())
+    else {
+        return Err(StrategyApiError::InvalidParams(vec![FieldError::new(
+            "wallet",
+            "signal alerts need a wallet; add one first",
+        )]));
+    };
+
+    let existing = state
+        .strategy_repo
+        .find_paper(strategy_id, claims.user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if existing.as_ref().is_some_and(|a| a.status == "active") {
+        return Err(StatusCode::CONFLICT.into());
+    }
+    let rules = state
+        .alert_repo
+        .list_rules(claims.user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let alert_rule_id = match existing
+        .and_then(|a| a.alert_rule_id)
+        .filter(|id| rules.iter().any(|r| r.id == *id))
+    {
+        Some(id) => id,
+        None => {
+            let rule = AlertRule {
+                id: Uuid::new_v4(),
+                user_id: claims.user_id,
+                r#type: "strategy_signal".to_string(),
+                threshold: 0.0,
+                enabled: true,
+                cooldown_secs: 0,
+            };
+            state
+                .alert_repo
+                .create_rule(&rule)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            rule.id
+        }
+    };
+
+    let now = Utc::now();
+    let account = PaperAccount {
+        strategy_id,
+        status: "active".to_string(),
+        symbol: payload
+            .symbol
+            .unwrap_or_else(|| "ETH".to_string())
+            .to_uppercase(),
+        chain_id: payload.chain_id.unwrap_or(1),
+        strategy_version: strategy.version,
+        wallet_id: wallet.id,
+        alert_rule_id: Some(alert_rule_id),
+        initial_equity,
+        equity: initial_equity,
+        position: 0.0,
+        last_price: None,
+        last_price_ts: None,
+        replay_from: None,
+        activated_at: now,
+        stopped_at: None,
+        updated_at: now,
+    };
+    let account = state
+        .strategy_repo
+        .activate_paper(&account)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::CONFLICT)?;
+    Ok((StatusCode::CREATED, Json(account)))
+}
+
+#[derive(Debug, Deserialize)]
+struct PaperQuery {
+    limit: Option<i64>,
+}
+
+/// The paper account with its equity curve and positions, scored like a backtest.
+async fn get_paper(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(strategy_id): Path<Uuid>,
+    Query(params): Query<PaperQuery>,
+) -> Result<Json<PaperTradingReport>, StatusCode> {
+    let user_id = user.claims().user_id;
+    let strategy = state
+        .strategy_repo
+        .find_by_id(strategy_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let account = state
+        .strategy_repo
+        .find_paper(strategy_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let limit = params.limit.unwrap_or(1_000).clamp(1, 10_000) as usize;
+    let points = state
+        .strategy_repo
+        .paper_equity(strategy_id, limit)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Start from the activation capital unless the curve was cut by `limit`.
+    let mut equity_curve = Vec::with_capacity(points.len() + 1);
+    if points.len() < limit {
+        equity_curve.push((account.activated_at, account.initial_equity));
+    }
+    equity_curve.extend(points.iter().map(|p| (p.timestamp, p.equity)));
+    let positions = points.iter().map(|p| (p.timestamp, p.position)).collect();
+    let version = state
+        .strategy_repo
+        .find_version(strategy_id, user_id, account.strategy_version)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let params = version.map_or(strategy.params, |v| v.params);
+    let metrics = metrics::build_metrics(
+        &equity_curve,
+        serde_json::json!({
+            "symbol": account.symbol,
+            "position": account.position,
+            "pnl": account.equity - account.initial_equity,
+        }),
+        &MetricsConfig::from_params(&params),
+    );
+    Ok(Json(PaperTradingReport {
+        account,
+        equity_curve,
+        positions,
+        metrics,
+    }))
+}
+
+async fn stop_paper(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(strategy_id): Path<Uuid>,
+) -> Result<Json<PaperAccount>, StatusCode> {
+    let user_id = user.claims().user_id;
+    if let Some(account) = state
+        .strategy_repo
+        .stop_paper(strategy_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Ok(Json(account));
+    }
+    match state
+        .strategy_repo
+        .find_paper(strategy_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        Some(_) => Err(StatusCode::CONFLICT),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Replays the strategy over a wallet's tracked daily holdings and compares it
+/// with the wallet's actual value. Nothing is stored.
+async fn run_what_if(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(strategy_id): Path<Uuid>,
+    Json(payload): Json<WhatIfRequest>,
+) -> Result<Json<WhatIfReport>, StrategyApiError> {
+    let user_id = user.claims().user_id;
+    let Some(strategy) = state
+        .strategy_repo
+        .find_by_id(strategy_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    else {
+        return Err(StatusCode::NOT_FOUND.into());
+    };
+    let wallet = state
+        .wallet_repo
+        .find_by_id(payload.wallet_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if wallet.user_id != user_id {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+    let report = whatif::run(&state, strategy, &payload).await?;
+    Ok(Json(report))
+}
@@ -6,8 +6,10 @@ use axum::{
     http::StatusCode,
     routing::{delete, get, post},
 };
-use domain::{CreateWalletRequest, WalletResponse};
+use domain::{CreateWalletRequest, Wallet, WalletBackfill, WalletResponse};
 use ethers::types::Address;
+use serde::Deserialize;
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::{auth_middleware::CurrentUser, state::AppState};
@@ -17,6 +19,15 @@ pub fn router() -> Router<AppState> {
         .route("/wallets", get(list_wallets).post(create_wallet))
         .route("/wallets/:wallet_id", delete(delete_wallet))
         .route("/wallets/:wallet_id/primary", post(set_primary_wallet))
+        .route(
+            "/wallets/:wallet_id/backfill",
+            get(get_backfill).post(request_backfill),
+        )
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BackfillRequest {
+    start_block: Option<u64>,
 }
 
 async fn list_wallets(
@@ -54,6 +65,19 @@ async fn create_wallet(
         .create_wallet(user.claims().user_id, &address, payload.chain_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if state.config.enable_backfill_worker {
+        if let Err(err) = state
+            .transaction_repo
+            .request_backfill(
+                wallet.id,
+                wallet.chain_id,
+                state.config.backfill_start_block as i64,
+            )
+            .await
+        {
+            warn!(error = %err, wallet_id = %wallet.id, "backfill request failed");
+        }
+    }
 
     Ok(Json(WalletResponse {
         id: wallet.id,
@@ -109,3 +133,65 @@ async fn set_primary_wallet(
         Err(StatusCode::NOT_FOUND)
     }
 }
+
+async fn owned_wallet(
+    state: &AppState,
+    user: &CurrentUser,
+    wallet_id: Uuid,
+) -> Result<Wallet, StatusCode> {
+    let wallet = state
+        .wallet_repo
+        .find_by_id(wallet_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if wallet.user_id != user.claims().user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(wallet)
+}
+
+/// Queues a historical backfill of the wallet from `start_block` (default
+/// `BACKFILL_START_BLOCK`); 409 while one is still pending or running.
+async fn request_backfill(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(wallet_id): Path<Uuid>,
+    payload: Option<Json<BackfillRequest>>,
+) -> Result<(StatusCode, Json<WalletBackfill>), StatusCode> {
+    let wallet = owned_wallet(&state, &user, wallet_id).await?;
+    let existing = state
+        .transaction_repo
+        .find_backfill(wallet.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if existing.is_some_and(|b| b.status == "pending" || b.status == "running") {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let start_block = payload
+        .and_then(|Json(body)| body.start_block)
+        .unwrap_or(state.config.backfill_start_block);
+    let start_block = i64::try_from(start_block).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let backfill = state
+        .transaction_repo
+        .request_backfill(wallet.id, wallet.chain_id, start_block)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::ACCEPTED, Json(backfill)))
+}
+
+async fn get_backfill(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(wallet_id): Path<Uuid>,
+) -> Result<Json<WalletBackfill>, StatusCode> {
+    let wallet = owned_wallet(&state, &user, wallet_id).await?;
+    state
+        .transaction_repo
+        .find_backfill(wallet.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
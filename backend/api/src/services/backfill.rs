@@ -0,0 +1,132 @@
+//! Historical portfolio backfill: daily token balances rebuilt from a
+//! wallet's stored ERC20 transfers, priced from `price_history` and written to
+//! `portfolio_daily_snapshots` for days the live sync never saw.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, Utc};
+use domain::{Position, Wallet, WalletTransaction};
+use tracing::warn;
+
+use crate::{
+    repositories::{PortfolioSnapshotRepository, PriceHistoryRepository, TransactionRepository},
+    services::portfolio::position_symbol,
+};
+
+/// Balances below this count as empty.
+const DUST: f64 = 1e-12;
+
+/// End-of-day token balances of `address` for every day from `from` to `to`,
+/// accumulated from zero over `transfers` (in chain order) and keyed by the
+/// transfer's asset symbol.
+///
+/// History before the first transfer is assumed empty, so balances are exact
+/// when the walk started at or before the wallet's first token transfer. A
+/// balance that would go negative (history started too late) is held at zero.
+pub fn daily_balances(
+    transfers: &[WalletTransaction],
+    address: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<(NaiveDate, BTreeMap<String, f64>)> {
+    let mut balances: BTreeMap<String, f64> = BTreeMap::new();
+    let mut pending = transfers.iter().peekable();
+    let mut days = Vec::new();
+    let mut day = from;
+    while day <= to {
+        while let Some(transfer) = pending.next_if(|t| t.block_timestamp.date_naive() <= day) {
+            let self_transfer = transfer.from_address.eq_ignore_ascii_case(address)
+                && transfer.to_address.eq_ignore_ascii_case(address);
+            if self_transfer {
+                continue;
+            }
+            let delta = match transfer.direction.as_str() {
+                "in" => transfer.amount,
+                "out" => -transfer.amount,
+                _ => 0.0,
+            };
+            let balance = balances.entry(transfer.asset_symbol.clone()).or_default();
+            *balance = (*balance + delta).max(0.0);
+        }
+        days.push((day, balances.clone()));
+        let Some(next) = day.succ_opt() else { break };
+        day = next;
+    }
+    days
+}
+
+/// Last instant of `day` in UTC.
+fn end_of_day(day: NaiveDate) -> DateTime<Utc> {
+    let midnight = day.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+    (midnight + ChronoDuration::days(1) - ChronoDuration::milliseconds(1)).and_utc()
+}
+
+/// The last recorded price of `symbol` at or before `at`, from the wallet
+/// chain's series or, when that has none, the chain-agnostic series that
+/// CoinGecko history is stored under (chain 0).
+pub async fn historical_price(
+    history_repo: &dyn PriceHistoryRepository,
+    symbol: &str,
+    chain_id: u64,
+    at: DateTime<Utc>,
+) -> Result<Option<f64>> {
+    let symbol = symbol.to_uppercase();
+    if let Some(price) = history_repo.price_at(&symbol, Some(chain_id), at).await? {
+        return Ok(Some(price));
+    }
+    if chain_id == 0 {
+        return Ok(None);
+    }
+    history_repo.price_at(&symbol, None, at).await
+}
+
+/// Rebuilds the wallet's daily balances from its stored transfers, from the
+/// day of the first transfer through `until`, and writes a daily snapshot for
+/// each day that has none yet. Each token is valued at its
+/// [`historical_price`] at the end of the day; a token with no price by then
+/// is listed at zero value. Only ERC20 balances are covered: native coin
+/// history is not in the Transfer logs.
+///
+/// Returns how many days were written.
+pub async fn fill_daily_snapshots(
+    tx_repo: &dyn TransactionRepository,
+    snapshot_repo: &dyn PortfolioSnapshotRepository,
+    history_repo: &dyn PriceHistoryRepository,
+    wallet: &Wallet,
+    until: NaiveDate,
+) -> Result<usize> {
+    let transfers = tx_repo.list_transactions(wallet.id).await?;
+    let Some(first) = transfers.first() else {
+        return Ok(0);
+    };
+    let from = first.block_timestamp.date_naive();
+
+    let mut filled = 0;
+    for (day, balances) in daily_balances(&transfers, &wallet.address, from, until) {
+        let at = end_of_day(day);
+        let mut positions = Vec::new();
+        for (symbol, amount) in balances {
+            if amount <= DUST {
+                continue;
+            }
+            let price = historical_price(history_repo, &symbol, wallet.chain_id, at).await?;
+            if price.is_none() {
+                warn!(wallet_id = %wallet.id, %symbol, %day, "no price history for backfill day");
+            }
+            positions.push(Position {
+                asset_symbol: position_symbol(wallet.chain_id, &symbol),
+                amount,
+                usd_value: amount * price.unwrap_or(0.0),
+            });
+        }
+        let total_usd_value = positions.iter().map(|p| p.usd_value).sum();
+        if snapshot_repo
+            .fill_daily_snapshot(wallet.id, day, total_usd_value, &positions)
+            .await?
+        {
+            filled += 1;
+        }
+    }
+    Ok(filled)
+}
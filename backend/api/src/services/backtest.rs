@@ -0,0 +1,775 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use domain::{BacktestManifest, BacktestResult, ManifestInput, Strategy};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use strategy_engine::{
+    candles, optimize::OptimizationRequest, resample, robustness, CancelToken, FieldError,
+    GapPolicy, MetricsConfig, PricePoint, PriceSeries, ResampleReport, RobustnessRequest,
+    StrategyError, StrategyKind, StrategyResult,
+};
+use uuid::Uuid;
+
+use crate::{
+    services::{history::load_prices_from_history, manifest},
+    state::AppState,
+};
+
+/// Why backtest inputs could not be resolved.
+#[derive(Debug, thiserror::Error)]
+pub enum InputError {
+    #[error(transparent)]
+    Invalid(#[from] StrategyError),
+    #[error("price storage error: {0}")]
+    Storage(#[from] anyhow::Error),
+    /// The engine run on the blocking pool panicked.
+    #[error("engine task failed: {0}")]
+    Engine(#[from] tokio::task::JoinError),
+}
+
+/// Backtest inputs shared by the synchronous endpoint and queued jobs; jobs
+/// store it verbatim so the worker can replay it later.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BacktestRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prices: Option<Vec<PriceInput>>,
+    /// Inline series per symbol for multi-asset kinds; symbols left out are
+    /// loaded from price history.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series: Option<BTreeMap<String, Vec<PriceInput>>>,
+    /// Uploaded dataset (see `POST /datasets`) to run on instead of price
+    /// history; `days` does not apply to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<String>,
+    /// Dataset per symbol for multi-asset kinds, below inline `series`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datasets: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short_window: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_window: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+    /// Compare against this symbol instead of buy-and-hold on the backtested series.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub benchmark_symbol: Option<String>,
+    /// Aggregate the prices into OHLCV candles this many seconds wide first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candle_secs: Option<u32>,
+    /// Resample into fixed bars this wide (`1m`, `1h`, `1d`, ...) before
+    /// backtesting; how much was filled is reported in `metrics.resampling`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_interval: Option<String>,
+    /// Treatment of bars without prices when `bar_interval` is set (default `ffill`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gap_policy: Option<GapPolicy>,
+    /// Strategy version to run; defaults to the latest. Queued jobs are pinned
+    /// to the version that was latest when they were enqueued.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+    /// Params to override for this run only, on top of the version's params.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Seed of the synthetic series used when price history is unavailable;
+    /// random when unset. The manifest of the result records it either way.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Resample the finished backtest and store the report with it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub robustness: Option<RobustnessRequest>,
+}
+
+/// Inputs of a queued parameter search: the single-series price sources of
+/// [`resolve_prices`] plus the search itself. Jobs store it verbatim.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizeRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prices: Option<Vec<PriceInput>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+    /// Strategy version searched; queued jobs are pinned to the version that
+    /// was latest when they were enqueued.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+    /// Seed of the synthetic series used when price history is unavailable;
+    /// drawn when the job is queued if unset, so the job keeps it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(flatten)]
+    pub search: OptimizationRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceInput {
+    pub timestamp: DateTime<Utc>,
+    /// Close of the bar.
+    pub price: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+}
+
+impl BacktestRequest {
+    /// Params this run overrides: `overrides` plus the `short_window` and
+    /// `long_window` shorthands, which win.
+    pub fn param_overrides(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut overrides = self.overrides.clone().unwrap_or_default();
+        if let Some(short) = self.short_window {
+            overrides.insert("short_window".to_string(), serde_json::json!(short));
+        }
+        if let Some(long) = self.long_window {
+            overrides.insert("long_window".to_string(), serde_json::json!(long));
+        }
+        overrides
+    }
+
+    /// Applies the overrides on top of the strategy params and returns them.
+    pub fn apply_overrides(
+        &self,
+        strategy: &mut Strategy,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        let overrides = self.param_overrides();
+        if !strategy.params.is_object() {
+            strategy.params = serde_json::json!({});
+        }
+        if let Some(params) = strategy.params.as_object_mut() {
+            for (key, value) in &overrides {
+                params.insert(key.clone(), value.clone());
+            }
+        }
+        overrides
+    }
+
+    /// Checks the request's own options before any prices are loaded.
+    pub fn validate(&self) -> StrategyResult<()> {
+        let mut errors = Vec::new();
+        if let Some(robustness) = &self.robustness {
+            if let Err(fields) = robustness.validate() {
+                errors.extend(fields);
+            }
+        }
+        match &self.bar_interval {
+            Some(interval) if resample::parse_interval(interval).is_none() => {
+                errors.push(FieldError::new(
+                    "bar_interval",
+                    "must be a count and unit such as 1m, 1h or 1d",
+                ));
+            }
+            Some(_) if self.candle_secs.is_some() => {
+                errors.push(FieldError::new(
+                    "candle_secs",
+                    "cannot be combined with bar_interval",
+                ));
+            }
+            Some(_) => {}
+            None if self.gap_policy.is_some() => {
+                errors.push(FieldError::new("gap_policy", "requires bar_interval"));
+            }
+            None => {}
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(StrategyError::InvalidParams(errors))
+        }
+    }
+
+    fn bar_interval_secs(&self) -> Option<i64> {
+        self.bar_interval
+            .as_deref()
+            .and_then(resample::parse_interval)
+    }
+}
+
+/// Swaps `strategy` (as stored, i.e. its latest version) for the version
+/// `request` asks for and applies the request's overrides, which must be
+/// params the strategy's kind knows. Returns the overrides so the result can
+/// record them.
+pub async fn prepare_strategy(
+    state: &AppState,
+    strategy: &mut Strategy,
+    request: &BacktestRequest,
+) -> Result<serde_json::Map<String, serde_json::Value>, InputError> {
+    if let Some(version) = request.version.filter(|v| *v != strategy.version) {
+        let Some(stored) = state
+            .strategy_repo
+            .find_version(strategy.id, strategy.user_id, version)
+            .await?
+        else {
+            return Err(StrategyError::InvalidParams(vec![FieldError::new(
+                "version",
+                format!("strategy has no version {version}"),
+            )])
+            .into());
+        };
+        strategy.name = stored.name;
+        strategy.r#type = stored.r#type;
+        strategy.params = stored.params;
+        strategy.version = stored.version;
+    }
+    let overrides = request.apply_overrides(strategy);
+    if !overrides.is_empty() {
+        let kind: StrategyKind = strategy.r#type.parse()?;
+        kind.validate_overrides(&strategy.params, &overrides)
+            .map_err(StrategyError::InvalidParams)?;
+    }
+    Ok(overrides)
+}
+
+/// Prices a backtest runs on: one series, or one per symbol for multi-asset kinds.
+pub enum BacktestPrices {
+    Single(Vec<PricePoint>),
+    Multi(PriceSeries),
+}
+
+/// Resolved prices plus what resampling did to them, if it ran, and where
+/// they came from for the result's manifest.
+pub struct BacktestInputs {
+    pub prices: BacktestPrices,
+    pub resampling: Option<ResampleReport>,
+    /// History of the request's `benchmark_symbol`, if it loaded.
+    pub benchmark: Option<BenchmarkInput>,
+    /// Series as loaded, before resampling, in load order.
+    pub sources: Vec<ManifestInput>,
+    pub seed: u64,
+}
+
+pub struct BenchmarkInput {
+    pub symbol: String,
+    pub points: Vec<PricePoint>,
+    pub source: ManifestInput,
+}
+
+impl BacktestInputs {
+    /// Hash of the series as the engine sees them, benchmark included.
+    fn hash(&self) -> String {
+        let mut series: Vec<(&str, &[PricePoint])> = match &self.prices {
+            BacktestPrices::Single(points) => vec![("", points.as_slice())],
+            BacktestPrices::Multi(series) => series
+                .iter()
+                .map(|(symbol, points)| (symbol.as_str(), points.as_slice()))
+                .collect(),
+        };
+        if let Some(benchmark) = &self.benchmark {
+            series.push(("benchmark", benchmark.points.as_slice()));
+        }
+        manifest::hash_series(series)
+    }
+}
+
+impl BacktestPrices {
+    fn first_timestamp(&self) -> Option<DateTime<Utc>> {
+        match self {
+            BacktestPrices::Single(points) => points.first().map(|p| p.timestamp),
+            BacktestPrices::Multi(series) => series
+                .values()
+                .filter_map(|points| points.first().map(|p| p.timestamp))
+                .min(),
+        }
+    }
+
+    fn resample(
+        self,
+        interval_secs: i64,
+        policy: GapPolicy,
+    ) -> StrategyResult<(Self, ResampleReport)> {
+        let mut report = ResampleReport::new(interval_secs, policy);
+        let prices = match self {
+            BacktestPrices::Single(points) => {
+                let (bars, stats) = resample::resample(&points, interval_secs, policy)?;
+                report.record(None, stats);
+                BacktestPrices::Single(bars)
+            }
+            BacktestPrices::Multi(series) => {
+                let mut resampled = PriceSeries::new();
+                for (symbol, points) in series {
+                    let (bars, stats) = resample::resample(&points, interval_secs, policy)
+                        .map_err(|err| with_symbol(err, &symbol))?;
+                    report.record(Some(&symbol), stats);
+                    resampled.insert(symbol, bars);
+                }
+                BacktestPrices::Multi(resampled)
+            }
+        };
+        Ok((prices, report))
+    }
+
+    fn into_candles(self, width: Duration) -> Self {
+        match self {
+            BacktestPrices::Single(points) => {
+                BacktestPrices::Single(candles::aggregate(&points, width))
+            }
+            BacktestPrices::Multi(series) => BacktestPrices::Multi(
+                series
+                    .into_iter()
+                    .map(|(symbol, points)| (symbol, candles::aggregate(&points, width)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Prefixes resampling errors of one series with its symbol.
+fn with_symbol(err: StrategyError, symbol: &str) -> StrategyError {
+    match err {
+        StrategyError::InvalidParams(fields) => StrategyError::InvalidParams(
+            fields
+                .into_iter()
+                .map(|f| FieldError::new(f.field, format!("{symbol}: {}", f.message)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Resolves the prices `strategy` needs: a series per symbol listed in the
+/// params of multi-asset kinds (inline `series` first, then `datasets`, then
+/// history), the single-series rules of [`resolve_prices`] otherwise, plus the
+/// `benchmark_symbol` history. With `bar_interval` the series are resampled
+/// into fixed bars, with `candle_secs` aggregated into candles of that width.
+pub async fn resolve_inputs(
+    state: &AppState,
+    strategy: &Strategy,
+    request: &BacktestRequest,
+) -> Result<BacktestInputs, InputError> {
+    let seed = request.seed.unwrap_or_else(rand::random);
+    let (prices, sources) = load_inputs(state, strategy, request, seed).await?;
+    let benchmark = match request.benchmark_symbol.as_deref() {
+        Some(symbol) => load_benchmark(state, symbol, prices.first_timestamp()).await,
+        None => None,
+    };
+    finish_inputs(request, prices, sources, benchmark, seed)
+}
+
+/// Reloads the series `manifest` records, pinned to their recorded sources,
+/// ranges and seeds instead of `days` counted back from now, and prepares
+/// them as `request` says. The sources describe what was found, so a replay
+/// can tell which series changed.
+pub async fn replay_inputs(
+    state: &AppState,
+    strategy: &Strategy,
+    request: &BacktestRequest,
+    manifest: &BacktestManifest,
+) -> Result<BacktestInputs, InputError> {
+    let mut loaded = Vec::with_capacity(manifest.inputs.len());
+    for input in &manifest.inputs {
+        let points = reload(state, strategy, request, input).await?;
+        let mut source = manifest::describe(input.symbol.as_deref(), &input.source, &points);
+        source.dataset = input.dataset.clone();
+        source.seed = input.seed;
+        loaded.push((points, source));
+    }
+    let sources = loaded.iter().map(|(_, source)| source.clone()).collect();
+    let prices = match series_symbols(strategy) {
+        Some(_) => BacktestPrices::Multi(
+            loaded
+                .into_iter()
+                .map(|(points, source)| (source.symbol.unwrap_or_default(), points))
+                .collect(),
+        ),
+        None => BacktestPrices::Single(
+            loaded
+                .into_iter()
+                .next()
+                .map(|(points, _)| points)
+                .unwrap_or_default(),
+        ),
+    };
+    let benchmark = match &manifest.benchmark {
+        Some(input) => {
+            let symbol = input.symbol.clone().unwrap_or_default();
+            let points = history_range(state, &symbol, input).await?;
+            let source = manifest::describe(Some(&symbol), &input.source, &points);
+            Some(BenchmarkInput {
+                symbol,
+                points,
+                source,
+            })
+        }
+        None => None,
+    };
+    finish_inputs(request, prices, sources, benchmark, manifest.seed)
+}
+
+/// Resamples or aggregates loaded prices as `request` asks.
+fn finish_inputs(
+    request: &BacktestRequest,
+    prices: BacktestPrices,
+    sources: Vec<ManifestInput>,
+    benchmark: Option<BenchmarkInput>,
+    seed: u64,
+) -> Result<BacktestInputs, InputError> {
+    let (prices, resampling) = match request.bar_interval_secs() {
+        Some(interval_secs) => {
+            let policy = request.gap_policy.unwrap_or_default();
+            let (prices, report) = prices.resample(interval_secs, policy)?;
+            (prices, Some(report))
+        }
+        None => match request.candle_secs.filter(|secs| *secs > 0) {
+            Some(secs) => (
+                prices.into_candles(Duration::seconds(i64::from(secs))),
+                None,
+            ),
+            None => (prices, None),
+        },
+    };
+    Ok(BacktestInputs {
+        prices,
+        resampling,
+        benchmark,
+        sources,
+        seed,
+    })
+}
+
+async fn load_inputs(
+    state: &AppState,
+    strategy: &Strategy,
+    request: &BacktestRequest,
+    seed: u64,
+) -> Result<(BacktestPrices, Vec<ManifestInput>), InputError> {
+    let Some(symbols) = series_symbols(strategy) else {
+        let (points, source) = resolve_prices(
+            state,
+            strategy.user_id,
+            request.prices.clone(),
+            request.dataset.as_deref(),
+            request.symbol.as_deref(),
+            request.days,
+            seed,
+        )
+        .await?;
+        return Ok((BacktestPrices::Single(points), vec![source]));
+    };
+    let mut inline = inline_series(request);
+    let datasets: BTreeMap<String, String> = request
+        .datasets
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(symbol, name)| (symbol.trim().to_uppercase(), name))
+        .collect();
+    let mut series = PriceSeries::new();
+    let mut sources = Vec::with_capacity(symbols.len());
+    for (i, symbol) in symbols.into_iter().enumerate() {
+        let points = inline.remove(&symbol);
+        let (points, source) = resolve_prices(
+            state,
+            strategy.user_id,
+            points,
+            datasets.get(&symbol).map(String::as_str),
+            Some(&symbol),
+            request.days,
+            seed.wrapping_add(i as u64),
+        )
+        .await?;
+        series.insert(symbol, points);
+        sources.push(source);
+    }
+    Ok((BacktestPrices::Multi(series), sources))
+}
+
+/// Inline `series` keyed by normalized symbol.
+fn inline_series(request: &BacktestRequest) -> BTreeMap<String, Vec<PriceInput>> {
+    request
+        .series
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(symbol, points)| (symbol.trim().to_uppercase(), points))
+        .collect()
+}
+
+/// Symbols of a multi-asset strategy. Invalid params yield an empty list so
+/// the engine reports them.
+fn series_symbols(strategy: &Strategy) -> Option<Vec<String>> {
+    let kind: StrategyKind = strategy.r#type.parse().ok()?;
+    if !kind.is_multi_asset() {
+        return None;
+    }
+    Some(
+        kind.params(&strategy.params)
+            .ok()
+            .and_then(|params| params.series_symbols())
+            .unwrap_or_default(),
+    )
+}
+
+/// Inline prices win, then `user_id`'s uploaded `dataset`; otherwise
+/// `symbol`/`days` come from price history, with a synthetic series generated
+/// from `seed` as the last resort. Also describes the series for the manifest.
+/// An unknown dataset is an invalid `dataset` param.
+pub async fn resolve_prices(
+    state: &AppState,
+    user_id: Uuid,
+    inline: Option<Vec<PriceInput>>,
+    dataset: Option<&str>,
+    symbol: Option<&str>,
+    days: Option<u32>,
+    seed: u64,
+) -> Result<(Vec<PricePoint>, ManifestInput), InputError> {
+    if let Some(points) = inline {
+        let points = from_inputs(points);
+        let source = manifest::describe(symbol, "inline", &points);
+        return Ok((points, source));
+    }
+    if let Some(name) = dataset {
+        let points = load_dataset(state, user_id, name).await?;
+        let mut source = manifest::describe(symbol, "dataset", &points);
+        source.dataset = Some(name.to_string());
+        return Ok((points, source));
+    }
+    let symbol = symbol.unwrap_or("ETH").to_uppercase();
+    let days = days.unwrap_or(30);
+    let history = match load_prices_from_history(state, &symbol, days).await {
+        Ok(points) if !points.is_empty() => Some(points),
+        Ok(_) => None,
+        Err(err) => {
+            tracing::warn!(%err, %symbol, days, "price history load failed, fallback to synthetic");
+            None
+        }
+    };
+    if let Some(points) = history {
+        let source = manifest::describe(Some(&symbol), "history", &points);
+        return Ok((points, source));
+    }
+    let points = synthetic_prices(days, seed, Utc::now());
+    let mut source = manifest::describe(Some(&symbol), "synthetic", &points);
+    source.seed = Some(seed);
+    Ok((points, source))
+}
+
+/// One recorded series again: inline prices from `request`, the dataset by
+/// name, history over the recorded range, synthetic prices from the recorded
+/// seed and end.
+async fn reload(
+    state: &AppState,
+    strategy: &Strategy,
+    request: &BacktestRequest,
+    input: &ManifestInput,
+) -> Result<Vec<PricePoint>, InputError> {
+    match input.source.as_str() {
+        "inline" => {
+            let points = match (&input.symbol, series_symbols(strategy)) {
+                (Some(symbol), Some(_)) => inline_series(request).remove(symbol),
+                _ => request.prices.clone(),
+            };
+            Ok(from_inputs(points.unwrap_or_default()))
+        }
+        "dataset" => {
+            let name = input.dataset.as_deref().unwrap_or_default();
+            load_dataset(state, strategy.user_id, name).await
+        }
+        "history" => history_range(state, input.symbol.as_deref().unwrap_or_default(), input).await,
+        "synthetic" => Ok(match (input.seed, input.to) {
+            (Some(seed), Some(end)) => synthetic_prices(input.points as u32, seed, end),
+            _ => Vec::new(),
+        }),
+        other => Err(anyhow::anyhow!("unknown manifest source {other}").into()),
+    }
+}
+
+/// Stored history of `symbol` between the recorded first and last timestamp,
+/// without fetching anything new.
+async fn history_range(
+    state: &AppState,
+    symbol: &str,
+    input: &ManifestInput,
+) -> Result<Vec<PricePoint>, InputError> {
+    let (Some(from), Some(to)) = (input.from, input.to) else {
+        return Ok(Vec::new());
+    };
+    let points = state
+        .price_history_repo
+        .fetch_range(symbol, None, from, to)
+        .await?;
+    Ok(points
+        .into_iter()
+        .map(|p| PricePoint::new(p.price_ts, p.price))
+        .collect())
+}
+
+fn from_inputs(points: Vec<PriceInput>) -> Vec<PricePoint> {
+    points
+        .into_iter()
+        .map(|p| PricePoint {
+            timestamp: p.timestamp,
+            price: p.price,
+            open: p.open,
+            high: p.high,
+            low: p.low,
+            volume: p.volume,
+        })
+        .collect()
+}
+
+/// All points of an uploaded dataset. Unlike history there is no synthetic
+/// fallback: a backtest asked for this data specifically.
+async fn load_dataset(
+    state: &AppState,
+    user_id: Uuid,
+    name: &str,
+) -> Result<Vec<PricePoint>, InputError> {
+    let Some(dataset) = state.price_history_repo.find_dataset(user_id, name).await? else {
+        return Err(StrategyError::InvalidParams(vec![FieldError::new(
+            "dataset",
+            format!("no dataset named {name}"),
+        )])
+        .into());
+    };
+    let points = state
+        .price_history_repo
+        .fetch_dataset_points(&dataset)
+        .await?;
+    Ok(points
+        .into_iter()
+        .map(|p| PricePoint {
+            timestamp: p.price_ts,
+            price: p.price,
+            open: p.open,
+            high: p.high,
+            low: p.low,
+            volume: p.volume,
+        })
+        .collect())
+}
+
+/// History of the benchmark symbol reaching back to the first backtested bar;
+/// `None` (keeping the default benchmark) when it is empty or fails to load.
+async fn load_benchmark(
+    state: &AppState,
+    symbol: &str,
+    first: Option<DateTime<Utc>>,
+) -> Option<BenchmarkInput> {
+    match load_prices_from_history(state, symbol, benchmark_days(first)).await {
+        Ok(points) if !points.is_empty() => {
+            let symbol = symbol.to_uppercase();
+            let source = manifest::describe(Some(&symbol), "history", &points);
+            Some(BenchmarkInput {
+                symbol,
+                points,
+                source,
+            })
+        }
+        Ok(_) => {
+            tracing::warn!(%symbol, "benchmark history empty, keeping default benchmark");
+            None
+        }
+        Err(err) => {
+            tracing::warn!(
+                %err,
+                %symbol,
+                "benchmark history load failed, keeping default benchmark"
+            );
+            None
+        }
+    }
+}
+
+/// Runs the strategy over `inputs`, swapping the default benchmark for the
+/// loaded benchmark history if there is one. With a `robustness` request the
+/// Monte Carlo report is attached to the result, and the bar resampling report
+/// of the inputs goes to `metrics.resampling`. The result records
+/// `strategy.version` and a manifest of inputs, params and `request`; callers
+/// add the overrides they applied. The engine and the robustness analysis
+/// stop with `StrategyError::Cancelled` once `cancel` fires.
+pub async fn run(
+    state: &AppState,
+    strategy: Strategy,
+    inputs: BacktestInputs,
+    request: &BacktestRequest,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let input_hash = inputs.hash();
+    let BacktestInputs {
+        prices,
+        resampling,
+        benchmark,
+        sources,
+        seed,
+    } = inputs;
+    let config = MetricsConfig::from_params(&strategy.params);
+    let mut result = match prices {
+        BacktestPrices::Single(points) => {
+            state
+                .strategy
+                .backtest_cancellable(&strategy, &points, cancel)
+                .await?
+        }
+        BacktestPrices::Multi(series) => {
+            state
+                .strategy
+                .backtest_multi_cancellable(&strategy, &series, cancel)
+                .await?
+        }
+    };
+    if let Some(benchmark) = &benchmark {
+        strategy_engine::benchmark::attach(
+            &mut result,
+            &benchmark.points,
+            &benchmark.symbol,
+            &config,
+        );
+    }
+    if let (Some(report), Some(metrics)) = (resampling, result.metrics.as_object_mut()) {
+        metrics.insert(
+            "resampling".to_string(),
+            serde_json::to_value(report).unwrap_or_default(),
+        );
+    }
+    if let Some(robustness) = &request.robustness {
+        result.robustness = Some(robustness::analyze(&result, robustness, &config, cancel)?);
+    }
+    result.strategy_version = Some(strategy.version);
+    result.manifest = Some(BacktestManifest {
+        input_hash,
+        result_hash: manifest::hash_result(&result),
+        inputs: sources,
+        benchmark: benchmark.map(|benchmark| benchmark.source),
+        seed,
+        engine_version: strategy_engine::ENGINE_VERSION.to_string(),
+        strategy_type: strategy.r#type,
+        params: strategy.params,
+        request: serde_json::to_value(request).unwrap_or_default(),
+    });
+    if result.completed_at.is_none() {
+        result.completed_at = Some(Utc::now());
+    }
+    Ok(result)
+}
+
+/// Days of benchmark history (counted back from now) needed to reach the first backtested bar.
+fn benchmark_days(first: Option<DateTime<Utc>>) -> u32 {
+    first
+        .map(|first| ((Utc::now() - first).num_days() + 1).max(1) as u32)
+        .unwrap_or(30)
+}
+
+/// A daily random walk of `days` (at least 7) bars ending at `end`; the same
+/// seed and end give the same series.
+fn synthetic_prices(days: u32, seed: u64, end: DateTime<Utc>) -> Vec<PricePoint> {
+    let days = days.max(7);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut price = 100.0;
+    let mut points = Vec::with_capacity(days as usize);
+    for i in (0..days).rev() {
+        let ts = end - chrono::Duration::days(i.into());
+        let drift = 0.0015;
+        let noise: f64 = rng.gen_range(-0.01..0.01);
+        price *= 1.0 + drift + noise;
+        points.push(PricePoint::new(ts, (price * 100.0).round() / 100.0));
+    }
+    points
+}
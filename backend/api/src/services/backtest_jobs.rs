@@ -0,0 +1,372 @@
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use domain::{BacktestJob, BacktestJobKind, BacktestResult, ManifestInput, Strategy};
+use serde::Serialize;
+use strategy_engine::{
+    optimize::{self, OptimizationResult},
+    CancelToken, StrategyError, StrategyResult,
+};
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinError;
+use tokio::time::sleep;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    services::backtest::{self, BacktestRequest, OptimizeRequest},
+    state::AppState,
+};
+
+/// Progress reported once the job request is parsed and overrides applied.
+const PROGRESS_PREPARED: f64 = 0.1;
+/// Progress reported once prices are loaded (possibly from CoinGecko).
+const PROGRESS_PRICES: f64 = 0.4;
+/// Progress reported once the engine and benchmark have finished.
+const PROGRESS_COMPUTED: f64 = 0.9;
+/// How long a claimed job stays ours without a renewal.
+const DEFAULT_LEASE: Duration = Duration::from_secs(60);
+
+/// Pool of workers draining queued backtests and optimizations from
+/// `strategy_backtests`.
+///
+/// Jobs are claimed with `FOR UPDATE SKIP LOCKED`, so several workers (or API
+/// processes) can share the queue. A claimed job holds a lease its worker
+/// renews every third of the lease; jobs whose lease ran out (their process
+/// died) are requeued by whichever pool notices first. The database status is
+/// the source of truth for cancellation: a failed renewal interrupts the job,
+/// and jobs running in this process are also interrupted right away.
+pub struct BacktestWorkerPool {
+    workers: usize,
+    poll_interval: Duration,
+    lease: Duration,
+    wake: Notify,
+    running: Mutex<HashMap<Uuid, Interrupt>>,
+}
+
+/// Stops one running job: the engine polls `token` between bars and trials,
+/// and `wake` ends the waits (e.g. a price download) in between.
+#[derive(Clone, Default)]
+struct Interrupt {
+    token: CancelToken,
+    wake: Arc<Notify>,
+}
+
+impl Interrupt {
+    fn fire(&self) {
+        self.token.cancel();
+        self.wake.notify_one();
+    }
+}
+
+impl BacktestWorkerPool {
+    pub fn new(workers: usize, poll_interval: Duration) -> Self {
+        Self {
+            workers,
+            poll_interval,
+            lease: DEFAULT_LEASE,
+            wake: Notify::new(),
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Lease held on a claimed job between renewals (default 60s).
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease.max(Duration::from_secs(1));
+        self
+    }
+
+    /// Wakes an idle worker after a job was queued.
+    pub fn notify_queued(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Stops a job running in this process after it was cancelled in the database.
+    pub async fn interrupt(&self, job_id: Uuid) {
+        if let Some(interrupt) = self.running.lock().await.get(&job_id) {
+            interrupt.fire();
+        }
+    }
+
+    /// Starts the workers, plus a sweep that requeues jobs whose lease expired
+    /// once per lease period.
+    pub fn spawn(self: Arc<Self>, state: AppState) {
+        if self.workers == 0 {
+            return;
+        }
+        for worker in 0..self.workers {
+            let pool = self.clone();
+            let state = state.clone();
+            tokio::spawn(async move { pool.work(worker, state).await });
+        }
+        tokio::spawn(async move {
+            loop {
+                match state.strategy_repo.requeue_expired_backtests().await {
+                    Ok(0) => {}
+                    Ok(count) => {
+                        info!(count, "requeued backtest jobs with expired leases");
+                        self.wake.notify_one();
+                    }
+                    Err(err) => warn!(error = %err, "failed to requeue expired backtest jobs"),
+                }
+                sleep(self.lease).await;
+            }
+        });
+    }
+
+    async fn work(&self, worker: usize, state: AppState) {
+        loop {
+            match self.run_next(&state).await {
+                Ok(true) => continue,
+                Ok(false) => {
+                    tokio::select! {
+                        _ = self.wake.notified() => {}
+                        _ = sleep(self.poll_interval) => {}
+                    }
+                }
+                Err(err) => {
+                    warn!(worker, error = %err, "backtest worker run failed");
+                    sleep(self.poll_interval).await;
+                }
+            }
+        }
+    }
+
+    /// Runs the oldest queued job, if any. Returns whether a job was claimed.
+    async fn run_next(&self, state: &AppState) -> anyhow::Result<bool> {
+        let Some((job, strategy)) = state.strategy_repo.claim_backtest(self.lease).await? else {
+            return Ok(false);
+        };
+        let interrupt = Interrupt::default();
+        self.running.lock().await.insert(job.id, interrupt.clone());
+        let renewals = job.started_at.map(|started_at| {
+            tokio::spawn(heartbeat(
+                state.clone(),
+                job.id,
+                started_at,
+                self.lease,
+                interrupt.clone(),
+            ))
+        });
+        let outcome = execute(state, &job, strategy, &interrupt).await;
+        if let Some(renewals) = renewals {
+            renewals.abort();
+        }
+        self.running.lock().await.remove(&job.id);
+
+        match outcome {
+            Ok(Some(output)) => {
+                let stored = match &output {
+                    JobOutput::Backtest(result) => {
+                        state
+                            .strategy_repo
+                            .complete_backtest(job.id, result)
+                            .await?
+                    }
+                    JobOutput::Optimization(result) => {
+                        state
+                            .strategy_repo
+                            .complete_optimization(job.id, &serde_json::to_value(result)?)
+                            .await?
+                    }
+                };
+                if stored {
+                    info!(job_id = %job.id, "backtest job succeeded");
+                } else {
+                    info!(job_id = %job.id, "cancelled backtest job finished; result dropped");
+                }
+            }
+            Ok(None) => info!(job_id = %job.id, "backtest job cancelled"),
+            Err(message) => {
+                warn!(job_id = %job.id, error = %message, "backtest job failed");
+                state.strategy_repo.fail_backtest(job.id, &message).await?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Renews the lease of the run that started at `started_at` until aborted, and
+/// interrupts it once a renewal finds the run over (e.g. cancelled from
+/// another process, or requeued after this one stalled past the lease).
+async fn heartbeat(
+    state: AppState,
+    job_id: Uuid,
+    started_at: DateTime<Utc>,
+    lease: Duration,
+    interrupt: Interrupt,
+) {
+    loop {
+        sleep(lease / 3).await;
+        match state
+            .strategy_repo
+            .renew_backtest_lease(job_id, started_at, lease)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                interrupt.fire();
+                return;
+            }
+            Err(err) => warn!(%job_id, error = %err, "backtest lease renewal failed"),
+        }
+    }
+}
+
+/// What a succeeded job stores.
+enum JobOutput {
+    Backtest(Box<BacktestResult>),
+    Optimization(Box<StoredOptimization>),
+}
+
+/// A finished search with the series it ran on, so it can be repeated on the
+/// same prices.
+#[derive(Serialize)]
+struct StoredOptimization {
+    #[serde(flatten)]
+    result: OptimizationResult,
+    input: ManifestInput,
+}
+
+/// Runs one claimed job. `Ok(None)` means it was cancelled along the way; errors
+/// are the message stored on the failed job.
+async fn execute(
+    state: &AppState,
+    job: &BacktestJob,
+    strategy: Strategy,
+    interrupt: &Interrupt,
+) -> Result<Option<JobOutput>, String> {
+    match job.kind {
+        BacktestJobKind::Backtest => Ok(run_backtest(state, job, strategy, interrupt)
+            .await?
+            .map(|result| JobOutput::Backtest(Box::new(result)))),
+        BacktestJobKind::Optimize => Ok(run_optimization(state, job, strategy, interrupt)
+            .await?
+            .map(|result| JobOutput::Optimization(Box::new(result)))),
+    }
+}
+
+async fn run_backtest(
+    state: &AppState,
+    job: &BacktestJob,
+    mut strategy: Strategy,
+    interrupt: &Interrupt,
+) -> Result<Option<BacktestResult>, String> {
+    let request: BacktestRequest = serde_json::from_value(job.request.clone())
+        .map_err(|err| format!("invalid job request: {err}"))?;
+    let overrides = backtest::prepare_strategy(state, &mut strategy, &request)
+        .await
+        .map_err(|err| err.to_string())?;
+    if !report(state, job.id, PROGRESS_PREPARED).await? {
+        return Ok(None);
+    }
+
+    let prices = tokio::select! {
+        prices = backtest::resolve_inputs(state, &strategy, &request) => {
+            prices.map_err(|err| err.to_string())?
+        }
+        _ = interrupt.wake.notified() => return Ok(None),
+    };
+    if !report(state, job.id, PROGRESS_PRICES).await? {
+        return Ok(None);
+    }
+
+    let run = {
+        let state = state.clone();
+        let token = interrupt.token.clone();
+        async move { backtest::run(&state, strategy, prices, &request, &token).await }
+    };
+    let Some(mut result) = job_outcome(on_blocking_pool(run).await)? else {
+        return Ok(None);
+    };
+    if !report(state, job.id, PROGRESS_COMPUTED).await? {
+        return Ok(None);
+    }
+    result.overrides = overrides;
+    Ok(Some(result))
+}
+
+async fn run_optimization(
+    state: &AppState,
+    job: &BacktestJob,
+    mut strategy: Strategy,
+    interrupt: &Interrupt,
+) -> Result<Option<StoredOptimization>, String> {
+    let request: OptimizeRequest = serde_json::from_value(job.request.clone())
+        .map_err(|err| format!("invalid job request: {err}"))?;
+    let pinned = BacktestRequest {
+        version: request.version,
+        ..BacktestRequest::default()
+    };
+    backtest::prepare_strategy(state, &mut strategy, &pinned)
+        .await
+        .map_err(|err| err.to_string())?;
+    if !report(state, job.id, PROGRESS_PREPARED).await? {
+        return Ok(None);
+    }
+
+    // Jobs queued before the seed was drawn at enqueue time have none.
+    let seed = request.seed.unwrap_or_else(rand::random);
+    let (prices, input) = tokio::select! {
+        prices = backtest::resolve_prices(
+            state,
+            strategy.user_id,
+            request.prices.clone(),
+            request.dataset.as_deref(),
+            request.symbol.as_deref(),
+            request.days,
+            seed,
+        ) => prices.map_err(|err| err.to_string())?,
+        _ = interrupt.wake.notified() => return Ok(None),
+    };
+    if !report(state, job.id, PROGRESS_PRICES).await? {
+        return Ok(None);
+    }
+
+    let run = {
+        let engine = state.strategy.clone();
+        let token = interrupt.token.clone();
+        async move {
+            optimize::optimize(engine.as_ref(), &strategy, &prices, &request.search, &token).await
+        }
+    };
+    let Some(result) = job_outcome(on_blocking_pool(run).await)? else {
+        return Ok(None);
+    };
+    if !report(state, job.id, PROGRESS_COMPUTED).await? {
+        return Ok(None);
+    }
+    Ok(Some(StoredOptimization { result, input }))
+}
+
+/// Drives an engine run on the blocking pool: it is CPU-bound and would stall
+/// a runtime worker. It cannot be aborted from outside; it polls its cancel
+/// token between bars and trials. `Err` means the run panicked.
+pub(crate) async fn on_blocking_pool<T, F>(run: F) -> Result<T, JoinError>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let runtime = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || runtime.block_on(run)).await
+}
+
+/// A job's view of an engine run: a cancelled run is `Ok(None)` and errors
+/// are the failed job's message.
+fn job_outcome<T>(outcome: Result<StrategyResult<T>, JoinError>) -> Result<Option<T>, String> {
+    match outcome {
+        Ok(Ok(output)) => Ok(Some(output)),
+        Ok(Err(StrategyError::Cancelled)) => Ok(None),
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(err) => Err(format!("backtest task failed: {err}")),
+    }
+}
+
+/// Records progress; `false` once the job is no longer running.
+async fn report(state: &AppState, job_id: Uuid, progress: f64) -> Result<bool, String> {
+    state
+        .strategy_repo
+        .update_backtest_progress(job_id, progress)
+        .await
+        .map_err(|err| format!("progress update failed: {err}"))
+}
@@ -0,0 +1,448 @@
+//! Parsing of uploaded price files. CSV and JSON uploads are both read into a
+//! table of named columns first, so column mapping and validation are shared.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use strategy_engine::{FieldError, PricePoint};
+
+/// Row errors reported per upload; the rest are summarized.
+pub const MAX_ROW_ERRORS: usize = 20;
+
+/// Canonical fields and the header names recognized for them without an
+/// explicit mapping (compared case-insensitively).
+const FIELDS: [(&str, &[&str]); 6] = [
+    (
+        "timestamp",
+        &["timestamp", "time", "date", "datetime", "ts"],
+    ),
+    ("open", &["open", "o"]),
+    ("high", &["high", "h"]),
+    ("low", &["low", "l"]),
+    ("close", &["close", "price", "c", "last"]),
+    ("volume", &["volume", "vol", "v"]),
+];
+
+/// Naive timestamp layouts tried after RFC 3339 and epoch numbers.
+const DATETIME_FORMATS: [&str; 5] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+    "%Y/%m/%d %H:%M:%S",
+];
+const DATE_FORMATS: [&str; 2] = ["%Y-%m-%d", "%Y/%m/%d"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadFormat {
+    Csv,
+    Json,
+}
+
+impl UploadFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "csv" => Some(UploadFormat::Csv),
+            "json" => Some(UploadFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Guesses from the file extension, then from the first non-blank character.
+    pub fn detect(file_name: Option<&str>, body: &str) -> Self {
+        if let Some(format) = file_name
+            .and_then(|name| name.rsplit_once('.'))
+            .and_then(|(_, ext)| Self::parse(ext))
+        {
+            return format;
+        }
+        match body.trim_start().chars().next() {
+            Some('[') | Some('{') => UploadFormat::Json,
+            _ => UploadFormat::Csv,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Detected from the file when `None`.
+    pub format: Option<UploadFormat>,
+    /// Offset of timestamps that carry none.
+    pub timezone: FixedOffset,
+    /// chrono format tried before the built-in layouts for naive timestamps.
+    pub timestamp_format: Option<String>,
+    /// Canonical field (`timestamp`, `open`, `high`, `low`, `close`/`price`,
+    /// `volume`) to the column holding it.
+    pub columns: BTreeMap<String, String>,
+    pub delimiter: char,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            timezone: FixedOffset::east_opt(0).expect("zero offset"),
+            timestamp_format: None,
+            columns: BTreeMap::new(),
+            delimiter: ',',
+        }
+    }
+}
+
+/// Reads `UTC`/`Z` or a fixed offset such as `+08:00`, `-0500` or `+8`.
+pub fn parse_timezone(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("utc") || value.eq_ignore_ascii_case("z") {
+        return FixedOffset::east_opt(0);
+    }
+    let value = value
+        .strip_prefix("UTC")
+        .or_else(|| value.strip_prefix("GMT"))
+        .unwrap_or(value);
+    let (sign, rest) = match value.chars().next()? {
+        '+' => (1, &value[1..]),
+        '-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parses an upload into points sorted by time. Timestamps must be strictly
+/// increasing: duplicates and rows going back in time are reported, not
+/// silently reordered.
+pub fn parse_upload(
+    body: &str,
+    options: &ParseOptions,
+) -> Result<Vec<PricePoint>, Vec<FieldError>> {
+    let format = options
+        .format
+        .unwrap_or_else(|| UploadFormat::detect(None, body));
+    let table = match format {
+        UploadFormat::Csv => csv_table(body, options.delimiter)?,
+        UploadFormat::Json => json_table(body)?,
+    };
+    let columns = resolve_columns(&table.headers, &options.columns)?;
+
+    let mut errors = Errors::default();
+    let mut points: Vec<(usize, PricePoint)> = Vec::with_capacity(table.rows.len());
+    for (row, cells) in &table.rows {
+        match parse_row(cells, &columns, options) {
+            Ok(point) => points.push((*row, point)),
+            Err(message) => errors.push(*row, message),
+        }
+    }
+    for pair in points.windows(2) {
+        let ((prev_row, prev), (row, point)) = (&pair[0], &pair[1]);
+        if point.timestamp == prev.timestamp {
+            errors.push(
+                *row,
+                format!(
+                    "duplicate timestamp {} (also row {prev_row})",
+                    point.timestamp
+                ),
+            );
+        } else if point.timestamp < prev.timestamp {
+            errors.push(
+                *row,
+                format!("timestamp {} is before row {prev_row}", point.timestamp),
+            );
+        }
+    }
+    if points.is_empty() && errors.is_empty() {
+        errors
+            .fields
+            .push(FieldError::new("file", "contains no price rows"));
+    }
+    if !errors.is_empty() {
+        return Err(errors.finish());
+    }
+    Ok(points.into_iter().map(|(_, point)| point).collect())
+}
+
+/// Header names and rows keyed by their 1-based row number in the file (the
+/// CSV line, or the position in the JSON array).
+struct Table {
+    headers: Vec<String>,
+    rows: Vec<(usize, Vec<Option<String>>)>,
+}
+
+#[derive(Default)]
+struct Errors {
+    fields: Vec<FieldError>,
+    omitted: usize,
+}
+
+impl Errors {
+    fn push(&mut self, row: usize, message: impl Into<String>) {
+        if self.fields.len() < MAX_ROW_ERRORS {
+            self.fields
+                .push(FieldError::new(format!("row {row}"), message));
+        } else {
+            self.omitted += 1;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    fn finish(mut self) -> Vec<FieldError> {
+        if self.omitted > 0 {
+            self.fields.push(FieldError::new(
+                "rows",
+                format!("{} more errors omitted", self.omitted),
+            ));
+        }
+        self.fields
+    }
+}
+
+fn csv_table(body: &str, delimiter: char) -> Result<Table, Vec<FieldError>> {
+    let mut lines = body
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return Err(vec![FieldError::new("file", "is empty")]);
+    };
+    let headers: Vec<String> = split_csv_line(header, delimiter)
+        .into_iter()
+        .map(|h| h.trim().trim_start_matches('\u{feff}').to_string())
+        .collect();
+    let rows = lines
+        .map(|(row, line)| {
+            let cells = split_csv_line(line, delimiter)
+                .into_iter()
+                .map(|cell| Some(cell).filter(|c| !c.trim().is_empty()))
+                .collect();
+            (row, cells)
+        })
+        .collect();
+    Ok(Table { headers, rows })
+}
+
+/// Splits one CSV line, honouring double-quoted cells with `""` escapes.
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    cells
+}
+
+/// Accepts an array of objects, or an object holding one under `prices`.
+fn json_table(body: &str) -> Result<Table, Vec<FieldError>> {
+    let value: serde_json::Value = serde_json::from_str(body)
+        .map_err(|err| vec![FieldError::new("file", format!("invalid JSON: {err}"))])?;
+    let items = match value {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(mut obj) => match obj.remove("prices") {
+            Some(serde_json::Value::Array(items)) => items,
+            _ => {
+                return Err(vec![FieldError::new(
+                    "file",
+                    "expected an array of price objects or {\"prices\": [...]}",
+                )])
+            }
+        },
+        _ => {
+            return Err(vec![FieldError::new(
+                "file",
+                "expected an array of price objects",
+            )])
+        }
+    };
+    let mut headers: Vec<String> = Vec::new();
+    for item in &items {
+        if let Some(obj) = item.as_object() {
+            for key in obj.keys() {
+                if !headers.contains(key) {
+                    headers.push(key.clone());
+                }
+            }
+        }
+    }
+    let rows = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let cells = headers
+                .iter()
+                .map(|key| match item.get(key) {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(serde_json::Value::String(s)) => Some(s.clone()),
+                    Some(other) => Some(other.to_string()),
+                })
+                .collect();
+            (i + 1, cells)
+        })
+        .collect();
+    Ok(Table { headers, rows })
+}
+
+/// Column index per canonical field. `timestamp` and `close` are required.
+fn resolve_columns(
+    headers: &[String],
+    mapping: &BTreeMap<String, String>,
+) -> Result<BTreeMap<&'static str, usize>, Vec<FieldError>> {
+    let find = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(name.trim()))
+    };
+    let mut errors = Vec::new();
+    let mut mapped: BTreeMap<&'static str, &str> = BTreeMap::new();
+    for (key, column) in mapping {
+        let key = match key.to_ascii_lowercase().as_str() {
+            "price" => "close".to_string(),
+            other => other.to_string(),
+        };
+        match FIELDS.iter().find(|(field, _)| *field == key) {
+            Some((field, _)) => {
+                mapped.insert(field, column);
+            }
+            None => errors.push(FieldError::new(
+                format!("columns.{key}"),
+                "unknown field; expected timestamp, open, high, low, close or volume",
+            )),
+        }
+    }
+
+    let mut columns = BTreeMap::new();
+    for (field, aliases) in FIELDS {
+        let index = match mapped.get(field) {
+            Some(column) => match find(column) {
+                Some(index) => Some(index),
+                None => {
+                    errors.push(FieldError::new(
+                        format!("columns.{field}"),
+                        format!("no column named {column}"),
+                    ));
+                    continue;
+                }
+            },
+            None => aliases.iter().find_map(|alias| find(alias)),
+        };
+        match index {
+            Some(index) => {
+                columns.insert(field, index);
+            }
+            None if field == "timestamp" || field == "close" => errors.push(FieldError::new(
+                format!("columns.{field}"),
+                format!("no {field} column found; map one with `columns`"),
+            )),
+            None => {}
+        }
+    }
+    if errors.is_empty() {
+        Ok(columns)
+    } else {
+        Err(errors)
+    }
+}
+
+fn parse_row(
+    cells: &[Option<String>],
+    columns: &BTreeMap<&'static str, usize>,
+    options: &ParseOptions,
+) -> Result<PricePoint, String> {
+    let cell = |field: &str| {
+        columns
+            .get(field)
+            .and_then(|i| cells.get(*i))
+            .and_then(|c| c.as_deref())
+            .map(str::trim)
+    };
+    let number = |field: &str| -> Result<Option<f64>, String> {
+        match cell(field) {
+            None => Ok(None),
+            Some(raw) => match raw.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => Ok(Some(v)),
+                _ => Err(format!("{field} {raw:?} is not a non-negative number")),
+            },
+        }
+    };
+
+    let raw_ts = cell("timestamp").ok_or("missing timestamp")?;
+    let timestamp = parse_timestamp(raw_ts, options)
+        .ok_or_else(|| format!("unrecognized timestamp {raw_ts:?}"))?;
+    let price = number("close")?
+        .filter(|p| *p > 0.0)
+        .ok_or("close must be a positive number")?;
+    let (open, high, low, volume) = (
+        number("open")?,
+        number("high")?,
+        number("low")?,
+        number("volume")?,
+    );
+    if let (Some(high), Some(low)) = (high, low) {
+        if high < low {
+            return Err(format!("high {high} is below low {low}"));
+        }
+    }
+    Ok(PricePoint {
+        timestamp,
+        price,
+        open,
+        high,
+        low,
+        volume,
+    })
+}
+
+/// Epoch seconds or milliseconds, RFC 3339, then naive layouts read in
+/// `options.timezone`.
+fn parse_timestamp(raw: &str, options: &ParseOptions) -> Option<DateTime<Utc>> {
+    if let Ok(epoch) = raw.parse::<f64>() {
+        // Anything past 1e11 is too far out for seconds (year 5138).
+        let millis = if epoch.abs() >= 1e11 {
+            epoch
+        } else {
+            epoch * 1000.0
+        };
+        return DateTime::from_timestamp_millis(millis.round() as i64);
+    }
+    if let Ok(ts) = DateTime::parse_from_rfc3339(raw) {
+        return Some(ts.with_timezone(&Utc));
+    }
+    let custom = options.timestamp_format.as_deref();
+    let naive = custom
+        .into_iter()
+        .chain(DATETIME_FORMATS)
+        .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok())
+        .or_else(|| {
+            custom
+                .into_iter()
+                .chain(DATE_FORMATS)
+                .find_map(|format| NaiveDate::parse_from_str(raw, format).ok())
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })?;
+    options
+        .timezone
+        .from_local_datetime(&naive)
+        .single()
+        .map(|ts| ts.with_timezone(&Utc))
+}
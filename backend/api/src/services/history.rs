@@ -28,10 +28,7 @@ pub async fn load_prices_from_history(
         if covered {
             return Ok(cached
                 .into_iter()
-                .map(|p| PricePoint {
-                    timestamp: p.price_ts,
-                    price: p.price,
-                })
+                .map(|p| PricePoint::new(p.price_ts, p.price))
                 .collect());
         }
     }
@@ -47,6 +44,7 @@ pub async fn load_prices_from_history(
             price_ts: p.timestamp,
             source: "coingecko".to_string(),
             chain_id: None,
+            ..PriceHistoryPoint::default()
         })
         .collect();
     state.price_history_repo.upsert_points(&points).await?;
@@ -0,0 +1,165 @@
+//! `eth_getLogs` paging: block ranges are walked in per-chain chunks, and a
+//! range the provider refuses ("query returned more than N results", "block
+//! range too large") is split until it is accepted.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use ethers::{
+    providers::Middleware,
+    types::{BlockNumber, Filter, Log},
+};
+use tracing::info;
+
+/// Why a provider refused a log range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeRejection {
+    /// The range holds more logs than the provider returns at once; only that
+    /// range needs splitting.
+    TooManyResults,
+    /// The range spans more blocks than the provider serves; every later
+    /// range on the chain is kept below it.
+    RangeTooLarge,
+}
+
+impl RangeRejection {
+    /// Recognises the range errors of common RPC providers from their message.
+    pub fn classify(message: &str) -> Option<Self> {
+        let message = message.to_lowercase();
+        const TOO_MANY_RESULTS: [&str; 4] = [
+            "returned more than",
+            "too many results",
+            "response size",
+            "response is too big",
+        ];
+        const RANGE_TOO_LARGE: [&str; 6] = [
+            "block range",
+            "range too large",
+            "range is too large",
+            "range limit",
+            "is limited to",
+            "too many blocks",
+        ];
+        if TOO_MANY_RESULTS.iter().any(|p| message.contains(p)) {
+            Some(Self::TooManyResults)
+        } else if RANGE_TOO_LARGE.iter().any(|p| message.contains(p)) {
+            Some(Self::RangeTooLarge)
+        } else {
+            None
+        }
+    }
+}
+
+/// Last block of the range a provider suggests retrying with, e.g. Infura's
+/// "Try with this block range [0x10, 0x1f]", if it lies inside `from..to`.
+fn suggested_end(message: &str, from: u64, to: u64) -> Option<u64> {
+    let start = message.find('[')?;
+    let end = start + message[start..].find(']')?;
+    let (first, last) = message[start + 1..end].split_once(',')?;
+    let parse = |value: &str| {
+        let value = value.trim();
+        let hex = value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))?;
+        u64::from_str_radix(hex, 16).ok()
+    };
+    let (first, last) = (parse(first)?, parse(last)?);
+    (first == from && last >= from && last < to).then_some(last)
+}
+
+/// Pages `eth_getLogs` through block chunks sized per chain.
+///
+/// Every chain starts at its configured chunk size. A "block range too large"
+/// rejection lowers the chain's chunk for the rest of the process, while a
+/// "more than N results" rejection only splits the range that hit it, as it
+/// depends on how busy those blocks were.
+pub struct LogFetcher {
+    default_chunk: u64,
+    chunk_by_chain: Mutex<HashMap<u64, u64>>,
+}
+
+impl LogFetcher {
+    pub fn new(default_chunk: u64, chunk_by_chain: HashMap<u64, u64>) -> Self {
+        Self {
+            default_chunk: default_chunk.max(1),
+            chunk_by_chain: Mutex::new(
+                chunk_by_chain
+                    .into_iter()
+                    .map(|(chain_id, blocks)| (chain_id, blocks.max(1)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Blocks per `eth_getLogs` call on `chain_id`.
+    pub fn chunk_blocks(&self, chain_id: u64) -> u64 {
+        self.chunk_by_chain
+            .lock()
+            .expect("chunk lock poisoned")
+            .get(&chain_id)
+            .copied()
+            .unwrap_or(self.default_chunk)
+    }
+
+    /// Last block of the chunk starting at `from`, capped at `to`.
+    pub fn chunk_end(&self, chain_id: u64, from: u64, to: u64) -> u64 {
+        from.saturating_add(self.chunk_blocks(chain_id) - 1).min(to)
+    }
+
+    fn narrow(&self, chain_id: u64, blocks: u64) {
+        let mut chunks = self.chunk_by_chain.lock().expect("chunk lock poisoned");
+        let current = chunks.get(&chain_id).copied().unwrap_or(self.default_chunk);
+        if blocks < current {
+            info!(
+                chain_id,
+                from = current,
+                to = blocks,
+                "narrowing eth_getLogs range"
+            );
+            chunks.insert(chain_id, blocks.max(1));
+        }
+    }
+
+    /// Logs matching `filter` in blocks `from..=to`, oldest first. The range
+    /// is fetched as is (callers page it with [`Self::chunk_end`]) and split
+    /// in two, or where the provider suggests, whenever it is rejected as too
+    /// large; any other error, or a rejection of a single block, is returned.
+    pub async fn fetch<M: Middleware>(
+        &self,
+        provider: &M,
+        chain_id: u64,
+        filter: &Filter,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<Log>> {
+        let mut logs = Vec::new();
+        let mut pending = vec![(from, to)];
+        while let Some((lo, hi)) = pending.pop() {
+            let ranged = filter
+                .clone()
+                .from_block(BlockNumber::Number(lo.into()))
+                .to_block(BlockNumber::Number(hi.into()));
+            let err = match provider.get_logs(&ranged).await {
+                Ok(found) => {
+                    logs.extend(found);
+                    continue;
+                }
+                Err(err) => err,
+            };
+            let message = err.to_string();
+            let Some(rejection) = RangeRejection::classify(&message) else {
+                return Err(anyhow!("eth_getLogs {lo}..={hi} failed: {message}"));
+            };
+            if lo == hi {
+                return Err(anyhow!("eth_getLogs rejected single block {lo}: {message}"));
+            }
+            let mid = suggested_end(&message, lo, hi).unwrap_or(lo + (hi - lo) / 2);
+            if rejection == RangeRejection::RangeTooLarge {
+                self.narrow(chain_id, mid - lo + 1);
+            }
+            pending.push((mid + 1, hi));
+            pending.push((lo, mid));
+        }
+        Ok(logs)
+    }
+}
@@ -0,0 +1,154 @@
+//! Reproducibility manifests: hashes of backtest inputs and results, and the
+//! comparison a replay reports.
+
+use domain::{BacktestManifest, BacktestResult, ManifestInput, ReplayMismatch};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use strategy_engine::PricePoint;
+
+/// Describes a loaded series. `dataset` and `seed` are filled in by the
+/// sources that have them.
+pub fn describe(symbol: Option<&str>, source: &str, points: &[PricePoint]) -> ManifestInput {
+    ManifestInput {
+        symbol: symbol.map(str::to_string),
+        source: source.to_string(),
+        dataset: None,
+        seed: None,
+        from: points.first().map(|p| p.timestamp),
+        to: points.last().map(|p| p.timestamp),
+        points: points.len(),
+        hash: hash_series([("", points)]),
+    }
+}
+
+/// Hex SHA-256 over labelled series: every timestamp and OHLCV field, so any
+/// changed, added or missing bar changes it.
+pub fn hash_series<'a>(series: impl IntoIterator<Item = (&'a str, &'a [PricePoint])>) -> String {
+    let mut hasher = Sha256::new();
+    for (label, points) in series {
+        hasher.update((label.len() as u64).to_le_bytes());
+        hasher.update(label.as_bytes());
+        hasher.update((points.len() as u64).to_le_bytes());
+        for p in points {
+            hasher.update(p.timestamp.timestamp_micros().to_le_bytes());
+            hasher.update(p.price.to_bits().to_le_bytes());
+            for field in [p.open, p.high, p.low, p.volume] {
+                match field {
+                    Some(value) => {
+                        hasher.update([1]);
+                        hasher.update(value.to_bits().to_le_bytes());
+                    }
+                    None => hasher.update([0]),
+                }
+            }
+        }
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Hex SHA-256 of what the engine computed. Ids, completion time, overrides
+/// and the manifest itself are left out.
+pub fn hash_result(result: &BacktestResult) -> String {
+    let computed = json!({
+        "equity_curve": result.equity_curve,
+        "trades": result.trades,
+        "metrics": result.metrics,
+        "benchmark_curve": result.benchmark_curve,
+        "weights_history": result.weights_history,
+        "robustness": result.robustness,
+    });
+    hex::encode(Sha256::digest(computed.to_string().as_bytes()))
+}
+
+/// Differences between a stored result and its replay: inputs first, then the
+/// hashes, then each top-level metric that changed.
+pub fn compare(
+    expected: &BacktestResult,
+    expected_manifest: &BacktestManifest,
+    actual: &BacktestResult,
+    actual_manifest: &BacktestManifest,
+) -> Vec<ReplayMismatch> {
+    let mut mismatches = Vec::new();
+    let count = expected_manifest
+        .inputs
+        .len()
+        .max(actual_manifest.inputs.len());
+    for i in 0..count {
+        let (want, got) = (
+            expected_manifest.inputs.get(i),
+            actual_manifest.inputs.get(i),
+        );
+        if want.map(|input| &input.hash) != got.map(|input| &input.hash) {
+            let symbol = want.or(got).and_then(|input| input.symbol.as_deref());
+            mismatches.push(mismatch(
+                format!("inputs[{}]", symbol.unwrap_or("prices")),
+                summary(want),
+                summary(got),
+            ));
+        }
+    }
+    let (want, got) = (
+        expected_manifest.benchmark.as_ref(),
+        actual_manifest.benchmark.as_ref(),
+    );
+    if want.map(|input| &input.hash) != got.map(|input| &input.hash) {
+        mismatches.push(mismatch(
+            "benchmark".to_string(),
+            summary(want),
+            summary(got),
+        ));
+    }
+    for (field, want, got) in [
+        (
+            "input_hash",
+            &expected_manifest.input_hash,
+            &actual_manifest.input_hash,
+        ),
+        (
+            "result_hash",
+            &expected_manifest.result_hash,
+            &actual_manifest.result_hash,
+        ),
+    ] {
+        if want != got {
+            mismatches.push(mismatch(field.to_string(), json!(want), json!(got)));
+        }
+    }
+
+    let empty = serde_json::Map::new();
+    let want = expected.metrics.as_object().unwrap_or(&empty);
+    let got = actual.metrics.as_object().unwrap_or(&empty);
+    let mut keys: Vec<&String> = want.keys().chain(got.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        if want.get(key) != got.get(key) {
+            mismatches.push(mismatch(
+                format!("metrics.{key}"),
+                want.get(key).cloned().unwrap_or(Value::Null),
+                got.get(key).cloned().unwrap_or(Value::Null),
+            ));
+        }
+    }
+    mismatches
+}
+
+fn summary(input: Option<&ManifestInput>) -> Value {
+    input.map_or(Value::Null, |input| {
+        json!({
+            "source": input.source,
+            "from": input.from,
+            "to": input.to,
+            "points": input.points,
+            "hash": input.hash,
+        })
+    })
+}
+
+fn mismatch(field: String, expected: Value, actual: Value) -> ReplayMismatch {
+    ReplayMismatch {
+        field,
+        expected,
+        actual,
+    }
+}
@@ -1,8 +1,20 @@
 pub mod alert;
+pub mod backfill;
+pub mod backtest;
+pub mod backtest_jobs;
+pub mod datasets;
 pub mod history;
+pub mod logs;
+pub mod manifest;
+pub mod paper;
 pub mod portfolio;
+pub mod versions;
+pub mod whatif;
 
 pub use alert::AlertEvaluator;
+pub use backtest_jobs::BacktestWorkerPool;
+pub use logs::LogFetcher;
+pub use paper::PaperTrader;
 pub use portfolio::{
     CachedPriceOracle, CoingeckoPriceOracle, DbPortfolioService, FallbackPriceOracle,
     PriceRefresher, RecordingPriceOracle, SimulationConfig, StaticPriceOracle, TokenConfig,
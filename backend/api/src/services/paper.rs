@@ -0,0 +1,263 @@
+//! Paper trading: activated strategies follow fresh `price_history` points
+//! (written by `RecordingPriceOracle`) with a simulated position, and signal
+//! flips fire through the alert pipeline.
+
+use std::{sync::Arc, time::Duration};
+
+use alert_engine::AlertNotifier;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use domain::{BacktestResult, PaperAccount, PaperEquityPoint, Strategy, TradeSide};
+use strategy_engine::PricePoint;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::{
+    services::{
+        backtest::{self, BacktestRequest},
+        backtest_jobs::on_blocking_pool,
+    },
+    state::AppState,
+};
+
+/// Points before activation the strategy sees as history, so its indicators
+/// are warmed up.
+const PAPER_WINDOW: usize = 500;
+/// Most new points one tick processes; a worker further behind than this
+/// catches up over the following ticks.
+const MAX_POINTS_PER_TICK: usize = 2_000;
+/// Positions closer to zero than this count as flat.
+const FLAT_EPSILON: f64 = 1e-9;
+
+/// Advances every active paper account on each tick.
+///
+/// Each tick backtests the account's pinned strategy version from the same
+/// first bar (`replay_from`, the warm-up before activation) through the next
+/// page of unseen points and books those: equity moves by the backtest's own
+/// bar returns (so costs and sizing match a backtest), and the position is the
+/// one the backtest holds there. Replaying from a fixed start keeps the state
+/// kinds carry from bar to bar (RSI latches, trailing stops, vol-target sizing,
+/// script `position`) the same as when the earlier bars were booked, so the
+/// account follows one continuous backtest; the cost of a tick grows with the
+/// account's age.
+pub struct PaperTrader {
+    notifier: Arc<dyn AlertNotifier>,
+    interval: Duration,
+}
+
+impl PaperTrader {
+    pub fn new(notifier: Arc<dyn AlertNotifier>, interval: Duration) -> Self {
+        Self { notifier, interval }
+    }
+
+    pub fn spawn(self: Arc<Self>, state: AppState) {
+        tokio::spawn(async move {
+            loop {
+                if let Err(err) = self.run_once(&state).await {
+                    warn!(error = %err, "paper trading run failed");
+                }
+                sleep(self.interval).await;
+            }
+        });
+    }
+
+    /// Advances all active accounts once; returns how many new points were booked.
+    pub async fn run_once(&self, state: &AppState) -> anyhow::Result<usize> {
+        let mut booked = 0;
+        for (account, strategy) in state.strategy_repo.list_active_paper().await? {
+            match self.advance(state, account, strategy).await {
+                Ok(count) => booked += count,
+                Err(err) => warn!(error = %err, "paper account update failed"),
+            }
+        }
+        Ok(booked)
+    }
+
+    async fn advance(
+        &self,
+        state: &AppState,
+        mut account: PaperAccount,
+        mut strategy: Strategy,
+    ) -> anyhow::Result<usize> {
+        let request = BacktestRequest {
+            version: Some(account.strategy_version),
+            ..BacktestRequest::default()
+        };
+        backtest::prepare_strategy(state, &mut strategy, &request).await?;
+
+        let repo = &state.price_history_repo;
+        let symbol = account.symbol.as_str();
+        let chain_id = Some(account.chain_id);
+        // Page forward from the last booked point (which the page starts with).
+        let page = repo
+            .fetch_since(
+                symbol,
+                chain_id,
+                account.last_price_ts.unwrap_or(account.activated_at),
+                MAX_POINTS_PER_TICK + 1,
+            )
+            .await?;
+        let Some(until) = page
+            .iter()
+            .rev()
+            .find(|p| account.last_price_ts.is_none_or(|last| p.price_ts > last))
+            .map(|p| p.price_ts)
+        else {
+            return Ok(0);
+        };
+        let replay_from = match account.replay_from {
+            Some(from) => from,
+            None => repo
+                .fetch_before(symbol, chain_id, account.activated_at, PAPER_WINDOW)
+                .await?
+                .first()
+                .map_or(account.activated_at, |p| p.price_ts),
+        };
+        let prices: Vec<PricePoint> = repo
+            .fetch_range(symbol, chain_id, replay_from, until)
+            .await?
+            .iter()
+            .map(|p| PricePoint::new(p.price_ts, p.price))
+            .collect();
+        let first_new = match account.last_price_ts {
+            Some(last) => prices.partition_point(|p| p.timestamp <= last),
+            None => prices.partition_point(|p| p.timestamp < account.activated_at),
+        };
+        if first_new == prices.len() {
+            return Ok(0);
+        }
+        account.replay_from = Some(replay_from);
+
+        // Scripts may run up to their time limit; keep that off the runtime.
+        let run = {
+            let engine = state.strategy.clone();
+            let strategy = strategy.clone();
+            let prices = prices.clone();
+            async move { engine.backtest(&strategy, &prices).await }
+        };
+        let result = on_blocking_pool(run).await??;
+        let positions = positions_from_trades(&result, &prices);
+
+        let mut points = Vec::with_capacity(prices.len() - first_new);
+        let mut flips = Vec::new();
+        for i in first_new..prices.len() {
+            let bar_return = match (i.checked_sub(1), result.equity_curve.get(i)) {
+                (Some(prev), Some((_, equity))) => result
+                    .equity_curve
+                    .get(prev)
+                    .filter(|(_, prev_equity)| *prev_equity > 0.0)
+                    .map_or(1.0, |(_, prev_equity)| equity / prev_equity),
+                _ => 1.0,
+            };
+            account.equity *= bar_return;
+            let position = positions[i];
+            if side(position) != side(account.position) {
+                flips.push((
+                    prices[i].timestamp,
+                    prices[i].price,
+                    side(account.position),
+                    side(position),
+                ));
+            }
+            account.position = position;
+            account.last_price = Some(prices[i].price);
+            account.last_price_ts = Some(prices[i].timestamp);
+            points.push(PaperEquityPoint {
+                timestamp: prices[i].timestamp,
+                price: prices[i].price,
+                position,
+                equity: account.equity,
+            });
+        }
+
+        if !state.strategy_repo.record_paper(&account, &points).await? {
+            // Stopped while this tick ran; leave the stopped account as it is.
+            return Ok(0);
+        }
+        for (timestamp, price, from, to) in flips {
+            let message = format!(
+                "Strategy {} on {}: signal {} -> {} at {} ({}, paper equity {:.4})",
+                strategy.name,
+                account.symbol,
+                from,
+                to,
+                price,
+                timestamp.to_rfc3339(),
+                account.equity
+            );
+            self.fire(state, &account, &strategy, &message).await?;
+        }
+        Ok(points.len())
+    }
+
+    /// Records and sends a signal alert on the account's rule, honouring its
+    /// cooldown the way `AlertEvaluator` does.
+    async fn fire(
+        &self,
+        state: &AppState,
+        account: &PaperAccount,
+        strategy: &Strategy,
+        message: &str,
+    ) -> anyhow::Result<()> {
+        let Some(rule_id) = account.alert_rule_id else {
+            return Ok(());
+        };
+        let rules = state.alert_repo.list_rules(strategy.user_id).await?;
+        let Some(rule) = rules.iter().find(|r| r.id == rule_id && r.enabled) else {
+            return Ok(());
+        };
+        if let Some(last) = state
+            .alert_repo
+            .last_trigger_at(rule.id, account.wallet_id)
+            .await
+            .ok()
+            .flatten()
+        {
+            let cooldown = ChronoDuration::seconds(rule.cooldown_secs.max(0));
+            if Utc::now() < last + cooldown {
+                return Ok(());
+            }
+        }
+        state
+            .alert_repo
+            .insert_trigger(rule.id, account.wallet_id, message)
+            .await?;
+        self.notifier
+            .notify(rule.id, account.wallet_id, message)
+            .await;
+        info!(strategy = %account.strategy_id, rule = %rule.id, "paper signal alert triggered");
+        Ok(())
+    }
+}
+
+/// Signed position held at each price: a trade holds its size from its entry
+/// bar until its exit bar, or to the end while it is still open.
+fn positions_from_trades(result: &BacktestResult, prices: &[PricePoint]) -> Vec<f64> {
+    let mut positions = vec![0.0; prices.len()];
+    let index_of = |ts: DateTime<Utc>| prices.partition_point(|p| p.timestamp < ts);
+    for trade in &result.trades {
+        let signed = match trade.side {
+            TradeSide::Long => trade.size,
+            TradeSide::Short => -trade.size,
+        };
+        let from = index_of(trade.entry_time);
+        let to = if trade.closed {
+            index_of(trade.exit_time)
+        } else {
+            prices.len()
+        };
+        for position in positions.iter_mut().take(to).skip(from) {
+            *position = signed;
+        }
+    }
+    positions
+}
+
+fn side(position: f64) -> &'static str {
+    if position > FLAT_EPSILON {
+        "long"
+    } else if position < -FLAT_EPSILON {
+        "short"
+    } else {
+        "flat"
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    ops::RangeInclusive,
     str::FromStr,
     sync::Arc,
     time::{Duration, Instant},
@@ -8,7 +9,9 @@ use std::{
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use chrono::{Duration as ChronoDuration, Utc};
-use domain::{PortfolioSnapshot, Position, PriceHistoryPoint, Wallet, WalletTransaction};
+use domain::{
+    PortfolioSnapshot, Position, PriceHistoryPoint, Wallet, WalletBackfill, WalletTransaction,
+};
 use ethers::{
     contract::abigen,
     providers::{Http, Middleware, Provider, Ws},
@@ -27,6 +30,7 @@ use crate::repositories::{
     PortfolioSnapshotRepository, PriceCacheRepository, PriceHistoryRepository,
     TransactionRepository, WalletRepository,
 };
+use crate::services::{backfill, logs::LogFetcher};
 use strategy_engine::PricePoint;
 
 #[async_trait]
@@ -284,10 +288,7 @@ impl CoingeckoPriceOracle {
                 if arr.len() >= 2 {
                     if let (Some(ts_ms), Some(price)) = (arr[0].as_i64(), arr[1].as_f64()) {
                         if let Some(ts) = chrono::DateTime::<Utc>::from_timestamp_millis(ts_ms) {
-                            points.push(PricePoint {
-                                timestamp: ts,
-                                price,
-                            });
+                            points.push(PricePoint::new(ts, price));
                         }
                     }
                 }
@@ -439,6 +440,7 @@ where
                 price_ts: now,
                 source: self.source.clone(),
                 chain_id: Some(chain_id),
+                ..PriceHistoryPoint::default()
             };
             if let Err(err) = self.history_repo.upsert_points(&[point]).await {
                 warn!(error = %err, %symbol, "price history persist failed");
@@ -518,6 +520,7 @@ where
     max_concurrency: usize,
     max_retries: u32,
     retry_backoff: Duration,
+    log_fetcher: LogFetcher,
 }
 
 impl<RW, RS, O> DbPortfolioService<RW, RS, O>
@@ -555,9 +558,16 @@ where
             max_concurrency: max_concurrency.max(1),
             max_retries: max_retries.max(1),
             retry_backoff,
+            log_fetcher: LogFetcher::new(2_000, HashMap::new()),
         }
     }
 
+    /// Replaces the default `eth_getLogs` paging (2000 blocks on every chain).
+    pub fn with_log_fetcher(mut self, log_fetcher: LogFetcher) -> Self {
+        self.log_fetcher = log_fetcher;
+        self
+    }
+
     pub fn spawn_indexer(self: Arc<Self>, interval: Duration) {
         tokio::spawn(async move {
             loop {
@@ -588,6 +598,118 @@ where
         }
     }
 
+    /// Works through requested wallet backfills every `interval`.
+    pub fn spawn_backfill(
+        self: Arc<Self>,
+        history_repo: Arc<dyn PriceHistoryRepository>,
+        interval: Duration,
+    ) {
+        tokio::spawn(async move {
+            loop {
+                if let Err(err) = self.run_backfills(history_repo.as_ref()).await {
+                    warn!(error = %err, "portfolio backfill run failed");
+                }
+                sleep(interval).await;
+            }
+        });
+    }
+
+    /// Advances every pending or running backfill; returns how many completed.
+    /// A failed walk keeps its progress and resumes on the next run.
+    pub async fn run_backfills(&self, history_repo: &dyn PriceHistoryRepository) -> Result<usize> {
+        let mut completed = 0;
+        for backfill in self.tx_repo.active_backfills().await? {
+            match self.backfill_wallet(&backfill, history_repo).await {
+                Ok(()) => completed += 1,
+                Err(err) => {
+                    warn!(
+                        error = %err,
+                        wallet_id = %backfill.wallet_id,
+                        "wallet backfill failed, will resume"
+                    );
+                    self.tx_repo
+                        .record_backfill_error(backfill.wallet_id, &err.to_string())
+                        .await
+                        .ok();
+                }
+            }
+        }
+        Ok(completed)
+    }
+
+    /// Walks the wallet's Transfer logs from where the backfill stopped to its
+    /// target block, storing each chunk before recording it as scanned, then
+    /// fills the daily snapshots of past days.
+    async fn backfill_wallet(
+        &self,
+        backfill: &WalletBackfill,
+        history_repo: &dyn PriceHistoryRepository,
+    ) -> Result<()> {
+        let wallet = self
+            .wallet_repo
+            .find_by_id(backfill.wallet_id)
+            .await?
+            .context("wallet not found")?;
+        let provider = self.provider_for_chain(wallet.chain_id);
+        let target_block = match backfill.target_block {
+            Some(block) => block.max(0) as u64,
+            None => provider.get_block_number().await?.as_u64(),
+        };
+        self.tx_repo
+            .update_backfill_progress(wallet.id, target_block as i64, backfill.scanned_block)
+            .await?;
+
+        let token_map = self.token_map(wallet.chain_id);
+        let mut from_block = match backfill.scanned_block {
+            Some(block) => (block.max(0) as u64).saturating_add(1),
+            None => backfill.start_block.max(0) as u64,
+        };
+        while !token_map.is_empty() && from_block <= target_block {
+            let to_block = self
+                .log_fetcher
+                .chunk_end(wallet.chain_id, from_block, target_block);
+            let logs = self
+                .fetch_wallet_transfers(provider.clone(), &wallet, &token_map, from_block, to_block)
+                .await?;
+            let txs = self
+                .transfers_from_logs(
+                    &wallet,
+                    provider.clone(),
+                    &token_map,
+                    logs,
+                    to_block,
+                    Some(history_repo),
+                )
+                .await?;
+            self.tx_repo.insert_transactions(&txs).await?;
+            self.tx_repo
+                .update_backfill_progress(wallet.id, target_block as i64, Some(to_block as i64))
+                .await?;
+            from_block = to_block + 1;
+        }
+
+        let yesterday = Utc::now().date_naive() - ChronoDuration::days(1);
+        let days = backfill::fill_daily_snapshots(
+            self.tx_repo.as_ref(),
+            self.snapshot_repo.as_ref(),
+            history_repo,
+            &wallet,
+            yesterday,
+        )
+        .await?;
+        self.tx_repo
+            .complete_backfill(wallet.id, i32::try_from(days).unwrap_or(i32::MAX))
+            .await?;
+        info!(
+            wallet_id = %wallet.id,
+            chain_id = wallet.chain_id,
+            target_block,
+            days,
+            "wallet backfill completed"
+        );
+        Ok(())
+    }
+
     async fn sync_all_wallets(self: Arc<Self>, chain_filter: Option<u64>) -> Result<()> {
         let wallets = if let Some(chain_id) = chain_filter {
             self.wallet_repo.list_by_chain(chain_id).await?
@@ -705,13 +827,7 @@ where
             return Ok(());
         }
 
-        let wallet_address = Address::from_str(&wallet.address)?;
-        let token_map: HashMap<Address, &TokenConfig> = self
-            .tokens
-            .iter()
-            .filter(|t| t.chain_id == wallet.chain_id)
-            .filter_map(|t| Some((t.address, t)))
-            .collect();
+        let token_map = self.token_map(wallet.chain_id);
         if token_map.is_empty() {
             self.tx_repo
                 .update_last_tx_block(wallet.id, wallet.chain_id, latest_block as i64)
@@ -720,44 +836,89 @@ where
             return Ok(());
         }
 
+        // 逐段推進 cursor：某段失敗時，下次從最後完成的區塊之後續跑，不會跳過
+        let mut from_block = start_block;
+        while from_block <= latest_block {
+            let to_block = self
+                .log_fetcher
+                .chunk_end(wallet.chain_id, from_block, latest_block);
+            let logs = self
+                .fetch_wallet_transfers(provider.clone(), wallet, &token_map, from_block, to_block)
+                .await?;
+            let txs = self
+                .transfers_from_logs(wallet, provider.clone(), &token_map, logs, to_block, None)
+                .await?;
+            self.tx_repo.insert_transactions(&txs).await?;
+            self.tx_repo
+                .update_last_tx_block(wallet.id, wallet.chain_id, to_block as i64)
+                .await?;
+            from_block = to_block + 1;
+        }
+        Ok(())
+    }
+
+    /// Tracked ERC20 tokens of a chain by contract address.
+    fn token_map(&self, chain_id: u64) -> HashMap<Address, &TokenConfig> {
+        self.tokens
+            .iter()
+            .filter(|t| t.chain_id == chain_id)
+            .map(|t| (t.address, t))
+            .collect()
+    }
+
+    /// Transfer logs of the tracked tokens sent from or to the wallet.
+    async fn fetch_wallet_transfers(
+        &self,
+        provider: Arc<Provider<Http>>,
+        wallet: &Wallet,
+        token_map: &HashMap<Address, &TokenConfig>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>> {
+        let wallet_address = Address::from_str(&wallet.address)?;
         let token_addresses: Vec<Address> = token_map.keys().cloned().collect();
-        let from_logs = self
+        let mut logs = self
             .fetch_transfer_logs(
                 provider.clone(),
+                wallet.chain_id,
                 &token_addresses,
                 wallet_address,
-                start_block,
-                latest_block,
+                from_block..=to_block,
                 true,
             )
             .await?;
-        let to_logs = self
-            .fetch_transfer_logs(
-                provider.clone(),
+        logs.extend(
+            self.fetch_transfer_logs(
+                provider,
+                wallet.chain_id,
                 &token_addresses,
                 wallet_address,
-                start_block,
-                latest_block,
+                from_block..=to_block,
                 false,
             )
-            .await?;
-
-        let mut all_logs = Vec::new();
-        all_logs.extend(from_logs);
-        all_logs.extend(to_logs);
-
-        if all_logs.is_empty() {
-            self.tx_repo
-                .update_last_tx_block(wallet.id, wallet.chain_id, latest_block as i64)
-                .await
-                .ok();
-            return Ok(());
-        }
+            .await?,
+        );
+        Ok(logs)
+    }
 
-        let mut txs = Vec::with_capacity(all_logs.len());
+    /// Turns Transfer logs into wallet transactions. Values use the live
+    /// oracle price, or with `history` the last recorded price at the block's
+    /// time, falling back to the chain-agnostic series (zero if there is none).
+    /// With `history` a block whose timestamp cannot be read fails the call;
+    /// live sync dates such transfers now.
+    async fn transfers_from_logs(
+        &self,
+        wallet: &Wallet,
+        provider: Arc<Provider<Http>>,
+        token_map: &HashMap<Address, &TokenConfig>,
+        logs: Vec<Log>,
+        latest_block: u64,
+        history: Option<&dyn PriceHistoryRepository>,
+    ) -> Result<Vec<WalletTransaction>> {
+        let mut txs = Vec::with_capacity(logs.len());
         let mut block_cache: HashMap<u64, chrono::DateTime<Utc>> = HashMap::new();
 
-        for log in all_logs {
+        for log in logs {
             let token = match token_map.get(&log.address) {
                 Some(t) => t,
                 None => continue,
@@ -773,19 +934,34 @@ where
             let amount: f64 = format_units(amount_raw, token.decimals as i32)?
                 .parse()
                 .unwrap_or(0.0);
-            let price = self
-                .oracle
-                .price_usd(&token.symbol, wallet.chain_id)
-                .await
-                .unwrap_or(0.0);
             let block_number = log
                 .block_number
                 .unwrap_or_else(|| U64::from(latest_block))
                 .as_u64();
-            let block_ts = self
+            let block_ts = match self
                 .block_timestamp(provider.clone(), block_number, &mut block_cache)
                 .await
-                .unwrap_or_else(|_| Utc::now());
+            {
+                Ok(ts) => ts,
+                // Backfilled transfers date the daily balances for good, so
+                // fail the chunk (it is retried) rather than store a guess.
+                Err(err) if history.is_some() => {
+                    return Err(err.context(format!("timestamp of block {block_number}")));
+                }
+                Err(_) => Utc::now(),
+            };
+            let price = match history {
+                Some(history) => {
+                    backfill::historical_price(history, &token.symbol, wallet.chain_id, block_ts)
+                        .await?
+                        .unwrap_or(0.0)
+                }
+                None => self
+                    .oracle
+                    .price_usd(&token.symbol, wallet.chain_id)
+                    .await
+                    .unwrap_or(0.0),
+            };
             txs.push(WalletTransaction {
                 id: Uuid::new_v4(),
                 wallet_id: wallet.id,
@@ -802,22 +978,16 @@ where
                 block_timestamp: block_ts,
             });
         }
-
-        self.tx_repo.insert_transactions(&txs).await?;
-        self.tx_repo
-            .update_last_tx_block(wallet.id, wallet.chain_id, latest_block as i64)
-            .await
-            .ok();
-        Ok(())
+        Ok(txs)
     }
 
     async fn fetch_transfer_logs(
         &self,
         provider: Arc<Provider<Http>>,
+        chain_id: u64,
         token_addresses: &[Address],
         wallet: Address,
-        from_block: u64,
-        to_block: u64,
+        blocks: RangeInclusive<u64>,
         match_from: bool,
     ) -> Result<Vec<Log>> {
         let transfer_sig: H256 = H256::from_slice(&ethers::utils::keccak256(
@@ -826,9 +996,7 @@ where
         let wallet_topic = H256::from_slice(wallet.as_bytes());
         let mut filter = Filter::new()
             .address(token_addresses.to_vec())
-            .topic0(transfer_sig)
-            .from_block(BlockNumber::Number(from_block.into()))
-            .to_block(BlockNumber::Number(to_block.into()));
+            .topic0(transfer_sig);
 
         if match_from {
             filter = filter.topic1(wallet_topic);
@@ -836,8 +1004,15 @@ where
             filter = filter.topic2(wallet_topic);
         }
 
-        let logs = provider.get_logs(&filter).await?;
-        Ok(logs)
+        self.log_fetcher
+            .fetch(
+                provider.as_ref(),
+                chain_id,
+                &filter,
+                *blocks.start(),
+                *blocks.end(),
+            )
+            .await
     }
 
     async fn block_timestamp(
@@ -971,12 +1146,7 @@ where
                 continue;
             }
 
-            // ⚠️ BSC 上的 Wrapped Token 用更清晰的名稱,避免與原生 ETH 混淆
-            let display_symbol = if wallet.chain_id == 56 && token.symbol == "ETH" {
-                "WETH (BSC)".to_string()
-            } else {
-                token.symbol.clone()
-            };
+            let display_symbol = position_symbol(wallet.chain_id, &token.symbol);
 
             // 使用原始 symbol 查詢價格 (不用 display_symbol)
             let price = self
@@ -1099,6 +1269,16 @@ where
     }
 }
 
+/// Symbol a token position is listed under.
+pub(crate) fn position_symbol(chain_id: u64, symbol: &str) -> String {
+    // ⚠️ BSC 上的 Wrapped Token 用更清晰的名稱,避免與原生 ETH 混淆
+    if chain_id == 56 && symbol == "ETH" {
+        "WETH (BSC)".to_string()
+    } else {
+        symbol.to_string()
+    }
+}
+
 fn topic_to_address(topic: Option<&H256>) -> String {
     topic
         .and_then(|t| {
@@ -0,0 +1,71 @@
+//! Comparison of strategy versions.
+
+use domain::{ParamChange, StrategyVersion, StrategyVersionDiff};
+use serde_json::Value;
+
+/// Changes from `from` to `to`: name and type as a whole, params key by key.
+/// Nested objects are compared recursively; arrays and scalars as values.
+pub fn diff(from: &StrategyVersion, to: &StrategyVersion) -> StrategyVersionDiff {
+    let mut changes = Vec::new();
+    if from.name != to.name {
+        changes.push(changed(
+            "name",
+            from.name.clone().into(),
+            to.name.clone().into(),
+        ));
+    }
+    if from.r#type != to.r#type {
+        changes.push(changed(
+            "type",
+            from.r#type.clone().into(),
+            to.r#type.clone().into(),
+        ));
+    }
+    diff_values("params", &from.params, &to.params, &mut changes);
+    StrategyVersionDiff {
+        strategy_id: to.strategy_id,
+        from: from.version,
+        to: to.version,
+        changes,
+    }
+}
+
+fn diff_values(path: &str, from: &Value, to: &Value, changes: &mut Vec<ParamChange>) {
+    match (from, to) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = format!("{path}.{key}");
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_values(&child, x, y, changes),
+                    (Some(x), None) => changes.push(ParamChange {
+                        path: child,
+                        change: "removed".to_string(),
+                        from: Some(x.clone()),
+                        to: None,
+                    }),
+                    (None, Some(y)) => changes.push(ParamChange {
+                        path: child,
+                        change: "added".to_string(),
+                        from: None,
+                        to: Some(y.clone()),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if from != to => changes.push(changed(path, from.clone(), to.clone())),
+        _ => {}
+    }
+}
+
+fn changed(path: &str, from: Value, to: Value) -> ParamChange {
+    ParamChange {
+        path: path.to_string(),
+        change: "changed".to_string(),
+        from: Some(from),
+        to: Some(to),
+    }
+}
@@ -0,0 +1,132 @@
+//! What-if backtests: a strategy replayed over a wallet's real holdings from
+//! `portfolio_daily_snapshots`, compared with the wallet's actual value.
+
+use chrono::NaiveDate;
+use domain::{Strategy, WhatIfReport};
+use serde::{Deserialize, Serialize};
+use strategy_engine::{
+    whatif, FieldError, MetricsConfig, PriceSeries, StrategyError, StrategyKind,
+};
+use uuid::Uuid;
+
+use crate::{
+    services::{
+        backtest::{self, BacktestRequest, InputError},
+        backtest_jobs::on_blocking_pool,
+    },
+    state::AppState,
+};
+
+/// Body of `POST /api/strategies/{id}/what-if`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhatIfRequest {
+    pub wallet_id: Uuid,
+    /// Held asset a single-series strategy manages (default `ETH`);
+    /// multi-asset kinds manage their `params.symbols`.
+    pub symbol: Option<String>,
+    /// First snapshot day; defaults to the oldest one.
+    pub from: Option<NaiveDate>,
+    /// Last snapshot day; defaults to the newest one.
+    pub to: Option<NaiveDate>,
+    pub version: Option<i32>,
+    pub overrides: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+fn invalid(field: &str, message: impl Into<String>) -> InputError {
+    StrategyError::InvalidParams(vec![FieldError::new(field, message)]).into()
+}
+
+/// Starts from the wallet's positions on the first snapshot day. The strategy
+/// is backtested on the prices implied by the snapshots for the assets it
+/// manages, and that sleeve follows the backtest's equity while every other
+/// starting position is held. The caller checks that the wallet belongs to
+/// the strategy's owner.
+pub async fn run(
+    state: &AppState,
+    mut strategy: Strategy,
+    request: &WhatIfRequest,
+) -> Result<WhatIfReport, InputError> {
+    let backtest_request = BacktestRequest {
+        version: request.version,
+        overrides: request.overrides.clone(),
+        ..BacktestRequest::default()
+    };
+    let overrides = backtest::prepare_strategy(state, &mut strategy, &backtest_request).await?;
+    if let (Some(from), Some(to)) = (request.from, request.to) {
+        if to < from {
+            return Err(invalid("to", "must not be before from"));
+        }
+    }
+
+    let days = state
+        .portfolio_repo
+        .daily_snapshots(request.wallet_id, request.from, request.to)
+        .await?;
+    let (Some(first), Some(last)) = (days.first(), days.last()) else {
+        return Err(invalid("wallet_id", "has no daily snapshots in the range"));
+    };
+    if days.len() < 2 {
+        return Err(invalid(
+            "wallet_id",
+            "needs at least two daily snapshots in the range",
+        ));
+    }
+
+    let kind: StrategyKind = strategy.r#type.parse()?;
+    let params = kind.params(&strategy.params)?;
+    let (field, managed) = match params.series_symbols() {
+        Some(symbols) => ("symbols", symbols),
+        None => {
+            let symbol = request.symbol.as_deref().unwrap_or("ETH");
+            ("symbol", vec![symbol.trim().to_uppercase()])
+        }
+    };
+    let starting_positions = whatif::starting_positions(first);
+    let missing: Vec<FieldError> = managed
+        .iter()
+        .filter(|s| !starting_positions.iter().any(|p| &p.asset_symbol == *s))
+        .map(|s| FieldError::new(field, format!("wallet held no {s} on {}", first.day)))
+        .collect();
+    if !missing.is_empty() {
+        return Err(StrategyError::InvalidParams(missing).into());
+    }
+
+    let mut prices = whatif::implied_prices(&days);
+    let engine = state.strategy.clone();
+    let policy_strategy = strategy.clone();
+    let mut policy = if kind.is_multi_asset() {
+        let series: PriceSeries = managed
+            .iter()
+            .filter_map(|s| prices.remove_entry(s))
+            .collect();
+        on_blocking_pool(async move { engine.backtest_multi(&policy_strategy, &series).await })
+            .await??
+    } else {
+        let points = prices.remove(&managed[0]).unwrap_or_default();
+        on_blocking_pool(async move { engine.backtest(&policy_strategy, &points).await }).await??
+    };
+    policy.strategy_version = Some(strategy.version);
+    policy.overrides = overrides;
+
+    let replay = whatif::replay(
+        &days,
+        &managed,
+        &policy,
+        &MetricsConfig::from_params(&strategy.params),
+    );
+    Ok(WhatIfReport {
+        wallet_id: request.wallet_id,
+        strategy_id: strategy.id,
+        strategy_version: strategy.version,
+        from: first.day,
+        to: last.day,
+        starting_positions,
+        managed_symbols: managed,
+        hypothetical_curve: replay.hypothetical_curve,
+        actual_curve: replay.actual_curve,
+        hypothetical_metrics: replay.hypothetical_metrics,
+        actual_metrics: replay.actual_metrics,
+        comparison: replay.comparison,
+        policy,
+    })
+}
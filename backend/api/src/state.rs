@@ -14,6 +14,7 @@ use crate::{
         SessionRepository, StrategyRepository, TransactionRepository, UserRepository,
         WalletRepository,
     },
+    services::BacktestWorkerPool,
 };
 
 #[allow(dead_code)]
@@ -36,6 +37,7 @@ pub struct AppState {
     pub price_history_repo: Arc<dyn PriceHistoryRepository>,
     pub transaction_repo: Arc<dyn TransactionRepository>,
     pub nonce_limiter: Arc<NonceLimiter>,
+    pub backtest_jobs: Arc<BacktestWorkerPool>,
 }
 
 // Ensure critical dependencies uphold Send/Sync for Axum state usage.
@@ -59,6 +61,7 @@ where
     dyn PriceCacheRepository: Send + Sync,
     dyn TransactionRepository: Send + Sync,
     NonceLimiter: Send + Sync,
+    BacktestWorkerPool: Send + Sync,
 {
 }
 
@@ -1,6 +1,6 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use alert_engine::InMemoryAlertService;
+use alert_engine::{AlertNotifier, InMemoryAlertService};
 use api::{
     app::build_router,
     config::AppConfig,
@@ -10,6 +10,7 @@ use api::{
         PostgresPriceHistoryRepository, PostgresSessionRepository, PostgresStrategyRepository,
         PostgresTransactionRepository, PostgresUserRepository, PostgresWalletRepository,
     },
+    services::{BacktestWorkerPool, LogFetcher, PaperTrader, backfill},
     state::AppState,
 };
 use async_trait::async_trait;
@@ -19,12 +20,18 @@ use axum::{
     http::{HeaderValue, Request, StatusCode},
 };
 use axum_extra::extract::cookie::SameSite;
-use chrono::{Duration as ChronoDuration, Utc};
-use domain::{LoginRequest, LoginResponse, NonceResponse, Role, Wallet};
-use ethers::providers::{Http, Provider};
+use chrono::{Duration as ChronoDuration, NaiveDate, Utc};
+use domain::{
+    LoginRequest, LoginResponse, NonceResponse, Position, PriceHistoryPoint, Role, Wallet,
+    WalletTransaction,
+};
+use ethers::{
+    providers::{Http, JsonRpcError, MockProvider, MockResponse, Provider},
+    types::{BlockNumber, Filter, H256, Log},
+};
 use indexer::InMemoryPortfolioService;
 use sqlx::PgPool;
-use strategy_engine::InMemoryStrategyService;
+use strategy_engine::{InMemoryStrategyService, PricePoint};
 use tower::ServiceExt;
 use uuid::Uuid;
 
@@ -143,6 +150,68 @@ fn test_config(database_url: String) -> AppConfig {
         port: 0,
         portfolio_simulation: false,
         enable_alert_worker: false,
+        enable_backtest_worker: false,
+        backtest_workers: 1,
+        backtest_lease: Duration::from_secs(60),
+        enable_paper_worker: false,
+        paper_interval: Duration::from_secs(60),
+        enable_backfill_worker: false,
+        backfill_interval: Duration::from_secs(60),
+        backfill_start_block: 0,
+        logs_chunk_blocks: 2_000,
+        chain_logs_chunk_blocks: Default::default(),
+    }
+}
+
+/// Viewer claims for `user_id`, as [`StubAuthService`] hands them out.
+fn auth_claims(user_id: Uuid) -> JwtClaims {
+    let config = test_config(String::new());
+    let now = Utc::now();
+    JwtClaims {
+        sub: "0x0000000000000000000000000000000000000000".to_string(),
+        role: Role::Viewer,
+        aud: config.jwt_audience,
+        iss: config.jwt_issuer,
+        exp: (now + ChronoDuration::minutes(15))
+            .timestamp()
+            .try_into()
+            .unwrap(),
+        iat: now.timestamp().try_into().unwrap(),
+        session_id: Uuid::new_v4(),
+        user_id,
+        wallet_id: Uuid::new_v4(),
+    }
+}
+
+/// Postgres-backed state with the in-memory services, authenticating every
+/// bearer token as `claims`.
+async fn test_state(pool: PgPool, claims: JwtClaims) -> AppState {
+    let config = test_config(std::env::var("DATABASE_URL").unwrap_or_default());
+    AppState {
+        config: config.clone(),
+        db: pool.clone(),
+        provider: Arc::new(
+            Provider::<Http>::try_from(config.rpc_url.as_str()).expect("provider should init"),
+        ),
+        auth: Arc::new(StubAuthService { claims }),
+        portfolio: Arc::new(InMemoryPortfolioService),
+        strategy: Arc::new(InMemoryStrategyService),
+        alerts: Arc::new(InMemoryAlertService),
+        user_repo: Arc::new(PostgresUserRepository::new(pool.clone())),
+        strategy_repo: Arc::new(PostgresStrategyRepository::new(pool.clone())),
+        alert_repo: Arc::new(PostgresAlertRepository::new(pool.clone())),
+        session_repo: Arc::new(PostgresSessionRepository::new(pool.clone())),
+        wallet_repo: Arc::new(PostgresWalletRepository::new(pool.clone())),
+        portfolio_repo: Arc::new(PostgresPortfolioSnapshotRepository::new(pool.clone())),
+        price_history_repo: Arc::new(PostgresPriceHistoryRepository::new(pool.clone())),
+        price_cache_repo: Arc::new(PostgresPriceCacheRepository::new(pool.clone())),
+        transaction_repo: Arc::new(PostgresTransactionRepository::new(pool)),
+        nonce_limiter: Arc::new(
+            NonceLimiter::new(Duration::from_secs(1), None)
+                .await
+                .expect("nonce limiter"),
+        ),
+        backtest_jobs: Arc::new(BacktestWorkerPool::new(1, Duration::from_millis(50))),
     }
 }
 
@@ -209,6 +278,7 @@ async fn get_me_returns_profile(pool: PgPool) {
                 .await
                 .expect("nonce limiter"),
         ),
+        backtest_jobs: Arc::new(BacktestWorkerPool::new(1, Duration::from_millis(50))),
     };
 
     let router = build_router(
@@ -320,6 +390,7 @@ async fn admin_lists_and_revokes_sessions(pool: PgPool) {
                 .await
                 .expect("nonce limiter"),
         ),
+        backtest_jobs: Arc::new(BacktestWorkerPool::new(1, Duration::from_millis(50))),
     };
 
     let router = build_router(
@@ -449,6 +520,7 @@ async fn non_admin_cannot_access_admin_sessions(pool: PgPool) {
                 .await
                 .expect("nonce limiter"),
         ),
+        backtest_jobs: Arc::new(BacktestWorkerPool::new(1, Duration::from_millis(50))),
     };
 
     let router = build_router(
@@ -468,3 +540,1744 @@ async fn non_admin_cannot_access_admin_sessions(pool: PgPool) {
         .expect("router response");
     assert_eq!(resp.status(), StatusCode::FORBIDDEN);
 }
+
+#[sqlx::test(migrations = "../migrations")]
+async fn backtest_persists_trade_ledger(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let wallet_address = "0x00000000000000000000000000000000000000cc";
+    let now = Utc::now();
+
+    sqlx::query("INSERT INTO users (id, primary_wallet) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(wallet_address)
+        .execute(&pool)
+        .await
+        .expect("insert user");
+
+    let state = test_state(pool.clone(), auth_claims(user_id)).await;
+
+    let router = build_router(
+        state.clone(),
+        vec![HeaderValue::from_static("http://localhost:3000")],
+    );
+
+    let create_resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/strategies")
+                .method("POST")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "name": "ma",
+                        "type": "ma_cross",
+                        "params": { "short_window": 2, "long_window": 4, "fee_bps": 10 }
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .expect("router response");
+    assert_eq!(create_resp.status(), StatusCode::OK);
+    let body = to_bytes(create_resp.into_body(), 1024 * 1024)
+        .await
+        .expect("body");
+    let strategy: domain::Strategy = serde_json::from_slice(&body).expect("json");
+
+    let prices: Vec<serde_json::Value> = (0..40)
+        .map(|i| {
+            serde_json::json!({
+                "timestamp": now - ChronoDuration::days(40 - i),
+                "price": 100.0 + 10.0 * (i as f64 / 3.0).sin(),
+            })
+        })
+        .collect();
+    let backtest_resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/strategies/{}/backtest", strategy.id))
+                .method("POST")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::json!({ "prices": prices }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .expect("router response");
+    assert_eq!(backtest_resp.status(), StatusCode::OK);
+    let body = to_bytes(backtest_resp.into_body(), 1024 * 1024)
+        .await
+        .expect("body");
+    let result: domain::BacktestResult = serde_json::from_slice(&body).expect("json");
+    assert!(!result.trades.is_empty());
+    assert!(result.metrics.get("win_rate").is_some());
+    assert_eq!(result.benchmark_curve.len(), result.equity_curve.len());
+    assert_eq!(result.metrics["benchmark"], "buy_and_hold");
+
+    // Optimizations are queued like backtest jobs; a bad space is rejected
+    // before anything is queued.
+    let optimize = |body: serde_json::Value| {
+        Request::builder()
+            .uri(format!("/api/strategies/{}/optimize", strategy.id))
+            .method("POST")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let (status, _) = send_json(
+        &router,
+        optimize(serde_json::json!({ "prices": prices, "space": { "windw": [2, 3] } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, job) = send_json(
+        &router,
+        optimize(serde_json::json!({
+            "prices": prices,
+            "space": { "short_window": [2, 3], "long_window": { "min": 4, "max": 6 } },
+            "objective": "sharpe"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(job["kind"], "optimize");
+    assert_eq!(job["status"], "queued");
+    assert!(job["request"]["seed"].is_u64());
+    let job_id = job["id"].as_str().expect("job id").to_string();
+
+    state.backtest_jobs.clone().spawn(state.clone());
+    let mut job = job;
+    for _ in 0..100 {
+        let request = Request::builder()
+            .uri(format!("/api/backtest-jobs/{job_id}"))
+            .header("Authorization", "Bearer test-token")
+            .body(Body::empty())
+            .unwrap();
+        let (status, polled) = send_json(&router, request).await;
+        assert_eq!(status, StatusCode::OK);
+        job = polled;
+        if job["status"] != "queued" && job["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(job["status"], "succeeded", "job: {job}");
+    assert!(job["result"].is_null());
+    let optimized = &job["optimization"];
+    assert_eq!(optimized["evaluated"], 6);
+    assert!(optimized["best"]["params"]["long_window"].is_number());
+    assert_eq!(optimized["input"]["source"], "inline");
+    assert_eq!(optimized["input"]["points"], prices.len());
+
+    let list_resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/strategies/{}/backtests", strategy.id))
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("router response");
+    assert_eq!(list_resp.status(), StatusCode::OK);
+    let body = to_bytes(list_resp.into_body(), 1024 * 1024)
+        .await
+        .expect("body");
+    let stored: Vec<domain::BacktestResult> = serde_json::from_slice(&body).expect("json");
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].trades.len(), result.trades.len());
+    assert_eq!(stored[0].benchmark_curve.len(), result.benchmark_curve.len());
+
+    // Multi-asset kinds take one inline series per symbol.
+    let (status, rebalance) = send_json(
+        &router,
+        Request::builder()
+            .uri("/api/strategies")
+            .method("POST")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::json!({
+                    "name": "60/40",
+                    "type": "rebalance",
+                    "params": {
+                        "symbols": ["ETH", "BTC"],
+                        "weighting": "fixed",
+                        "weights": { "ETH": 0.6, "BTC": 0.4 },
+                        "rebalance_every": 7,
+                        "fee_bps": 10
+                    }
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let btc: Vec<serde_json::Value> = (0..40)
+        .map(|i| {
+            serde_json::json!({
+                "timestamp": now - ChronoDuration::days(40 - i),
+                "price": 30_000.0 + 100.0 * i as f64,
+            })
+        })
+        .collect();
+    let rebalance_id = rebalance["id"].as_str().expect("strategy id");
+    let (status, result) = send_json(
+        &router,
+        Request::builder()
+            .uri(format!("/api/strategies/{rebalance_id}/backtest"))
+            .method("POST")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "series": { "eth": prices, "BTC": btc } }).to_string(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {result}");
+    let weights = result["weights_history"].as_array().expect("weights");
+    assert_eq!(weights.len(), 40);
+    assert_eq!(weights[7]["rebalanced"], true);
+    assert_eq!(weights[7]["weights"]["ETH"], 0.6);
+    assert!(result["metrics"]["total_costs"].as_f64().unwrap() > 0.0);
+    assert_eq!(result["metrics"]["benchmark"], "equal_weight_buy_and_hold");
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn create_strategy_validates_params(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let wallet_address = "0x00000000000000000000000000000000000000dd";
+
+    sqlx::query("INSERT INTO users (id, primary_wallet) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(wallet_address)
+        .execute(&pool)
+        .await
+        .expect("insert user");
+
+    let state = test_state(pool.clone(), auth_claims(user_id)).await;
+
+    let router = build_router(
+        state,
+        vec![HeaderValue::from_static("http://localhost:3000")],
+    );
+
+    let kinds_resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/strategies/kinds")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("router response");
+    assert_eq!(kinds_resp.status(), StatusCode::OK);
+    let body = to_bytes(kinds_resp.into_body(), 1024 * 1024)
+        .await
+        .expect("body");
+    let kinds: Vec<serde_json::Value> = serde_json::from_slice(&body).expect("json");
+    let ma = kinds
+        .iter()
+        .find(|k| k["kind"] == "ma_cross")
+        .expect("ma_cross kind");
+    assert!(ma["schema"]["properties"]["long_window"].is_object());
+    assert_eq!(ma["schema"]["additionalProperties"], false);
+
+    let create_resp = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/strategies")
+                .method("POST")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "name": "typo",
+                        "type": "MA_CROSS",
+                        "params": { "short_window": 30, "long_windw": 10 }
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .expect("router response");
+    assert_eq!(create_resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = to_bytes(create_resp.into_body(), 1024 * 1024)
+        .await
+        .expect("body");
+    let error: serde_json::Value = serde_json::from_slice(&body).expect("json");
+    let fields: Vec<&str> = error["fields"]
+        .as_array()
+        .expect("fields")
+        .iter()
+        .filter_map(|f| f["field"].as_str())
+        .collect();
+    assert_eq!(fields, vec!["long_windw"]);
+
+    let create_resp = router
+        .oneshot(
+            Request::builder()
+                .uri("/api/strategies")
+                .method("POST")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "name": "inverted",
+                        "type": "MA_CROSS",
+                        "params": { "short_window": 30, "long_window": 10 }
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .expect("router response");
+    assert_eq!(create_resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+async fn send_json(
+    router: &axum::Router,
+    request: Request<Body>,
+) -> (StatusCode, serde_json::Value) {
+    let resp = router.clone().oneshot(request).await.expect("router response");
+    let status = resp.status();
+    let body = to_bytes(resp.into_body(), 1024 * 1024)
+        .await
+        .expect("body");
+    let value = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).expect("json")
+    };
+    (status, value)
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn backtest_jobs_run_and_cancel(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let strategy_id = Uuid::new_v4();
+    let wallet_address = "0x00000000000000000000000000000000000000ee";
+    let now = Utc::now();
+
+    sqlx::query("INSERT INTO users (id, primary_wallet) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(wallet_address)
+        .execute(&pool)
+        .await
+        .expect("insert user");
+    sqlx::query(
+        "INSERT INTO strategies (id, user_id, name, type, params) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(strategy_id)
+    .bind(user_id)
+    .bind("ma")
+    .bind("ma_cross")
+    .bind(serde_json::json!({ "short_window": 2, "long_window": 4 }))
+    .execute(&pool)
+    .await
+    .expect("insert strategy");
+
+    let state = test_state(pool.clone(), auth_claims(user_id)).await;
+    let router = build_router(
+        state.clone(),
+        vec![HeaderValue::from_static("http://localhost:3000")],
+    );
+
+    let prices: Vec<serde_json::Value> = (0..40)
+        .map(|i| {
+            serde_json::json!({
+                "timestamp": now - ChronoDuration::days(40 - i),
+                "price": 100.0 + 10.0 * (i as f64 / 3.0).sin(),
+            })
+        })
+        .collect();
+    let enqueue = |body: serde_json::Value| {
+        Request::builder()
+            .uri(format!("/api/strategies/{strategy_id}/backtest-jobs"))
+            .method("POST")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let post = |uri: String| {
+        Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header("Authorization", "Bearer test-token")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let get = |uri: String| {
+        Request::builder()
+            .uri(uri)
+            .header("Authorization", "Bearer test-token")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Invalid overrides are rejected before anything is queued.
+    let (status, _) = send_json(
+        &router,
+        enqueue(serde_json::json!({ "short_window": 10, "long_window": 4 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // No workers yet: the job stays queued and can be cancelled.
+    let body = serde_json::json!({ "prices": prices });
+    let (status, queued) = send_json(&router, enqueue(body.clone())).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(queued["status"], "queued");
+    let queued_id = queued["id"].as_str().expect("job id").to_string();
+    let (status, cancelled) =
+        send_json(&router, post(format!("/api/backtest-jobs/{queued_id}/cancel"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(cancelled["status"], "cancelled");
+
+    // A running job is only requeued once its lease runs out, and the stale
+    // run learns it lost the job at its next renewal.
+    let (status, leased) = send_json(&router, enqueue(body.clone())).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let (claimed, _) = state
+        .strategy_repo
+        .claim_backtest(Duration::from_secs(60))
+        .await
+        .expect("claim")
+        .expect("queued job");
+    assert_eq!(claimed.id.to_string(), leased["id"].as_str().unwrap());
+    let started_at = claimed.started_at.expect("started");
+    assert_eq!(
+        state
+            .strategy_repo
+            .requeue_expired_backtests()
+            .await
+            .unwrap(),
+        0
+    );
+    assert!(
+        state
+            .strategy_repo
+            .renew_backtest_lease(claimed.id, started_at, Duration::from_secs(60))
+            .await
+            .unwrap()
+    );
+    sqlx::query(
+        "UPDATE strategy_backtests SET lease_until = NOW() - INTERVAL '1 second' WHERE id = $1",
+    )
+    .bind(claimed.id)
+    .execute(&pool)
+    .await
+    .expect("expire lease");
+    assert_eq!(
+        state
+            .strategy_repo
+            .requeue_expired_backtests()
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(
+        !state
+            .strategy_repo
+            .renew_backtest_lease(claimed.id, started_at, Duration::from_secs(60))
+            .await
+            .unwrap()
+    );
+    assert!(
+        state
+            .strategy_repo
+            .cancel_backtest(claimed.id, user_id)
+            .await
+            .unwrap()
+    );
+    state.backtest_jobs.clone().spawn(state.clone());
+    let (status, job) = send_json(&router, enqueue(body)).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let job_id = job["id"].as_str().expect("job id").to_string();
+    let mut job = job;
+    for _ in 0..100 {
+        let (status, polled) =
+            send_json(&router, get(format!("/api/backtest-jobs/{job_id}"))).await;
+        assert_eq!(status, StatusCode::OK);
+        job = polled;
+        if job["status"] != "queued" && job["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(job["status"], "succeeded", "job: {job}");
+    assert_eq!(job["progress"], 1.0);
+    assert!(job["started_at"].is_string());
+    assert!(!job["result"]["equity_curve"].as_array().expect("curve").is_empty());
+
+    let (status, _) =
+        send_json(&router, post(format!("/api/backtest-jobs/{job_id}/cancel"))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, jobs) = send_json(&router, get("/api/backtest-jobs".to_string())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(jobs.as_array().expect("jobs").len(), 3);
+    let (_, cancelled_jobs) =
+        send_json(&router, get("/api/backtest-jobs?status=cancelled".to_string())).await;
+    assert_eq!(cancelled_jobs.as_array().expect("jobs").len(), 2);
+
+    // Only the finished run shows up in the backtest history.
+    let (status, history) =
+        send_json(&router, get(format!("/api/strategies/{strategy_id}/backtests"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(history.as_array().expect("history").len(), 1);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn uploaded_dataset_backs_a_backtest(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let strategy_id = Uuid::new_v4();
+    let wallet_address = "0x00000000000000000000000000000000000000dd";
+
+    sqlx::query("INSERT INTO users (id, primary_wallet) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(wallet_address)
+        .execute(&pool)
+        .await
+        .expect("insert user");
+    sqlx::query(
+        "INSERT INTO strategies (id, user_id, name, type, params) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(strategy_id)
+    .bind(user_id)
+    .bind("ma")
+    .bind("ma_cross")
+    .bind(serde_json::json!({ "short_window": 2, "long_window": 4 }))
+    .execute(&pool)
+    .await
+    .expect("insert strategy");
+
+    let state = test_state(pool.clone(), auth_claims(user_id)).await;
+    let router = build_router(
+        state,
+        vec![HeaderValue::from_static("http://localhost:3000")],
+    );
+
+    let upload = |fields: &[(&str, &str)], file: &str| {
+        let boundary = "dataset-boundary";
+        let mut body = String::new();
+        for (name, value) in fields {
+            body.push_str(&format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            ));
+        }
+        body.push_str(&format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"prices.csv\"\r\nContent-Type: text/csv\r\n\r\n{file}\r\n--{boundary}--\r\n"
+        ));
+        Request::builder()
+            .uri("/api/datasets")
+            .method("POST")
+            .header("Authorization", "Bearer test-token")
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    // Semicolon-separated, naive local times at +08:00, custom column names.
+    let mut csv = String::from("Date;Open;High;Low;Last;Vol\n");
+    for i in 0..30 {
+        let close = 100.0 + 10.0 * (i as f64 / 3.0).sin();
+        csv.push_str(&format!(
+            "2024-01-{:02} 08:00;{close};{};{};{close};{}\n",
+            i + 1,
+            close + 1.0,
+            close - 1.0,
+            1000 + i
+        ));
+    }
+    let (status, dataset) = send_json(
+        &router,
+        upload(
+            &[
+                ("name", "eth-daily"),
+                ("symbol", "eth"),
+                ("delimiter", ";"),
+                ("timezone", "+08:00"),
+                ("columns", r#"{"close": "Last"}"#),
+            ],
+            &csv,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "dataset: {dataset}");
+    assert_eq!(dataset["symbol"], "ETH");
+    assert_eq!(dataset["point_count"], 30);
+    assert_eq!(dataset["first_ts"], "2024-01-01T00:00:00Z");
+
+    // Bad rows are reported by line, nothing is stored.
+    let bad = "timestamp,close,high,low\n\
+               2024-01-01,100,99,101\n\
+               2024-01-03,-1,,\n\
+               2024-01-05,100,,\n\
+               2024-01-04,100,,\n";
+    let (status, body) = send_json(&router, upload(&[("name", "broken")], bad)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let fields: Vec<&str> = body["fields"]
+        .as_array()
+        .expect("fields")
+        .iter()
+        .map(|f| f["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["row 2", "row 3", "row 5"]);
+
+    let backtest = |body: serde_json::Value| {
+        Request::builder()
+            .uri(format!("/api/strategies/{strategy_id}/backtest"))
+            .method("POST")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let (status, result) = send_json(
+        &router,
+        backtest(serde_json::json!({ "dataset": "eth-daily" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "result: {result}");
+    let curve = result["equity_curve"].as_array().expect("curve");
+    assert_eq!(curve.len(), 30);
+    assert_eq!(curve[0][0], "2024-01-01T00:00:00Z");
+
+    // Daily data on 12h bars: every other bar is a gap.
+    let (status, result) = send_json(
+        &router,
+        backtest(serde_json::json!({ "dataset": "eth-daily", "bar_interval": "12h" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "result: {result}");
+    let resampling = &result["metrics"]["resampling"];
+    assert_eq!(resampling["interval"], "12h");
+    assert_eq!(resampling["gap_policy"], "ffill");
+    assert_eq!(resampling["bars"], 59);
+    assert_eq!(resampling["filled_bars"], 29);
+    assert_eq!(result["equity_curve"][0][0], "2024-01-01T12:00:00Z");
+    let (status, body) = send_json(
+        &router,
+        backtest(serde_json::json!({
+            "dataset": "eth-daily",
+            "bar_interval": "12h",
+            "gap_policy": "fail",
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["fields"][0]["field"], "gap_policy");
+
+    let (status, body) = send_json(
+        &router,
+        backtest(serde_json::json!({ "dataset": "missing" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["fields"][0]["field"], "dataset");
+
+    let list = Request::builder()
+        .uri("/api/datasets")
+        .header("Authorization", "Bearer test-token")
+        .body(Body::empty())
+        .unwrap();
+    let (status, datasets) = send_json(&router, list).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(datasets.as_array().expect("datasets").len(), 1);
+
+    let delete = || {
+        Request::builder()
+            .uri("/api/datasets/eth-daily")
+            .method("DELETE")
+            .header("Authorization", "Bearer test-token")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let (status, _) = send_json(&router, delete()).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send_json(&router, delete()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn strategy_updates_create_versions(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let wallet_address = "0x00000000000000000000000000000000000000cc";
+    let now = Utc::now();
+
+    sqlx::query("INSERT INTO users (id, primary_wallet) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(wallet_address)
+        .execute(&pool)
+        .await
+        .expect("insert user");
+
+    let state = test_state(pool.clone(), auth_claims(user_id)).await;
+    let router = build_router(
+        state,
+        vec![HeaderValue::from_static("http://localhost:3000")],
+    );
+    let json = |method: &str, uri: String, body: serde_json::Value| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let get = |uri: String| {
+        Request::builder()
+            .uri(uri)
+            .header("Authorization", "Bearer test-token")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let (status, created) = send_json(
+        &router,
+        json(
+            "POST",
+            "/api/strategies".to_string(),
+            serde_json::json!({
+                "name": "ma",
+                "type": "ma_cross",
+                "params": { "short_window": 2, "long_window": 4 },
+            }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["version"], 1);
+    let strategy_id = created["id"].as_str().expect("id").to_string();
+    let update = |params: serde_json::Value| {
+        json(
+            "PUT",
+            format!("/api/strategies/{strategy_id}"),
+            serde_json::json!({ "name": "ma", "type": "ma_cross", "params": params }),
+        )
+    };
+
+    let v2 = serde_json::json!({ "short_window": 3, "long_window": 6, "fee_bps": 10 });
+    let (status, updated) = send_json(&router, update(v2.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["version"], 2);
+    // Same settings again: no new version.
+    let (_, unchanged) = send_json(&router, update(v2)).await;
+    assert_eq!(unchanged["version"], 2);
+    let (status, _) = send_json(
+        &router,
+        update(serde_json::json!({ "short_window": 9, "long_window": 6 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, listed) = send_json(
+        &router,
+        get(format!("/api/strategies/{strategy_id}/versions")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let versions: Vec<i64> = listed
+        .as_array()
+        .expect("versions")
+        .iter()
+        .map(|v| v["version"].as_i64().unwrap())
+        .collect();
+    assert_eq!(versions, [2, 1]);
+    let (_, first) = send_json(
+        &router,
+        get(format!("/api/strategies/{strategy_id}/versions/1")),
+    )
+    .await;
+    assert_eq!(first["params"]["long_window"], 4);
+
+    let (status, diff) =
+        send_json(&router, get(format!("/api/strategies/{strategy_id}/diff"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        (diff["from"].as_i64(), diff["to"].as_i64()),
+        (Some(1), Some(2))
+    );
+    let paths: Vec<(&str, &str)> = diff["changes"]
+        .as_array()
+        .expect("changes")
+        .iter()
+        .map(|c| (c["path"].as_str().unwrap(), c["change"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            ("params.fee_bps", "added"),
+            ("params.long_window", "changed"),
+            ("params.short_window", "changed"),
+        ]
+    );
+
+    // Backtests record the version and overrides they ran with.
+    let prices: Vec<serde_json::Value> = (0..40)
+        .map(|i| {
+            serde_json::json!({
+                "timestamp": now - ChronoDuration::days(40 - i),
+                "price": 100.0 + 10.0 * (i as f64 / 3.0).sin(),
+            })
+        })
+        .collect();
+    let backtest = |body: serde_json::Value| {
+        json(
+            "POST",
+            format!("/api/strategies/{strategy_id}/backtest"),
+            body,
+        )
+    };
+    let (status, result) = send_json(
+        &router,
+        backtest(serde_json::json!({
+            "prices": prices,
+            "version": 1,
+            "overrides": { "long_window": 5 },
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "result: {result}");
+    assert_eq!(result["strategy_version"], 1);
+    assert_eq!(result["overrides"], serde_json::json!({ "long_window": 5 }));
+    // Overrides are checked as strictly as the params of an update.
+    let (status, body) = send_json(
+        &router,
+        backtest(serde_json::json!({
+            "prices": prices,
+            "overrides": { "long_windw": 5, "short_window": "3" },
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let fields: Vec<&str> = body["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|f| f["field"].as_str())
+        .collect();
+    assert_eq!(fields, vec!["long_windw", "short_window"]);
+    let (status, result) =
+        send_json(&router, backtest(serde_json::json!({ "prices": prices }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(result["strategy_version"], 2);
+    assert!(result.get("overrides").is_none());
+    let (status, body) = send_json(
+        &router,
+        backtest(serde_json::json!({ "prices": prices, "version": 7 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["fields"][0]["field"], "version");
+
+    let (_, v1_runs) = send_json(
+        &router,
+        get(format!("/api/strategies/{strategy_id}/backtests?version=1")),
+    )
+    .await;
+    assert_eq!(v1_runs.as_array().expect("runs").len(), 1);
+    let (_, all_runs) = send_json(
+        &router,
+        get(format!("/api/strategies/{strategy_id}/backtests")),
+    )
+    .await;
+    assert_eq!(all_runs.as_array().expect("runs").len(), 2);
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn backtests_replay_from_manifest(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let wallet_address = "0x00000000000000000000000000000000000000ee";
+    let now = Utc::now();
+
+    sqlx::query("INSERT INTO users (id, primary_wallet) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(wallet_address)
+        .execute(&pool)
+        .await
+        .expect("insert user");
+
+    let state = test_state(pool.clone(), auth_claims(user_id)).await;
+    let router = build_router(
+        state,
+        vec![HeaderValue::from_static("http://localhost:3000")],
+    );
+    let json = |method: &str, uri: String, body: serde_json::Value| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let upload = |file: String| {
+        let boundary = "replay-boundary";
+        let body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nreplay\r\n\
+             --{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"prices.csv\"\r\nContent-Type: text/csv\r\n\r\n{file}\r\n--{boundary}--\r\n"
+        );
+        Request::builder()
+            .uri("/api/datasets")
+            .method("POST")
+            .header("Authorization", "Bearer test-token")
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    };
+    let csv = |bump: f64| {
+        let mut csv = String::from("timestamp,close\n");
+        for i in 0..30 {
+            let close = 100.0 + 10.0 * (i as f64 / 3.0).sin();
+            let close = if i == 20 { close + bump } else { close };
+            csv.push_str(&format!("2024-01-{:02},{close}\n", i + 1));
+        }
+        csv
+    };
+
+    let (status, created) = send_json(
+        &router,
+        json(
+            "POST",
+            "/api/strategies".to_string(),
+            serde_json::json!({
+                "name": "ma",
+                "type": "ma_cross",
+                "params": { "short_window": 2, "long_window": 4 },
+            }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let strategy_id = created["id"].as_str().expect("id").to_string();
+    let (status, _) = send_json(&router, upload(csv(0.0))).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let backtest = |body: serde_json::Value| {
+        json(
+            "POST",
+            format!("/api/strategies/{strategy_id}/backtest"),
+            body,
+        )
+    };
+    let replay = |backtest_id: &str| {
+        json(
+            "POST",
+            format!("/api/strategies/{strategy_id}/backtests/{backtest_id}/replay"),
+            serde_json::json!({}),
+        )
+    };
+
+    // The manifest records where the prices came from and hashes them.
+    let (status, result) = send_json(
+        &router,
+        backtest(serde_json::json!({
+            "dataset": "replay",
+            "bar_interval": "1d",
+            "overrides": { "long_window": 5 },
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "result: {result}");
+    let manifest = &result["manifest"];
+    assert_eq!(manifest["inputs"][0]["source"], "dataset");
+    assert_eq!(manifest["inputs"][0]["dataset"], "replay");
+    assert_eq!(manifest["inputs"][0]["points"], 30);
+    assert_eq!(manifest["params"]["long_window"], 5);
+    assert_eq!(manifest["input_hash"].as_str().map(str::len), Some(64));
+    let dataset_run = result["id"].as_str().expect("backtest id").to_string();
+
+    let (status, replayed) = send_json(&router, replay(&dataset_run)).await;
+    assert_eq!(status, StatusCode::OK, "replay: {replayed}");
+    assert_eq!(replayed["reproduced"], true, "replay: {replayed}");
+    assert_eq!(replayed["mismatches"], serde_json::json!([]));
+    assert_eq!(
+        replayed["result"]["manifest"]["result_hash"],
+        manifest["result_hash"]
+    );
+
+    // Inline prices replay from the stored request.
+    let prices: Vec<serde_json::Value> = (0..40)
+        .map(|i| {
+            serde_json::json!({
+                "timestamp": now - ChronoDuration::days(40 - i),
+                "price": 100.0 + 10.0 * (i as f64 / 4.0).cos(),
+            })
+        })
+        .collect();
+    let (_, inline) = send_json(&router, backtest(serde_json::json!({ "prices": prices }))).await;
+    assert_eq!(inline["manifest"]["inputs"][0]["source"], "inline");
+    let (_, replayed) = send_json(&router, replay(inline["id"].as_str().expect("id"))).await;
+    assert_eq!(replayed["reproduced"], true, "replay: {replayed}");
+
+    // Changed data is caught and pinned down to the series.
+    let (status, _) = send_json(&router, upload(csv(5.0))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, replayed) = send_json(&router, replay(&dataset_run)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(replayed["reproduced"], false);
+    let fields: Vec<&str> = replayed["mismatches"]
+        .as_array()
+        .expect("mismatches")
+        .iter()
+        .map(|m| m["field"].as_str().unwrap())
+        .collect();
+    assert!(
+        fields.starts_with(&["inputs[prices]", "input_hash", "result_hash"]),
+        "mismatches: {fields:?}"
+    );
+
+    let (status, _) = send_json(&router, replay(&Uuid::new_v4().to_string())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+/// Collects the messages the paper trader sends.
+#[derive(Default)]
+struct RecordingNotifier {
+    messages: std::sync::Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl AlertNotifier for RecordingNotifier {
+    async fn notify(&self, _rule_id: Uuid, _wallet_id: Uuid, message: &str) {
+        self.messages.lock().unwrap().push(message.to_string());
+    }
+}
+
+#[sqlx::test(migrations = "../migrations")]
+async fn paper_trading_tracks_equity_and_alerts(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let wallet_id = Uuid::new_v4();
+    let wallet_address = "0x00000000000000000000000000000000000000ff";
+    let now = Utc::now();
+
+    sqlx::query("INSERT INTO users (id, primary_wallet) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(wallet_address)
+        .execute(&pool)
+        .await
+        .expect("insert user");
+    sqlx::query("INSERT INTO wallets (id, user_id, address, chain_id) VALUES ($1, $2, $3, 1)")
+        .bind(wallet_id)
+        .bind(user_id)
+        .bind(wallet_address)
+        .execute(&pool)
+        .await
+        .expect("insert wallet");
+
+    let state = test_state(pool.clone(), auth_claims(user_id)).await;
+    let router = build_router(
+        state.clone(),
+        vec![HeaderValue::from_static("http://localhost:3000")],
+    );
+    let json = |method: &str, uri: String, body: serde_json::Value| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let record = |prices: Vec<(chrono::DateTime<Utc>, f64)>| {
+        let repo = state.price_history_repo.clone();
+        async move {
+            let points: Vec<PriceHistoryPoint> = prices
+                .into_iter()
+                .map(|(price_ts, price)| PriceHistoryPoint {
+                    id: Uuid::new_v4(),
+                    symbol: "PAPER".to_string(),
+                    price,
+                    price_ts,
+                    source: "test".to_string(),
+                    chain_id: Some(1),
+                    ..PriceHistoryPoint::default()
+                })
+                .collect();
+            repo.upsert_points(&points).await.expect("record prices");
+        }
+    };
+    let notifier = Arc::new(RecordingNotifier::default());
+    let trader = PaperTrader::new(notifier.clone(), Duration::from_secs(60));
+
+    let (status, created) = send_json(
+        &router,
+        json(
+            "POST",
+            "/api/strategies".to_string(),
+            serde_json::json!({
+                "name": "ma",
+                "type": "ma_cross",
+                "params": { "short_window": 2, "long_window": 4 },
+            }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let strategy_id = created["id"].as_str().expect("id").to_string();
+
+    // Falling prices before activation only warm the indicators up.
+    record(
+        (0..10)
+            .map(|i| (now - ChronoDuration::minutes(20 - i), 100.0 - i as f64))
+            .collect(),
+    )
+    .await;
+    let (status, account) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/strategies/{strategy_id}/paper"),
+            serde_json::json!({ "symbol": "paper", "chain_id": 1, "initial_equity": 1000.0 }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(account["status"], "active");
+    assert_eq!(account["symbol"], "PAPER");
+    assert_eq!(account["wallet_id"], wallet_id.to_string());
+    let (status, _) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/strategies/{strategy_id}/paper"),
+            serde_json::json!({}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(trader.run_once(&state).await.expect("tick"), 0);
+
+    // A rally after activation turns the signal long.
+    let rally = [92.0, 94.0, 97.0, 101.0, 106.0];
+    record(
+        rally
+            .iter()
+            .enumerate()
+            .map(|(i, price)| (now + ChronoDuration::minutes(i as i64 + 1), *price))
+            .collect(),
+    )
+    .await;
+    assert_eq!(trader.run_once(&state).await.expect("tick"), 5);
+    assert_eq!(trader.run_once(&state).await.expect("tick"), 0);
+
+    let (status, report) = send_json(
+        &router,
+        json(
+            "GET",
+            format!("/api/strategies/{strategy_id}/paper"),
+            serde_json::Value::Null,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let curve = report["equity_curve"].as_array().expect("equity curve");
+    assert_eq!(curve.len(), 6);
+    assert_eq!(curve[0][1].as_f64(), Some(1000.0));
+    assert_eq!(report["account"]["position"].as_f64(), Some(1.0));
+    assert_eq!(report["account"]["last_price"].as_f64(), Some(106.0));
+    let equity = report["account"]["equity"].as_f64().expect("equity");
+    assert!(equity > 1000.0, "long through the rally gains: {equity}");
+    assert_eq!(curve[5][1].as_f64(), Some(equity));
+    assert!(report["metrics"]["total_return"].is_number());
+    assert_eq!(notifier.messages.lock().unwrap().len(), 1);
+    assert!(notifier.messages.lock().unwrap()[0].contains("flat -> long"));
+
+    // A sell-off flips it back to flat on a later tick.
+    record(
+        [100.0, 93.0, 88.0]
+            .iter()
+            .enumerate()
+            .map(|(i, price)| (now + ChronoDuration::minutes(i as i64 + 10), *price))
+            .collect(),
+    )
+    .await;
+    assert_eq!(trader.run_once(&state).await.expect("tick"), 3);
+    let (status, triggers) = send_json(
+        &router,
+        json(
+            "GET",
+            "/api/alerts/triggers".to_string(),
+            serde_json::Value::Null,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let triggers = triggers.as_array().expect("triggers");
+    assert_eq!(triggers.len(), 2);
+    assert!(
+        notifier.messages.lock().unwrap()[1].contains("long -> flat"),
+        "{:?}",
+        notifier.messages.lock().unwrap()
+    );
+
+    // The ticks booked one continuous backtest over the warm-up and every
+    // point since, not a backtest per tick.
+    let (_, report) = send_json(
+        &router,
+        json(
+            "GET",
+            format!("/api/strategies/{strategy_id}/paper"),
+            serde_json::Value::Null,
+        ),
+    )
+    .await;
+    assert!(report["account"]["replay_from"].is_string());
+    let strategy = state
+        .strategy_repo
+        .find_by_id(strategy_id.parse().unwrap(), user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let series: Vec<PricePoint> = state
+        .price_history_repo
+        .fetch_range(
+            "PAPER",
+            Some(1),
+            now - ChronoDuration::hours(1),
+            now + ChronoDuration::hours(1),
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|p| PricePoint::new(p.price_ts, p.price))
+        .collect();
+    let single = state.strategy.backtest(&strategy, &series).await.unwrap();
+    let curve = &single.equity_curve;
+    let expected = 1000.0 * curve[curve.len() - 1].1 / curve[9].1;
+    let equity = report["account"]["equity"].as_f64().unwrap();
+    assert!((equity - expected).abs() < 1e-6, "{equity} vs {expected}");
+
+    let (status, stopped) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/strategies/{strategy_id}/paper/stop"),
+            serde_json::Value::Null,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stopped["status"], "stopped");
+    let (status, _) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/strategies/{strategy_id}/paper/stop"),
+            serde_json::Value::Null,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    record(vec![(now + ChronoDuration::minutes(30), 120.0)]).await;
+    assert_eq!(trader.run_once(&state).await.expect("tick"), 0);
+
+    let (status, _) = send_json(
+        &router,
+        json(
+            "GET",
+            format!("/api/strategies/{}/paper", Uuid::new_v4()),
+            serde_json::Value::Null,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+#[sqlx::test(migrations = "../migrations")]
+async fn what_if_replays_strategy_over_wallet_holdings(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let wallet_id = Uuid::new_v4();
+    let other_wallet_id = Uuid::new_v4();
+    let wallet_address = "0x00000000000000000000000000000000000000fe";
+
+    let other_user_id = Uuid::new_v4();
+    for (id, address) in [
+        (user_id, wallet_address),
+        (other_user_id, "0x00000000000000000000000000000000000000fd"),
+    ] {
+        sqlx::query("INSERT INTO users (id, primary_wallet) VALUES ($1, $2)")
+            .bind(id)
+            .bind(address)
+            .execute(&pool)
+            .await
+            .expect("insert user");
+    }
+    for (id, owner, address) in [
+        (wallet_id, user_id, wallet_address),
+        (
+            other_wallet_id,
+            other_user_id,
+            "0x00000000000000000000000000000000000000fd",
+        ),
+    ] {
+        sqlx::query("INSERT INTO wallets (id, user_id, address, chain_id) VALUES ($1, $2, $3, 1)")
+            .bind(id)
+            .bind(owner)
+            .bind(address)
+            .execute(&pool)
+            .await
+            .expect("insert wallet");
+    }
+
+    let state = test_state(pool.clone(), auth_claims(user_id)).await;
+    let router = build_router(
+        state.clone(),
+        vec![HeaderValue::from_static("http://localhost:3000")],
+    );
+    let json = |method: &str, uri: String, body: serde_json::Value| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    // 2 ETH and 100 USDC; ETH goes 100 -> 120 -> 90 and the wallet really
+    // sold one ETH (and the proceeds left the wallet) on the last day.
+    let start = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+    for (offset, eth, eth_price, total) in [
+        (0, 2.0, 100.0, 300.0),
+        (1, 2.0, 120.0, 340.0),
+        (2, 1.0, 90.0, 190.0),
+    ] {
+        let positions = [
+            Position {
+                asset_symbol: "ETH".to_string(),
+                amount: eth,
+                usd_value: eth * eth_price,
+            },
+            Position {
+                asset_symbol: "USDC".to_string(),
+                amount: 100.0,
+                usd_value: 100.0,
+            },
+        ];
+        state
+            .portfolio_repo
+            .upsert_daily_snapshot(
+                wallet_id,
+                start + ChronoDuration::days(offset),
+                total,
+                &positions,
+            )
+            .await
+            .expect("daily snapshot");
+    }
+
+    let create = |body: serde_json::Value| json("POST", "/api/strategies".to_string(), body);
+    let (status, hedge) = send_json(
+        &router,
+        create(serde_json::json!({
+            "name": "half hedge",
+            "type": "script",
+            "params": { "source": "0.5" },
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let hedge_id = hedge["id"].as_str().unwrap().to_string();
+
+    let (status, report) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/strategies/{hedge_id}/what-if"),
+            serde_json::json!({ "wallet_id": wallet_id }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["managed_symbols"], serde_json::json!(["ETH"]));
+    assert_eq!(report["from"], "2024-03-01");
+    assert_eq!(report["to"], "2024-03-03");
+    let values: Vec<f64> = report["hypothetical_curve"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|point| point[1].as_f64().unwrap())
+        .collect();
+    for (value, expected) in values.iter().zip([300.0, 320.0, 292.5]) {
+        assert!((value - expected).abs() < 1e-9, "{values:?}");
+    }
+    assert_eq!(report["actual_curve"][2][1], 190.0);
+    assert_eq!(report["comparison"]["final_difference_usd"], 102.5);
+    assert_eq!(report["policy"]["strategy_version"], 1);
+
+    // Rebalancing ETH/USDC to 50/50 from the same starting positions.
+    let (status, rebalance) = send_json(
+        &router,
+        create(serde_json::json!({
+            "name": "50/50",
+            "type": "rebalance",
+            "params": {
+                "symbols": ["ETH", "USDC"],
+                "weighting": "fixed",
+                "weights": { "ETH": 0.5, "USDC": 0.5 },
+                "rebalance_every": 1,
+            },
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{rebalance}");
+    let rebalance_id = rebalance["id"].as_str().unwrap().to_string();
+    let (status, report) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/strategies/{rebalance_id}/what-if"),
+            serde_json::json!({ "wallet_id": wallet_id, "to": "2024-03-02" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["hypothetical_curve"].as_array().unwrap().len(), 2);
+    assert!(
+        !report["policy"]["weights_history"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    let (status, body) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/strategies/{hedge_id}/what-if"),
+            serde_json::json!({ "wallet_id": wallet_id, "symbol": "wbtc" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["fields"][0]["field"], "symbol");
+    let (status, body) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/strategies/{hedge_id}/what-if"),
+            serde_json::json!({ "wallet_id": wallet_id, "from": "2024-03-03" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["fields"][0]["field"], "wallet_id");
+    let (status, _) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/strategies/{hedge_id}/what-if"),
+            serde_json::json!({ "wallet_id": other_wallet_id }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+#[sqlx::test(migrations = "../migrations")]
+async fn wallet_backfill_rebuilds_daily_snapshots(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let wallet_id = Uuid::new_v4();
+    let other_wallet_id = Uuid::new_v4();
+    let wallet_address = "0x00000000000000000000000000000000000000fc";
+
+    let other_user_id = Uuid::new_v4();
+    for (id, address) in [
+        (user_id, wallet_address),
+        (other_user_id, "0x00000000000000000000000000000000000000fb"),
+    ] {
+        sqlx::query("INSERT INTO users (id, primary_wallet) VALUES ($1, $2)")
+            .bind(id)
+            .bind(address)
+            .execute(&pool)
+            .await
+            .expect("insert user");
+    }
+    for (id, owner, address) in [
+        (wallet_id, user_id, wallet_address),
+        (
+            other_wallet_id,
+            other_user_id,
+            "0x00000000000000000000000000000000000000fb",
+        ),
+    ] {
+        sqlx::query("INSERT INTO wallets (id, user_id, address, chain_id) VALUES ($1, $2, $3, 1)")
+            .bind(id)
+            .bind(owner)
+            .bind(address)
+            .execute(&pool)
+            .await
+            .expect("insert wallet");
+    }
+
+    let state = test_state(pool.clone(), auth_claims(user_id)).await;
+    let router = build_router(
+        state.clone(),
+        vec![HeaderValue::from_static("http://localhost:3000")],
+    );
+    let json = |method: &str, uri: String, body: serde_json::Value| {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let backfill_uri = format!("/api/wallets/{wallet_id}/backfill");
+    let (status, _) = send_json(
+        &router,
+        json("GET", backfill_uri.clone(), serde_json::json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, backfill) = send_json(
+        &router,
+        json(
+            "POST",
+            backfill_uri.clone(),
+            serde_json::json!({ "start_block": 100 }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{backfill}");
+    assert_eq!(backfill["status"], "pending");
+    assert_eq!(backfill["start_block"], 100);
+    let (status, _) = send_json(
+        &router,
+        json("POST", backfill_uri.clone(), serde_json::json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send_json(
+        &router,
+        json(
+            "POST",
+            format!("/api/wallets/{other_wallet_id}/backfill"),
+            serde_json::json!({}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Received 2 WETH and 100 USDC on day one, moved 1 WETH to itself on day
+    // two and sent 0.5 WETH away on day four. WETH is priced on days one and
+    // two only, from CoinGecko's chain-agnostic history on day one and the
+    // wallet chain's own series on day two; USDC is never priced.
+    let day =
+        |offset: i64| NaiveDate::from_ymd_opt(2024, 5, 1).unwrap() + ChronoDuration::days(offset);
+    let at = |offset: i64, hour: u32| day(offset).and_hms_opt(hour, 0, 0).unwrap().and_utc();
+    let other = "0x00000000000000000000000000000000000000aa";
+    let transfer =
+        |block: i64, symbol: &str, amount: f64, direction: &str, from: &str, to: &str, ts| {
+            WalletTransaction {
+                id: Uuid::new_v4(),
+                wallet_id,
+                chain_id: 1,
+                tx_hash: format!("0x{block:064x}"),
+                block_number: block,
+                log_index: 0,
+                asset_symbol: symbol.to_string(),
+                amount,
+                usd_value: 0.0,
+                direction: direction.to_string(),
+                from_address: from.to_string(),
+                to_address: to.to_string(),
+                block_timestamp: ts,
+            }
+        };
+    state
+        .transaction_repo
+        .insert_transactions(&[
+            transfer(110, "WETH", 2.0, "in", other, wallet_address, at(0, 9)),
+            transfer(111, "USDC", 100.0, "in", other, wallet_address, at(0, 10)),
+            transfer(
+                120,
+                "WETH",
+                1.0,
+                "in",
+                wallet_address,
+                wallet_address,
+                at(1, 9),
+            ),
+            transfer(140, "WETH", 0.5, "out", wallet_address, other, at(3, 9)),
+        ])
+        .await
+        .expect("insert transfers");
+    let price = |price: f64, ts, chain_id| PriceHistoryPoint {
+        id: Uuid::new_v4(),
+        symbol: "WETH".to_string(),
+        price,
+        price_ts: ts,
+        source: "test".to_string(),
+        chain_id,
+        ..PriceHistoryPoint::default()
+    };
+    state
+        .price_history_repo
+        .upsert_points(&[
+            price(100.0, at(0, 12), None),
+            price(110.0, at(1, 12), None),
+            price(120.0, at(1, 12), Some(1)),
+        ])
+        .await
+        .expect("insert prices");
+    // Day three already has a live snapshot, which the backfill keeps.
+    state
+        .portfolio_repo
+        .upsert_daily_snapshot(wallet_id, day(2), 999.0, &[])
+        .await
+        .expect("live snapshot");
+
+    let wallet = state
+        .wallet_repo
+        .find_by_id(wallet_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let filled = backfill::fill_daily_snapshots(
+        state.transaction_repo.as_ref(),
+        state.portfolio_repo.as_ref(),
+        state.price_history_repo.as_ref(),
+        &wallet,
+        day(3),
+    )
+    .await
+    .expect("fill daily snapshots");
+    assert_eq!(filled, 3);
+
+    let days = state
+        .portfolio_repo
+        .daily_snapshots(wallet_id, None, None)
+        .await
+        .unwrap();
+    let totals: Vec<(NaiveDate, f64)> = days.iter().map(|d| (d.day, d.total_usd_value)).collect();
+    assert_eq!(
+        totals,
+        vec![
+            (day(0), 200.0),
+            (day(1), 240.0),
+            (day(2), 999.0),
+            (day(3), 180.0)
+        ]
+    );
+    assert!(days.iter().all(|d| d.total_usd_value > 0.0));
+    let last = &days[3].positions;
+    assert_eq!(last.len(), 2);
+    assert_eq!(
+        (last[1].asset_symbol.as_str(), last[1].amount),
+        ("WETH", 1.5)
+    );
+    assert_eq!(
+        (last[0].asset_symbol.as_str(), last[0].usd_value),
+        ("USDC", 0.0)
+    );
+
+    state
+        .transaction_repo
+        .complete_backfill(wallet_id, filled as i32)
+        .await
+        .unwrap();
+    let (status, backfill) = send_json(
+        &router,
+        json("GET", backfill_uri.clone(), serde_json::json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(backfill["status"], "completed");
+    assert_eq!(backfill["days_filled"], 3);
+    let (status, backfill) =
+        send_json(&router, json("POST", backfill_uri, serde_json::json!({}))).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(backfill["start_block"], 0);
+}
+
+#[tokio::test]
+async fn log_fetcher_splits_rejected_ranges() {
+    let (provider, mock) = Provider::<MockProvider>::mocked();
+    let fetcher = LogFetcher::new(100, HashMap::from([(56, 50)]));
+    assert_eq!(fetcher.chunk_end(1, 0, 1_000), 99);
+    assert_eq!(fetcher.chunk_end(56, 0, 1_000), 49);
+    assert_eq!(fetcher.chunk_end(1, 950, 1_000), 1_000);
+
+    let log = |block: u64| Log {
+        block_number: Some(block.into()),
+        ..Log::default()
+    };
+    let error = |code: i64, message: &str| {
+        MockResponse::Error(JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        })
+    };
+    let too_many = "query returned more than 10000 results";
+    let filter = Filter::new().topic0(H256::repeat_byte(0xdd));
+    let ranged = |from: u64, to: u64| {
+        filter
+            .clone()
+            .from_block(BlockNumber::Number(from.into()))
+            .to_block(BlockNumber::Number(to.into()))
+    };
+
+    // The mock answers the last pushed response first. 0..=99 is split where
+    // the provider suggests, 20..=99 in half.
+    mock.push::<Vec<Log>, _>(vec![log(70), log(99)]).unwrap();
+    mock.push::<Vec<Log>, _>(vec![log(30)]).unwrap();
+    mock.push_response(error(-32005, too_many));
+    mock.push::<Vec<Log>, _>(vec![log(5)]).unwrap();
+    mock.push_response(error(
+        -32005,
+        &format!("{too_many}. Try with this block range [0x0, 0x13]."),
+    ));
+    let logs = fetcher.fetch(&provider, 1, &filter, 0, 99).await.unwrap();
+    let blocks: Vec<u64> = logs
+        .iter()
+        .map(|l| l.block_number.unwrap().as_u64())
+        .collect();
+    assert_eq!(blocks, vec![5, 30, 70, 99]);
+    for (from, to) in [(0, 99), (0, 19), (20, 99), (20, 59), (60, 99)] {
+        mock.assert_request("eth_getLogs", [ranged(from, to)])
+            .unwrap();
+    }
+    // Result limits depend on the blocks, so the chunk size stays.
+    assert_eq!(fetcher.chunk_blocks(1), 100);
+
+    // A block range limit narrows every later chunk on the chain.
+    mock.push::<Vec<Log>, _>(Vec::new()).unwrap();
+    mock.push::<Vec<Log>, _>(vec![log(10)]).unwrap();
+    mock.push_response(error(-32000, "block range too large"));
+    let logs = fetcher.fetch(&provider, 1, &filter, 0, 99).await.unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(fetcher.chunk_blocks(1), 50);
+    assert_eq!(fetcher.chunk_end(1, 100, 1_000), 149);
+    assert_eq!(fetcher.chunk_blocks(56), 50);
+
+    // Other errors, and a single block that is still rejected, are returned.
+    mock.push_response(error(-32000, "header not found"));
+    assert!(fetcher.fetch(&provider, 1, &filter, 0, 9).await.is_err());
+    mock.push_response(error(-32005, too_many));
+    assert!(fetcher.fetch(&provider, 1, &filter, 7, 7).await.is_err());
+}
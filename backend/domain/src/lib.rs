@@ -1,4 +1,6 @@
-use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -70,7 +72,27 @@ pub struct WalletTransaction {
     pub block_timestamp: DateTime<Utc>,
 }
 
+/// Progress of a wallet's historical backfill, kept on its sync cursor.
 #[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WalletBackfill {
+    pub wallet_id: Uuid,
+    pub chain_id: u64,
+    /// `pending`, `running` or `completed`.
+    pub status: String,
+    pub start_block: i64,
+    /// Chain head when the walk started; later blocks are left to the live sync.
+    pub target_block: Option<i64>,
+    /// Last block whose Transfer logs are stored.
+    pub scanned_block: Option<i64>,
+    /// Daily snapshots written for past days once the walk finished.
+    pub days_filled: i32,
+    /// Last failure; the walk retries from `scanned_block` on the next run.
+    pub error: Option<String>,
+    pub requested_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct PriceHistoryPoint {
     pub id: Uuid,
     pub symbol: String,
@@ -79,6 +101,115 @@ pub struct PriceHistoryPoint {
     pub source: String,
     #[serde(default)]
     pub chain_id: Option<u64>,
+    /// Bar fields, only present for uploaded OHLCV datasets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+}
+
+/// A named price series uploaded by a user. Its points are stored in
+/// `price_history` under `series_key` rather than a real symbol, so they never
+/// mix with fetched prices.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PriceDataset {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    /// Asset the series prices, for display.
+    pub symbol: String,
+    pub source: String,
+    pub series_key: String,
+    pub point_count: i64,
+    pub first_ts: DateTime<Utc>,
+    pub last_ts: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Paper trading state of an activated strategy: the simulated position and
+/// equity as of the last price it processed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaperAccount {
+    pub strategy_id: Uuid,
+    /// `active` or `stopped`.
+    pub status: String,
+    pub symbol: String,
+    pub chain_id: u64,
+    /// Strategy version the account runs, pinned at activation.
+    pub strategy_version: i32,
+    /// Wallet signal alerts are recorded against.
+    pub wallet_id: Uuid,
+    /// `strategy_signal` alert rule fired on signal flips.
+    pub alert_rule_id: Option<Uuid>,
+    pub initial_equity: f64,
+    pub equity: f64,
+    /// Signed exposure held after the last processed price.
+    pub position: f64,
+    pub last_price: Option<f64>,
+    pub last_price_ts: Option<DateTime<Utc>>,
+    /// First bar of the series replayed on every tick: the warm-up before
+    /// activation, fixed once the first bar is booked.
+    #[serde(default)]
+    pub replay_from: Option<DateTime<Utc>>,
+    pub activated_at: DateTime<Utc>,
+    pub stopped_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Equity after one processed price, and the position taken at it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PaperEquityPoint {
+    pub timestamp: DateTime<Utc>,
+    pub price: f64,
+    pub position: f64,
+    pub equity: f64,
+}
+
+/// A paper account with its equity so far, shaped like a backtest result.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaperTradingReport {
+    pub account: PaperAccount,
+    pub equity_curve: Vec<(DateTime<Utc>, f64)>,
+    pub positions: Vec<(DateTime<Utc>, f64)>,
+    pub metrics: serde_json::Value,
+}
+
+/// A wallet's holdings at the end of a day, from `portfolio_daily_snapshots`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DailySnapshot {
+    pub wallet_id: Uuid,
+    pub day: NaiveDate,
+    pub total_usd_value: f64,
+    pub positions: Vec<Position>,
+}
+
+/// A strategy replayed over a wallet's real holdings next to what the wallet
+/// actually did. The strategy manages `managed_symbols` from the starting
+/// positions; the other starting positions are held untouched.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WhatIfReport {
+    pub wallet_id: Uuid,
+    pub strategy_id: Uuid,
+    pub strategy_version: i32,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub starting_positions: Vec<Position>,
+    pub managed_symbols: Vec<String>,
+    /// Hypothetical wallet value in USD, one point per snapshot day.
+    pub hypothetical_curve: Vec<(DateTime<Utc>, f64)>,
+    /// The wallet's recorded `total_usd_value` on the same days.
+    pub actual_curve: Vec<(DateTime<Utc>, f64)>,
+    pub hypothetical_metrics: serde_json::Value,
+    pub actual_metrics: serde_json::Value,
+    /// Hypothetical against actual: excess return, USD difference, alpha,
+    /// beta, tracking error and information ratio.
+    pub comparison: serde_json::Value,
+    /// The strategy's own backtest over the managed holdings, with its trades.
+    pub policy: BacktestResult,
 }
 
 #[derive(Debug, Deserialize)]
@@ -101,14 +232,329 @@ pub struct Strategy {
     pub name: String,
     pub r#type: String,
     pub params: serde_json::Value,
+    /// Latest version; every update that changes the strategy adds one.
+    #[serde(default)]
+    pub version: i32,
+}
+
+/// An immutable snapshot of a strategy's settings.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StrategyVersion {
+    pub strategy_id: Uuid,
+    pub version: i32,
+    pub name: String,
+    pub r#type: String,
+    pub params: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Differences between two versions of a strategy. Params are compared key by
+/// key, descending into nested objects; `path` joins the keys with dots.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StrategyVersionDiff {
+    pub strategy_id: Uuid,
+    pub from: i32,
+    pub to: i32,
+    pub changes: Vec<ParamChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ParamChange {
+    /// `name`, `type`, or `params.<key>` for params.
+    pub path: String,
+    /// `added`, `removed` or `changed`.
+    pub change: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    Long,
+    Short,
+}
+
+/// One round trip in a backtest, from leaving flat (or flipping side) until the
+/// position is closed. `pnl` is in equity units (the curve starts at 1.0) and
+/// includes trading costs; trades still open at the end are marked to the last bar.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BacktestTrade {
+    pub side: TradeSide,
+    pub size: f64,
+    pub entry_time: DateTime<Utc>,
+    pub entry_price: f64,
+    pub exit_time: DateTime<Utc>,
+    pub exit_price: f64,
+    pub pnl: f64,
+    pub return_pct: f64,
+    pub holding_secs: i64,
+    pub closed: bool,
+    /// Why the trade was closed; `None` while it is still open at the end.
+    #[serde(default)]
+    pub exit_reason: Option<ExitReason>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    /// The strategy's own signal went flat or flipped side.
+    Signal,
+    StopLoss,
+    TakeProfit,
+    TrailingStop,
+    MaxHolding,
+}
+
+impl ExitReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExitReason::Signal => "signal",
+            ExitReason::StopLoss => "stop_loss",
+            ExitReason::TakeProfit => "take_profit",
+            ExitReason::TrailingStop => "trailing_stop",
+            ExitReason::MaxHolding => "max_holding",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BacktestResult {
+    /// Id of the stored backtest, as taken by the replay endpoint; `None`
+    /// until the result is saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
     pub strategy_id: Uuid,
     pub equity_curve: Vec<(DateTime<Utc>, f64)>,
     pub metrics: serde_json::Value,
+    #[serde(default)]
+    pub trades: Vec<BacktestTrade>,
+    /// Benchmark equity aligned to `equity_curve` timestamps, starting at 1.0.
+    #[serde(default)]
+    pub benchmark_curve: Vec<(DateTime<Utc>, f64)>,
+    /// Per-asset weights at each bar of a multi-asset backtest; empty otherwise.
+    #[serde(default)]
+    pub weights_history: Vec<WeightsPoint>,
+    /// Strategy version the backtest ran; `None` for runs older than versioning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy_version: Option<i32>,
+    /// Params overridden for this run on top of that version.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub overrides: serde_json::Map<String, serde_json::Value>,
+    /// Monte Carlo resampling of the result, when it was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub robustness: Option<RobustnessReport>,
+    /// What the backtest ran on, for replaying it; `None` for runs older than manifests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<BacktestManifest>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Everything needed to re-run a backtest and check that it still gives the
+/// same result. Hashes are hex SHA-256.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BacktestManifest {
+    /// Hash of the series the engine ran on, after resampling, and of the benchmark.
+    pub input_hash: String,
+    /// Hash of the equity curve, trades, metrics and reports of the result.
+    pub result_hash: String,
+    /// Series as loaded, in load order.
+    pub inputs: Vec<ManifestInput>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub benchmark: Option<ManifestInput>,
+    /// Seed of synthetic series; each one records the seed derived from it.
+    pub seed: u64,
+    /// Version of the strategy engine that computed the result.
+    pub engine_version: String,
+    pub strategy_type: String,
+    /// Params the engine ran with, overrides applied.
+    pub params: serde_json::Value,
+    /// The backtest request, for its resampling, benchmark and robustness options.
+    pub request: serde_json::Value,
+}
+
+/// Where one series of a backtest came from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ManifestInput {
+    /// Symbol the series was loaded for; `None` for inline prices of a
+    /// single-series backtest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// `inline`, `dataset`, `history` or `synthetic`.
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<String>,
+    /// Seed a synthetic series was generated from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// First and last timestamp of the series.
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub points: usize,
+    pub hash: String,
+}
+
+/// Outcome of re-running a stored backtest from its manifest.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BacktestReplay {
+    pub backtest_id: Uuid,
+    /// Both the inputs and the result hash matched.
+    pub reproduced: bool,
+    /// Engine version recorded in the manifest and the one that replayed it.
+    pub recorded_engine_version: String,
+    pub engine_version: String,
+    /// What differed, from inputs down to single metrics.
+    pub mismatches: Vec<ReplayMismatch>,
+    /// The re-run result; it is not stored.
+    pub result: BacktestResult,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReplayMismatch {
+    /// `input_hash`, `result_hash`, `inputs[<symbol>]`, `benchmark` or `metrics.<key>`.
+    pub field: String,
+    pub expected: serde_json::Value,
+    pub actual: serde_json::Value,
+}
+
+/// Outcome of resampling a backtest `iterations` times with a fixed seed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RobustnessReport {
+    /// `block_bootstrap` or `trade_shuffle`.
+    pub method: String,
+    pub iterations: usize,
+    pub seed: u64,
+    /// Bars per resampled block; only set for the block bootstrap.
+    #[serde(default)]
+    pub block_size: Option<usize>,
+    /// Equity (the curve starts at 1.0) at or below which a path counts as ruined.
+    pub ruin_threshold: f64,
+    /// Share of paths that touched `ruin_threshold`.
+    pub probability_of_ruin: f64,
+    /// Percentiles of the simulated equity across paths, at up to a few hundred
+    /// evenly spaced points of the curve.
+    pub equity_bands: Vec<EquityBand>,
+    pub total_return: Distribution,
+    pub max_drawdown: Distribution,
+    pub sharpe: Distribution,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EquityBand {
+    pub timestamp: DateTime<Utc>,
+    pub p5: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p95: f64,
+}
+
+/// Mean and percentiles of a statistic across simulated paths.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Distribution {
+    pub mean: f64,
+    pub p5: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p95: f64,
+}
+
+/// Portfolio weights (fractions of equity) held after the close of a bar.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WeightsPoint {
+    pub timestamp: DateTime<Utc>,
+    pub weights: BTreeMap<String, f64>,
+    /// Whether the portfolio was traded back to its targets on this bar.
+    pub rebalanced: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BacktestJobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl BacktestJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BacktestJobStatus::Queued => "queued",
+            BacktestJobStatus::Running => "running",
+            BacktestJobStatus::Succeeded => "succeeded",
+            BacktestJobStatus::Failed => "failed",
+            BacktestJobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(BacktestJobStatus::Queued),
+            "running" => Some(BacktestJobStatus::Running),
+            "succeeded" => Some(BacktestJobStatus::Succeeded),
+            "failed" => Some(BacktestJobStatus::Failed),
+            "cancelled" => Some(BacktestJobStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Finished jobs never change status again.
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, BacktestJobStatus::Queued | BacktestJobStatus::Running)
+    }
+}
+
+/// What a queued job runs: a single backtest, or a parameter search.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BacktestJobKind {
+    #[default]
+    Backtest,
+    Optimize,
+}
+
+impl BacktestJobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BacktestJobKind::Backtest => "backtest",
+            BacktestJobKind::Optimize => "optimize",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "backtest" => Some(BacktestJobKind::Backtest),
+            "optimize" => Some(BacktestJobKind::Optimize),
+            _ => None,
+        }
+    }
+}
+
+/// A backtest or optimization queued for the worker pool. `progress` runs
+/// from 0.0 to 1.0; once the job has succeeded, a backtest carries `result`
+/// and an optimization `optimization` (the ranked trials).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BacktestJob {
+    pub id: Uuid,
+    pub strategy_id: Uuid,
+    #[serde(default)]
+    pub kind: BacktestJobKind,
+    pub status: BacktestJobStatus,
+    pub progress: f64,
+    pub error: Option<String>,
+    pub request: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<BacktestResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimization: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
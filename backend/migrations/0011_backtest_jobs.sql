@@ -0,0 +1,14 @@
+-- Backtests run as queued jobs; rows written before this migration are finished runs.
+ALTER TABLE strategy_backtests
+    ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'succeeded',
+    ADD COLUMN IF NOT EXISTS progress DOUBLE PRECISION NOT NULL DEFAULT 1,
+    ADD COLUMN IF NOT EXISTS error TEXT,
+    ADD COLUMN IF NOT EXISTS request JSONB NOT NULL DEFAULT '{}'::jsonb,
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- Queued jobs have not started yet.
+ALTER TABLE strategy_backtests ALTER COLUMN started_at DROP NOT NULL;
+ALTER TABLE strategy_backtests ALTER COLUMN started_at DROP DEFAULT;
+
+CREATE INDEX IF NOT EXISTS idx_strategy_backtests_status_created
+    ON strategy_backtests (status, created_at);
@@ -0,0 +1,21 @@
+-- Uploaded price series. Their points live in price_history under `series_key`
+-- (never a real symbol), so they cannot mix with fetched prices.
+ALTER TABLE price_history
+    ADD COLUMN IF NOT EXISTS open DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS high DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS low DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS volume DOUBLE PRECISION;
+
+CREATE TABLE IF NOT EXISTS price_datasets (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    source TEXT NOT NULL,
+    series_key TEXT NOT NULL UNIQUE,
+    point_count BIGINT NOT NULL,
+    first_ts TIMESTAMPTZ NOT NULL,
+    last_ts TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
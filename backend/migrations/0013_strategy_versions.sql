@@ -0,0 +1,29 @@
+-- Strategies are editable; each change is kept as an immutable version and
+-- `strategies` holds the latest one.
+ALTER TABLE strategies
+    ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE TABLE IF NOT EXISTS strategy_versions (
+    strategy_id UUID NOT NULL REFERENCES strategies(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    type TEXT NOT NULL,
+    params JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (strategy_id, version)
+);
+
+INSERT INTO strategy_versions (strategy_id, version, name, type, params, created_at)
+SELECT id, version, name, type, params, created_at FROM strategies
+ON CONFLICT DO NOTHING;
+
+-- Until now every strategy had a single version; overrides of old runs are unknown.
+ALTER TABLE strategy_backtests
+    ADD COLUMN IF NOT EXISTS strategy_version INTEGER,
+    ADD COLUMN IF NOT EXISTS overrides JSONB;
+
+UPDATE strategy_backtests SET strategy_version = 1 WHERE strategy_version IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_strategy_backtests_strategy_version
+    ON strategy_backtests (strategy_id, strategy_version);
@@ -0,0 +1,30 @@
+-- Live paper trading: an activated strategy follows fresh price_history points
+-- with a simulated position, one account per strategy.
+CREATE TABLE IF NOT EXISTS paper_accounts (
+    strategy_id UUID PRIMARY KEY REFERENCES strategies(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'active',
+    symbol TEXT NOT NULL,
+    chain_id BIGINT NOT NULL,
+    strategy_version INTEGER NOT NULL,
+    wallet_id UUID NOT NULL REFERENCES wallets(id) ON DELETE CASCADE,
+    alert_rule_id UUID REFERENCES alert_rules(id) ON DELETE SET NULL,
+    initial_equity DOUBLE PRECISION NOT NULL,
+    equity DOUBLE PRECISION NOT NULL,
+    position DOUBLE PRECISION NOT NULL DEFAULT 0,
+    last_price DOUBLE PRECISION,
+    last_price_ts TIMESTAMPTZ,
+    activated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    stopped_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_paper_accounts_status ON paper_accounts (status);
+
+CREATE TABLE IF NOT EXISTS paper_equity (
+    strategy_id UUID NOT NULL REFERENCES paper_accounts(strategy_id) ON DELETE CASCADE,
+    ts TIMESTAMPTZ NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    position DOUBLE PRECISION NOT NULL,
+    equity DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (strategy_id, ts)
+);
@@ -0,0 +1,13 @@
+-- Historical backfill progress per wallet: the Transfer-log walk from
+-- backfill_start_block to backfill_target_block resumes after backfill_block.
+ALTER TABLE wallet_sync_cursors
+    ADD COLUMN IF NOT EXISTS backfill_status TEXT,
+    ADD COLUMN IF NOT EXISTS backfill_start_block BIGINT,
+    ADD COLUMN IF NOT EXISTS backfill_target_block BIGINT,
+    ADD COLUMN IF NOT EXISTS backfill_block BIGINT,
+    ADD COLUMN IF NOT EXISTS backfill_days INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS backfill_error TEXT,
+    ADD COLUMN IF NOT EXISTS backfill_requested_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS backfill_completed_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_wallet_sync_cursors_backfill ON wallet_sync_cursors (backfill_status);
@@ -0,0 +1,8 @@
+-- Running backtest jobs hold a lease their worker keeps renewing; a job whose
+-- lease ran out lost its worker and goes back in the queue.
+ALTER TABLE strategy_backtests
+    ADD COLUMN IF NOT EXISTS lease_until TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_strategy_backtests_running_lease
+    ON strategy_backtests (lease_until)
+    WHERE status = 'running';
@@ -0,0 +1,4 @@
+-- Parameter searches share the backtest job queue; `result` holds the ranked
+-- trials of an `optimize` job and only `backtest` rows are backtest history.
+ALTER TABLE strategy_backtests
+    ADD COLUMN IF NOT EXISTS kind TEXT NOT NULL DEFAULT 'backtest';
@@ -0,0 +1,5 @@
+-- First bar of the series a paper account replays on every tick (its warm-up
+-- before activation), fixed when the account books its first bar so the
+-- history behind already booked bars never shifts.
+ALTER TABLE paper_accounts
+    ADD COLUMN IF NOT EXISTS replay_from TIMESTAMPTZ;
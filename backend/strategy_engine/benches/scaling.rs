@@ -0,0 +1,97 @@
+//! Backtest throughput against series length. Run with
+//! `cargo bench -p strategy_engine --bench scaling`; nanoseconds per bar
+//! should stay flat as the series grows, and the quadrupled window of the
+//! second MA run should not change it.
+
+use std::time::Instant;
+
+use chrono::{Duration, TimeZone, Utc};
+use domain::Strategy;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use strategy_engine::{InMemoryStrategyService, PricePoint, StrategyService};
+use uuid::Uuid;
+
+const SIZES: [usize; 5] = [125_000, 250_000, 500_000, 1_000_000, 2_000_000];
+
+/// Minute bars of a seeded random walk.
+fn minute_bars(len: usize) -> Vec<PricePoint> {
+    let mut rng = StdRng::seed_from_u64(7);
+    let start = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+    let mut price = 1_000.0;
+    (0..len)
+        .map(|i| {
+            price *= 1.0 + rng.gen_range(-0.001..0.001);
+            PricePoint::new(start + Duration::minutes(i as i64), price)
+        })
+        .collect()
+}
+
+fn strategy(kind: &str, params: serde_json::Value) -> Strategy {
+    Strategy {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        name: "bench".to_string(),
+        r#type: kind.to_string(),
+        params,
+        version: 1,
+    }
+}
+
+fn main() {
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    let service = InMemoryStrategyService;
+    let cases = [
+        (
+            "ma_cross 50/500",
+            strategy(
+                "ma_cross",
+                serde_json::json!({ "short_window": 50, "long_window": 500 }),
+            ),
+        ),
+        (
+            "ma_cross 200/2000",
+            strategy(
+                "ma_cross",
+                serde_json::json!({ "short_window": 200, "long_window": 2000 }),
+            ),
+        ),
+        (
+            "bollinger 1440 + atr stop",
+            strategy(
+                "bollinger",
+                serde_json::json!({ "period": 1440, "atr_stop": 3.0 }),
+            ),
+        ),
+        (
+            "macd + vol_target",
+            strategy(
+                "macd",
+                serde_json::json!({ "sizing": "vol_target", "sizing_lookback": 1440 }),
+            ),
+        ),
+    ];
+
+    println!(
+        "{:<28} {:>10} {:>12} {:>10}",
+        "case", "bars", "elapsed ms", "ns/bar"
+    );
+    for (name, strategy) in &cases {
+        for len in SIZES {
+            let prices = minute_bars(len);
+            let started = Instant::now();
+            let result = runtime
+                .block_on(service.backtest(strategy, &prices))
+                .expect("backtest");
+            let elapsed = started.elapsed();
+            assert_eq!(result.equity_curve.len(), len);
+            println!(
+                "{:<28} {:>10} {:>12.1} {:>10.1}",
+                name,
+                len,
+                elapsed.as_secs_f64() * 1e3,
+                elapsed.as_nanos() as f64 / len as f64
+            );
+        }
+    }
+}
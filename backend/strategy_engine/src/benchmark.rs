@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use domain::BacktestResult;
+
+use crate::metrics::{mean, simple_returns, MetricsConfig};
+use crate::PricePoint;
+
+/// Label used when the strategy is compared with holding its own asset.
+pub const BUY_AND_HOLD: &str = "buy_and_hold";
+
+/// Benchmark equity (starting at 1.0) sampled at each of `timestamps`.
+///
+/// Uses the last benchmark price at or before every timestamp, so a second
+/// symbol with a different sampling grid still lines up with the strategy
+/// curve. Timestamps before the first benchmark price hold at 1.0. Both inputs
+/// are expected in ascending time order.
+pub fn benchmark_curve(
+    timestamps: &[DateTime<Utc>],
+    benchmark: &[PricePoint],
+) -> Vec<(DateTime<Utc>, f64)> {
+    let Some(base) = benchmark.iter().map(|p| p.price).find(|p| *p > 0.0) else {
+        return Vec::new();
+    };
+    let mut cursor = 0;
+    let mut last = base;
+    timestamps
+        .iter()
+        .map(|ts| {
+            while cursor < benchmark.len() && benchmark[cursor].timestamp <= *ts {
+                if benchmark[cursor].price > 0.0 {
+                    last = benchmark[cursor].price;
+                }
+                cursor += 1;
+            }
+            (*ts, last / base)
+        })
+        .collect()
+}
+
+/// Attaches `benchmark` to a finished backtest: stores the aligned benchmark
+/// curve and inserts `benchmark`, `benchmark_total_return`, `alpha`, `beta`,
+/// `tracking_error` and `information_ratio` into the metrics. Alpha, tracking
+/// error and information ratio are annualized with `config`, like the rest of
+/// the metrics. Replaces any benchmark attached earlier.
+pub fn attach(
+    result: &mut BacktestResult,
+    benchmark: &[PricePoint],
+    label: &str,
+    config: &MetricsConfig,
+) {
+    let timestamps: Vec<DateTime<Utc>> = result.equity_curve.iter().map(|(ts, _)| *ts).collect();
+    let curve = benchmark_curve(&timestamps, benchmark);
+    if curve.is_empty() {
+        return;
+    }
+
+    let strategy_returns = simple_returns(&result.equity_curve);
+    let benchmark_returns = simple_returns(&curve);
+    let periods_per_year = config
+        .annualization(timestamps.iter().copied())
+        .periods_per_year;
+    let stats = relative_stats(&strategy_returns, &benchmark_returns, periods_per_year);
+    let benchmark_total_return = curve.last().map(|(_, v)| v - 1.0).unwrap_or(0.0);
+
+    if let Some(obj) = result.metrics.as_object_mut() {
+        obj.insert("benchmark".to_string(), serde_json::json!(label));
+        obj.insert(
+            "benchmark_total_return".to_string(),
+            serde_json::json!(benchmark_total_return),
+        );
+        obj.insert("alpha".to_string(), serde_json::json!(stats.alpha));
+        obj.insert("beta".to_string(), serde_json::json!(stats.beta));
+        obj.insert(
+            "tracking_error".to_string(),
+            serde_json::json!(stats.tracking_error),
+        );
+        obj.insert(
+            "information_ratio".to_string(),
+            serde_json::json!(stats.information_ratio),
+        );
+    }
+    result.benchmark_curve = curve;
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RelativeStats {
+    pub alpha: f64,
+    pub beta: f64,
+    pub tracking_error: f64,
+    /// Undefined (`None`) when the strategy tracks the benchmark exactly.
+    pub information_ratio: Option<f64>,
+}
+
+/// Regression of strategy returns on benchmark returns (zero risk-free rate).
+pub fn relative_stats(strategy: &[f64], benchmark: &[f64], periods_per_year: f64) -> RelativeStats {
+    let n = strategy.len().min(benchmark.len());
+    if n < 2 {
+        return RelativeStats::default();
+    }
+    let (strategy, benchmark) = (&strategy[..n], &benchmark[..n]);
+    let mean_s = mean(strategy);
+    let mean_b = mean(benchmark);
+    let mut cov = 0.0;
+    let mut var_b = 0.0;
+    for (s, b) in strategy.iter().zip(benchmark) {
+        cov += (s - mean_s) * (b - mean_b);
+        var_b += (b - mean_b).powi(2);
+    }
+    let beta = if var_b > 0.0 { cov / var_b } else { 0.0 };
+    let alpha = (mean_s - beta * mean_b) * periods_per_year;
+
+    let active: Vec<f64> = strategy.iter().zip(benchmark).map(|(s, b)| s - b).collect();
+    let mean_active = mean(&active);
+    let active_var = active
+        .iter()
+        .map(|a| (a - mean_active).powi(2))
+        .sum::<f64>()
+        / active.len() as f64;
+    let tracking_error = active_var.sqrt() * periods_per_year.sqrt();
+    let information_ratio = if tracking_error > 1e-12 {
+        Some(mean_active * periods_per_year / tracking_error)
+    } else {
+        None
+    };
+
+    RelativeStats {
+        alpha,
+        beta,
+        tracking_error,
+        information_ratio,
+    }
+}
@@ -0,0 +1,38 @@
+//! Cooperative cancellation: long runs poll a shared flag between bars,
+//! resamples and optimizer trials, and stop with [`StrategyError::Cancelled`].
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{StrategyError, StrategyResult};
+
+/// Flag shared between whoever may stop a run and the run itself. Clones
+/// share the flag; a fresh token is never cancelled unless asked to be.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every run holding a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// `Err(Cancelled)` once the token was cancelled.
+    pub fn check(&self) -> StrategyResult<()> {
+        if self.is_cancelled() {
+            Err(StrategyError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
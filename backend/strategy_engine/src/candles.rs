@@ -0,0 +1,46 @@
+//! Aggregation of price ticks into OHLCV candles.
+
+use chrono::Duration;
+
+use crate::PricePoint;
+
+/// Buckets `points` into candles `width` wide, aligned to the Unix epoch.
+/// Each candle opens at its first point, closes at its last and is stamped
+/// with the last point's time, so it is only known once that point is.
+/// Volume is summed when any point in the bucket carries one. Points must be
+/// sorted by time; a non-positive width returns them unchanged.
+pub fn aggregate(points: &[PricePoint], width: Duration) -> Vec<PricePoint> {
+    let width_secs = width.num_seconds();
+    if width_secs <= 0 {
+        return points.to_vec();
+    }
+    let mut candles: Vec<PricePoint> = Vec::new();
+    let mut current_bucket = None;
+    for point in points {
+        let bucket = point.timestamp.timestamp().div_euclid(width_secs);
+        match candles.last_mut() {
+            Some(candle) if current_bucket == Some(bucket) => {
+                candle.timestamp = point.timestamp;
+                candle.high = Some(candle.high().max(point.high()));
+                candle.low = Some(candle.low().min(point.low()));
+                candle.price = point.price;
+                candle.volume = match (candle.volume, point.volume) {
+                    (Some(a), Some(b)) => Some(a + b),
+                    (a, b) => a.or(b),
+                };
+            }
+            _ => {
+                current_bucket = Some(bucket);
+                candles.push(PricePoint {
+                    timestamp: point.timestamp,
+                    price: point.price,
+                    open: Some(point.open()),
+                    high: Some(point.high()),
+                    low: Some(point.low()),
+                    volume: point.volume,
+                });
+            }
+        }
+    }
+    candles
+}
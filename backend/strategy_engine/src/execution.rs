@@ -0,0 +1,324 @@
+use chrono::Utc;
+use domain::{BacktestTrade, ExitReason, TradeSide};
+
+use crate::{CancelToken, PricePoint, StrategyResult};
+
+/// How fills deviate from the observed price when the position changes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlippageModel {
+    /// Constant penalty in basis points of traded notional.
+    Fixed { bps: f64 },
+    /// Penalty proportional to recent realized volatility (stddev of bar returns).
+    Volatility { multiplier: f64, lookback: usize },
+}
+
+/// Friction applied on every position change. Equity is expressed in units of
+/// `initial_capital`, so the fixed gas cost is scaled by it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostModel {
+    pub fee_bps: f64,
+    pub gas_cost: f64,
+    pub initial_capital: f64,
+    pub slippage: SlippageModel,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            fee_bps: 0.0,
+            gas_cost: 0.0,
+            initial_capital: 10_000.0,
+            slippage: SlippageModel::Fixed { bps: 0.0 },
+        }
+    }
+}
+
+impl CostModel {
+    /// Reads `fee_bps`, `gas_cost`, `initial_capital`, `slippage_model`
+    /// (`fixed` | `volatility`), `slippage_bps`, `slippage_vol_mult` and
+    /// `slippage_lookback` from strategy params; missing keys keep fills frictionless.
+    pub fn from_params(params: &serde_json::Value) -> Self {
+        let num = |key: &str| params.get(key).and_then(|v| v.as_f64());
+        let defaults = Self::default();
+        let slippage = match params.get("slippage_model").and_then(|v| v.as_str()) {
+            Some(model) if model.eq_ignore_ascii_case("volatility") => SlippageModel::Volatility {
+                multiplier: num("slippage_vol_mult").unwrap_or(0.5).max(0.0),
+                lookback: params
+                    .get("slippage_lookback")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(20)
+                    .max(2) as usize,
+            },
+            _ => SlippageModel::Fixed {
+                bps: num("slippage_bps").unwrap_or(0.0).max(0.0),
+            },
+        };
+        Self {
+            fee_bps: num("fee_bps").unwrap_or(defaults.fee_bps).max(0.0),
+            gas_cost: num("gas_cost").unwrap_or(defaults.gas_cost).max(0.0),
+            initial_capital: num("initial_capital")
+                .filter(|v| *v > 0.0)
+                .unwrap_or(defaults.initial_capital),
+            slippage,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let slippage = match &self.slippage {
+            SlippageModel::Fixed { bps } => serde_json::json!({ "model": "fixed", "bps": bps }),
+            SlippageModel::Volatility {
+                multiplier,
+                lookback,
+            } => serde_json::json!({
+                "model": "volatility",
+                "multiplier": multiplier,
+                "lookback": lookback
+            }),
+        };
+        serde_json::json!({
+            "fee_bps": self.fee_bps,
+            "gas_cost": self.gas_cost,
+            "initial_capital": self.initial_capital,
+            "slippage": slippage
+        })
+    }
+
+    pub(crate) fn slippage_bps(&self, prices: &[PricePoint], index: usize) -> f64 {
+        match &self.slippage {
+            SlippageModel::Fixed { bps } => *bps,
+            SlippageModel::Volatility {
+                multiplier,
+                lookback,
+            } => {
+                let start = index.saturating_sub(*lookback);
+                let returns: Vec<f64> = prices[start..=index]
+                    .windows(2)
+                    .filter(|w| w[0].price > 0.0)
+                    .map(|w| w[1].price / w[0].price - 1.0)
+                    .collect();
+                if returns.len() < 2 {
+                    return 0.0;
+                }
+                let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+                let var =
+                    returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / returns.len() as f64;
+                var.sqrt() * multiplier * 10_000.0
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecutionStats {
+    pub trade_count: usize,
+    /// Sum of absolute position changes, in multiples of equity.
+    pub turnover: f64,
+    /// Costs paid, as a fraction of starting equity.
+    pub total_costs: f64,
+}
+
+impl ExecutionStats {
+    pub fn insert_into(&self, metrics: &mut serde_json::Value, costs: &CostModel) {
+        if let Some(obj) = metrics.as_object_mut() {
+            obj.insert(
+                "trade_count".to_string(),
+                serde_json::json!(self.trade_count),
+            );
+            obj.insert("turnover".to_string(), serde_json::json!(self.turnover));
+            obj.insert(
+                "total_costs".to_string(),
+                serde_json::json!(self.total_costs),
+            );
+            obj.insert(
+                "total_costs_usd".to_string(),
+                serde_json::json!(self.total_costs * costs.initial_capital),
+            );
+            obj.insert("costs".to_string(), costs.to_json());
+        }
+    }
+}
+
+pub struct Simulation {
+    pub equity_curve: Vec<(chrono::DateTime<Utc>, f64)>,
+    pub trades: Vec<BacktestTrade>,
+    pub stats: ExecutionStats,
+}
+
+struct OpenTrade {
+    side: TradeSide,
+    size: f64,
+    entry_index: usize,
+    entry_equity: f64,
+}
+
+impl OpenTrade {
+    fn close(
+        self,
+        prices: &[PricePoint],
+        exit_index: usize,
+        exit_equity: f64,
+        exit_reason: Option<ExitReason>,
+    ) -> BacktestTrade {
+        let entry = &prices[self.entry_index];
+        let exit = &prices[exit_index];
+        let pnl = exit_equity - self.entry_equity;
+        BacktestTrade {
+            side: self.side,
+            size: self.size,
+            entry_time: entry.timestamp,
+            entry_price: entry.price,
+            exit_time: exit.timestamp,
+            exit_price: exit.price,
+            pnl,
+            return_pct: if self.entry_equity > 0.0 {
+                pnl / self.entry_equity
+            } else {
+                0.0
+            },
+            holding_secs: (exit.timestamp - entry.timestamp).num_seconds(),
+            closed: exit_reason.is_some(),
+            exit_reason,
+        }
+    }
+}
+
+fn side_of(position: f64) -> Option<TradeSide> {
+    if position > f64::EPSILON {
+        Some(TradeSide::Long)
+    } else if position < -f64::EPSILON {
+        Some(TradeSide::Short)
+    } else {
+        None
+    }
+}
+
+/// Compounds bar-to-bar returns, holding the position decided on the previous
+/// bar, and charges `costs` at the close of every bar where the position changes.
+/// Round trips are recorded whenever the position leaves flat or flips side.
+pub fn simulate(prices: &[PricePoint], positions: &[f64], costs: &CostModel) -> Simulation {
+    simulate_with_exits(prices, positions, &[], costs, &CancelToken::new())
+        .expect("a fresh token is never cancelled")
+}
+
+/// [`simulate`], tagging trades closed on bar `i` with `exits[i]` (e.g. a
+/// risk-overlay stop) instead of [`ExitReason::Signal`]. `cancel` is checked
+/// before every bar.
+pub fn simulate_with_exits(
+    prices: &[PricePoint],
+    positions: &[f64],
+    exits: &[Option<ExitReason>],
+    costs: &CostModel,
+    cancel: &CancelToken,
+) -> StrategyResult<Simulation> {
+    let mut equity = 1.0;
+    let mut held = 0.0;
+    let mut stats = ExecutionStats::default();
+    let mut equity_curve = Vec::with_capacity(prices.len());
+    let mut trades = Vec::new();
+    let mut open: Option<OpenTrade> = None;
+    for (i, point) in prices.iter().enumerate() {
+        cancel.check()?;
+        if i > 0 {
+            let prev_price = prices[i - 1].price;
+            if prev_price > 0.0 {
+                let ret = (point.price - prev_price) / prev_price;
+                equity *= 1.0 + ret * held;
+            }
+        }
+        let target = positions.get(i).copied().unwrap_or(held);
+        let delta = (target - held).abs();
+        if delta > f64::EPSILON {
+            let bps = costs.fee_bps + costs.slippage_bps(prices, i);
+            let cost = equity * delta * bps / 10_000.0 + costs.gas_cost / costs.initial_capital;
+            let side_changed = side_of(held) != side_of(target);
+            // When flipping, the share of the cost spent closing the old side
+            // belongs to the old trade; the rest is charged to the new one.
+            let close_cost = if side_changed && side_of(held).is_some() {
+                cost * held.abs() / delta
+            } else {
+                0.0
+            };
+            if side_changed {
+                if let Some(trade) = open.take() {
+                    let reason = exits
+                        .get(i)
+                        .copied()
+                        .flatten()
+                        .unwrap_or(ExitReason::Signal);
+                    trades.push(trade.close(prices, i, equity - close_cost, Some(reason)));
+                }
+                open = side_of(target).map(|side| OpenTrade {
+                    side,
+                    size: target.abs(),
+                    entry_index: i,
+                    entry_equity: equity - close_cost,
+                });
+            }
+            equity = (equity - cost).max(0.0);
+            stats.trade_count += 1;
+            stats.turnover += delta;
+            stats.total_costs += cost;
+        }
+        held = target;
+        equity_curve.push((point.timestamp, equity));
+    }
+    if let Some(trade) = open.take() {
+        trades.push(trade.close(prices, prices.len() - 1, equity, None));
+    }
+    Ok(Simulation {
+        equity_curve,
+        trades,
+        stats,
+    })
+}
+
+/// Inserts win rate, average win/loss, profit factor and the longest losing
+/// streak derived from the trade ledger.
+pub fn insert_trade_stats(metrics: &mut serde_json::Value, trades: &[BacktestTrade]) {
+    let wins: Vec<f64> = trades.iter().map(|t| t.pnl).filter(|p| *p > 0.0).collect();
+    let losses: Vec<f64> = trades.iter().map(|t| t.pnl).filter(|p| *p <= 0.0).collect();
+    let win_rate = if trades.is_empty() {
+        0.0
+    } else {
+        wins.len() as f64 / trades.len() as f64
+    };
+    let avg = |values: &[f64]| {
+        if values.is_empty() {
+            0.0
+        } else {
+            values.iter().sum::<f64>() / values.len() as f64
+        }
+    };
+    let gross_profit: f64 = wins.iter().sum();
+    let gross_loss: f64 = losses.iter().sum::<f64>().abs();
+    // Undefined (null) when there are no losing trades.
+    let profit_factor = if gross_loss > 0.0 {
+        Some(gross_profit / gross_loss)
+    } else {
+        None
+    };
+    let mut longest_losing_streak = 0;
+    let mut streak = 0;
+    for trade in trades {
+        if trade.pnl <= 0.0 {
+            streak += 1;
+            longest_losing_streak = longest_losing_streak.max(streak);
+        } else {
+            streak = 0;
+        }
+    }
+
+    if let Some(obj) = metrics.as_object_mut() {
+        obj.insert("win_rate".to_string(), serde_json::json!(win_rate));
+        obj.insert("avg_win".to_string(), serde_json::json!(avg(&wins)));
+        obj.insert("avg_loss".to_string(), serde_json::json!(avg(&losses)));
+        obj.insert(
+            "profit_factor".to_string(),
+            serde_json::json!(profit_factor),
+        );
+        obj.insert(
+            "longest_losing_streak".to_string(),
+            serde_json::json!(longest_losing_streak),
+        );
+    }
+}
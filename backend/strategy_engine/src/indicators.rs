@@ -0,0 +1,150 @@
+//! Technical indicators over price series. Each returns one value per input
+//! bar, `None` until enough history exists, so results line up with the bars.
+//! All of them run in a single pass on the [`crate::rolling`] accumulators.
+
+use crate::rolling::{RollingExtremum, RollingStats};
+use crate::PricePoint;
+
+/// Lower, middle and upper lines of a band indicator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub lower: f64,
+    pub middle: f64,
+    pub upper: f64,
+}
+
+impl Band {
+    /// Where `value` sits in the band: 0 at the lower line, 1 at the upper.
+    pub fn percent_b(&self, value: f64) -> Option<f64> {
+        let width = self.upper - self.lower;
+        (width > 0.0).then(|| (value - self.lower) / width)
+    }
+
+    /// Band width relative to the middle line.
+    pub fn bandwidth(&self) -> Option<f64> {
+        (self.middle != 0.0).then(|| (self.upper - self.lower) / self.middle)
+    }
+}
+
+pub fn closes(bars: &[PricePoint]) -> Vec<f64> {
+    bars.iter().map(|b| b.price).collect()
+}
+
+/// Simple moving average over the trailing `period` values.
+pub fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    rolling_stats(values, period, |stats| stats.mean())
+}
+
+/// Exponential moving average seeded with the first value.
+pub fn ema(values: &[f64], period: usize) -> Vec<f64> {
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut out = Vec::with_capacity(values.len());
+    let mut prev: Option<f64> = None;
+    for &v in values {
+        let next = match prev {
+            Some(p) => alpha * v + (1.0 - alpha) * p,
+            None => v,
+        };
+        out.push(next);
+        prev = Some(next);
+    }
+    out
+}
+
+/// Population standard deviation over the trailing `period` values.
+pub fn rolling_std(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    rolling_stats(values, period, |stats| stats.std_dev())
+}
+
+/// Bollinger Bands: SMA middle line, `num_std` rolling standard deviations
+/// above and below.
+pub fn bollinger(values: &[f64], period: usize, num_std: f64) -> Vec<Option<Band>> {
+    let mut stats = RollingStats::new(period);
+    values
+        .iter()
+        .map(|&v| {
+            stats.push(v);
+            if period == 0 || !stats.is_full() {
+                return None;
+            }
+            let (middle, sd) = (stats.mean()?, stats.std_dev()?);
+            Some(Band {
+                lower: middle - num_std * sd,
+                middle,
+                upper: middle + num_std * sd,
+            })
+        })
+        .collect()
+}
+
+/// True range: the bar's high-low range extended to the previous close.
+pub fn true_range(bars: &[PricePoint]) -> Vec<f64> {
+    bars.iter()
+        .enumerate()
+        .map(|(i, bar)| {
+            let range = bar.high() - bar.low();
+            match i.checked_sub(1).map(|prev| bars[prev].price) {
+                Some(prev_close) => range
+                    .max((bar.high() - prev_close).abs())
+                    .max((bar.low() - prev_close).abs()),
+                None => range,
+            }
+        })
+        .collect()
+}
+
+/// Average true range with Wilder's smoothing, seeded by the mean of the
+/// first `period` true ranges. Close-only bars degrade to close-to-close moves.
+pub fn atr(bars: &[PricePoint], period: usize) -> Vec<Option<f64>> {
+    let tr = true_range(bars);
+    let mut out = vec![None; bars.len()];
+    if period == 0 || tr.len() < period {
+        return out;
+    }
+    let mut value = tr[..period].iter().sum::<f64>() / period as f64;
+    out[period - 1] = Some(value);
+    for i in period..tr.len() {
+        value = (value * (period as f64 - 1.0) + tr[i]) / period as f64;
+        out[i] = Some(value);
+    }
+    out
+}
+
+/// Donchian channel: highest high and lowest low of the trailing `period`
+/// bars, including the current one.
+pub fn donchian(bars: &[PricePoint], period: usize) -> Vec<Option<Band>> {
+    let mut highest = RollingExtremum::max(period);
+    let mut lowest = RollingExtremum::min(period);
+    bars.iter()
+        .map(|bar| {
+            let (upper, lower) = (highest.push(bar.high()), lowest.push(bar.low()));
+            if period == 0 {
+                return None;
+            }
+            let (upper, lower) = (upper?, lower?);
+            Some(Band {
+                lower,
+                middle: (upper + lower) / 2.0,
+                upper,
+            })
+        })
+        .collect()
+}
+
+fn rolling_stats<F>(values: &[f64], period: usize, f: F) -> Vec<Option<f64>>
+where
+    F: Fn(&RollingStats) -> Option<f64>,
+{
+    let mut stats = RollingStats::new(period);
+    values
+        .iter()
+        .map(|&v| {
+            stats.push(v);
+            if period > 0 && stats.is_full() {
+                f(&stats)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,854 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use schemars::{schema_for, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::script::{self, Script};
+use crate::{StrategyError, StrategyResult};
+
+/// Strategy types understood by the engine. Stored strategies keep the type
+/// as text; [`StrategyKind::from_str`] accepts any casing plus aliases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyKind {
+    MaCross,
+    Volatility,
+    Rsi,
+    Macd,
+    Rebalance,
+    Pairs,
+    Bollinger,
+    Script,
+}
+
+impl StrategyKind {
+    pub const ALL: &'static [StrategyKind] = &[
+        StrategyKind::MaCross,
+        StrategyKind::Volatility,
+        StrategyKind::Rsi,
+        StrategyKind::Macd,
+        StrategyKind::Rebalance,
+        StrategyKind::Pairs,
+        StrategyKind::Bollinger,
+        StrategyKind::Script,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StrategyKind::MaCross => "ma_cross",
+            StrategyKind::Volatility => "volatility",
+            StrategyKind::Rsi => "rsi",
+            StrategyKind::Macd => "macd",
+            StrategyKind::Rebalance => "rebalance",
+            StrategyKind::Pairs => "pairs",
+            StrategyKind::Bollinger => "bollinger",
+            StrategyKind::Script => "script",
+        }
+    }
+
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            StrategyKind::MaCross => &["ma"],
+            StrategyKind::Rebalance => &["portfolio"],
+            // `correlation` used to be a single-series autocorrelation stub;
+            // cross-asset correlation trading is what pairs does.
+            StrategyKind::Pairs => &["pairs_trading", "correlation"],
+            StrategyKind::Bollinger => &["bollinger_bands", "bb"],
+            _ => &[],
+        }
+    }
+
+    /// Kinds that trade several symbols and need
+    /// [`StrategyService::backtest_multi`](crate::StrategyService::backtest_multi).
+    pub fn is_multi_asset(&self) -> bool {
+        matches!(self, StrategyKind::Rebalance | StrategyKind::Pairs)
+    }
+
+    /// JSON Schema for the kind's params, including the shared cost and
+    /// metrics keys. Unknown keys are rejected (`additionalProperties: false`).
+    pub fn schema(&self) -> serde_json::Value {
+        match self {
+            StrategyKind::MaCross => params_schema::<MaCrossParams>(),
+            StrategyKind::Volatility => params_schema::<VolatilityParams>(),
+            StrategyKind::Rsi => params_schema::<RsiParams>(),
+            StrategyKind::Macd => params_schema::<MacdParams>(),
+            StrategyKind::Rebalance => params_schema::<RebalanceParams>(),
+            StrategyKind::Pairs => params_schema::<PairsParams>(),
+            StrategyKind::Bollinger => params_schema::<BollingerParams>(),
+            StrategyKind::Script => params_schema::<ScriptParams>(),
+        }
+    }
+
+    /// Strict check used when a strategy is created: unknown keys, wrong
+    /// types and rule violations are all reported per field.
+    pub fn validate(&self, params: &serde_json::Value) -> Result<StrategyParams, Vec<FieldError>> {
+        self.parse_params(params, &|_| true)
+    }
+
+    /// Typed params for a backtest. Unlike [`validate`](Self::validate),
+    /// unknown keys are ignored so strategies saved before validation existed
+    /// keep running.
+    pub fn params(&self, params: &serde_json::Value) -> StrategyResult<StrategyParams> {
+        self.parse_params(params, &|_| false)
+            .map_err(StrategyError::InvalidParams)
+    }
+
+    /// Typed params for a run that overrides some keys of the stored params:
+    /// the overridden keys are checked as strictly as in
+    /// [`validate`](Self::validate), so a misspelled override is reported
+    /// instead of ignored, while stored keys stay as lenient as in
+    /// [`params`](Self::params).
+    pub fn validate_overrides(
+        &self,
+        params: &serde_json::Value,
+        overrides: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<StrategyParams, Vec<FieldError>> {
+        self.parse_params(params, &|key| overrides.contains_key(key))
+    }
+
+    /// `strict` says which keys must be known to the kind.
+    fn parse_params(
+        &self,
+        params: &serde_json::Value,
+        strict: &dyn Fn(&str) -> bool,
+    ) -> Result<StrategyParams, Vec<FieldError>> {
+        Ok(match self {
+            StrategyKind::MaCross => {
+                StrategyParams::MaCross(parse::<MaCrossParams>(params, strict)?)
+            }
+            StrategyKind::Volatility => {
+                StrategyParams::Volatility(parse::<VolatilityParams>(params, strict)?)
+            }
+            StrategyKind::Rsi => StrategyParams::Rsi(parse::<RsiParams>(params, strict)?),
+            StrategyKind::Macd => StrategyParams::Macd(parse::<MacdParams>(params, strict)?),
+            StrategyKind::Rebalance => {
+                StrategyParams::Rebalance(parse::<RebalanceParams>(params, strict)?)
+            }
+            StrategyKind::Pairs => StrategyParams::Pairs(parse::<PairsParams>(params, strict)?),
+            StrategyKind::Bollinger => {
+                StrategyParams::Bollinger(parse::<BollingerParams>(params, strict)?)
+            }
+            StrategyKind::Script => StrategyParams::Script(parse::<ScriptParams>(params, strict)?),
+        })
+    }
+}
+
+impl fmt::Display for StrategyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StrategyKind {
+    type Err = StrategyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        StrategyKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.as_str() == lower || kind.aliases().contains(&lower.as_str()))
+            .ok_or_else(|| StrategyError::UnknownType(s.to_string()))
+    }
+}
+
+/// Catalog entry served by `GET /api/strategies/kinds`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyKindInfo {
+    pub kind: StrategyKind,
+    pub aliases: &'static [&'static str],
+    pub schema: serde_json::Value,
+}
+
+pub fn catalog() -> Vec<StrategyKindInfo> {
+    StrategyKind::ALL
+        .iter()
+        .map(|kind| StrategyKindInfo {
+            kind: *kind,
+            aliases: kind.aliases(),
+            schema: kind.schema(),
+        })
+        .collect()
+}
+
+/// A single rejected param.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Validated params, one variant per [`StrategyKind`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrategyParams {
+    MaCross(MaCrossParams),
+    Volatility(VolatilityParams),
+    Rsi(RsiParams),
+    Macd(MacdParams),
+    Rebalance(RebalanceParams),
+    Pairs(PairsParams),
+    Bollinger(BollingerParams),
+    Script(ScriptParams),
+}
+
+impl StrategyParams {
+    /// Symbols whose price series a multi-asset kind needs, normalized to
+    /// uppercase; `None` for single-series kinds.
+    pub fn series_symbols(&self) -> Option<Vec<String>> {
+        match self {
+            StrategyParams::Rebalance(p) => Some(normalize_symbols(&p.symbols)),
+            StrategyParams::Pairs(p) => Some(normalize_symbols(&p.symbols)),
+            _ => None,
+        }
+    }
+}
+
+/// Cross-field rules that the schema alone cannot express.
+trait Rules {
+    fn check(&self, errors: &mut Vec<FieldError>);
+}
+
+/// Moving-average crossover: long while the short SMA is above the long SMA.
+/// Requires `short_window < long_window`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct MaCrossParams {
+    #[schemars(range(min = 1))]
+    pub short_window: usize,
+    #[schemars(range(min = 2))]
+    pub long_window: usize,
+}
+
+impl Default for MaCrossParams {
+    fn default() -> Self {
+        Self {
+            short_window: 5,
+            long_window: 20,
+        }
+    }
+}
+
+impl Rules for MaCrossParams {
+    fn check(&self, errors: &mut Vec<FieldError>) {
+        if self.short_window == 0 {
+            errors.push(FieldError::new("short_window", "must be at least 1"));
+        }
+        if self.short_window >= self.long_window {
+            errors.push(FieldError::new(
+                "long_window",
+                "must be greater than short_window",
+            ));
+        }
+    }
+}
+
+/// Volatility targeting: long with exposure `target_vol` / realized vol over
+/// the trailing `lookback` bars, capped at `max_leverage` (both shared keys,
+/// see [`CommonParams`]).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct VolatilityParams {
+    #[schemars(range(min = 2))]
+    pub lookback: usize,
+}
+
+impl Default for VolatilityParams {
+    fn default() -> Self {
+        Self { lookback: 20 }
+    }
+}
+
+impl Rules for VolatilityParams {
+    fn check(&self, errors: &mut Vec<FieldError>) {
+        if self.lookback < 2 {
+            errors.push(FieldError::new("lookback", "must be at least 2"));
+        }
+    }
+}
+
+/// RSI mean reversion: enter below `oversold`, exit above `overbought`.
+/// Requires `0 <= oversold < overbought <= 100`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct RsiParams {
+    #[schemars(range(min = 1))]
+    pub period: usize,
+    #[schemars(range(min = 0, max = 100))]
+    pub overbought: f64,
+    #[schemars(range(min = 0, max = 100))]
+    pub oversold: f64,
+}
+
+impl Default for RsiParams {
+    fn default() -> Self {
+        Self {
+            period: 14,
+            overbought: 70.0,
+            oversold: 30.0,
+        }
+    }
+}
+
+impl Rules for RsiParams {
+    fn check(&self, errors: &mut Vec<FieldError>) {
+        if self.period == 0 {
+            errors.push(FieldError::new("period", "must be at least 1"));
+        }
+        for (field, value) in [("overbought", self.overbought), ("oversold", self.oversold)] {
+            if !(0.0..=100.0).contains(&value) {
+                errors.push(FieldError::new(field, "must be between 0 and 100"));
+            }
+        }
+        if self.oversold >= self.overbought {
+            errors.push(FieldError::new("oversold", "must be less than overbought"));
+        }
+    }
+}
+
+/// MACD signal-line crossover. Requires `fast < slow`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct MacdParams {
+    #[schemars(range(min = 1))]
+    pub fast: usize,
+    #[schemars(range(min = 2))]
+    pub slow: usize,
+    #[schemars(range(min = 1))]
+    pub signal: usize,
+}
+
+impl Default for MacdParams {
+    fn default() -> Self {
+        Self {
+            fast: 12,
+            slow: 26,
+            signal: 9,
+        }
+    }
+}
+
+impl Rules for MacdParams {
+    fn check(&self, errors: &mut Vec<FieldError>) {
+        for (field, value) in [("fast", self.fast), ("signal", self.signal)] {
+            if value == 0 {
+                errors.push(FieldError::new(field, "must be at least 1"));
+            }
+        }
+        if self.fast >= self.slow {
+            errors.push(FieldError::new("slow", "must be greater than fast"));
+        }
+    }
+}
+
+/// Bollinger Band mean reversion: long once the close drops below the lower
+/// band (short above the upper band when `allow_short`), out again when it
+/// crosses back over the middle line.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct BollingerParams {
+    #[schemars(range(min = 2))]
+    pub period: usize,
+    /// Band half-width in rolling standard deviations.
+    pub num_std: f64,
+    pub allow_short: bool,
+    #[schemars(range(min = 1))]
+    pub atr_period: usize,
+    /// Stop out once the close moves this many ATRs against the entry.
+    pub atr_stop: Option<f64>,
+}
+
+impl Default for BollingerParams {
+    fn default() -> Self {
+        Self {
+            period: 20,
+            num_std: 2.0,
+            allow_short: false,
+            atr_period: 14,
+            atr_stop: None,
+        }
+    }
+}
+
+impl Rules for BollingerParams {
+    fn check(&self, errors: &mut Vec<FieldError>) {
+        if self.period < 2 {
+            errors.push(FieldError::new("period", "must be at least 2"));
+        }
+        if self.num_std <= 0.0 {
+            errors.push(FieldError::new("num_std", "must be positive"));
+        }
+        if self.atr_period == 0 {
+            errors.push(FieldError::new("atr_period", "must be at least 1"));
+        }
+        if self.atr_stop.is_some_and(|stop| stop <= 0.0) {
+            errors.push(FieldError::new("atr_stop", "must be positive"));
+        }
+    }
+}
+
+/// User-written signal in the sandboxed language of [`crate::script`]: the
+/// script's value on each bar is the target position. Runs are capped at
+/// `max_operations` evaluation steps and `timeout_ms` of wall time.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct ScriptParams {
+    pub source: String,
+    #[schemars(range(min = 1, max = 50_000_000))]
+    pub max_operations: u64,
+    #[schemars(range(min = 1, max = 10_000))]
+    pub timeout_ms: u64,
+}
+
+impl ScriptParams {
+    pub fn limits(&self) -> script::Limits {
+        script::Limits {
+            max_operations: self.max_operations,
+            timeout: Duration::from_millis(self.timeout_ms),
+        }
+    }
+}
+
+impl Default for ScriptParams {
+    fn default() -> Self {
+        Self {
+            source: String::new(),
+            max_operations: 1_000_000,
+            timeout_ms: 1_000,
+        }
+    }
+}
+
+impl Rules for ScriptParams {
+    fn check(&self, errors: &mut Vec<FieldError>) {
+        if self.source.trim().is_empty() {
+            errors.push(FieldError::new("source", "must not be empty"));
+        } else if self.source.len() > script::MAX_SOURCE_LEN {
+            errors.push(FieldError::new(
+                "source",
+                format!("must be at most {} bytes", script::MAX_SOURCE_LEN),
+            ));
+        } else if let Err(err) = Script::compile(&self.source) {
+            errors.push(FieldError::new("source", err.to_string()));
+        }
+        if !(1..=script::MAX_OPERATIONS).contains(&self.max_operations) {
+            errors.push(FieldError::new(
+                "max_operations",
+                format!("must be between 1 and {}", script::MAX_OPERATIONS),
+            ));
+        }
+        if !(1..=script::MAX_TIMEOUT_MS).contains(&self.timeout_ms) {
+            errors.push(FieldError::new(
+                "timeout_ms",
+                format!("must be between 1 and {}", script::MAX_TIMEOUT_MS),
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Weighting {
+    /// Target weights from `weights`.
+    Fixed,
+    Equal,
+    /// Weights proportional to 1 / trailing volatility.
+    InverseVol,
+    /// Equal risk contribution from the trailing covariance matrix.
+    RiskParity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RebalanceTrigger {
+    /// Every `rebalance_every` bars.
+    Periodic,
+    /// Whenever a weight drifts more than `drift_threshold` from its target.
+    Threshold,
+}
+
+/// Multi-asset allocation across `symbols`, traded back to target weights on
+/// a periodic or drift-threshold schedule.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct RebalanceParams {
+    /// Symbols held; at least two.
+    pub symbols: Vec<String>,
+    pub weighting: Weighting,
+    /// Target weight per symbol for `fixed` weighting, normalized to sum to 1.
+    pub weights: BTreeMap<String, f64>,
+    pub rebalance: RebalanceTrigger,
+    #[schemars(range(min = 1))]
+    pub rebalance_every: usize,
+    #[schemars(range(min = 0, max = 1))]
+    pub drift_threshold: f64,
+    /// Trailing bars of returns used by `inverse_vol` and `risk_parity`.
+    #[schemars(range(min = 2))]
+    pub vol_lookback: usize,
+}
+
+impl Default for RebalanceParams {
+    fn default() -> Self {
+        Self {
+            symbols: Vec::new(),
+            weighting: Weighting::Equal,
+            weights: BTreeMap::new(),
+            rebalance: RebalanceTrigger::Periodic,
+            rebalance_every: 20,
+            drift_threshold: 0.05,
+            vol_lookback: 20,
+        }
+    }
+}
+
+impl RebalanceParams {
+    /// Symbols in upper case, the form price history is stored under.
+    pub fn normalized_symbols(&self) -> Vec<String> {
+        normalize_symbols(&self.symbols)
+    }
+}
+
+fn normalize_symbols(symbols: &[String]) -> Vec<String> {
+    symbols.iter().map(|s| s.trim().to_uppercase()).collect()
+}
+
+/// Rejects empty or repeated symbols.
+fn check_symbols(symbols: &[String], errors: &mut Vec<FieldError>) {
+    if symbols.iter().any(|s| s.is_empty()) {
+        errors.push(FieldError::new("symbols", "must not contain empty symbols"));
+    }
+    let mut unique = symbols.to_vec();
+    unique.sort();
+    unique.dedup();
+    if unique.len() != symbols.len() {
+        errors.push(FieldError::new("symbols", "must not repeat a symbol"));
+    }
+}
+
+impl Rules for RebalanceParams {
+    fn check(&self, errors: &mut Vec<FieldError>) {
+        let symbols = self.normalized_symbols();
+        if symbols.len() < 2 {
+            errors.push(FieldError::new("symbols", "must list at least two symbols"));
+        }
+        check_symbols(&symbols, errors);
+        if self.weighting == Weighting::Fixed {
+            if self.weights.values().any(|w| *w < 0.0) {
+                errors.push(FieldError::new("weights", "must not be negative"));
+            }
+            if self.weights.values().sum::<f64>() <= 0.0 {
+                errors.push(FieldError::new(
+                    "weights",
+                    "must give a positive total weight for fixed weighting",
+                ));
+            }
+            if let Some(extra) = self
+                .weights
+                .keys()
+                .find(|k| !symbols.contains(&k.trim().to_uppercase()))
+            {
+                errors.push(FieldError::new(
+                    "weights",
+                    format!("{extra} is not listed in symbols"),
+                ));
+            }
+        }
+        if self.rebalance_every == 0 {
+            errors.push(FieldError::new("rebalance_every", "must be at least 1"));
+        }
+        if !(self.drift_threshold > 0.0 && self.drift_threshold < 1.0) {
+            errors.push(FieldError::new(
+                "drift_threshold",
+                "must be between 0 and 1 (exclusive)",
+            ));
+        }
+        if self.vol_lookback < 2 {
+            errors.push(FieldError::new("vol_lookback", "must be at least 2"));
+        }
+    }
+}
+
+/// Pairs trading on `symbols = [y, x]`: the spread `ln y - beta * ln x` uses a
+/// rolling OLS hedge ratio, and its z-score drives mean-reversion trades.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct PairsParams {
+    /// Exactly two symbols: the traded leg first, then the hedge leg.
+    pub symbols: Vec<String>,
+    /// Bars used for the hedge ratio, the spread z-score and rolling correlation.
+    #[schemars(range(min = 3))]
+    pub lookback: usize,
+    /// Open a trade once |z| reaches this level.
+    pub entry_z: f64,
+    /// Close once |z| falls back to this level.
+    pub exit_z: f64,
+    /// Close at a loss if |z| keeps widening to this level.
+    pub stop_z: Option<f64>,
+}
+
+impl Default for PairsParams {
+    fn default() -> Self {
+        Self {
+            symbols: Vec::new(),
+            lookback: 30,
+            entry_z: 2.0,
+            exit_z: 0.5,
+            stop_z: None,
+        }
+    }
+}
+
+impl Rules for PairsParams {
+    fn check(&self, errors: &mut Vec<FieldError>) {
+        let symbols = normalize_symbols(&self.symbols);
+        if symbols.len() != 2 {
+            errors.push(FieldError::new("symbols", "must list exactly two symbols"));
+        }
+        check_symbols(&symbols, errors);
+        if self.lookback < 3 {
+            errors.push(FieldError::new("lookback", "must be at least 3"));
+        }
+        if self.entry_z <= 0.0 {
+            errors.push(FieldError::new("entry_z", "must be positive"));
+        }
+        if self.exit_z < 0.0 || self.exit_z >= self.entry_z {
+            errors.push(FieldError::new(
+                "exit_z",
+                "must be non-negative and below entry_z",
+            ));
+        }
+        if self.stop_z.is_some_and(|stop| stop <= self.entry_z) {
+            errors.push(FieldError::new("stop_z", "must be greater than entry_z"));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SlippageModelName {
+    Fixed,
+    Volatility,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SizingMode {
+    Fixed,
+    VolTarget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnualizationName {
+    /// 365 days per year.
+    Crypto,
+    /// 252 trading days per year.
+    Equity,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ConfidenceLevels {
+    One(f64),
+    Many(Vec<f64>),
+}
+
+/// Keys accepted by every kind: trading costs (see [`crate::CostModel`]) and
+/// metric settings (see [`crate::MetricsConfig`]).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct CommonParams {
+    /// Exchange fee in basis points of traded notional.
+    #[schemars(range(min = 0))]
+    pub fee_bps: Option<f64>,
+    /// Fixed gas cost per position change, in USD.
+    #[schemars(range(min = 0))]
+    pub gas_cost: Option<f64>,
+    /// Starting capital in USD (default 10000).
+    pub initial_capital: Option<f64>,
+    pub slippage_model: Option<SlippageModelName>,
+    #[schemars(range(min = 0))]
+    pub slippage_bps: Option<f64>,
+    #[schemars(range(min = 0))]
+    pub slippage_vol_mult: Option<f64>,
+    #[schemars(range(min = 2))]
+    pub slippage_lookback: Option<usize>,
+    /// VaR/CVaR confidence level(s), each between 0 and 1 exclusive.
+    pub var_confidence: Option<ConfidenceLevels>,
+    pub annualization: Option<AnnualizationName>,
+    pub days_per_year: Option<f64>,
+    pub bar_interval_secs: Option<f64>,
+    pub periods_per_year: Option<f64>,
+    /// Risk overlay: exit when the loss since entry reaches this fraction.
+    pub stop_loss: Option<f64>,
+    /// Risk overlay: exit when the gain since entry reaches this fraction.
+    pub take_profit: Option<f64>,
+    /// Risk overlay: exit after giving back this fraction from the best close.
+    pub trailing_stop: Option<f64>,
+    /// Risk overlay: exit after holding for this many bars.
+    #[schemars(range(min = 1))]
+    pub max_holding_bars: Option<usize>,
+    /// `vol_target` scales the raw signal to `target_vol`; `fixed` (default)
+    /// trades the signal as is.
+    pub sizing: Option<SizingMode>,
+    /// Annualized volatility targeted by vol-target sizing (default 0.2).
+    pub target_vol: Option<f64>,
+    /// Cap on exposure from vol-target sizing, in multiples of equity (default 1).
+    pub max_leverage: Option<f64>,
+    /// Bars of returns used to estimate realized volatility (default 20).
+    #[schemars(range(min = 2))]
+    pub sizing_lookback: Option<usize>,
+}
+
+impl Rules for CommonParams {
+    fn check(&self, errors: &mut Vec<FieldError>) {
+        let non_negative = [
+            ("fee_bps", self.fee_bps),
+            ("gas_cost", self.gas_cost),
+            ("slippage_bps", self.slippage_bps),
+            ("slippage_vol_mult", self.slippage_vol_mult),
+        ];
+        for (field, value) in non_negative {
+            if value.is_some_and(|v| v < 0.0) {
+                errors.push(FieldError::new(field, "must not be negative"));
+            }
+        }
+        let positive = [
+            ("initial_capital", self.initial_capital),
+            ("target_vol", self.target_vol),
+            ("max_leverage", self.max_leverage),
+            ("days_per_year", self.days_per_year),
+            ("bar_interval_secs", self.bar_interval_secs),
+            ("periods_per_year", self.periods_per_year),
+        ];
+        for (field, value) in positive {
+            if value.is_some_and(|v| v <= 0.0) {
+                errors.push(FieldError::new(field, "must be positive"));
+            }
+        }
+        for (field, value) in [
+            ("stop_loss", self.stop_loss),
+            ("trailing_stop", self.trailing_stop),
+        ] {
+            if value.is_some_and(|v| v <= 0.0 || v >= 1.0) {
+                errors.push(FieldError::new(
+                    field,
+                    "must be between 0 and 1 (exclusive)",
+                ));
+            }
+        }
+        if self.take_profit.is_some_and(|v| v <= 0.0) {
+            errors.push(FieldError::new("take_profit", "must be positive"));
+        }
+        if self.max_holding_bars.is_some_and(|v| v < 1) {
+            errors.push(FieldError::new("max_holding_bars", "must be at least 1"));
+        }
+        if self.slippage_lookback.is_some_and(|v| v < 2) {
+            errors.push(FieldError::new("slippage_lookback", "must be at least 2"));
+        }
+        if self.sizing_lookback.is_some_and(|v| v < 2) {
+            errors.push(FieldError::new("sizing_lookback", "must be at least 2"));
+        }
+        let levels = match &self.var_confidence {
+            Some(ConfidenceLevels::One(level)) => vec![*level],
+            Some(ConfidenceLevels::Many(levels)) => levels.clone(),
+            None => Vec::new(),
+        };
+        if levels.iter().any(|c| *c <= 0.0 || *c >= 1.0) {
+            errors.push(FieldError::new(
+                "var_confidence",
+                "must be between 0 and 1 (exclusive)",
+            ));
+        }
+    }
+}
+
+fn property_names<P: JsonSchema>() -> Vec<String> {
+    schema_for!(P)
+        .schema
+        .object
+        .map(|obj| obj.properties.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn params_schema<P: JsonSchema>() -> serde_json::Value {
+    let mut schema = serde_json::to_value(schema_for!(P)).unwrap_or_default();
+    let common = serde_json::to_value(schema_for!(CommonParams)).unwrap_or_default();
+    for key in ["properties", "definitions"] {
+        if let Some(extra) = common.get(key).and_then(|v| v.as_object()) {
+            let target = schema
+                .as_object_mut()
+                .map(|obj| obj.entry(key).or_insert_with(|| serde_json::json!({})));
+            if let Some(target) = target.and_then(|t| t.as_object_mut()) {
+                for (name, value) in extra {
+                    target.insert(name.clone(), value.clone());
+                }
+            }
+        }
+    }
+    if let Some(obj) = schema.as_object_mut() {
+        obj.insert("additionalProperties".to_string(), serde_json::json!(false));
+    }
+    schema
+}
+
+/// Deserializes `P` field by field so every bad key is reported, then applies
+/// the cross-field rules of `P` and [`CommonParams`].
+fn parse<P>(params: &serde_json::Value, strict: &dyn Fn(&str) -> bool) -> Result<P, Vec<FieldError>>
+where
+    P: DeserializeOwned + JsonSchema + Rules,
+{
+    let empty = serde_json::Map::new();
+    let object = match params {
+        serde_json::Value::Object(map) => map,
+        serde_json::Value::Null => &empty,
+        _ => return Err(vec![FieldError::new("params", "must be an object")]),
+    };
+    let kind_fields = property_names::<P>();
+    let common_fields = property_names::<CommonParams>();
+    let mut errors = Vec::new();
+    for (key, value) in object {
+        let single = serde_json::json!({ key.as_str(): value });
+        let result = if kind_fields.contains(key) {
+            serde_json::from_value::<P>(single).map(|_| ())
+        } else if common_fields.contains(key) {
+            serde_json::from_value::<CommonParams>(single).map(|_| ())
+        } else {
+            if strict(key) {
+                errors.push(FieldError::new(key.as_str(), "unknown field"));
+            }
+            continue;
+        };
+        if let Err(err) = result {
+            errors.push(FieldError::new(key.as_str(), err.to_string()));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let value = serde_json::Value::Object(object.clone());
+    let typed: P = serde_json::from_value(value.clone())
+        .map_err(|err| vec![FieldError::new("params", err.to_string())])?;
+    let common: CommonParams = serde_json::from_value(value)
+        .map_err(|err| vec![FieldError::new("params", err.to_string())])?;
+    typed.check(&mut errors);
+    common.check(&mut errors);
+    if errors.is_empty() {
+        Ok(typed)
+    } else {
+        Err(errors)
+    }
+}
@@ -1,257 +1,1197 @@
+use std::collections::BTreeMap;
+
 use async_trait::async_trait;
 use chrono::Utc;
-use domain::{BacktestResult, Strategy};
+use domain::{BacktestResult, ExitReason, Strategy};
 use serde::Deserialize;
+use thiserror::Error;
+
+pub mod benchmark;
+pub mod cancel;
+pub mod candles;
+pub mod execution;
+pub mod indicators;
+pub mod kinds;
+pub mod metrics;
+pub mod optimize;
+pub mod overlay;
+pub mod pairs;
+pub mod portfolio;
+pub mod resample;
+pub mod robustness;
+pub mod rolling;
+pub mod script;
+pub mod sizing;
+pub mod whatif;
+
+pub use cancel::CancelToken;
+pub use execution::{CostModel, ExecutionStats, SlippageModel};
+pub use kinds::{FieldError, StrategyKind, StrategyParams};
+pub use metrics::MetricsConfig;
+pub use overlay::RiskOverlay;
+pub use portfolio::PriceSeries;
+pub use resample::{GapPolicy, ResampleReport};
+pub use robustness::RobustnessRequest;
+pub use rolling::{RollingCovariance, RollingExtremum, RollingStats};
+pub use sizing::VolTarget;
+
+use kinds::{
+    BollingerParams, MaCrossParams, MacdParams, RsiParams, ScriptParams, VolatilityParams,
+};
+
+/// Engine version recorded in backtest manifests; a replay on another version
+/// may legitimately differ.
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Error)]
+pub enum StrategyError {
+    #[error("unknown strategy type: {0}")]
+    UnknownType(String),
+    #[error("invalid strategy params: {}", describe_fields(.0))]
+    InvalidParams(Vec<FieldError>),
+    #[error("backtest cancelled")]
+    Cancelled,
+}
+
+pub type StrategyResult<T> = Result<T, StrategyError>;
+
+fn describe_fields(fields: &[FieldError]) -> String {
+    fields
+        .iter()
+        .map(|f| format!("{}: {}", f.field, f.message))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
 #[async_trait]
 pub trait StrategyService: Send + Sync {
-    async fn backtest(&self, strategy: Strategy, prices: Vec<PricePoint>) -> BacktestResult;
+    /// Backtests a single-series kind. Inputs are borrowed so callers running
+    /// many variants over one long series (the optimizer) never copy it.
+    /// Stops with [`StrategyError::Cancelled`] once `cancel` fires.
+    async fn backtest_cancellable(
+        &self,
+        strategy: &Strategy,
+        prices: &[PricePoint],
+        cancel: &CancelToken,
+    ) -> StrategyResult<BacktestResult>;
+
+    /// Backtests a multi-asset kind (see [`StrategyKind::is_multi_asset`]) on
+    /// one price series per symbol.
+    async fn backtest_multi_cancellable(
+        &self,
+        strategy: &Strategy,
+        series: &PriceSeries,
+        cancel: &CancelToken,
+    ) -> StrategyResult<BacktestResult>;
+
+    /// [`Self::backtest_cancellable`] for runs nobody cancels.
+    async fn backtest(
+        &self,
+        strategy: &Strategy,
+        prices: &[PricePoint],
+    ) -> StrategyResult<BacktestResult> {
+        self.backtest_cancellable(strategy, prices, &CancelToken::new())
+            .await
+    }
+
+    /// [`Self::backtest_multi_cancellable`] for runs nobody cancels.
+    async fn backtest_multi(
+        &self,
+        strategy: &Strategy,
+        series: &PriceSeries,
+    ) -> StrategyResult<BacktestResult> {
+        self.backtest_multi_cancellable(strategy, series, &CancelToken::new())
+            .await
+    }
 }
 
 #[derive(Clone, Default)]
 pub struct InMemoryStrategyService;
 
-#[derive(Debug, Clone, Deserialize)]
+/// One bar of market data. `price` is the close; the OHLCV fields are optional
+/// so plain price ticks work everywhere, and the accessors fall back to the
+/// close when they are missing.
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct PricePoint {
     pub timestamp: chrono::DateTime<Utc>,
     pub price: f64,
+    #[serde(default)]
+    pub open: Option<f64>,
+    #[serde(default)]
+    pub high: Option<f64>,
+    #[serde(default)]
+    pub low: Option<f64>,
+    #[serde(default)]
+    pub volume: Option<f64>,
+}
+
+impl PricePoint {
+    /// A close-only point.
+    pub fn new(timestamp: chrono::DateTime<Utc>, price: f64) -> Self {
+        Self {
+            timestamp,
+            price,
+            ..Self::default()
+        }
+    }
+
+    pub fn open(&self) -> f64 {
+        self.open.unwrap_or(self.price)
+    }
+
+    pub fn high(&self) -> f64 {
+        self.high.unwrap_or(self.price).max(self.price)
+    }
+
+    pub fn low(&self) -> f64 {
+        self.low.unwrap_or(self.price).min(self.price)
+    }
 }
 
 #[async_trait]
 impl StrategyService for InMemoryStrategyService {
-    async fn backtest(&self, strategy: Strategy, prices: Vec<PricePoint>) -> BacktestResult {
-        let strat_type = strategy.r#type.to_lowercase();
-        match strat_type.as_str() {
-            "ma_cross" | "ma" => backtest_ma(strategy, prices),
-            "volatility" => backtest_volatility(strategy, prices),
-            "correlation" => backtest_correlation(strategy, prices),
-            _ => backtest_ma(strategy, prices),
+    async fn backtest_cancellable(
+        &self,
+        strategy: &Strategy,
+        prices: &[PricePoint],
+        cancel: &CancelToken,
+    ) -> StrategyResult<BacktestResult> {
+        let kind: StrategyKind = strategy.r#type.parse()?;
+        let params = kind.params(&strategy.params)?;
+        let mut result = match params {
+            StrategyParams::MaCross(p) => backtest_ma(strategy, prices, p, cancel)?,
+            StrategyParams::Volatility(p) => backtest_volatility(strategy, prices, p, cancel)?,
+            StrategyParams::Rsi(p) => backtest_rsi(strategy, prices, p, cancel)?,
+            StrategyParams::Macd(p) => backtest_macd(strategy, prices, p, cancel)?,
+            StrategyParams::Bollinger(p) => backtest_bollinger(strategy, prices, p, cancel)?,
+            StrategyParams::Script(p) => backtest_script(strategy, prices, p, cancel)?,
+            StrategyParams::Rebalance(_) | StrategyParams::Pairs(_) => {
+                return Err(StrategyError::InvalidParams(vec![FieldError::new(
+                    "symbols",
+                    format!("{kind} backtests need one price series per symbol"),
+                )]));
+            }
+        };
+        // Default benchmark: buy-and-hold on the same series. Callers can swap
+        // in another symbol afterwards with `benchmark::attach`.
+        benchmark::attach(
+            &mut result,
+            prices,
+            benchmark::BUY_AND_HOLD,
+            &MetricsConfig::from_params(&strategy.params),
+        );
+        Ok(result)
+    }
+
+    async fn backtest_multi_cancellable(
+        &self,
+        strategy: &Strategy,
+        series: &PriceSeries,
+        cancel: &CancelToken,
+    ) -> StrategyResult<BacktestResult> {
+        let kind: StrategyKind = strategy.r#type.parse()?;
+        let params = kind.params(&strategy.params)?;
+        let Some(symbols) = params.series_symbols() else {
+            return Err(StrategyError::InvalidParams(vec![FieldError::new(
+                "type",
+                format!("{kind} backtests take a single price series"),
+            )]));
+        };
+        let series: BTreeMap<String, &[PricePoint]> = series
+            .iter()
+            .map(|(symbol, points)| (symbol.to_uppercase(), points.as_slice()))
+            .filter(|(symbol, _)| symbols.contains(symbol))
+            .collect();
+        let missing: Vec<FieldError> = symbols
+            .iter()
+            .filter(|s| series.get(*s).is_none_or(|points| points.is_empty()))
+            .map(|s| FieldError::new("symbols", format!("no prices for {s}")))
+            .collect();
+        if !missing.is_empty() {
+            return Err(StrategyError::InvalidParams(missing));
         }
+
+        let aligned = portfolio::align(&series);
+        let mut result = match &params {
+            StrategyParams::Pairs(p) => pairs::backtest_pairs(strategy, &aligned, p, cancel)?,
+            StrategyParams::Rebalance(p) => {
+                portfolio::backtest_rebalance(strategy, &aligned, p, cancel)?
+            }
+            _ => unreachable!("series_symbols is only set for multi-asset kinds"),
+        };
+        benchmark::attach(
+            &mut result,
+            &aligned.equal_weight_index(),
+            portfolio::EQUAL_WEIGHT_BUY_AND_HOLD,
+            &MetricsConfig::from_params(&strategy.params),
+        );
+        Ok(result)
     }
 }
 
-fn backtest_ma(strategy: Strategy, prices: Vec<PricePoint>) -> BacktestResult {
-    let short = strategy
-        .params
-        .get("short_window")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(5) as usize;
-    let long = strategy
-        .params
-        .get("long_window")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(20) as usize;
-    let mut equity = 1.0;
-    let mut equity_curve = Vec::with_capacity(prices.len());
-    let mut window: Vec<f64> = Vec::new();
-    let mut prev_position: f64 = 0.0;
+fn backtest_ma(
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    params: MaCrossParams,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let short = params.short_window;
+    let long = params.long_window;
+    // The long average runs over a partial window during warm-up; the short
+    // one falls back to the price until it has `short` values.
+    let mut short_stats = RollingStats::new(short);
+    let mut long_stats = RollingStats::new(long);
+    let mut positions = Vec::with_capacity(prices.len());
 
     for point in prices {
-        window.push(point.price);
-        if window.len() > long {
-            window.remove(0);
-        }
-        let short_ma = if window.len() >= short {
-            window.iter().rev().take(short).sum::<f64>() / short as f64
+        short_stats.push(point.price);
+        long_stats.push(point.price);
+        let short_ma = if short_stats.is_full() {
+            short_stats.mean().unwrap_or(point.price)
         } else {
             point.price
         };
-        let long_ma = window.iter().sum::<f64>() / window.len() as f64;
-        let position = if short_ma > long_ma { 1.0 } else { 0.0 };
-
-        if let Some(prev_price) = window.get(window.len().saturating_sub(2)) {
-            let ret = (point.price - prev_price) / prev_price;
-            equity *= 1.0 + ret * prev_position;
-        }
-        prev_position = position;
-        equity_curve.push((point.timestamp, equity));
+        let long_ma = long_stats.mean().unwrap_or(point.price);
+        positions.push(if short_ma > long_ma { 1.0 } else { 0.0 });
     }
 
-    let metrics = build_metrics(
-        &equity_curve,
+    simulated_result(
+        strategy,
+        prices,
+        &positions,
         serde_json::json!({
             "short_window": short,
             "long_window": long,
             "type": "ma_cross"
         }),
-    );
-
-    BacktestResult {
-        strategy_id: strategy.id,
-        equity_curve,
-        metrics,
-        completed_at: Some(Utc::now()),
-    }
+        cancel,
+    )
 }
 
-fn backtest_volatility(strategy: Strategy, prices: Vec<PricePoint>) -> BacktestResult {
-    let lookback = strategy
-        .params
-        .get("lookback")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(20) as usize;
-    let mut returns = Vec::new();
-    for w in prices.windows(2) {
-        let r = (w[1].price - w[0].price) / w[0].price;
-        returns.push(r);
-    }
-    let vol = if returns.len() >= lookback {
-        let slice = &returns[returns.len() - lookback..];
-        let mean = slice.iter().copied().sum::<f64>() / slice.len() as f64;
-        let var =
-            slice.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (slice.len() as f64).max(1.0);
-        var.sqrt() * (252.0_f64).sqrt()
+fn backtest_volatility(
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    params: VolatilityParams,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let lookback = params.lookback;
+    let sizing = VolTarget {
+        lookback,
+        ..VolTarget::from_params(&strategy.params)
+    };
+    let config = MetricsConfig::from_params(&strategy.params);
+    let annualization = config.annualization(prices.iter().map(|p| p.timestamp));
+    // A zero or negative previous price has no defined return; skip the pair
+    // rather than let an inf/NaN poison the volatility.
+    let returns: Vec<f64> = prices
+        .windows(2)
+        .filter(|w| w[0].price > 0.0)
+        .map(|w| (w[1].price - w[0].price) / w[0].price)
+        .collect();
+    // Asset volatility over the most recent window, for reference.
+    let asset_vol = if returns.len() >= lookback {
+        metrics::std_dev(&returns[returns.len() - lookback..])
+            * annualization.periods_per_year.sqrt()
     } else {
         0.0
     };
 
-    let equity_curve: Vec<_> = prices
+    sized_result(
+        strategy,
+        prices,
+        &vec![1.0; prices.len()],
+        &[],
+        Some(sizing),
+        serde_json::json!({
+            "asset_vol": asset_vol,
+            "lookback": lookback,
+            "type": "volatility"
+        }),
+        cancel,
+    )
+}
+
+fn backtest_rsi(
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    params: RsiParams,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let RsiParams {
+        period,
+        overbought,
+        oversold,
+    } = params;
+
+    // Mean reversion: go long once RSI dips below the oversold band and stay
+    // in until it climbs above the overbought band.
+    let closes: Vec<f64> = prices.iter().map(|p| p.price).collect();
+    let rsi_values = rsi(&closes, period);
+    let mut position = 0.0;
+    let positions: Vec<f64> = rsi_values
         .iter()
-        .scan(1.0, |equity, p| {
-            if let Some(prev) = returns.get(returns.len().saturating_sub(1)) {
-                *equity *= 1.0 + *prev;
+        .map(|value| {
+            if let Some(value) = value {
+                if *value < oversold {
+                    position = 1.0;
+                } else if *value > overbought {
+                    position = 0.0;
+                }
             }
-            Some((p.timestamp, *equity))
+            position
         })
         .collect();
+    let last_rsi = rsi_values.iter().rev().find_map(|v| *v);
 
-    let metrics = build_metrics(
-        &equity_curve,
+    simulated_result(
+        strategy,
+        prices,
+        &positions,
         serde_json::json!({
-            "annualized_vol": vol,
-            "lookback": lookback,
-            "type": "volatility"
+            "period": period,
+            "overbought": overbought,
+            "oversold": oversold,
+            "last_rsi": last_rsi,
+            "type": "rsi"
         }),
-    );
-
-    BacktestResult {
-        strategy_id: strategy.id,
-        equity_curve,
-        metrics,
-        completed_at: Some(Utc::now()),
-    }
+        cancel,
+    )
 }
 
-fn backtest_correlation(strategy: Strategy, prices: Vec<PricePoint>) -> BacktestResult {
-    // Placeholder: treat price itself vs a shifted series to compute correlation
-    let lag = strategy
-        .params
-        .get("lag")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(5) as usize;
-    let mut x = Vec::new();
-    let mut y = Vec::new();
-    for w in prices.windows(lag + 1) {
-        x.push(w[lag].price);
-        y.push(w[0].price);
-    }
-    let corr = correlation(&x, &y);
-    let equity_curve: Vec<_> = prices
+fn backtest_macd(
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    params: MacdParams,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let MacdParams { fast, slow, signal } = params;
+
+    let closes = indicators::closes(prices);
+    let fast_ema = indicators::ema(&closes, fast);
+    let slow_ema = indicators::ema(&closes, slow);
+    let macd_line: Vec<f64> = fast_ema
         .iter()
+        .zip(slow_ema.iter())
+        .map(|(f, s)| f - s)
+        .collect();
+    let signal_line = indicators::ema(&macd_line, signal);
+    // Stay flat until the slow EMA and the signal line have warmed up.
+    let warmup = slow + signal;
+    let positions: Vec<f64> = macd_line
+        .iter()
+        .zip(signal_line.iter())
         .enumerate()
-        .map(|(i, p)| (p.timestamp, 1.0 + (i as f64) * 0.0 + corr * 0.0))
+        .map(|(i, (m, s))| if i + 1 >= warmup && m > s { 1.0 } else { 0.0 })
         .collect();
-    let metrics = build_metrics(
-        &equity_curve,
+
+    simulated_result(
+        strategy,
+        prices,
+        &positions,
         serde_json::json!({
-            "lag": lag,
-            "correlation": corr,
-            "type": "correlation"
+            "fast": fast,
+            "slow": slow,
+            "signal": signal,
+            "last_macd": macd_line.last(),
+            "last_signal": signal_line.last(),
+            "type": "macd"
         }),
-    );
-    BacktestResult {
-        strategy_id: strategy.id,
-        equity_curve,
-        metrics,
-        completed_at: Some(Utc::now()),
+        cancel,
+    )
+}
+
+fn backtest_bollinger(
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    params: BollingerParams,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let closes = indicators::closes(prices);
+    let bands = indicators::bollinger(&closes, params.period, params.num_std);
+    let atr = indicators::atr(prices, params.atr_period);
+    let mut positions = Vec::with_capacity(prices.len());
+    let mut exits = vec![None; prices.len()];
+    let mut side = 0.0_f64;
+    let mut stop_level: Option<f64> = None;
+    // After a stop, wait for the close to return inside the bands.
+    let mut stopped = false;
+    let mut atr_stops = 0;
+
+    for (i, (&close, band)) in closes.iter().zip(&bands).enumerate() {
+        let Some(band) = band else {
+            positions.push(0.0);
+            continue;
+        };
+        if side != 0.0 && stop_level.is_some_and(|stop| (close - stop) * side <= 0.0) {
+            side = 0.0;
+            stop_level = None;
+            stopped = true;
+            exits[i] = Some(ExitReason::StopLoss);
+            atr_stops += 1;
+        } else if side > 0.0 && close >= band.middle || side < 0.0 && close <= band.middle {
+            side = 0.0;
+            stop_level = None;
+        }
+        let below = close < band.lower;
+        let above = close > band.upper;
+        if stopped && !below && !above {
+            stopped = false;
+        }
+        if side == 0.0 && !stopped && exits[i].is_none() {
+            if below {
+                side = 1.0;
+            } else if above && params.allow_short {
+                side = -1.0;
+            }
+            if side != 0.0 {
+                stop_level = params
+                    .atr_stop
+                    .zip(atr[i])
+                    .map(|(multiple, atr)| close - side * multiple * atr);
+            }
+        }
+        positions.push(side);
     }
+
+    let last_band = bands.last().copied().flatten();
+    let last_close = closes.last().copied();
+    sized_result(
+        strategy,
+        prices,
+        &positions,
+        &exits,
+        VolTarget::from_sizing_params(&strategy.params),
+        serde_json::json!({
+            "period": params.period,
+            "num_std": params.num_std,
+            "allow_short": params.allow_short,
+            "atr_stop": params.atr_stop,
+            "atr_stops": atr_stops,
+            "last_upper": last_band.map(|b| b.upper),
+            "last_middle": last_band.map(|b| b.middle),
+            "last_lower": last_band.map(|b| b.lower),
+            "bandwidth": last_band.and_then(|b| b.bandwidth()),
+            "percent_b": last_band.zip(last_close).and_then(|(b, c)| b.percent_b(c)),
+            "last_atr": atr.last().copied().flatten(),
+            "type": "bollinger"
+        }),
+        cancel,
+    )
 }
 
-fn correlation(x: &[f64], y: &[f64]) -> f64 {
-    if x.len() != y.len() || x.is_empty() {
-        return 0.0;
-    }
-    let mean_x = x.iter().copied().sum::<f64>() / x.len() as f64;
-    let mean_y = y.iter().copied().sum::<f64>() / y.len() as f64;
-    let mut num = 0.0;
-    let mut den_x = 0.0;
-    let mut den_y = 0.0;
-    for (a, b) in x.iter().zip(y.iter()) {
-        let dx = a - mean_x;
-        let dy = b - mean_y;
-        num += dx * dy;
-        den_x += dx.powi(2);
-        den_y += dy.powi(2);
-    }
-    if den_x == 0.0 || den_y == 0.0 {
-        return 0.0;
-    }
-    num / (den_x.sqrt() * den_y.sqrt())
+/// Runs the user's script for its positions. Compile errors and exhausted
+/// limits surface as invalid `source`, so the API answers 422 with the message.
+fn backtest_script(
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    params: ScriptParams,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let run = script::Script::compile(&params.source)
+        .and_then(|script| script.positions(prices, &params.limits()))
+        .map_err(|err| {
+            StrategyError::InvalidParams(vec![FieldError::new("source", err.to_string())])
+        })?;
+    simulated_result(
+        strategy,
+        prices,
+        &run.positions,
+        serde_json::json!({
+            "max_operations": params.max_operations,
+            "timeout_ms": params.timeout_ms,
+            "script_operations": run.operations,
+            "type": "script"
+        }),
+        cancel,
+    )
 }
 
-fn build_metrics(
-    equity_curve: &[(chrono::DateTime<Utc>, f64)],
-    mut base: serde_json::Value,
-) -> serde_json::Value {
-    if equity_curve.is_empty() {
-        return base;
-    }
-    let returns: Vec<f64> = equity_curve
-        .windows(2)
-        .filter_map(|w| {
-            let prev = w[0].1;
-            let curr = w[1].1;
-            if prev > 0.0 {
-                Some(curr / prev - 1.0)
-            } else {
-                None
-            }
-        })
+/// Sizes a raw position series with the strategy's `sizing` mode, then hands it
+/// to [`sized_result`].
+fn simulated_result(
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    positions: &[f64],
+    base: serde_json::Value,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let sizing = VolTarget::from_sizing_params(&strategy.params);
+    sized_result(strategy, prices, positions, &[], sizing, base, cancel)
+}
+
+/// Scales raw positions by `sizing`, applies the risk overlay, runs the result
+/// through the execution simulator using the cost params and attaches
+/// performance and trading metrics. `signal_exits` tags exits the kind forced
+/// itself; the overlay's reasons take precedence on the same bar.
+fn sized_result(
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    positions: &[f64],
+    signal_exits: &[Option<ExitReason>],
+    sizing: Option<VolTarget>,
+    base: serde_json::Value,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let config = MetricsConfig::from_params(&strategy.params);
+    let periods_per_year = config
+        .annualization(prices.iter().map(|p| p.timestamp))
+        .periods_per_year;
+    let sized = match &sizing {
+        Some(sizing) => sizing.apply(prices, positions, periods_per_year),
+        None => positions.to_vec(),
+    };
+    let costs = CostModel::from_params(&strategy.params);
+    let overlay = RiskOverlay::from_params(&strategy.params);
+    let overlaid = overlay.apply(prices, &sized);
+    let exits: Vec<Option<ExitReason>> = overlaid
+        .exits
+        .iter()
+        .enumerate()
+        .map(|(i, exit)| exit.or(signal_exits.get(i).copied().flatten()))
         .collect();
-    let start = equity_curve.first().map(|(_, v)| *v).unwrap_or(1.0);
-    let end = equity_curve.last().map(|(_, v)| *v).unwrap_or(start);
-    let total_return = if start > 0.0 { end / start - 1.0 } else { 0.0 };
-
-    let mut max_drawdown: f64 = 0.0;
-    let mut peak = equity_curve[0].1;
-    for &(_, v) in equity_curve.iter() {
-        if v > peak {
-            peak = v;
+    let sim = execution::simulate_with_exits(prices, &overlaid.positions, &exits, &costs, cancel)?;
+    let mut metrics = metrics::build_metrics(&sim.equity_curve, base, &config);
+    sim.stats.insert_into(&mut metrics, &costs);
+    execution::insert_trade_stats(&mut metrics, &sim.trades);
+    if let Some(obj) = metrics.as_object_mut() {
+        if overlay.is_enabled() {
+            obj.insert("risk_overlay".to_string(), overlay.to_json());
+            obj.insert(
+                "risk_exits".to_string(),
+                serde_json::json!(overlaid.exit_counts()),
+            );
         }
-        if peak > 0.0 {
-            max_drawdown = max_drawdown.min(v / peak - 1.0);
+        if let Some(sizing) = &sizing {
+            let exposures: Vec<f64> = overlaid.positions.iter().map(|p| p.abs()).collect();
+            obj.insert("sizing".to_string(), sizing.to_json());
+            obj.insert(
+                "target_vol".to_string(),
+                serde_json::json!(sizing.target_vol),
+            );
+            obj.insert(
+                "realized_vol".to_string(),
+                serde_json::json!(sizing::realized_vol(
+                    &sim.equity_curve,
+                    &overlaid.positions,
+                    periods_per_year
+                )),
+            );
+            obj.insert(
+                "avg_exposure".to_string(),
+                serde_json::json!(metrics::mean(&exposures)),
+            );
+            obj.insert(
+                "max_exposure".to_string(),
+                serde_json::json!(exposures.iter().copied().fold(0.0, f64::max)),
+            );
         }
     }
 
-    let mut vol = 0.0;
-    let mut sharpe = 0.0;
-    if !returns.is_empty() {
-        let mean = returns.iter().copied().sum::<f64>() / returns.len() as f64;
-        let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>()
-            / (returns.len() as f64).max(1.0);
-        vol = (var.sqrt()) * (252.0_f64).sqrt(); // assuming daily-ish samples
-        if vol > 0.0 {
-            sharpe = (mean * 252.0_f64.sqrt()) / vol;
-        }
+    Ok(BacktestResult {
+        id: None,
+        strategy_id: strategy.id,
+        equity_curve: sim.equity_curve,
+        metrics,
+        trades: sim.trades,
+        benchmark_curve: Vec::new(),
+        weights_history: Vec::new(),
+        strategy_version: None,
+        overrides: serde_json::Map::new(),
+        robustness: None,
+        manifest: None,
+        completed_at: Some(Utc::now()),
+    })
+}
+
+/// Wilder's RSI; `None` until `period` price changes have been observed.
+fn rsi(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if values.len() <= period {
+        return out;
+    }
+    let mut avg_gain = 0.0;
+    let mut avg_loss = 0.0;
+    for i in 1..=period {
+        let change = values[i] - values[i - 1];
+        avg_gain += change.max(0.0);
+        avg_loss += (-change).max(0.0);
+    }
+    avg_gain /= period as f64;
+    avg_loss /= period as f64;
+    out[period] = Some(rsi_from_averages(avg_gain, avg_loss));
+    for i in (period + 1)..values.len() {
+        let change = values[i] - values[i - 1];
+        avg_gain = (avg_gain * (period as f64 - 1.0) + change.max(0.0)) / period as f64;
+        avg_loss = (avg_loss * (period as f64 - 1.0) + (-change).max(0.0)) / period as f64;
+        out[i] = Some(rsi_from_averages(avg_gain, avg_loss));
     }
+    out
+}
 
-    let days_span = if let (Some(first), Some(last)) = (equity_curve.first(), equity_curve.last()) {
-        (last.0 - first.0).num_days().max(1)
-    } else {
-        1
-    } as f64;
-    let cagr = if days_span > 0.0 && start > 0.0 {
-        (end / start).powf(365.0 / days_span) - 1.0
+fn rsi_from_averages(avg_gain: f64, avg_loss: f64) -> f64 {
+    if avg_loss == 0.0 {
+        if avg_gain == 0.0 {
+            50.0
+        } else {
+            100.0
+        }
     } else {
-        0.0
-    };
+        100.0 - 100.0 / (1.0 + avg_gain / avg_loss)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    pub(crate) fn strategy(kind: &str, params: serde_json::Value) -> Strategy {
+        Strategy {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "test".to_string(),
+            r#type: kind.to_string(),
+            params,
+            version: 1,
+        }
+    }
+
+    pub(crate) fn series(prices: &[f64]) -> Vec<PricePoint> {
+        series_from(
+            Utc::now() - chrono::Duration::days(prices.len() as i64),
+            prices,
+        )
+    }
+
+    pub(crate) fn series_from(start: chrono::DateTime<Utc>, prices: &[f64]) -> Vec<PricePoint> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, p)| PricePoint::new(start + chrono::Duration::days(i as i64), *p))
+            .collect()
+    }
+
+    #[test]
+    fn indicators_match_hand_computed_values() {
+        let values = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(
+            indicators::sma(&values, 2),
+            vec![None, Some(1.5), Some(2.5), Some(3.5)]
+        );
+        assert_eq!(indicators::rolling_std(&values, 2)[3], Some(0.5));
+        let band = indicators::bollinger(&values, 2, 2.0)[3].unwrap();
+        assert_eq!((band.lower, band.middle, band.upper), (2.5, 3.5, 4.5));
+        assert_eq!(band.percent_b(4.5), Some(1.0));
+
+        let start = Utc::now();
+        let bar = |i: i64, high: f64, low: f64, close: f64| PricePoint {
+            high: Some(high),
+            low: Some(low),
+            ..PricePoint::new(start + chrono::Duration::days(i), close)
+        };
+        let bars = [
+            bar(0, 10.0, 8.0, 9.0),
+            bar(1, 12.0, 9.0, 11.0),
+            bar(2, 11.0, 10.0, 10.0),
+        ];
+        assert_eq!(indicators::true_range(&bars), vec![2.0, 3.0, 1.0]);
+        assert_eq!(indicators::atr(&bars, 2), vec![None, Some(2.5), Some(1.75)]);
+        let channel = indicators::donchian(&bars, 2)[2].unwrap();
+        assert_eq!(
+            (channel.lower, channel.middle, channel.upper),
+            (9.0, 10.5, 12.0)
+        );
+    }
+
+    #[test]
+    fn ticks_aggregate_into_candles() {
+        let start: chrono::DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        let tick = |secs: i64, price: f64, volume: Option<f64>| PricePoint {
+            volume,
+            ..PricePoint::new(start + chrono::Duration::seconds(secs), price)
+        };
+        let ticks = [
+            tick(10, 1.0, Some(2.0)),
+            tick(30, 0.5, None),
+            tick(50, 3.0, Some(1.0)),
+            tick(65, 2.0, None),
+        ];
+        let candles = candles::aggregate(&ticks, chrono::Duration::minutes(1));
+        assert_eq!(candles.len(), 2);
+        let first = &candles[0];
+        assert_eq!(first.timestamp, ticks[2].timestamp);
+        assert_eq!(
+            (first.open(), first.high(), first.low(), first.price),
+            (1.0, 3.0, 0.5, 3.0)
+        );
+        assert_eq!(first.volume, Some(3.0));
+        assert_eq!((candles[1].price, candles[1].volume), (2.0, None));
+    }
+
+    #[tokio::test]
+    async fn bollinger_reverts_to_the_middle_band() {
+        let svc = InMemoryStrategyService;
+        let params = serde_json::json!({ "period": 5, "num_std": 1.0 });
+        let result = svc
+            .backtest(
+                &strategy("bollinger", params),
+                &series(&[100.0, 101.0, 100.0, 101.0, 100.0, 94.0, 97.0, 101.0, 100.0]),
+            )
+            .await
+            .unwrap();
+        // Bought the dip below the lower band, sold back at the middle line.
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.trades[0].entry_price, 94.0);
+        assert_eq!(result.trades[0].exit_price, 101.0);
+        assert_eq!(result.trades[0].exit_reason, Some(ExitReason::Signal));
+        assert!(result.metrics["percent_b"].is_number());
+
+        let params = serde_json::json!({
+            "period": 5, "num_std": 1.0, "atr_period": 2, "atr_stop": 1.0
+        });
+        let result = svc
+            .backtest(
+                &strategy("bb", params),
+                &series(&[100.0, 101.0, 100.0, 101.0, 100.0, 94.0, 85.0, 80.0]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.trades[0].exit_price, 85.0);
+        assert_eq!(result.trades[0].exit_reason, Some(ExitReason::StopLoss));
+        assert_eq!(result.metrics["atr_stops"], 1);
+
+        let errors = StrategyKind::Bollinger
+            .validate(&serde_json::json!({ "period": 1, "atr_stop": -1.0 }))
+            .unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["period", "atr_stop"]);
+    }
+
+    #[test]
+    fn rsi_saturates_on_monotonic_series() {
+        let rising: Vec<f64> = (1..=30).map(|v| v as f64).collect();
+        let values = rsi(&rising, 14);
+        assert!(values[13].is_none());
+        assert_eq!(values[14], Some(100.0));
+        let falling: Vec<f64> = rising.iter().rev().copied().collect();
+        assert_eq!(rsi(&falling, 14)[29], Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn macd_goes_long_in_uptrend() {
+        let prices: Vec<f64> = (0..80).map(|i| 100.0 * 1.01_f64.powi(i)).collect();
+        let result = InMemoryStrategyService
+            .backtest(&strategy("MACD", serde_json::json!({})), &series(&prices))
+            .await
+            .expect("macd backtest");
+        let total_return = result.metrics["total_return"].as_f64().unwrap();
+        assert!(total_return > 0.0);
+        assert_eq!(result.equity_curve.len(), prices.len());
+    }
+
+    #[tokio::test]
+    async fn trading_costs_reduce_equity() {
+        let prices: Vec<f64> = (0..60)
+            .map(|i| 100.0 + 10.0 * (i as f64 / 4.0).sin())
+            .collect();
+        let frictionless = InMemoryStrategyService
+            .backtest(
+                &strategy("ma_cross", serde_json::json!({})),
+                &series(&prices),
+            )
+            .await
+            .unwrap();
+        let costly = InMemoryStrategyService
+            .backtest(
+                &strategy(
+                    "ma_cross",
+                    serde_json::json!({ "fee_bps": 30, "slippage_bps": 10, "gas_cost": 5 }),
+                ),
+                &series(&prices),
+            )
+            .await
+            .unwrap();
+        let trades = costly.metrics["trade_count"].as_u64().unwrap();
+        assert!(trades > 0);
+        assert_eq!(
+            frictionless.metrics["trade_count"].as_u64().unwrap(),
+            trades
+        );
+        assert_eq!(frictionless.metrics["total_costs"].as_f64().unwrap(), 0.0);
+        assert!(costly.metrics["total_costs"].as_f64().unwrap() > 0.0);
+        assert!(
+            costly.metrics["total_return"].as_f64().unwrap()
+                < frictionless.metrics["total_return"].as_f64().unwrap()
+        );
+    }
+
+    #[test]
+    fn ledger_records_round_trips_with_costs() {
+        let points = series(&[100.0, 100.0, 110.0, 121.0, 110.0, 100.0, 100.0]);
+        let positions = [0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0];
+        let costs = CostModel {
+            fee_bps: 10.0,
+            ..CostModel::default()
+        };
+        let sim = execution::simulate(&points, &positions, &costs);
+        assert_eq!(sim.trades.len(), 2);
+        let first = &sim.trades[0];
+        assert_eq!(first.side, domain::TradeSide::Long);
+        assert!(first.closed);
+        assert_eq!(first.entry_price, 100.0);
+        assert_eq!(first.exit_price, 121.0);
+        assert!(first.pnl > 0.0 && first.pnl < 0.21);
+        assert_eq!(first.holding_secs, 2 * 86_400);
+        assert!(!sim.trades[1].closed);
 
-    if let Some(obj) = base.as_object_mut() {
-        obj.insert("total_return".to_string(), serde_json::json!(total_return));
-        obj.insert("max_drawdown".to_string(), serde_json::json!(max_drawdown));
-        obj.insert("annualized_vol".to_string(), serde_json::json!(vol));
-        obj.insert("sharpe".to_string(), serde_json::json!(sharpe));
-        obj.insert("cagr".to_string(), serde_json::json!(cagr));
+        let mut metrics = serde_json::json!({});
+        execution::insert_trade_stats(&mut metrics, &sim.trades);
+        assert_eq!(metrics["win_rate"].as_f64(), Some(0.5));
+        assert_eq!(metrics["longest_losing_streak"].as_u64(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn unknown_type_is_rejected() {
+        let result = InMemoryStrategyService
+            .backtest(
+                &strategy("momentum", serde_json::json!({})),
+                &series(&[1.0, 2.0]),
+            )
+            .await;
+        assert!(matches!(result, Err(StrategyError::UnknownType(_))));
+    }
+
+    #[test]
+    fn benchmark_aligns_and_scores_relative_to_strategy() {
+        let points = series(&[100.0, 102.0, 101.0, 105.0, 104.0, 108.0]);
+        // Sparser benchmark: only every other bar, as-of aligned.
+        let sparse: Vec<PricePoint> = points.iter().step_by(2).cloned().collect();
+        let timestamps: Vec<_> = points.iter().map(|p| p.timestamp).collect();
+        let curve = benchmark::benchmark_curve(&timestamps, &sparse);
+        assert_eq!(curve.len(), points.len());
+        assert_eq!(curve[1].1, 1.0);
+        assert_eq!(curve[2].1, 1.01);
+
+        // Fully invested without costs tracks buy-and-hold exactly.
+        let sim = execution::simulate(&points, &[1.0; 6], &CostModel::default());
+        let mut result = BacktestResult {
+            id: None,
+            strategy_id: uuid::Uuid::new_v4(),
+            equity_curve: sim.equity_curve,
+            metrics: serde_json::json!({}),
+            trades: Vec::new(),
+            benchmark_curve: Vec::new(),
+            weights_history: Vec::new(),
+            strategy_version: None,
+            overrides: serde_json::Map::new(),
+            robustness: None,
+            manifest: None,
+            completed_at: None,
+        };
+        benchmark::attach(
+            &mut result,
+            &points,
+            benchmark::BUY_AND_HOLD,
+            &MetricsConfig::default(),
+        );
+        assert_eq!(result.benchmark_curve.len(), points.len());
+        assert!((result.metrics["beta"].as_f64().unwrap() - 1.0).abs() < 1e-9);
+        assert!(result.metrics["alpha"].as_f64().unwrap().abs() < 1e-9);
+        assert!(result.metrics["tracking_error"].as_f64().unwrap() < 1e-9);
+        assert!(result.metrics["information_ratio"].is_null());
+        assert!((result.metrics["benchmark_total_return"].as_f64().unwrap() - 0.08).abs() < 1e-9);
+    }
+
+    #[test]
+    fn drawdown_duration_and_recovery() {
+        let curve: Vec<_> = series(&[100.0, 90.0, 80.0, 95.0, 100.0, 110.0, 99.0])
+            .into_iter()
+            .map(|p| (p.timestamp, p.price))
+            .collect();
+        let stats = metrics::drawdown_stats(&curve);
+        assert!((stats.max_drawdown + 0.2).abs() < 1e-12);
+        // Peak on day 0, back to the peak on day 4; trough on day 2.
+        assert_eq!(stats.max_duration_secs, 4 * 86_400);
+        assert_eq!(stats.time_to_recovery_secs, Some(2 * 86_400));
+
+        let unrecovered = &curve[..4];
+        let stats = metrics::drawdown_stats(unrecovered);
+        assert_eq!(stats.time_to_recovery_secs, None);
+        assert_eq!(stats.max_duration_secs, 3 * 86_400);
+    }
+
+    #[test]
+    fn tail_risk_matches_known_values() {
+        assert!((metrics::inverse_normal_cdf(0.95) - 1.644_853_6).abs() < 1e-6);
+        assert!((metrics::inverse_normal_cdf(0.01) + 2.326_347_9).abs() < 1e-6);
+
+        let returns: Vec<f64> = (1..=100).map(|i| (i as f64 - 50.0) / 1000.0).collect();
+        let (var, cvar) = metrics::historical_var_cvar(&returns, 0.95);
+        // The five worst returns are -0.049..=-0.045.
+        assert!((var - 0.045).abs() < 1e-12);
+        assert!((cvar - 0.047).abs() < 1e-12);
+        let (pvar, pcvar) = metrics::parametric_var_cvar(&returns, 0.95);
+        assert!(pvar > 0.0 && pcvar > pvar);
+
+        assert_eq!(metrics::sortino(&[0.01, 0.02], 252.0), None);
+        assert_eq!(metrics::calmar(0.3, -0.15), Some(2.0));
+    }
+
+    #[test]
+    fn annualization_follows_sampling_interval() {
+        let start = Utc::now();
+        let hourly: Vec<_> = (0..48)
+            .map(|i| start + chrono::Duration::hours(i))
+            .collect();
+        let config = MetricsConfig::default();
+        let detected = config.annualization(hourly.iter().copied());
+        assert_eq!(detected.source, "detected");
+        assert_eq!(detected.bar_interval_secs, 3600.0);
+        assert_eq!(detected.periods_per_year, 365.0 * 24.0);
+
+        let equity = MetricsConfig::from_params(&serde_json::json!({ "annualization": "equity" }));
+        let daily = series(&[1.0, 2.0, 3.0]).into_iter().map(|p| p.timestamp);
+        assert_eq!(equity.annualization(daily).periods_per_year, 252.0);
+
+        let explicit =
+            MetricsConfig::from_params(&serde_json::json!({ "bar_interval_secs": 86400 }));
+        let annualization = explicit.annualization(hourly.iter().copied());
+        assert_eq!(annualization.source, "explicit");
+        assert_eq!(annualization.periods_per_year, 365.0);
+    }
+
+    #[test]
+    fn kinds_parse_and_validate_params() {
+        assert_eq!("MA".parse::<StrategyKind>().unwrap(), StrategyKind::MaCross);
+        assert_eq!("Rsi".parse::<StrategyKind>().unwrap(), StrategyKind::Rsi);
+        assert!("momentum".parse::<StrategyKind>().is_err());
+
+        let parsed = StrategyKind::MaCross
+            .validate(&serde_json::json!({ "short_window": 3, "fee_bps": 5 }))
+            .unwrap();
+        assert_eq!(
+            parsed,
+            StrategyParams::MaCross(kinds::MaCrossParams {
+                short_window: 3,
+                long_window: 20
+            })
+        );
+
+        let errors = StrategyKind::MaCross
+            .validate(&serde_json::json!({ "long_windw": 10, "fee_bps": "x" }))
+            .unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["fee_bps", "long_windw"]);
+
+        let errors = StrategyKind::Rsi
+            .validate(&serde_json::json!({ "oversold": 80, "overbought": 70 }))
+            .unwrap_err();
+        assert_eq!(errors[0].field, "oversold");
+
+        // Backtests tolerate unknown keys but still enforce the rules.
+        assert!(StrategyKind::Macd
+            .params(&serde_json::json!({ "short_window": 5 }))
+            .is_ok());
+        assert!(matches!(
+            StrategyKind::Macd.params(&serde_json::json!({ "fast": 30, "slow": 10 })),
+            Err(StrategyError::InvalidParams(_))
+        ));
+
+        let schema = StrategyKind::Volatility.schema();
+        assert_eq!(schema["additionalProperties"], false);
+        assert!(schema["properties"]["lookback"].is_object());
+        assert!(schema["properties"]["fee_bps"].is_object());
+    }
+
+    #[tokio::test]
+    async fn cancelled_runs_stop_with_cancelled() {
+        let prices: Vec<f64> = (0..200).map(|i| 100.0 + (i as f64 / 5.0).sin()).collect();
+        let points = series(&prices);
+        let base = strategy("ma_cross", serde_json::json!({}));
+        let cancel = CancelToken::new();
+        assert!(InMemoryStrategyService
+            .backtest_cancellable(&base, &points, &cancel)
+            .await
+            .is_ok());
+
+        cancel.clone().cancel();
+        assert!(matches!(
+            InMemoryStrategyService
+                .backtest_cancellable(&base, &points, &cancel)
+                .await,
+            Err(StrategyError::Cancelled)
+        ));
+        let request: optimize::OptimizationRequest =
+            serde_json::from_value(serde_json::json!({ "space": { "short_window": [2, 3] } }))
+                .unwrap();
+        assert!(matches!(
+            optimize::optimize(&InMemoryStrategyService, &base, &points, &request, &cancel).await,
+            Err(StrategyError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn var_confidence_is_configurable() {
+        let config = MetricsConfig::from_params(&serde_json::json!({ "var_confidence": 0.9 }));
+        assert_eq!(config.confidence_levels, vec![0.9]);
+        let config = MetricsConfig::from_params(&serde_json::json!({ "var_confidence": [1.5] }));
+        assert_eq!(config, MetricsConfig::default());
+    }
+
+    #[tokio::test]
+    async fn rebalance_tracks_weights_and_costs() {
+        let start = Utc::now() - chrono::Duration::days(40);
+        let rising: Vec<f64> = (0..40).map(|i| 100.0 * 1.02_f64.powi(i)).collect();
+        let flat = vec![50.0; 40];
+        let mut input = PriceSeries::new();
+        input.insert("eth".to_string(), series_from(start, &rising));
+        input.insert("USDC".to_string(), series_from(start, &flat));
+        let params = serde_json::json!({
+            "symbols": ["ETH", "USDC"],
+            "weighting": "fixed",
+            "weights": { "ETH": 3.0, "USDC": 1.0 },
+            "rebalance_every": 10,
+            "fee_bps": 10
+        });
+        let result = InMemoryStrategyService
+            .backtest_multi(&strategy("rebalance", params.clone()), &input)
+            .await
+            .expect("rebalance backtest");
+        assert_eq!(result.weights_history.len(), 40);
+        let rebalanced: Vec<usize> = result
+            .weights_history
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.rebalanced)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(rebalanced, vec![0, 10, 20, 30]);
+        assert!((result.weights_history[10].weights["ETH"] - 0.75).abs() < 1e-12);
+        // ETH outgrows its target between rebalances.
+        assert!(result.weights_history[9].weights["ETH"] > 0.75);
+        assert_eq!(result.metrics["rebalance_count"], 4);
+        assert!(result.metrics["total_costs"].as_f64().unwrap() > 0.0);
+        assert_eq!(result.metrics["benchmark"], "equal_weight_buy_and_hold");
+        assert_eq!(result.benchmark_curve.len(), 40);
+
+        let mut threshold = params.clone();
+        threshold["rebalance"] = serde_json::json!("threshold");
+        threshold["drift_threshold"] = serde_json::json!(0.5);
+        let result = InMemoryStrategyService
+            .backtest_multi(&strategy("rebalance", threshold), &input)
+            .await
+            .unwrap();
+        assert_eq!(result.metrics["rebalance_count"], 1);
+
+        assert!(matches!(
+            InMemoryStrategyService
+                .backtest(&strategy("rebalance", params), &series(&rising))
+                .await,
+            Err(StrategyError::InvalidParams(_))
+        ));
+        let errors = StrategyKind::Rebalance
+            .validate(&serde_json::json!({ "symbols": ["ETH"], "weighting": "fixed" }))
+            .unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["symbols", "weights"]);
+    }
+
+    #[test]
+    fn volatility_weights_equalize_risk() {
+        let covariance = vec![vec![0.04, 0.0], vec![0.0, 0.01]];
+        let weights = portfolio::risk_parity_weights(&covariance).unwrap();
+        assert!((weights[0] - 1.0 / 3.0).abs() < 1e-9);
+        assert!((weights[1] - 2.0 / 3.0).abs() < 1e-9);
+
+        let correlated = vec![
+            vec![0.04, 0.006, 0.0],
+            vec![0.006, 0.01, 0.002],
+            vec![0.0, 0.002, 0.02],
+        ];
+        let weights = portfolio::risk_parity_weights(&correlated).unwrap();
+        let contributions: Vec<f64> = (0..3)
+            .map(|i| weights[i] * (0..3).map(|j| correlated[i][j] * weights[j]).sum::<f64>())
+            .collect();
+        assert!(contributions.windows(2).all(|w| (w[0] - w[1]).abs() < 1e-9));
+
+        let returns: [&[f64]; 2] = [&[0.02, -0.02, 0.02, -0.02], &[0.01, -0.01, 0.01, -0.01]];
+        let weights = portfolio::inverse_vol_weights(&returns).unwrap();
+        assert!((weights[1] - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn align_uses_last_known_prices() {
+        let start = Utc::now() - chrono::Duration::days(10);
+        let daily = series_from(start, &[1.0, 2.0, 3.0, 4.0]);
+        let sparse: Vec<PricePoint> = [(1, 10.0), (3, 30.0)]
+            .iter()
+            .map(|(day, price)| {
+                PricePoint::new(
+                    start + chrono::Duration::days(*day) + chrono::Duration::minutes(5),
+                    *price,
+                )
+            })
+            .collect();
+        let mut input = PriceSeries::new();
+        input.insert("A".to_string(), daily);
+        input.insert("B".to_string(), sparse);
+        let aligned = portfolio::align(&input);
+        // B is the sparser series, so its timeline is used.
+        assert_eq!(aligned.len(), 2);
+        assert_eq!(aligned.symbols, vec!["A", "B"]);
+        let a: Vec<f64> = aligned.columns[0].iter().map(|p| p.price).collect();
+        assert_eq!(a, vec![2.0, 4.0]);
+    }
+
+    #[tokio::test]
+    async fn what_if_replays_a_policy_over_real_holdings() {
+        let day = |d: u32, eth: f64, eth_price: f64, total: f64| domain::DailySnapshot {
+            wallet_id: Uuid::nil(),
+            day: chrono::NaiveDate::from_ymd_opt(2024, 3, d).unwrap(),
+            total_usd_value: total,
+            positions: vec![
+                domain::Position {
+                    asset_symbol: "eth".to_string(),
+                    amount: eth,
+                    usd_value: eth * eth_price,
+                },
+                domain::Position {
+                    asset_symbol: "USDC".to_string(),
+                    amount: 100.0,
+                    usd_value: 100.0,
+                },
+            ],
+        };
+        // The wallet really sold 1 ETH on the last day.
+        let days = [
+            day(1, 2.0, 100.0, 300.0),
+            day(2, 2.0, 120.0, 340.0),
+            day(3, 1.0, 90.0, 190.0),
+        ];
+        let prices = whatif::implied_prices(&days);
+        assert_eq!(prices["ETH"].len(), 3);
+        assert_eq!(prices["ETH"][2].price, 90.0);
+
+        // Hedge half of the ETH exposure.
+        let policy = InMemoryStrategyService
+            .backtest(
+                &strategy("script", serde_json::json!({ "source": "0.5" })),
+                &prices["ETH"],
+            )
+            .await
+            .unwrap();
+        let managed = ["ETH".to_string()];
+        let replay = whatif::replay(&days, &managed, &policy, &MetricsConfig::default());
+        let values: Vec<f64> = replay.hypothetical_curve.iter().map(|(_, v)| *v).collect();
+        let expected = [300.0, 320.0, 292.5];
+        for (value, expected) in values.iter().zip(expected) {
+            assert!((value - expected).abs() < 1e-9, "{values:?}");
+        }
+        assert_eq!(replay.actual_curve[2].1, 190.0);
+        let excess = replay.comparison["excess_return"].as_f64().unwrap();
+        assert!((excess - (292.5 / 300.0 - 190.0 / 300.0)).abs() < 1e-9);
+        assert_eq!(replay.comparison["final_difference_usd"], 102.5);
+        assert_eq!(replay.hypothetical_metrics["managed_value"], 200.0);
     }
-    base
 }
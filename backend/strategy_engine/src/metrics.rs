@@ -0,0 +1,471 @@
+//! Performance and downside-risk metrics over an equity (or portfolio value)
+//! curve. Everything here works on plain `(timestamp, value)` series so the
+//! same functions serve backtests and portfolio history analytics.
+
+use chrono::{DateTime, Utc};
+
+pub type Curve = [(DateTime<Utc>, f64)];
+
+/// Calendar days per year for assets that trade around the clock.
+pub const CRYPTO_DAYS_PER_YEAR: f64 = 365.0;
+/// Trading days per year for exchange-traded assets.
+pub const EQUITY_DAYS_PER_YEAR: f64 = 252.0;
+
+const SECS_PER_DAY: f64 = 86_400.0;
+
+/// Knobs for [`build_metrics`], read from strategy params.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsConfig {
+    /// Confidence levels for VaR/CVaR, each in (0, 1).
+    pub confidence_levels: Vec<f64>,
+    /// Trading days per year used to annualize per-bar statistics.
+    pub days_per_year: f64,
+    /// Bar spacing; detected from the median timestamp gap when `None`.
+    pub bar_interval_secs: Option<f64>,
+    /// Overrides the derived periods per year entirely.
+    pub periods_per_year: Option<f64>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            confidence_levels: vec![0.95, 0.99],
+            days_per_year: CRYPTO_DAYS_PER_YEAR,
+            bar_interval_secs: None,
+            periods_per_year: None,
+        }
+    }
+}
+
+/// How per-bar statistics were scaled to annual figures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annualization {
+    pub periods_per_year: f64,
+    pub bar_interval_secs: f64,
+    /// `explicit`, `detected` or `default` (daily bars when nothing else is known).
+    pub source: &'static str,
+}
+
+impl Annualization {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "periods_per_year": self.periods_per_year,
+            "bar_interval_secs": self.bar_interval_secs,
+            "source": self.source
+        })
+    }
+}
+
+impl MetricsConfig {
+    /// Reads `var_confidence` as a single number or an array of numbers;
+    /// values outside (0, 1) are dropped and an empty result keeps the defaults.
+    /// Annualization comes from `annualization` (`crypto` = 365 days, `equity` =
+    /// 252), `days_per_year`, `bar_interval_secs` and `periods_per_year`.
+    pub fn from_params(params: &serde_json::Value) -> Self {
+        let positive = |key: &str| {
+            params
+                .get(key)
+                .and_then(|v| v.as_f64())
+                .filter(|v| *v > 0.0)
+        };
+        let convention = match params.get("annualization").and_then(|v| v.as_str()) {
+            Some(name) if name.eq_ignore_ascii_case("equity") => EQUITY_DAYS_PER_YEAR,
+            _ => CRYPTO_DAYS_PER_YEAR,
+        };
+        let levels: Vec<f64> = match params.get("var_confidence") {
+            Some(serde_json::Value::Array(values)) => {
+                values.iter().filter_map(|v| v.as_f64()).collect()
+            }
+            Some(value) => value.as_f64().into_iter().collect(),
+            None => Vec::new(),
+        };
+        let levels: Vec<f64> = levels
+            .into_iter()
+            .filter(|c| *c > 0.0 && *c < 1.0)
+            .collect();
+        let defaults = Self::default();
+        Self {
+            confidence_levels: if levels.is_empty() {
+                defaults.confidence_levels
+            } else {
+                levels
+            },
+            days_per_year: positive("days_per_year").unwrap_or(convention),
+            bar_interval_secs: positive("bar_interval_secs"),
+            periods_per_year: positive("periods_per_year"),
+        }
+    }
+
+    /// Resolves periods per year for a series sampled at `timestamps`.
+    pub fn annualization<I>(&self, timestamps: I) -> Annualization
+    where
+        I: IntoIterator<Item = DateTime<Utc>>,
+    {
+        let (bar_interval_secs, source) = match self.bar_interval_secs {
+            Some(secs) => (secs, "explicit"),
+            None => match median_interval_secs(timestamps) {
+                Some(secs) => (secs, "detected"),
+                None => (SECS_PER_DAY, "default"),
+            },
+        };
+        match self.periods_per_year {
+            Some(periods) => Annualization {
+                periods_per_year: periods,
+                bar_interval_secs,
+                source: "explicit",
+            },
+            None => Annualization {
+                periods_per_year: self.days_per_year * SECS_PER_DAY / bar_interval_secs,
+                bar_interval_secs,
+                source,
+            },
+        }
+    }
+}
+
+/// Median gap between consecutive timestamps, ignoring non-positive gaps.
+pub fn median_interval_secs<I>(timestamps: I) -> Option<f64>
+where
+    I: IntoIterator<Item = DateTime<Utc>>,
+{
+    let mut prev: Option<DateTime<Utc>> = None;
+    let mut gaps = Vec::new();
+    for ts in timestamps {
+        if let Some(p) = prev {
+            let gap = (ts - p).num_milliseconds() as f64 / 1000.0;
+            if gap > 0.0 {
+                gaps.push(gap);
+            }
+        }
+        prev = Some(ts);
+    }
+    if gaps.is_empty() {
+        return None;
+    }
+    gaps.sort_by(|a, b| a.total_cmp(b));
+    let mid = gaps.len() / 2;
+    Some(if gaps.len() % 2 == 0 {
+        (gaps[mid - 1] + gaps[mid]) / 2.0
+    } else {
+        gaps[mid]
+    })
+}
+
+/// Bar-to-bar simple returns, one per bar after the first; a bar following a
+/// non-positive value (e.g. a wiped-out account) counts as a zero return.
+pub fn simple_returns(curve: &Curve) -> Vec<f64> {
+    curve
+        .windows(2)
+        .map(|w| {
+            if w[0].1 > 0.0 {
+                w[1].1 / w[0].1 - 1.0
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+/// Population standard deviation.
+pub fn std_dev(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let m = mean(values);
+    (values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / values.len() as f64).sqrt()
+}
+
+/// Pearson correlation; 0.0 for mismatched, empty or constant inputs.
+pub fn correlation(x: &[f64], y: &[f64]) -> f64 {
+    if x.len() != y.len() || x.is_empty() {
+        return 0.0;
+    }
+    let mean_x = x.iter().copied().sum::<f64>() / x.len() as f64;
+    let mean_y = y.iter().copied().sum::<f64>() / y.len() as f64;
+    let mut num = 0.0;
+    let mut den_x = 0.0;
+    let mut den_y = 0.0;
+    for (a, b) in x.iter().zip(y.iter()) {
+        let dx = a - mean_x;
+        let dy = b - mean_y;
+        num += dx * dy;
+        den_x += dx.powi(2);
+        den_y += dy.powi(2);
+    }
+    if den_x == 0.0 || den_y == 0.0 {
+        return 0.0;
+    }
+    num / (den_x.sqrt() * den_y.sqrt())
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DrawdownStats {
+    /// Deepest peak-to-trough decline, as a negative fraction.
+    pub max_drawdown: f64,
+    /// Longest time spent below a previous peak, including an unrecovered tail.
+    pub max_duration_secs: i64,
+    /// Time from the deepest trough back to its prior peak; `None` if the curve
+    /// never recovered.
+    pub time_to_recovery_secs: Option<i64>,
+}
+
+pub fn drawdown_stats(curve: &Curve) -> DrawdownStats {
+    let Some(&(first_ts, first_value)) = curve.first() else {
+        return DrawdownStats::default();
+    };
+    let mut stats = DrawdownStats::default();
+    let (mut peak_ts, mut peak) = (first_ts, first_value);
+    // Trough of the deepest drawdown seen so far and whether it has recovered.
+    let mut worst_trough_ts = first_ts;
+    let mut worst_recovered = true;
+    let mut underwater = false;
+    for &(ts, value) in curve {
+        if value >= peak {
+            if underwater {
+                stats.max_duration_secs = stats.max_duration_secs.max((ts - peak_ts).num_seconds());
+                underwater = false;
+            }
+            if !worst_recovered {
+                stats.time_to_recovery_secs = Some((ts - worst_trough_ts).num_seconds());
+                worst_recovered = true;
+            }
+            peak_ts = ts;
+            peak = value;
+            continue;
+        }
+        underwater = true;
+        if peak > 0.0 {
+            let drawdown = value / peak - 1.0;
+            if drawdown < stats.max_drawdown {
+                stats.max_drawdown = drawdown;
+                stats.time_to_recovery_secs = None;
+                worst_trough_ts = ts;
+                worst_recovered = false;
+            }
+        }
+    }
+    if let (true, Some(&(last_ts, _))) = (underwater, curve.last()) {
+        stats.max_duration_secs = stats
+            .max_duration_secs
+            .max((last_ts - peak_ts).num_seconds());
+    }
+    stats
+}
+
+/// Annualized Sortino ratio (zero target). `None` without any losing period.
+pub fn sortino(returns: &[f64], periods_per_year: f64) -> Option<f64> {
+    if returns.is_empty() {
+        return None;
+    }
+    let downside =
+        (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64).sqrt();
+    if downside > 0.0 {
+        Some(mean(returns) * periods_per_year.sqrt() / downside)
+    } else {
+        None
+    }
+}
+
+/// CAGR over the absolute max drawdown. `None` when there was no drawdown.
+pub fn calmar(cagr: f64, max_drawdown: f64) -> Option<f64> {
+    if max_drawdown < 0.0 {
+        Some(cagr / max_drawdown.abs())
+    } else {
+        None
+    }
+}
+
+/// One-period VaR and CVaR (expected shortfall) at a confidence level,
+/// reported as positive loss fractions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TailRisk {
+    pub confidence: f64,
+    pub historical_var: f64,
+    pub historical_cvar: f64,
+    pub parametric_var: f64,
+    pub parametric_cvar: f64,
+}
+
+pub fn tail_risk(returns: &[f64], confidence: f64) -> TailRisk {
+    let (historical_var, historical_cvar) = historical_var_cvar(returns, confidence);
+    let (parametric_var, parametric_cvar) = parametric_var_cvar(returns, confidence);
+    TailRisk {
+        confidence,
+        historical_var,
+        historical_cvar,
+        parametric_var,
+        parametric_cvar,
+    }
+}
+
+/// Empirical quantile of the return distribution and the mean of returns at or
+/// below it.
+pub fn historical_var_cvar(returns: &[f64], confidence: f64) -> (f64, f64) {
+    if returns.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mut sorted = returns.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    // Epsilon keeps e.g. (1 - 0.95) * 100 from rounding up to 6 observations.
+    let tail = ((1.0 - confidence) * sorted.len() as f64 - 1e-9)
+        .ceil()
+        .max(1.0) as usize;
+    let tail = tail.min(sorted.len());
+    let var = -sorted[tail - 1];
+    let cvar = -mean(&sorted[..tail]);
+    (var.max(0.0), cvar.max(0.0))
+}
+
+/// VaR/CVaR assuming normally distributed returns with the sample mean and
+/// standard deviation.
+pub fn parametric_var_cvar(returns: &[f64], confidence: f64) -> (f64, f64) {
+    if returns.len() < 2 {
+        return (0.0, 0.0);
+    }
+    let mu = mean(returns);
+    let sigma = std_dev(returns);
+    let z = inverse_normal_cdf(confidence);
+    let density = (-0.5 * z * z).exp() / (2.0 * std::f64::consts::PI).sqrt();
+    let var = z * sigma - mu;
+    let cvar = sigma * density / (1.0 - confidence) - mu;
+    (var.max(0.0), cvar.max(0.0))
+}
+
+/// Acklam's rational approximation of the standard normal quantile
+/// (relative error below 1.2e-9).
+pub fn inverse_normal_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    let p = p.clamp(1e-12, 1.0 - 1e-12);
+    let low = 0.02425;
+    if p < low {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - low {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -inverse_normal_cdf(1.0 - p)
+    }
+}
+
+/// Adds performance and downside-risk metrics for `equity_curve` to `base`.
+pub fn build_metrics(
+    equity_curve: &Curve,
+    mut base: serde_json::Value,
+    config: &MetricsConfig,
+) -> serde_json::Value {
+    if equity_curve.is_empty() {
+        return base;
+    }
+    let returns = simple_returns(equity_curve);
+    let start = equity_curve.first().map(|(_, v)| *v).unwrap_or(1.0);
+    let end = equity_curve.last().map(|(_, v)| *v).unwrap_or(start);
+    let total_return = if start > 0.0 { end / start - 1.0 } else { 0.0 };
+    let drawdown = drawdown_stats(equity_curve);
+
+    let annualization = config.annualization(equity_curve.iter().map(|(ts, _)| *ts));
+    let periods_per_year = annualization.periods_per_year;
+    let mut vol = 0.0;
+    let mut sharpe = 0.0;
+    if !returns.is_empty() {
+        vol = std_dev(&returns) * periods_per_year.sqrt();
+        if vol > 0.0 {
+            sharpe = (mean(&returns) * periods_per_year.sqrt()) / vol;
+        }
+    }
+
+    let days_span = if let (Some(first), Some(last)) = (equity_curve.first(), equity_curve.last()) {
+        (last.0 - first.0).num_days().max(1)
+    } else {
+        1
+    } as f64;
+    let cagr = if days_span > 0.0 && start > 0.0 {
+        (end / start).powf(365.0 / days_span) - 1.0
+    } else {
+        0.0
+    };
+
+    let tail: Vec<serde_json::Value> = config
+        .confidence_levels
+        .iter()
+        .map(|c| {
+            let risk = tail_risk(&returns, *c);
+            serde_json::json!({
+                "confidence": risk.confidence,
+                "historical_var": risk.historical_var,
+                "historical_cvar": risk.historical_cvar,
+                "parametric_var": risk.parametric_var,
+                "parametric_cvar": risk.parametric_cvar
+            })
+        })
+        .collect();
+
+    if let Some(obj) = base.as_object_mut() {
+        obj.insert("total_return".to_string(), serde_json::json!(total_return));
+        obj.insert(
+            "max_drawdown".to_string(),
+            serde_json::json!(drawdown.max_drawdown),
+        );
+        obj.insert(
+            "max_drawdown_duration_secs".to_string(),
+            serde_json::json!(drawdown.max_duration_secs),
+        );
+        obj.insert(
+            "time_to_recovery_secs".to_string(),
+            serde_json::json!(drawdown.time_to_recovery_secs),
+        );
+        obj.insert("annualized_vol".to_string(), serde_json::json!(vol));
+        obj.insert("sharpe".to_string(), serde_json::json!(sharpe));
+        obj.insert(
+            "sortino".to_string(),
+            serde_json::json!(sortino(&returns, periods_per_year)),
+        );
+        obj.insert("cagr".to_string(), serde_json::json!(cagr));
+        obj.insert(
+            "calmar".to_string(),
+            serde_json::json!(calmar(cagr, drawdown.max_drawdown)),
+        );
+        obj.insert("tail_risk".to_string(), serde_json::json!(tail));
+        obj.insert("annualization".to_string(), annualization.to_json());
+    }
+    base
+}
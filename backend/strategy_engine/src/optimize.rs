@@ -0,0 +1,514 @@
+//! Parameter search over a single price series: grid or seeded random
+//! sampling, ranked by an objective metric, with optional walk-forward
+//! evaluation to expose overfitting.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use domain::Strategy;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::{self, MetricsConfig};
+use crate::{
+    CancelToken, FieldError, PricePoint, StrategyError, StrategyKind, StrategyResult,
+    StrategyService,
+};
+
+/// Upper bound on candidates per search, so one request cannot pin a worker.
+pub const MAX_CANDIDATES: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    #[default]
+    Sharpe,
+    Sortino,
+    Calmar,
+    TotalReturn,
+    Cagr,
+}
+
+impl Objective {
+    fn key(&self) -> &'static str {
+        match self {
+            Objective::Sharpe => "sharpe",
+            Objective::Sortino => "sortino",
+            Objective::Calmar => "calmar",
+            Objective::TotalReturn => "total_return",
+            Objective::Cagr => "cagr",
+        }
+    }
+
+    /// Reads the objective from backtest metrics; `None` when undefined (e.g.
+    /// Calmar without a drawdown).
+    pub fn score(&self, metrics: &serde_json::Value) -> Option<f64> {
+        metrics
+            .get(self.key())
+            .and_then(|v| v.as_f64())
+            .filter(|v| v.is_finite())
+    }
+}
+
+/// Candidate values for one param: an explicit list, or an inclusive
+/// `min..=max` range walked in `step` increments (default 1).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ParamRange {
+    Values(Vec<serde_json::Value>),
+    Range {
+        min: f64,
+        max: f64,
+        #[serde(default)]
+        step: Option<f64>,
+    },
+}
+
+impl ParamRange {
+    fn candidates(&self) -> Vec<serde_json::Value> {
+        match self {
+            ParamRange::Values(values) => values.clone(),
+            ParamRange::Range { min, max, step } => {
+                let step = step.filter(|s| *s > 0.0).unwrap_or(1.0);
+                let integral = min.fract() == 0.0 && step.fract() == 0.0;
+                let mut out = Vec::new();
+                let mut i = 0usize;
+                loop {
+                    let value = min + step * i as f64;
+                    if value > max + 1e-9 || out.len() > MAX_CANDIDATES {
+                        break;
+                    }
+                    out.push(if integral {
+                        serde_json::json!(value as i64)
+                    } else {
+                        serde_json::json!(value)
+                    });
+                    i += 1;
+                }
+                out
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Search {
+    /// Every combination of the candidate values.
+    #[default]
+    Grid,
+    /// `samples` combinations drawn uniformly with a fixed seed.
+    Random {
+        samples: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+/// Rolling windows of `train_bars` in-sample bars followed by `test_bars`
+/// out-of-sample bars, advanced by `test_bars` each fold.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WalkForward {
+    pub train_bars: usize,
+    pub test_bars: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OptimizationRequest {
+    /// Param name to candidate values; merged over the strategy's own params.
+    pub space: BTreeMap<String, ParamRange>,
+    #[serde(default)]
+    pub search: Search,
+    #[serde(default)]
+    pub objective: Objective,
+    /// How many ranked trials to return (default 20).
+    #[serde(default)]
+    pub top: Option<usize>,
+    #[serde(default)]
+    pub walk_forward: Option<WalkForward>,
+}
+
+impl OptimizationRequest {
+    /// Checks the search space and settings against `kind`'s params without
+    /// running anything; walk-forward windows are only checked against the
+    /// prices at run time.
+    pub fn validate(&self, kind: StrategyKind) -> StrategyResult<()> {
+        candidates(kind, self).map(|_| ())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Trial {
+    /// The candidate overrides, not the full merged params.
+    pub params: serde_json::Value,
+    pub score: Option<f64>,
+    pub metrics: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fold {
+    pub train_start: DateTime<Utc>,
+    pub test_start: DateTime<Utc>,
+    pub test_end: DateTime<Utc>,
+    pub best_params: serde_json::Value,
+    pub in_sample_score: Option<f64>,
+    pub out_of_sample_score: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WalkForwardReport {
+    pub folds: Vec<Fold>,
+    pub mean_in_sample: Option<f64>,
+    pub mean_out_of_sample: Option<f64>,
+    /// Out-of-sample over in-sample mean score; well below 1 hints at overfitting.
+    pub efficiency: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OptimizationResult {
+    pub strategy_id: uuid::Uuid,
+    pub objective: Objective,
+    pub evaluated: usize,
+    /// Candidates rejected by the kind's param rules (e.g. short >= long).
+    pub skipped: usize,
+    pub best: Option<Trial>,
+    pub trials: Vec<Trial>,
+    pub walk_forward: Option<WalkForwardReport>,
+}
+
+/// Runs the search through `service` on `prices` and ranks trials by the
+/// objective, best first. Trials without a defined score rank last. `cancel`
+/// is checked before every trial, and by the engine between bars.
+pub async fn optimize<S>(
+    service: &S,
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    request: &OptimizationRequest,
+    cancel: &CancelToken,
+) -> StrategyResult<OptimizationResult>
+where
+    S: StrategyService + ?Sized,
+{
+    let kind: StrategyKind = strategy.r#type.parse()?;
+    let candidates = candidates(kind, request)?;
+
+    let (mut trials, skipped) =
+        evaluate(service, strategy, prices, &candidates, request, cancel).await?;
+    trials.truncate(request.top.unwrap_or(20).max(1));
+
+    let walk_forward = match &request.walk_forward {
+        Some(wf) => {
+            Some(walk_forward(service, strategy, prices, &candidates, request, wf, cancel).await?)
+        }
+        None => None,
+    };
+
+    Ok(OptimizationResult {
+        strategy_id: strategy.id,
+        objective: request.objective,
+        evaluated: candidates.len() - skipped,
+        skipped,
+        best: trials.first().cloned(),
+        trials,
+        walk_forward,
+    })
+}
+
+/// Expands the search space into candidate param overrides.
+fn candidates(
+    kind: StrategyKind,
+    request: &OptimizationRequest,
+) -> StrategyResult<Vec<serde_json::Map<String, serde_json::Value>>> {
+    let schema = kind.schema();
+    let mut errors = Vec::new();
+    let mut dims: Vec<(String, Vec<serde_json::Value>)> = Vec::new();
+    for (name, range) in &request.space {
+        if schema["properties"].get(name).is_none() {
+            errors.push(FieldError::new(format!("space.{name}"), "unknown field"));
+            continue;
+        }
+        let values = range.candidates();
+        if values.is_empty() {
+            errors.push(FieldError::new(format!("space.{name}"), "has no values"));
+        }
+        dims.push((name.clone(), values));
+    }
+    if request.space.is_empty() {
+        errors.push(FieldError::new("space", "must list at least one param"));
+    }
+    if !errors.is_empty() {
+        return Err(StrategyError::InvalidParams(errors));
+    }
+
+    match &request.search {
+        Search::Grid => {
+            let total = dims
+                .iter()
+                .try_fold(1usize, |acc, (_, v)| acc.checked_mul(v.len()))
+                .unwrap_or(usize::MAX);
+            if total > MAX_CANDIDATES {
+                return Err(StrategyError::InvalidParams(vec![FieldError::new(
+                    "space",
+                    format!("grid has {total} combinations; max {MAX_CANDIDATES}"),
+                )]));
+            }
+            let mut out = vec![serde_json::Map::new()];
+            for (name, values) in &dims {
+                out = out
+                    .into_iter()
+                    .flat_map(|base| {
+                        values.iter().map(move |value| {
+                            let mut next = base.clone();
+                            next.insert(name.clone(), value.clone());
+                            next
+                        })
+                    })
+                    .collect();
+            }
+            Ok(out)
+        }
+        Search::Random { samples, seed } => {
+            if *samples == 0 || *samples > MAX_CANDIDATES {
+                return Err(StrategyError::InvalidParams(vec![FieldError::new(
+                    "search.samples",
+                    format!("must be between 1 and {MAX_CANDIDATES}"),
+                )]));
+            }
+            let mut rng = StdRng::seed_from_u64(*seed);
+            Ok((0..*samples)
+                .map(|_| {
+                    dims.iter()
+                        .map(|(name, values)| {
+                            (name.clone(), values[rng.gen_range(0..values.len())].clone())
+                        })
+                        .collect()
+                })
+                .collect())
+        }
+    }
+}
+
+fn merged(strategy: &Strategy, overrides: &serde_json::Map<String, serde_json::Value>) -> Strategy {
+    let mut candidate = strategy.clone();
+    if !candidate.params.is_object() {
+        candidate.params = serde_json::json!({});
+    }
+    if let Some(params) = candidate.params.as_object_mut() {
+        for (key, value) in overrides {
+            params.insert(key.clone(), value.clone());
+        }
+    }
+    candidate
+}
+
+/// Backtests every candidate and returns the ranked trials plus the number of
+/// candidates the kind's rules rejected.
+async fn evaluate<S>(
+    service: &S,
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    candidates: &[serde_json::Map<String, serde_json::Value>],
+    request: &OptimizationRequest,
+    cancel: &CancelToken,
+) -> StrategyResult<(Vec<Trial>, usize)>
+where
+    S: StrategyService + ?Sized,
+{
+    let mut trials = Vec::with_capacity(candidates.len());
+    let mut skipped = 0;
+    for overrides in candidates {
+        cancel.check()?;
+        match service
+            .backtest_cancellable(&merged(strategy, overrides), prices, cancel)
+            .await
+        {
+            Ok(result) => trials.push(Trial {
+                params: serde_json::Value::Object(overrides.clone()),
+                score: request.objective.score(&result.metrics),
+                metrics: result.metrics,
+            }),
+            Err(StrategyError::InvalidParams(_)) => skipped += 1,
+            Err(err) => return Err(err),
+        }
+    }
+    trials.sort_by(|a, b| match (a.score, b.score) {
+        (Some(x), Some(y)) => y.total_cmp(&x),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    Ok((trials, skipped))
+}
+
+/// Fits on each in-sample window, then scores the winner on the following
+/// out-of-sample window. The out-of-sample run starts at the in-sample start
+/// so indicators are warm, and only the out-of-sample slice is scored.
+async fn walk_forward<S>(
+    service: &S,
+    strategy: &Strategy,
+    prices: &[PricePoint],
+    candidates: &[serde_json::Map<String, serde_json::Value>],
+    request: &OptimizationRequest,
+    wf: &WalkForward,
+    cancel: &CancelToken,
+) -> StrategyResult<WalkForwardReport>
+where
+    S: StrategyService + ?Sized,
+{
+    if wf.train_bars < 2 || wf.test_bars < 2 || wf.train_bars + wf.test_bars > prices.len() {
+        return Err(StrategyError::InvalidParams(vec![FieldError::new(
+            "walk_forward",
+            format!(
+                "train_bars and test_bars must be at least 2 and fit in {} bars",
+                prices.len()
+            ),
+        )]));
+    }
+
+    let mut folds = Vec::new();
+    let mut start = 0;
+    while start + wf.train_bars + wf.test_bars <= prices.len() {
+        let split = start + wf.train_bars;
+        let end = split + wf.test_bars;
+        let (trials, _) = evaluate(
+            service,
+            strategy,
+            &prices[start..split],
+            candidates,
+            request,
+            cancel,
+        )
+        .await?;
+        let Some(best) = trials.into_iter().next() else {
+            break;
+        };
+        let overrides = best.params.as_object().cloned().unwrap_or_default();
+        let candidate = merged(strategy, &overrides);
+        let config = MetricsConfig::from_params(&candidate.params);
+        let full = service
+            .backtest_cancellable(&candidate, &prices[start..end], cancel)
+            .await?;
+        let out_of_sample = rebased(&full.equity_curve[wf.train_bars - 1..]);
+        let oos_metrics = metrics::build_metrics(&out_of_sample, serde_json::json!({}), &config);
+        folds.push(Fold {
+            train_start: prices[start].timestamp,
+            test_start: prices[split].timestamp,
+            test_end: prices[end - 1].timestamp,
+            best_params: best.params,
+            in_sample_score: best.score,
+            out_of_sample_score: request.objective.score(&oos_metrics),
+        });
+        start += wf.test_bars;
+    }
+
+    let mean_of = |scores: Vec<f64>| {
+        if scores.is_empty() {
+            None
+        } else {
+            Some(metrics::mean(&scores))
+        }
+    };
+    let mean_in_sample = mean_of(folds.iter().filter_map(|f| f.in_sample_score).collect());
+    let mean_out_of_sample = mean_of(folds.iter().filter_map(|f| f.out_of_sample_score).collect());
+    let efficiency = match (mean_in_sample, mean_out_of_sample) {
+        (Some(is), Some(oos)) if is.abs() > 1e-12 => Some(oos / is),
+        _ => None,
+    };
+    Ok(WalkForwardReport {
+        folds,
+        mean_in_sample,
+        mean_out_of_sample,
+        efficiency,
+    })
+}
+
+fn rebased(curve: &metrics::Curve) -> Vec<(DateTime<Utc>, f64)> {
+    let base = curve.first().map(|(_, v)| *v).filter(|v| *v > 0.0);
+    curve
+        .iter()
+        .map(|(ts, v)| (*ts, base.map(|b| v / b).unwrap_or(*v)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{series, strategy};
+    use crate::{InMemoryStrategyService, StrategyError};
+
+    #[tokio::test]
+    async fn optimizer_ranks_grid_and_walks_forward() {
+        let prices: Vec<f64> = (0..120)
+            .map(|i| 100.0 + 0.3 * i as f64 + 8.0 * (i as f64 / 6.0).sin())
+            .collect();
+        let points = series(&prices);
+        let base = strategy("ma_cross", serde_json::json!({}));
+        let request: OptimizationRequest = serde_json::from_value(serde_json::json!({
+            "space": {
+                "short_window": [2, 5, 30],
+                "long_window": { "min": 10, "max": 20, "step": 5 }
+            },
+            "objective": "total_return",
+            "walk_forward": { "train_bars": 60, "test_bars": 20 }
+        }))
+        .unwrap();
+        let result = optimize(
+            &InMemoryStrategyService,
+            &base,
+            &points,
+            &request,
+            &CancelToken::new(),
+        )
+        .await
+        .unwrap();
+        // short_window = 30 is never below long_window, so those 3 are skipped.
+        assert_eq!(result.skipped, 3);
+        assert_eq!(result.evaluated, 6);
+        let scores: Vec<f64> = result.trials.iter().filter_map(|t| t.score).collect();
+        assert!(scores.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(result.best.as_ref().unwrap().score, Some(scores[0]));
+        let wf = result.walk_forward.unwrap();
+        assert_eq!(wf.folds.len(), 3);
+        assert!(wf.folds.iter().all(|f| f.out_of_sample_score.is_some()));
+
+        let random: OptimizationRequest = serde_json::from_value(serde_json::json!({
+            "space": { "short_window": { "min": 2, "max": 8 } },
+            "search": { "mode": "random", "samples": 4, "seed": 7 }
+        }))
+        .unwrap();
+        let first = optimize(
+            &InMemoryStrategyService,
+            &base,
+            &points,
+            &random,
+            &CancelToken::new(),
+        )
+        .await
+        .unwrap();
+        let second = optimize(
+            &InMemoryStrategyService,
+            &base,
+            &points,
+            &random,
+            &CancelToken::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.trials, second.trials);
+
+        let unknown: OptimizationRequest =
+            serde_json::from_value(serde_json::json!({ "space": { "windw": [1, 2] } })).unwrap();
+        assert!(matches!(
+            optimize(
+                &InMemoryStrategyService,
+                &base,
+                &points,
+                &unknown,
+                &CancelToken::new()
+            )
+            .await,
+            Err(StrategyError::InvalidParams(_))
+        ));
+    }
+}
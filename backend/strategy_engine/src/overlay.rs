@@ -0,0 +1,241 @@
+//! Risk exits layered on top of any position-based strategy: the raw signal
+//! decides when to enter, the overlay may force an earlier exit.
+
+use std::collections::BTreeMap;
+
+use domain::ExitReason;
+
+use crate::PricePoint;
+
+/// Exit rules read from strategy params. Levels are fractions of the entry
+/// price (`0.05` = 5%) and are checked on bar closes, so a fill happens at the
+/// close of the bar that crossed the level, not at the level itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RiskOverlay {
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+    /// Exit once the price gives back this fraction from its best close since entry.
+    pub trailing_stop: Option<f64>,
+    pub max_holding_bars: Option<usize>,
+}
+
+/// Positions after the overlay, plus the reason for each forced exit by bar.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overlaid {
+    pub positions: Vec<f64>,
+    pub exits: Vec<Option<ExitReason>>,
+}
+
+impl Overlaid {
+    /// Forced exits counted by reason, for the backtest metrics.
+    pub fn exit_counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for reason in self.exits.iter().flatten() {
+            *counts.entry(reason.as_str()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+struct Holding {
+    side: f64,
+    entry_index: usize,
+    entry_price: f64,
+    best_price: f64,
+}
+
+impl RiskOverlay {
+    /// Reads `stop_loss`, `take_profit`, `trailing_stop` and `max_holding_bars`;
+    /// missing or non-positive values disable the rule.
+    pub fn from_params(params: &serde_json::Value) -> Self {
+        let level = |key: &str| {
+            params
+                .get(key)
+                .and_then(|v| v.as_f64())
+                .filter(|v| *v > 0.0)
+        };
+        Self {
+            stop_loss: level("stop_loss"),
+            take_profit: level("take_profit"),
+            trailing_stop: level("trailing_stop"),
+            max_holding_bars: params
+                .get("max_holding_bars")
+                .and_then(|v| v.as_u64())
+                .filter(|v| *v > 0)
+                .map(|v| v as usize),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.stop_loss.is_some()
+            || self.take_profit.is_some()
+            || self.trailing_stop.is_some()
+            || self.max_holding_bars.is_some()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "stop_loss": self.stop_loss,
+            "take_profit": self.take_profit,
+            "trailing_stop": self.trailing_stop,
+            "max_holding_bars": self.max_holding_bars
+        })
+    }
+
+    /// Applies the exits to `raw` positions (one per bar, decided at the close).
+    /// After a forced exit the position stays flat until the raw signal leaves
+    /// that side, so a stop is not immediately re-entered by the same signal.
+    pub fn apply(&self, prices: &[PricePoint], raw: &[f64]) -> Overlaid {
+        let mut positions = Vec::with_capacity(raw.len());
+        let mut exits = vec![None; raw.len()];
+        let mut holding: Option<Holding> = None;
+        let mut blocked_side: Option<f64> = None;
+        for (i, &target) in raw.iter().enumerate() {
+            let price = prices.get(i).map(|p| p.price).unwrap_or(0.0);
+            let side = side_of(target);
+            if blocked_side.is_some_and(|blocked| blocked != side) {
+                blocked_side = None;
+            }
+            if blocked_side.is_some() {
+                positions.push(0.0);
+                continue;
+            }
+
+            holding = match holding.take() {
+                Some(h) if h.side == side => Some(h),
+                _ if side != 0.0 && price > 0.0 => Some(Holding {
+                    side,
+                    entry_index: i,
+                    entry_price: price,
+                    best_price: price,
+                }),
+                _ => None,
+            };
+            let Some(h) = holding.as_mut() else {
+                positions.push(if side == 0.0 { target } else { 0.0 });
+                continue;
+            };
+            if price > 0.0 && (price - h.best_price) * h.side > 0.0 {
+                h.best_price = price;
+            }
+            match self.exit_reason(h, i, price) {
+                Some(reason) => {
+                    exits[i] = Some(reason);
+                    blocked_side = Some(h.side);
+                    holding = None;
+                    positions.push(0.0);
+                }
+                None => positions.push(target),
+            }
+        }
+        Overlaid { positions, exits }
+    }
+
+    fn exit_reason(&self, holding: &Holding, index: usize, price: f64) -> Option<ExitReason> {
+        if index == holding.entry_index || price <= 0.0 {
+            return None;
+        }
+        let gain = (price / holding.entry_price - 1.0) * holding.side;
+        let giveback = (1.0 - price / holding.best_price) * holding.side;
+        if self.stop_loss.is_some_and(|level| gain <= -level) {
+            Some(ExitReason::StopLoss)
+        } else if self.take_profit.is_some_and(|level| gain >= level) {
+            Some(ExitReason::TakeProfit)
+        } else if self.trailing_stop.is_some_and(|level| giveback >= level) {
+            Some(ExitReason::TrailingStop)
+        } else if self
+            .max_holding_bars
+            .is_some_and(|bars| index - holding.entry_index >= bars)
+        {
+            Some(ExitReason::MaxHolding)
+        } else {
+            None
+        }
+    }
+}
+
+fn side_of(position: f64) -> f64 {
+    if position > f64::EPSILON {
+        1.0
+    } else if position < -f64::EPSILON {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{series, strategy};
+    use crate::{InMemoryStrategyService, StrategyKind, StrategyService};
+
+    #[test]
+    fn risk_overlay_forces_exits() {
+        let prices = series(&[100.0, 95.0, 89.0, 92.0, 100.0, 104.0, 111.0, 112.0]);
+        let always_long = [1.0; 8];
+
+        let stop = RiskOverlay {
+            stop_loss: Some(0.1),
+            ..RiskOverlay::default()
+        };
+        let overlaid = stop.apply(&prices, &always_long);
+        // Stopped out at 89; the still-long signal must not re-enter.
+        assert_eq!(
+            overlaid.positions,
+            vec![1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(overlaid.exits[2], Some(domain::ExitReason::StopLoss));
+
+        // A fresh signal after going flat re-enters.
+        let signal = [1.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+        let overlaid = stop.apply(&prices, &signal);
+        assert_eq!(overlaid.positions[4..], [1.0, 1.0, 1.0, 1.0]);
+
+        let take = RiskOverlay {
+            take_profit: Some(0.1),
+            ..RiskOverlay::default()
+        };
+        let overlaid = take.apply(&prices, &signal);
+        assert_eq!(overlaid.exits[6], Some(domain::ExitReason::TakeProfit));
+
+        let trailing = RiskOverlay {
+            trailing_stop: Some(0.08),
+            max_holding_bars: Some(3),
+            ..RiskOverlay::default()
+        };
+        let overlaid = trailing.apply(&prices, &signal);
+        assert_eq!(overlaid.exits[2], Some(domain::ExitReason::TrailingStop));
+        assert_eq!(overlaid.exits[7], Some(domain::ExitReason::MaxHolding));
+
+        let short = trailing.apply(&prices, &[-1.0; 8]);
+        assert_eq!(short.exits[3], Some(domain::ExitReason::MaxHolding));
+        assert_eq!(short.exit_counts()["max_holding"], 1);
+    }
+
+    #[tokio::test]
+    async fn risk_exits_are_recorded_on_trades() {
+        let params = serde_json::json!({
+            "short_window": 2,
+            "long_window": 5,
+            "trailing_stop": 0.05
+        });
+        // Still long on the MA signal at 125, but 10% off the 140 peak.
+        let prices = series(&[100.0, 110.0, 120.0, 130.0, 140.0, 125.0, 124.0]);
+        let result = InMemoryStrategyService
+            .backtest(&strategy("ma_cross", params), &prices)
+            .await
+            .unwrap();
+        let reasons: Vec<_> = result.trades.iter().map(|t| t.exit_reason).collect();
+        assert_eq!(reasons, vec![Some(domain::ExitReason::TrailingStop)]);
+        assert_eq!(result.trades[0].exit_price, 125.0);
+        assert_eq!(result.metrics["risk_exits"]["trailing_stop"], 1);
+        assert_eq!(result.metrics["risk_overlay"]["trailing_stop"], 0.05);
+
+        let errors = StrategyKind::MaCross
+            .validate(&serde_json::json!({ "stop_loss": 1.5, "max_holding_bars": 0 }))
+            .unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["stop_loss", "max_holding_bars"]);
+    }
+}
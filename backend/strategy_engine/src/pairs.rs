@@ -0,0 +1,474 @@
+//! Two-asset pairs trading: a rolling OLS hedge ratio between log prices, a
+//! z-scored spread that drives mean-reversion trades, and Engle-Granger
+//! cointegration statistics for the whole sample.
+
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use domain::{BacktestResult, BacktestTrade, ExitReason, Strategy, TradeSide, WeightsPoint};
+
+use crate::execution::{self, CostModel, ExecutionStats};
+use crate::kinds::PairsParams;
+use crate::metrics::{self, MetricsConfig};
+use crate::portfolio::AlignedPrices;
+use crate::rolling::RollingCovariance;
+use crate::{CancelToken, StrategyResult};
+
+/// Asymptotic Engle-Granger critical values for two variables with a constant
+/// (MacKinnon), keyed by significance level.
+pub const ENGLE_GRANGER_CRITICAL: [(&str, f64); 3] = [("1%", -3.90), ("5%", -3.34), ("10%", -3.04)];
+
+/// `y = alpha + beta * x`, fitted by least squares.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HedgeFit {
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl HedgeFit {
+    pub fn residual(&self, x: f64, y: f64) -> f64 {
+        y - self.alpha - self.beta * x
+    }
+}
+
+/// `None` when `x` has no variance.
+pub fn ols(x: &[f64], y: &[f64]) -> Option<HedgeFit> {
+    if x.len() != y.len() || x.len() < 2 {
+        return None;
+    }
+    let mean_x = metrics::mean(x);
+    let mean_y = metrics::mean(y);
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (a, b) in x.iter().zip(y) {
+        cov += (a - mean_x) * (b - mean_y);
+        var += (a - mean_x).powi(2);
+    }
+    if var <= 0.0 {
+        return None;
+    }
+    let beta = cov / var;
+    Some(HedgeFit {
+        alpha: mean_y - beta * mean_x,
+        beta,
+    })
+}
+
+/// Engle-Granger two-step test on log prices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cointegration {
+    pub fit: HedgeFit,
+    /// Dickey-Fuller t-statistic of the residuals (no lagged differences).
+    pub adf_stat: f64,
+    /// Bars for a deviation from the long-run spread to halve; `None` when
+    /// the residuals do not mean-revert.
+    pub half_life_bars: Option<f64>,
+}
+
+impl Cointegration {
+    /// Whether the test rejects "no cointegration" at the 5% level.
+    pub fn is_cointegrated(&self) -> bool {
+        self.adf_stat < ENGLE_GRANGER_CRITICAL[1].1
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "hedge_ratio": self.fit.beta,
+            "intercept": self.fit.alpha,
+            "adf_stat": self.adf_stat,
+            "critical_values": ENGLE_GRANGER_CRITICAL
+                .iter()
+                .map(|(level, value)| (level.to_string(), *value))
+                .collect::<BTreeMap<_, _>>(),
+            "cointegrated": self.is_cointegrated(),
+            "half_life_bars": self.half_life_bars
+        })
+    }
+}
+
+pub fn engle_granger(x: &[f64], y: &[f64]) -> Option<Cointegration> {
+    let fit = ols(x, y)?;
+    let residuals: Vec<f64> = x.iter().zip(y).map(|(a, b)| fit.residual(*a, *b)).collect();
+    // Regress the residual changes on the lagged residuals: de = c + gamma * e.
+    let lagged = &residuals[..residuals.len() - 1];
+    let diffs: Vec<f64> = residuals.windows(2).map(|w| w[1] - w[0]).collect();
+    if lagged.len() < 3 {
+        return None;
+    }
+    let df = ols(lagged, &diffs)?;
+    let ssr: f64 = lagged
+        .iter()
+        .zip(&diffs)
+        .map(|(e, d)| df.residual(*e, *d).powi(2))
+        .sum();
+    let mean_lagged = metrics::mean(lagged);
+    let spread: f64 = lagged.iter().map(|e| (e - mean_lagged).powi(2)).sum();
+    let se = (ssr / (lagged.len() - 2) as f64 / spread).sqrt();
+    let adf_stat = if se > 0.0 {
+        df.beta / se
+    } else {
+        f64::NEG_INFINITY
+    };
+    let half_life_bars =
+        (df.beta < 0.0 && df.beta > -1.0).then(|| -std::f64::consts::LN_2 / (1.0 + df.beta).ln());
+    Some(Cointegration {
+        fit,
+        adf_stat,
+        half_life_bars,
+    })
+}
+
+/// Hedge ratio and spread z-score at each bar, fitted on the trailing
+/// `lookback` bars (inclusive); `None` during warm-up or for a flat window.
+/// In-window OLS residuals have zero mean and variance `var(y) - cov² / var(x)`,
+/// so both come straight from the rolling moments.
+pub fn rolling_zscores(x: &[f64], y: &[f64], lookback: usize) -> Vec<Option<(HedgeFit, f64)>> {
+    let mut window = RollingCovariance::new(lookback);
+    x.iter()
+        .zip(y)
+        .map(|(&xi, &yi)| {
+            window.push(xi, yi);
+            if lookback < 2 || !window.is_full() {
+                return None;
+            }
+            let (var_x, var_y) = window.variances();
+            if var_x <= 0.0 {
+                return None;
+            }
+            let cov = window.covariance();
+            let (mean_x, mean_y) = window.means();
+            let beta = cov / var_x;
+            let fit = HedgeFit {
+                alpha: mean_y - beta * mean_x,
+                beta,
+            };
+            let residual_var = var_y - cov * cov / var_x;
+            // Below this the fit is exact up to rounding and z is meaningless.
+            (residual_var > var_y * 1e-12)
+                .then(|| (fit, fit.residual(xi, yi) / residual_var.sqrt()))
+        })
+        .collect()
+}
+
+struct OpenSpread {
+    direction: f64,
+    entry_index: usize,
+    entry_equity: f64,
+}
+
+/// Trades the spread of `symbols[0]` against `symbols[1]` at bar closes: long
+/// the spread (long y, short beta x) below `-entry_z`, short above `entry_z`,
+/// flat again once |z| is back within `exit_z` or beyond `stop_z`. Legs are
+/// sized to a gross exposure of 1 with the hedge ratio fixed at entry and held
+/// at constant weights; costs are charged per leg on entry and exit.
+pub fn backtest_pairs(
+    strategy: &Strategy,
+    prices: &AlignedPrices,
+    params: &PairsParams,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let symbols: Vec<String> = params
+        .symbols
+        .iter()
+        .map(|s| s.trim().to_uppercase())
+        .collect();
+    let column = |symbol: &String| {
+        prices
+            .symbols
+            .iter()
+            .position(|s| s == symbol)
+            .map(|j| &prices.columns[j])
+    };
+    let (Some(y_col), Some(x_col)) = (column(&symbols[0]), column(&symbols[1])) else {
+        return Ok(BacktestResult {
+            id: None,
+            strategy_id: strategy.id,
+            equity_curve: Vec::new(),
+            metrics: serde_json::json!({ "type": "pairs" }),
+            trades: Vec::new(),
+            benchmark_curve: Vec::new(),
+            weights_history: Vec::new(),
+            strategy_version: None,
+            overrides: serde_json::Map::new(),
+            robustness: None,
+            manifest: None,
+            completed_at: Some(Utc::now()),
+        });
+    };
+    let log_y: Vec<f64> = y_col.iter().map(|p| p.price.ln()).collect();
+    let log_x: Vec<f64> = x_col.iter().map(|p| p.price.ln()).collect();
+    let zscores = rolling_zscores(&log_x, &log_y, params.lookback);
+    let costs = CostModel::from_params(&strategy.params);
+
+    let mut equity = 1.0;
+    let mut weights = [0.0, 0.0];
+    let mut open: Option<OpenSpread> = None;
+    let mut blocked = false;
+    let mut stats = ExecutionStats::default();
+    let mut trades = Vec::new();
+    let mut equity_curve = Vec::with_capacity(prices.len());
+    let mut weights_history = Vec::with_capacity(prices.len());
+    for i in 0..prices.len() {
+        cancel.check()?;
+        if i > 0 {
+            let ry = y_col[i].price / y_col[i - 1].price - 1.0;
+            let rx = x_col[i].price / x_col[i - 1].price - 1.0;
+            equity *= 1.0 + weights[0] * ry + weights[1] * rx;
+        }
+
+        let mut target = weights;
+        let mut exit_reason = None;
+        if let Some((fit, z)) = zscores[i] {
+            if blocked && z.abs() < params.entry_z {
+                blocked = false;
+            }
+            match &open {
+                Some(trade) => {
+                    if params.stop_z.is_some_and(|stop| z.abs() >= stop) {
+                        exit_reason = Some(ExitReason::StopLoss);
+                        blocked = true;
+                    } else if z * trade.direction >= -params.exit_z {
+                        exit_reason = Some(ExitReason::Signal);
+                    }
+                    if exit_reason.is_some() {
+                        target = [0.0, 0.0];
+                    }
+                }
+                None if !blocked && z.abs() >= params.entry_z => {
+                    let direction = -z.signum();
+                    let gross = 1.0 + fit.beta.abs();
+                    target = [direction / gross, -direction * fit.beta / gross];
+                }
+                None => {}
+            }
+        }
+
+        let mut cost = 0.0;
+        let mut traded = false;
+        for (leg, col) in [y_col, x_col].into_iter().enumerate() {
+            let delta = (target[leg] - weights[leg]).abs();
+            if delta <= f64::EPSILON {
+                continue;
+            }
+            let bps = costs.fee_bps + costs.slippage_bps(col, i);
+            cost += equity * delta * bps / 10_000.0 + costs.gas_cost / costs.initial_capital;
+            stats.trade_count += 1;
+            stats.turnover += delta;
+            traded = true;
+        }
+        if traded {
+            stats.total_costs += cost;
+            let before = equity;
+            equity = (equity - cost).max(0.0);
+            match open.take() {
+                Some(trade) => trades.push(close_spread(
+                    trade,
+                    prices,
+                    y_col,
+                    x_col,
+                    i,
+                    equity,
+                    exit_reason,
+                )),
+                None => {
+                    open = Some(OpenSpread {
+                        direction: target[0].signum(),
+                        entry_index: i,
+                        entry_equity: before,
+                    })
+                }
+            }
+            weights = target;
+        }
+
+        equity_curve.push((prices.timestamps[i], equity));
+        weights_history.push(WeightsPoint {
+            timestamp: prices.timestamps[i],
+            weights: symbols.iter().cloned().zip(weights).collect(),
+            rebalanced: traded,
+        });
+    }
+    if let Some(trade) = open.take() {
+        let last = prices.len() - 1;
+        trades.push(close_spread(
+            trade, prices, y_col, x_col, last, equity, None,
+        ));
+    }
+
+    let returns = |col: &[crate::PricePoint]| -> Vec<f64> {
+        col.windows(2)
+            .map(|w| w[1].price / w[0].price - 1.0)
+            .collect()
+    };
+    let (ry, rx) = (returns(y_col), returns(x_col));
+    let mut window = RollingCovariance::new(params.lookback);
+    let rolling: Vec<f64> = ry
+        .iter()
+        .zip(&rx)
+        .filter_map(|(a, b)| {
+            window.push(*a, *b);
+            window.is_full().then(|| window.correlation())
+        })
+        .collect();
+    let last_fit = zscores.iter().rev().find_map(|z| *z);
+    let mut metrics = metrics::build_metrics(
+        &equity_curve,
+        serde_json::json!({
+            "type": "pairs",
+            "symbols": symbols,
+            "lookback": params.lookback,
+            "entry_z": params.entry_z,
+            "exit_z": params.exit_z,
+            "stop_z": params.stop_z,
+            "hedge_ratio": last_fit.map(|(fit, _)| fit.beta),
+            "last_zscore": last_fit.map(|(_, z)| z),
+            "correlation": metrics::correlation(&ry, &rx),
+            "rolling_correlation": {
+                "last": rolling.last(),
+                "mean": (!rolling.is_empty()).then(|| metrics::mean(&rolling)),
+                "min": rolling.iter().copied().reduce(f64::min),
+                "max": rolling.iter().copied().reduce(f64::max)
+            },
+            "cointegration": engle_granger(&log_x, &log_y).map(|c| c.to_json())
+        }),
+        &MetricsConfig::from_params(&strategy.params),
+    );
+    stats.insert_into(&mut metrics, &costs);
+    execution::insert_trade_stats(&mut metrics, &trades);
+
+    Ok(BacktestResult {
+        id: None,
+        strategy_id: strategy.id,
+        equity_curve,
+        metrics,
+        trades,
+        benchmark_curve: Vec::new(),
+        weights_history,
+        strategy_version: None,
+        overrides: serde_json::Map::new(),
+        robustness: None,
+        manifest: None,
+        completed_at: Some(Utc::now()),
+    })
+}
+
+/// Records a spread round trip; prices are the `y / x` price ratio.
+fn close_spread(
+    trade: OpenSpread,
+    prices: &AlignedPrices,
+    y_col: &[crate::PricePoint],
+    x_col: &[crate::PricePoint],
+    exit_index: usize,
+    exit_equity: f64,
+    exit_reason: Option<ExitReason>,
+) -> BacktestTrade {
+    let ratio = |i: usize| y_col[i].price / x_col[i].price;
+    let entry = trade.entry_index;
+    let pnl = exit_equity - trade.entry_equity;
+    BacktestTrade {
+        side: if trade.direction > 0.0 {
+            TradeSide::Long
+        } else {
+            TradeSide::Short
+        },
+        size: 1.0,
+        entry_time: prices.timestamps[entry],
+        entry_price: ratio(entry),
+        exit_time: prices.timestamps[exit_index],
+        exit_price: ratio(exit_index),
+        pnl,
+        return_pct: if trade.entry_equity > 0.0 {
+            pnl / trade.entry_equity
+        } else {
+            0.0
+        },
+        holding_secs: (prices.timestamps[exit_index] - prices.timestamps[entry]).num_seconds(),
+        closed: exit_reason.is_some(),
+        exit_reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{series_from, strategy};
+    use crate::{InMemoryStrategyService, PriceSeries, StrategyKind, StrategyService};
+
+    #[tokio::test]
+    async fn pairs_trade_the_spread_of_a_cointegrated_pair() {
+        let start = Utc::now() - chrono::Duration::days(200);
+        // ln y = 1 + 1.5 ln x + a stationary wobble around the long-run spread.
+        let log_x: Vec<f64> = (0..200)
+            .map(|i| 100f64.ln() + 0.002 * i as f64 + 0.1 * (i as f64 / 15.0).sin())
+            .collect();
+        let x: Vec<f64> = log_x.iter().map(|v| v.exp()).collect();
+        let y: Vec<f64> = log_x
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (1.0 + 1.5 * v + 0.03 * (i as f64 * 0.9).sin()).exp())
+            .collect();
+        let coint =
+            engle_granger(&log_x, &y.iter().map(|v| v.ln()).collect::<Vec<_>>()).expect("fit");
+        assert!((coint.fit.beta - 1.5).abs() < 0.05);
+        assert!(coint.is_cointegrated(), "adf {}", coint.adf_stat);
+
+        let mut input = PriceSeries::new();
+        input.insert("ETH".to_string(), series_from(start, &y));
+        input.insert("WBTC".to_string(), series_from(start, &x));
+        let params = serde_json::json!({
+            "symbols": ["eth", "wbtc"],
+            "lookback": 20,
+            "entry_z": 1.0,
+            "exit_z": 0.2,
+            "fee_bps": 5
+        });
+        let result = InMemoryStrategyService
+            .backtest_multi(&strategy("pairs", params.clone()), &input)
+            .await
+            .expect("pairs backtest");
+        assert!(!result.trades.is_empty());
+        assert!(result
+            .trades
+            .iter()
+            .filter(|t| t.closed)
+            .all(|t| t.exit_reason == Some(domain::ExitReason::Signal)));
+        let held = result
+            .weights_history
+            .iter()
+            .find(|p| p.weights["ETH"] != 0.0)
+            .expect("a position");
+        // Legs offset each other and sum to a gross exposure of 1.
+        assert!(held.weights["ETH"] * held.weights["WBTC"] < 0.0);
+        let gross: f64 = held.weights.values().map(|w| w.abs()).sum();
+        assert!((gross - 1.0).abs() < 1e-9);
+        assert!(result.metrics["total_return"].as_f64().unwrap() > 0.0);
+        assert_eq!(result.metrics["cointegration"]["cointegrated"], true);
+        assert!(result.metrics["rolling_correlation"]["mean"].is_number());
+        assert!(result.metrics["hedge_ratio"].is_number());
+
+        // `correlation` is an alias of pairs, not a flat-curve stub.
+        assert_eq!(
+            "correlation".parse::<StrategyKind>().unwrap(),
+            StrategyKind::Pairs
+        );
+        let correlation = InMemoryStrategyService
+            .backtest_multi(&strategy("correlation", params.clone()), &input)
+            .await
+            .expect("correlation backtest");
+        assert!(!correlation.trades.is_empty());
+        let first = correlation.equity_curve[0].1;
+        assert!(correlation
+            .equity_curve
+            .iter()
+            .any(|(_, equity)| (equity - first).abs() > 1e-9));
+        assert_eq!(
+            correlation.metrics["total_return"],
+            result.metrics["total_return"]
+        );
+
+        let errors = StrategyKind::Pairs
+            .validate(&serde_json::json!({ "symbols": ["ETH"], "exit_z": 3.0 }))
+            .unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["symbols", "exit_z"]);
+    }
+}
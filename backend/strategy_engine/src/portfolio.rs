@@ -0,0 +1,377 @@
+//! Multi-asset allocation backtests: several price series aligned on one
+//! timeline, held at target weights and traded back to them on a periodic or
+//! drift-threshold schedule.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use domain::{BacktestResult, Strategy, WeightsPoint};
+
+use crate::execution::{CostModel, ExecutionStats};
+use crate::kinds::{RebalanceParams, RebalanceTrigger, Weighting};
+use crate::metrics::{self, MetricsConfig};
+use crate::{CancelToken, PricePoint, StrategyResult};
+
+/// Price series keyed by symbol; the input of multi-asset backtests.
+pub type PriceSeries = BTreeMap<String, Vec<PricePoint>>;
+
+/// Label of the default multi-asset benchmark: equal weights, never rebalanced.
+pub const EQUAL_WEIGHT_BUY_AND_HOLD: &str = "equal_weight_buy_and_hold";
+
+/// Series sampled on one shared timeline; `columns[j][i]` is the price of
+/// `symbols[j]` at `timestamps[i]`.
+#[derive(Debug, Clone, Default)]
+pub struct AlignedPrices {
+    pub timestamps: Vec<DateTime<Utc>>,
+    pub symbols: Vec<String>,
+    pub columns: Vec<Vec<PricePoint>>,
+}
+
+impl AlignedPrices {
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// Value of an equal-weight basket bought on the first bar and never
+    /// rebalanced, as a price series starting at 1.0.
+    pub fn equal_weight_index(&self) -> Vec<PricePoint> {
+        let n = self.columns.len() as f64;
+        (0..self.len())
+            .map(|i| {
+                PricePoint::new(
+                    self.timestamps[i],
+                    self.columns
+                        .iter()
+                        .map(|col| col[i].price / col[0].price)
+                        .sum::<f64>()
+                        / n,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Aligns `series` on the timeline of the sparsest one (ties go to the first
+/// symbol), starting once every symbol has a positive price. Each other symbol
+/// contributes its last positive price at or before each timestamp, so series
+/// sampled on slightly different grids still line up. Inputs must be sorted by
+/// time; an empty or all-zero series yields an empty result. Takes owned or
+/// borrowed series.
+pub fn align<P: AsRef<[PricePoint]>>(series: &BTreeMap<String, P>) -> AlignedPrices {
+    let Some((reference, _)) = series
+        .iter()
+        .min_by_key(|(_, points)| points.as_ref().len())
+    else {
+        return AlignedPrices::default();
+    };
+    let mut start: Option<DateTime<Utc>> = None;
+    for points in series.values() {
+        let Some(first) = points.as_ref().iter().find(|p| p.price > 0.0) else {
+            return AlignedPrices::default();
+        };
+        start = Some(start.map_or(first.timestamp, |s| s.max(first.timestamp)));
+    }
+    let start = start.unwrap_or_default();
+    let timestamps: Vec<DateTime<Utc>> = series[reference]
+        .as_ref()
+        .iter()
+        .filter(|p| p.timestamp >= start && p.price > 0.0)
+        .map(|p| p.timestamp)
+        .collect();
+
+    let mut symbols = Vec::with_capacity(series.len());
+    let mut columns = Vec::with_capacity(series.len());
+    for (symbol, points) in series {
+        let points = points.as_ref();
+        let mut cursor = 0;
+        let mut last = 0.0;
+        let column = timestamps
+            .iter()
+            .map(|ts| {
+                while cursor < points.len() && points[cursor].timestamp <= *ts {
+                    if points[cursor].price > 0.0 {
+                        last = points[cursor].price;
+                    }
+                    cursor += 1;
+                }
+                PricePoint::new(*ts, last)
+            })
+            .collect();
+        symbols.push(symbol.clone());
+        columns.push(column);
+    }
+    AlignedPrices {
+        timestamps,
+        symbols,
+        columns,
+    }
+}
+
+/// Weights proportional to the inverse of each asset's return volatility.
+/// `None` when an asset has no variance (the weights would be undefined).
+pub fn inverse_vol_weights(returns: &[&[f64]]) -> Option<Vec<f64>> {
+    let inverse: Vec<f64> = returns
+        .iter()
+        .map(|r| {
+            let vol = metrics::std_dev(r);
+            if vol > 0.0 {
+                Some(1.0 / vol)
+            } else {
+                None
+            }
+        })
+        .collect::<Option<_>>()?;
+    normalize(inverse)
+}
+
+/// Equal-risk-contribution weights for a covariance matrix, found by
+/// multiplicative fixed-point updates starting from inverse-vol weights.
+pub fn risk_parity_weights(covariance: &[Vec<f64>]) -> Option<Vec<f64>> {
+    let n = covariance.len();
+    if n == 0 || covariance.iter().any(|row| row.len() != n) {
+        return None;
+    }
+    let inverse: Vec<f64> = (0..n)
+        .map(|i| {
+            let var = covariance[i][i];
+            if var > 0.0 {
+                Some(1.0 / var.sqrt())
+            } else {
+                None
+            }
+        })
+        .collect::<Option<_>>()?;
+    let mut weights = normalize(inverse)?;
+    for _ in 0..500 {
+        let marginal: Vec<f64> = (0..n)
+            .map(|i| (0..n).map(|j| covariance[i][j] * weights[j]).sum())
+            .collect();
+        let contributions: Vec<f64> = (0..n).map(|i| weights[i] * marginal[i]).collect();
+        let total: f64 = contributions.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let target = total / n as f64;
+        if contributions
+            .iter()
+            .all(|c| (c - target).abs() <= 1e-10 * total)
+        {
+            break;
+        }
+        let updated = weights
+            .iter()
+            .zip(&contributions)
+            .map(|(w, c)| {
+                if *c > 0.0 {
+                    w * (target / c).sqrt()
+                } else {
+                    *w
+                }
+            })
+            .collect();
+        weights = normalize(updated)?;
+    }
+    Some(weights)
+}
+
+/// Sample covariance (population normalization) of equally long return series.
+pub fn covariance_matrix(returns: &[&[f64]]) -> Vec<Vec<f64>> {
+    let means: Vec<f64> = returns.iter().map(|r| metrics::mean(r)).collect();
+    let len = returns.iter().map(|r| r.len()).min().unwrap_or(0);
+    returns
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            returns
+                .iter()
+                .enumerate()
+                .map(|(j, b)| {
+                    if len == 0 {
+                        return 0.0;
+                    }
+                    (0..len)
+                        .map(|k| (a[k] - means[i]) * (b[k] - means[j]))
+                        .sum::<f64>()
+                        / len as f64
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn normalize(values: Vec<f64>) -> Option<Vec<f64>> {
+    let total: f64 = values.iter().sum();
+    if total > 0.0 && total.is_finite() {
+        Some(values.into_iter().map(|v| v / total).collect())
+    } else {
+        None
+    }
+}
+
+/// Target weights at bar `index`. Volatility-based schemes fall back to equal
+/// weights until `vol_lookback` returns are available.
+fn target_weights(
+    params: &RebalanceParams,
+    symbols: &[String],
+    returns: &[Vec<f64>],
+    index: usize,
+) -> Vec<f64> {
+    let n = symbols.len();
+    let equal = vec![1.0 / n as f64; n];
+    let trailing = || -> Option<Vec<&[f64]>> {
+        // returns[j][k] is the return into bar k + 1.
+        if index < params.vol_lookback {
+            return None;
+        }
+        Some(
+            returns
+                .iter()
+                .map(|r| &r[index - params.vol_lookback..index])
+                .collect(),
+        )
+    };
+    match params.weighting {
+        Weighting::Equal => equal,
+        Weighting::Fixed => {
+            let mut weights = vec![0.0; n];
+            for (symbol, weight) in &params.weights {
+                let symbol = symbol.trim().to_uppercase();
+                if let Some(j) = symbols.iter().position(|s| *s == symbol) {
+                    weights[j] += weight.max(0.0);
+                }
+            }
+            normalize(weights).unwrap_or(equal)
+        }
+        Weighting::InverseVol => trailing()
+            .and_then(|window| inverse_vol_weights(&window))
+            .unwrap_or(equal),
+        Weighting::RiskParity => trailing()
+            .and_then(|window| risk_parity_weights(&covariance_matrix(&window)))
+            .unwrap_or(equal),
+    }
+}
+
+/// Simulates the allocation over `prices`. Trades happen at the close of a
+/// bar: the initial allocation on the first bar, then whenever the schedule
+/// fires. Each traded leg pays the strategy's fee and slippage on its notional
+/// plus the fixed gas cost.
+pub fn backtest_rebalance(
+    strategy: &Strategy,
+    prices: &AlignedPrices,
+    params: &RebalanceParams,
+    cancel: &CancelToken,
+) -> StrategyResult<BacktestResult> {
+    let costs = CostModel::from_params(&strategy.params);
+    let n = prices.symbols.len();
+    let returns: Vec<Vec<f64>> = prices
+        .columns
+        .iter()
+        .map(|col| {
+            col.windows(2)
+                .map(|w| w[1].price / w[0].price - 1.0)
+                .collect()
+        })
+        .collect();
+
+    let mut equity = 1.0;
+    let mut held = vec![0.0; n];
+    let mut stats = ExecutionStats::default();
+    let mut rebalance_count = 0;
+    let mut equity_curve = Vec::with_capacity(prices.len());
+    let mut weights_history = Vec::with_capacity(prices.len());
+    for i in 0..prices.len() {
+        cancel.check()?;
+        if i > 0 {
+            let grown: Vec<f64> = held
+                .iter()
+                .zip(&returns)
+                .map(|(w, r)| w * (1.0 + r[i - 1]))
+                .collect();
+            let growth: f64 = grown.iter().sum::<f64>() + (1.0 - held.iter().sum::<f64>());
+            equity *= growth;
+            if growth > 0.0 {
+                held = grown.into_iter().map(|w| w / growth).collect();
+            }
+        }
+
+        let target = target_weights(params, &prices.symbols, &returns, i);
+        let due = i == 0
+            || match params.rebalance {
+                RebalanceTrigger::Periodic => i % params.rebalance_every == 0,
+                RebalanceTrigger::Threshold => held
+                    .iter()
+                    .zip(&target)
+                    .any(|(w, t)| (w - t).abs() > params.drift_threshold),
+            };
+        let mut rebalanced = false;
+        if due && equity > 0.0 {
+            let mut cost = 0.0;
+            for j in 0..n {
+                let delta = (target[j] - held[j]).abs();
+                if delta <= f64::EPSILON {
+                    continue;
+                }
+                let bps = costs.fee_bps + costs.slippage_bps(&prices.columns[j], i);
+                cost += equity * delta * bps / 10_000.0 + costs.gas_cost / costs.initial_capital;
+                stats.trade_count += 1;
+                stats.turnover += delta;
+                rebalanced = true;
+            }
+            if rebalanced {
+                rebalance_count += 1;
+                stats.total_costs += cost;
+                equity = (equity - cost).max(0.0);
+                held = target;
+            }
+        }
+
+        equity_curve.push((prices.timestamps[i], equity));
+        weights_history.push(WeightsPoint {
+            timestamp: prices.timestamps[i],
+            weights: prices
+                .symbols
+                .iter()
+                .cloned()
+                .zip(held.iter().copied())
+                .collect(),
+            rebalanced,
+        });
+    }
+
+    let final_weights = weights_history
+        .last()
+        .map(|point| serde_json::json!(point.weights))
+        .unwrap_or_else(|| serde_json::json!({}));
+    let mut metrics = metrics::build_metrics(
+        &equity_curve,
+        serde_json::json!({
+            "type": "rebalance",
+            "symbols": prices.symbols,
+            "weighting": params.weighting,
+            "rebalance": params.rebalance,
+            "rebalance_count": rebalance_count,
+            "final_weights": final_weights
+        }),
+        &MetricsConfig::from_params(&strategy.params),
+    );
+    stats.insert_into(&mut metrics, &costs);
+
+    Ok(BacktestResult {
+        id: None,
+        strategy_id: strategy.id,
+        equity_curve,
+        metrics,
+        trades: Vec::new(),
+        benchmark_curve: Vec::new(),
+        weights_history,
+        strategy_version: None,
+        overrides: serde_json::Map::new(),
+        robustness: None,
+        manifest: None,
+        completed_at: Some(Utc::now()),
+    })
+}
@@ -0,0 +1,282 @@
+//! Resampling of irregular price series into fixed, epoch-aligned bars.
+//! History mixes hourly fetches, minute oracle ticks and holes; indicators
+//! count bars, so they need every bar to span the same time.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{candles, FieldError, PricePoint, StrategyError, StrategyResult};
+
+/// Upper bound on bars per resampled series, gaps included.
+pub const MAX_BARS: usize = 2_000_000;
+
+/// What to do with a bar that no price falls into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapPolicy {
+    /// Repeat the previous close as a flat, zero-volume bar.
+    #[default]
+    #[serde(alias = "forward_fill")]
+    Ffill,
+    /// Leave the bar out, so the series skips over the gap.
+    Drop,
+    /// Reject the series.
+    Fail,
+}
+
+impl GapPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GapPolicy::Ffill => "ffill",
+            GapPolicy::Drop => "drop",
+            GapPolicy::Fail => "fail",
+        }
+    }
+}
+
+/// Reads an interval such as `1m`, `15m`, `1h`, `4h` or `1d` into seconds.
+pub fn parse_interval(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let unit = value.chars().last()?;
+    let count: i64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    let unit_secs = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3_600,
+        'd' => 86_400,
+        'w' => 604_800,
+        _ => return None,
+    };
+    if count <= 0 {
+        return None;
+    }
+    count.checked_mul(unit_secs)
+}
+
+/// Shortest label for `secs`, the inverse of [`parse_interval`].
+pub fn format_interval(secs: i64) -> String {
+    [(604_800, 'w'), (86_400, 'd'), (3_600, 'h'), (60, 'm')]
+        .into_iter()
+        .find(|(unit, _)| secs % unit == 0)
+        .map(|(unit, suffix)| format!("{}{suffix}", secs / unit))
+        .unwrap_or_else(|| format!("{secs}s"))
+}
+
+/// Counts for one resampled series. `bars` is what the backtest sees,
+/// filled bars included and dropped ones not.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResampleStats {
+    pub source_points: usize,
+    pub bars: usize,
+    pub filled_bars: usize,
+    pub dropped_bars: usize,
+    /// Share of `bars` that were forward-filled.
+    pub filled_ratio: f64,
+}
+
+impl ResampleStats {
+    fn add(&mut self, other: &ResampleStats) {
+        self.source_points += other.source_points;
+        self.bars += other.bars;
+        self.filled_bars += other.filled_bars;
+        self.dropped_bars += other.dropped_bars;
+        self.update_ratio();
+    }
+
+    fn update_ratio(&mut self) {
+        self.filled_ratio = if self.bars > 0 {
+            self.filled_bars as f64 / self.bars as f64
+        } else {
+            0.0
+        };
+    }
+}
+
+/// What a backtest's resampling did, reported under `metrics.resampling`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResampleReport {
+    pub interval: String,
+    pub interval_secs: i64,
+    pub gap_policy: GapPolicy,
+    /// Totals across all series.
+    #[serde(flatten)]
+    pub totals: ResampleStats,
+    /// Per-symbol counts of multi-asset backtests.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub symbols: BTreeMap<String, ResampleStats>,
+}
+
+impl ResampleReport {
+    pub fn new(interval_secs: i64, gap_policy: GapPolicy) -> Self {
+        Self {
+            interval: format_interval(interval_secs),
+            interval_secs,
+            gap_policy,
+            totals: ResampleStats::default(),
+            symbols: BTreeMap::new(),
+        }
+    }
+
+    /// Adds one series' counts; `symbol` is `None` for single-series backtests.
+    pub fn record(&mut self, symbol: Option<&str>, stats: ResampleStats) {
+        self.totals.add(&stats);
+        if let Some(symbol) = symbol {
+            self.symbols.insert(symbol.to_string(), stats);
+        }
+    }
+}
+
+/// Builds one bar per `interval_secs` bucket between the first and last
+/// point, aligned to the Unix epoch. Bars aggregate their points like
+/// [`candles::aggregate`] but are stamped with the bucket's close, so every
+/// bar is exactly one interval after the previous one; empty buckets follow
+/// `policy`. Points must be sorted by time.
+pub fn resample(
+    points: &[PricePoint],
+    interval_secs: i64,
+    policy: GapPolicy,
+) -> StrategyResult<(Vec<PricePoint>, ResampleStats)> {
+    let mut stats = ResampleStats {
+        source_points: points.len(),
+        ..ResampleStats::default()
+    };
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Ok((Vec::new(), stats));
+    };
+    if interval_secs <= 0 {
+        return Err(invalid("bar_interval", "must be positive".to_string()));
+    }
+    let bucket_of = |ts: DateTime<Utc>| ts.timestamp().div_euclid(interval_secs);
+    let span = (bucket_of(last.timestamp) - bucket_of(first.timestamp) + 1).max(1) as usize;
+    if span > MAX_BARS {
+        return Err(invalid(
+            "bar_interval",
+            format!("would produce {span} bars (limit {MAX_BARS}); use a wider interval"),
+        ));
+    }
+    let close_of = |bucket: i64| {
+        DateTime::from_timestamp((bucket + 1) * interval_secs, 0)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    };
+
+    let mut bars: Vec<PricePoint> = Vec::with_capacity(match policy {
+        GapPolicy::Ffill => span,
+        _ => span.min(points.len()),
+    });
+    let mut next_bucket = bucket_of(first.timestamp);
+    let mut first_gap = None;
+    for candle in candles::aggregate(points, Duration::seconds(interval_secs)) {
+        let bucket = bucket_of(candle.timestamp);
+        if bucket > next_bucket {
+            first_gap.get_or_insert(next_bucket);
+            let missing = (bucket - next_bucket) as usize;
+            match (policy, bars.last().cloned()) {
+                (GapPolicy::Ffill, Some(prev)) => {
+                    for gap in next_bucket..bucket {
+                        bars.push(PricePoint {
+                            timestamp: close_of(gap),
+                            price: prev.price,
+                            open: Some(prev.price),
+                            high: Some(prev.price),
+                            low: Some(prev.price),
+                            volume: prev.volume.map(|_| 0.0),
+                        });
+                    }
+                    stats.filled_bars += missing;
+                }
+                _ => stats.dropped_bars += missing,
+            }
+        }
+        bars.push(PricePoint {
+            timestamp: close_of(bucket),
+            ..candle
+        });
+        next_bucket = bucket + 1;
+    }
+
+    if let (GapPolicy::Fail, Some(gap)) = (policy, first_gap) {
+        return Err(invalid(
+            "gap_policy",
+            format!(
+                "{} empty {} bars, the first closing at {}",
+                stats.dropped_bars,
+                format_interval(interval_secs),
+                close_of(gap)
+            ),
+        ));
+    }
+    stats.bars = bars.len();
+    stats.update_ratio();
+    Ok((bars, stats))
+}
+
+fn invalid(field: &str, message: String) -> StrategyError {
+    StrategyError::InvalidParams(vec![FieldError::new(field, message)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resampling_builds_fixed_bars_and_applies_gap_policy() {
+        assert_eq!(parse_interval("1m"), Some(60));
+        assert_eq!(parse_interval("4h"), Some(14_400));
+        assert_eq!(parse_interval("0d"), None);
+        assert_eq!(parse_interval("1x"), None);
+        assert_eq!(format_interval(86_400), "1d");
+        assert_eq!(format_interval(90), "90s");
+
+        // Minute ticks in hour 0, nothing in hours 1-2, one tick in hour 3.
+        let start = chrono::DateTime::from_timestamp(0, 0).unwrap();
+        let mut points: Vec<PricePoint> = (0..3)
+            .map(|i| PricePoint::new(start + chrono::Duration::minutes(i * 20), 100.0 + i as f64))
+            .collect();
+        points.push(PricePoint::new(
+            start + chrono::Duration::minutes(190),
+            110.0,
+        ));
+
+        let (bars, stats) = resample(&points, 3_600, GapPolicy::Ffill).unwrap();
+        let stamps: Vec<i64> = bars.iter().map(|b| b.timestamp.timestamp()).collect();
+        assert_eq!(stamps, vec![3_600, 7_200, 10_800, 14_400]);
+        assert_eq!(bars[0].open, Some(100.0));
+        assert_eq!(bars[0].high, Some(102.0));
+        assert_eq!(bars[0].price, 102.0);
+        assert_eq!(bars[1].price, 102.0);
+        assert_eq!(bars[2].high, Some(102.0));
+        assert_eq!(bars[3].price, 110.0);
+        assert_eq!(stats.source_points, 4);
+        assert_eq!((stats.bars, stats.filled_bars), (4, 2));
+        assert!((stats.filled_ratio - 0.5).abs() < 1e-12);
+
+        let (bars, stats) = resample(&points, 3_600, GapPolicy::Drop).unwrap();
+        assert_eq!(bars.len(), 2);
+        assert_eq!((stats.filled_bars, stats.dropped_bars), (0, 2));
+
+        match resample(&points, 3_600, GapPolicy::Fail) {
+            Err(StrategyError::InvalidParams(fields)) => assert_eq!(fields[0].field, "gap_policy"),
+            other => panic!("expected a gap error, got {other:?}"),
+        }
+        // Without gaps every policy agrees.
+        assert!(resample(&points[..3], 3_600, GapPolicy::Fail).is_ok());
+
+        let mut report = ResampleReport::new(3_600, GapPolicy::Ffill);
+        report.record(
+            Some("ETH"),
+            resample(&points, 3_600, GapPolicy::Ffill).unwrap().1,
+        );
+        report.record(
+            Some("BTC"),
+            resample(&points[..3], 3_600, GapPolicy::Ffill).unwrap().1,
+        );
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["interval"], "1h");
+        assert_eq!(json["gap_policy"], "ffill");
+        assert_eq!(json["bars"], 5);
+        assert_eq!(json["filled_bars"], 2);
+        assert_eq!(json["symbols"]["ETH"]["bars"], 4);
+    }
+}
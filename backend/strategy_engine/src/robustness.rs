@@ -0,0 +1,371 @@
+//! Monte Carlo robustness analysis of a finished backtest: resample its
+//! returns (block bootstrap) or its trade order many times with a fixed seed
+//! and report how wide the spread of outcomes is.
+
+use domain::{BacktestResult, Distribution, EquityBand, RobustnessReport};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::{self, MetricsConfig};
+use crate::{CancelToken, FieldError, StrategyError, StrategyResult};
+
+/// Upper bound on resampled paths per analysis.
+pub const MAX_ITERATIONS: usize = 10_000;
+/// Equity band points kept per report; longer curves are sampled evenly.
+pub const MAX_BAND_POINTS: usize = 250;
+
+const DEFAULT_ITERATIONS: usize = 1_000;
+const DEFAULT_RUIN_THRESHOLD: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    /// Moving-block bootstrap of per-bar returns. Blocks keep short-range
+    /// autocorrelation (volatility clusters) that a plain bootstrap destroys.
+    #[default]
+    BlockBootstrap,
+    /// Random permutations of the trade sequence. Only the path changes, so
+    /// total return and Sharpe stay put while drawdowns and ruin move.
+    TradeShuffle,
+}
+
+impl Method {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Method::BlockBootstrap => "block_bootstrap",
+            Method::TradeShuffle => "trade_shuffle",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+pub struct RobustnessRequest {
+    #[serde(default)]
+    pub method: Method,
+    /// Resampled paths (default 1000).
+    #[serde(default)]
+    pub iterations: Option<usize>,
+    #[serde(default)]
+    pub seed: u64,
+    /// Bars per bootstrap block; defaults to the cube root of the curve length.
+    #[serde(default)]
+    pub block_size: Option<usize>,
+    /// Equity level in (0, 1) that counts as ruin (default 0.5).
+    #[serde(default)]
+    pub ruin_threshold: Option<f64>,
+}
+
+impl RobustnessRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if let Some(n) = self.iterations {
+            if n == 0 || n > MAX_ITERATIONS {
+                errors.push(FieldError::new(
+                    "robustness.iterations",
+                    format!("must be between 1 and {MAX_ITERATIONS}"),
+                ));
+            }
+        }
+        if self.block_size == Some(0) {
+            errors.push(FieldError::new("robustness.block_size", "must be positive"));
+        }
+        if let Some(t) = self.ruin_threshold {
+            if !(t > 0.0 && t < 1.0) {
+                errors.push(FieldError::new(
+                    "robustness.ruin_threshold",
+                    "must be between 0 and 1",
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Resamples `result` as `request` asks. Sharpe is annualized with `config`
+/// over the original timestamps, like the backtest's own metrics. `cancel` is
+/// checked before every resampled path.
+pub fn analyze(
+    result: &BacktestResult,
+    request: &RobustnessRequest,
+    config: &MetricsConfig,
+    cancel: &CancelToken,
+) -> StrategyResult<RobustnessReport> {
+    request.validate().map_err(StrategyError::InvalidParams)?;
+    let iterations = request.iterations.unwrap_or(DEFAULT_ITERATIONS);
+    let ruin_threshold = request.ruin_threshold.unwrap_or(DEFAULT_RUIN_THRESHOLD);
+    let mut rng = StdRng::seed_from_u64(request.seed);
+
+    // Each path is a sequence of per-step growth factors laid on `timestamps`.
+    let (timestamps, factors, block_size) = match request.method {
+        Method::BlockBootstrap => {
+            let factors: Vec<f64> = metrics::simple_returns(&result.equity_curve)
+                .iter()
+                .map(|r| 1.0 + r)
+                .collect();
+            if factors.len() < 2 {
+                return Err(not_enough("the equity curve needs at least 3 bars"));
+            }
+            let block = request
+                .block_size
+                .unwrap_or_else(|| (factors.len() as f64).cbrt().round() as usize)
+                .clamp(1, factors.len());
+            let timestamps: Vec<_> = result.equity_curve.iter().map(|(ts, _)| *ts).collect();
+            (timestamps, factors, Some(block))
+        }
+        Method::TradeShuffle => {
+            let factors = trade_factors(result);
+            if factors.len() < 2 {
+                return Err(not_enough("needs at least 2 trades"));
+            }
+            let mut timestamps = vec![result.equity_curve[0].0];
+            timestamps.extend(result.trades.iter().map(|t| t.exit_time));
+            (timestamps, factors, None)
+        }
+    };
+
+    let periods_per_year = config
+        .annualization(timestamps.iter().copied())
+        .periods_per_year;
+    let band_indices = band_indices(timestamps.len());
+    let mut band_values: Vec<Vec<f64>> = vec![Vec::with_capacity(iterations); band_indices.len()];
+    let mut total_returns = Vec::with_capacity(iterations);
+    let mut drawdowns = Vec::with_capacity(iterations);
+    let mut sharpes = Vec::with_capacity(iterations);
+    let mut ruined = 0usize;
+    let mut path = factors.clone();
+
+    for _ in 0..iterations {
+        cancel.check()?;
+        match block_size {
+            Some(block) => bootstrap_into(&factors, block, &mut path, &mut rng),
+            None => path.shuffle(&mut rng),
+        }
+        let stats = walk(&path, &band_indices, ruin_threshold, &mut band_values);
+        let returns: Vec<f64> = path.iter().map(|f| f - 1.0).collect();
+        let vol = metrics::std_dev(&returns);
+        sharpes.push(if vol > 0.0 {
+            metrics::mean(&returns) / vol * periods_per_year.sqrt()
+        } else {
+            0.0
+        });
+        total_returns.push(stats.equity - 1.0);
+        drawdowns.push(stats.max_drawdown);
+        if stats.ruined {
+            ruined += 1;
+        }
+    }
+
+    let equity_bands = band_indices
+        .iter()
+        .zip(band_values.iter_mut())
+        .map(|(&i, values)| {
+            let d = distribution(values);
+            EquityBand {
+                timestamp: timestamps[i],
+                p5: d.p5,
+                p25: d.p25,
+                p50: d.p50,
+                p75: d.p75,
+                p95: d.p95,
+            }
+        })
+        .collect();
+
+    Ok(RobustnessReport {
+        method: request.method.as_str().to_string(),
+        iterations,
+        seed: request.seed,
+        block_size,
+        ruin_threshold,
+        probability_of_ruin: ruined as f64 / iterations as f64,
+        equity_bands,
+        total_return: distribution(&mut total_returns),
+        max_drawdown: distribution(&mut drawdowns),
+        sharpe: distribution(&mut sharpes),
+    })
+}
+
+fn not_enough(message: &str) -> StrategyError {
+    StrategyError::InvalidParams(vec![FieldError::new("robustness.method", message)])
+}
+
+/// Growth factor of equity over each trade: its pnl relative to the equity
+/// when it was entered.
+fn trade_factors(result: &BacktestResult) -> Vec<f64> {
+    let curve = &result.equity_curve;
+    if curve.is_empty() {
+        return Vec::new();
+    }
+    result
+        .trades
+        .iter()
+        .map(|trade| {
+            let at = curve
+                .partition_point(|(ts, _)| *ts <= trade.entry_time)
+                .max(1);
+            let equity = curve[at - 1].1;
+            if equity > 0.0 {
+                1.0 + trade.pnl / equity
+            } else {
+                1.0
+            }
+        })
+        .collect()
+}
+
+/// Fills `out` with blocks of `block` consecutive values of `source` starting
+/// at uniformly drawn offsets, truncating the last block.
+fn bootstrap_into(source: &[f64], block: usize, out: &mut [f64], rng: &mut StdRng) {
+    let starts = source.len() - block + 1;
+    for chunk in out.chunks_mut(block) {
+        let start = rng.gen_range(0..starts);
+        chunk.copy_from_slice(&source[start..start + chunk.len()]);
+    }
+}
+
+/// Up to [`MAX_BAND_POINTS`] evenly spaced indices into a curve of `len`
+/// points, always including both ends.
+fn band_indices(len: usize) -> Vec<usize> {
+    if len <= MAX_BAND_POINTS {
+        return (0..len).collect();
+    }
+    let step = (len - 1) as f64 / (MAX_BAND_POINTS - 1) as f64;
+    let mut indices: Vec<usize> = (0..MAX_BAND_POINTS)
+        .map(|k| (k as f64 * step).round() as usize)
+        .collect();
+    indices.dedup();
+    indices
+}
+
+struct PathStats {
+    equity: f64,
+    max_drawdown: f64,
+    ruined: bool,
+}
+
+/// Compounds `factors` from 1.0, recording equity at `band_indices` (index 0
+/// is the starting point) into `bands`.
+fn walk(
+    factors: &[f64],
+    band_indices: &[usize],
+    ruin_threshold: f64,
+    bands: &mut [Vec<f64>],
+) -> PathStats {
+    let mut equity = 1.0_f64;
+    let mut peak = 1.0_f64;
+    let mut max_drawdown = 0.0_f64;
+    let mut ruined = false;
+    let mut next_band = 0;
+    for i in 0..=factors.len() {
+        if i > 0 {
+            equity = (equity * factors[i - 1]).max(0.0);
+            peak = peak.max(equity);
+            if peak > 0.0 {
+                max_drawdown = max_drawdown.min(equity / peak - 1.0);
+            }
+            ruined |= equity <= ruin_threshold;
+        }
+        if band_indices.get(next_band) == Some(&i) {
+            bands[next_band].push(equity);
+            next_band += 1;
+        }
+    }
+    PathStats {
+        equity,
+        max_drawdown,
+        ruined,
+    }
+}
+
+/// Mean and linearly interpolated percentiles; sorts `values` in place.
+fn distribution(values: &mut [f64]) -> Distribution {
+    if values.is_empty() {
+        return Distribution::default();
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let at = |q: f64| {
+        let pos = q * (values.len() - 1) as f64;
+        let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+        values[lo] + (values[hi] - values[lo]) * (pos - lo as f64)
+    };
+    Distribution {
+        mean: metrics::mean(values),
+        p5: at(0.05),
+        p25: at(0.25),
+        p50: at(0.5),
+        p75: at(0.75),
+        p95: at(0.95),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{series, strategy};
+    use crate::{InMemoryStrategyService, StrategyService};
+
+    #[tokio::test]
+    async fn robustness_resamples_with_a_fixed_seed() {
+        let prices: Vec<f64> = (0..200)
+            .map(|i| 100.0 + 10.0 * (i as f64 / 6.0).sin() + i as f64 * 0.1)
+            .collect();
+        let result = InMemoryStrategyService
+            .backtest(
+                &strategy(
+                    "ma_cross",
+                    serde_json::json!({ "short_window": 3, "long_window": 8 }),
+                ),
+                &series(&prices),
+            )
+            .await
+            .unwrap();
+        let config = MetricsConfig::default();
+        let request = RobustnessRequest {
+            iterations: Some(300),
+            seed: 42,
+            ..Default::default()
+        };
+        let report = analyze(&result, &request, &config, &CancelToken::new()).unwrap();
+        let again = analyze(&result, &request, &config, &CancelToken::new()).unwrap();
+        assert_eq!(report.sharpe.p50, again.sharpe.p50);
+        assert_eq!(report.method, "block_bootstrap");
+        assert_eq!(report.equity_bands.len(), result.equity_curve.len());
+        let last = report.equity_bands.last().unwrap();
+        assert!(last.p5 <= last.p50 && last.p50 <= last.p95);
+        assert!(report.max_drawdown.p95 <= 0.0);
+        assert!((0.0..=1.0).contains(&report.probability_of_ruin));
+
+        // Reordering trades moves the path, never the end point.
+        let shuffle = RobustnessRequest {
+            method: Method::TradeShuffle,
+            iterations: Some(100),
+            ..Default::default()
+        };
+        let report = analyze(&result, &shuffle, &config, &CancelToken::new()).unwrap();
+        assert_eq!(report.equity_bands.len(), result.trades.len() + 1);
+        assert!((report.total_return.p5 - report.total_return.p95).abs() < 1e-9);
+        assert!(report.max_drawdown.p5 <= report.max_drawdown.p95);
+
+        let invalid = RobustnessRequest {
+            iterations: Some(0),
+            ruin_threshold: Some(1.5),
+            ..Default::default()
+        };
+        let fields: Vec<String> = invalid
+            .validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(
+            fields,
+            vec!["robustness.iterations", "robustness.ruin_threshold"]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Streaming window accumulators. Each push is amortized O(1) regardless of
+//! the window length, so indicators stay linear in the number of bars.
+
+use std::collections::VecDeque;
+
+/// Mean and population variance of the last `period` values, updated with
+/// Welford's add/remove steps. The moments are recomputed from the buffer
+/// every few thousand evictions so rounding error cannot build up over very
+/// long series.
+#[derive(Debug, Clone)]
+pub struct RollingStats {
+    period: usize,
+    window: VecDeque<f64>,
+    mean: f64,
+    m2: f64,
+    evictions: usize,
+}
+
+const RESYNC_EVERY: usize = 4096;
+
+impl RollingStats {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period: period.max(1),
+            window: VecDeque::with_capacity(period.max(1) + 1),
+            mean: 0.0,
+            m2: 0.0,
+            evictions: 0,
+        }
+    }
+
+    pub fn push(&mut self, value: f64) {
+        self.window.push_back(value);
+        let n = self.window.len() as f64;
+        let delta = value - self.mean;
+        self.mean += delta / n;
+        self.m2 += delta * (value - self.mean);
+        if self.window.len() > self.period {
+            let old = self.window.pop_front().unwrap_or_default();
+            let n = self.window.len() as f64;
+            let delta = old - self.mean;
+            self.mean -= delta / n;
+            self.m2 = (self.m2 - delta * (old - self.mean)).max(0.0);
+            self.evictions += 1;
+            if self.evictions.is_multiple_of(RESYNC_EVERY * self.period) {
+                self.resync();
+            }
+        }
+    }
+
+    /// Values currently in the window (at most `period`).
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.window.len() == self.period
+    }
+
+    /// Mean of the values in the window, full or not; `None` when empty.
+    pub fn mean(&self) -> Option<f64> {
+        (!self.window.is_empty()).then_some(self.mean)
+    }
+
+    /// Population variance of the values in the window.
+    pub fn variance(&self) -> Option<f64> {
+        (!self.window.is_empty()).then(|| self.m2 / self.window.len() as f64)
+    }
+
+    pub fn std_dev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    fn resync(&mut self) {
+        let n = self.window.len() as f64;
+        self.mean = self.window.iter().sum::<f64>() / n;
+        self.m2 = self.window.iter().map(|v| (v - self.mean).powi(2)).sum();
+    }
+}
+
+/// Running maximum (or minimum) of the last `period` values, kept in a
+/// monotonic deque of candidates.
+#[derive(Debug, Clone)]
+pub struct RollingExtremum {
+    period: usize,
+    max: bool,
+    index: usize,
+    candidates: VecDeque<(usize, f64)>,
+}
+
+impl RollingExtremum {
+    pub fn max(period: usize) -> Self {
+        Self::new(period, true)
+    }
+
+    pub fn min(period: usize) -> Self {
+        Self::new(period, false)
+    }
+
+    fn new(period: usize, max: bool) -> Self {
+        Self {
+            period: period.max(1),
+            max,
+            index: 0,
+            candidates: VecDeque::new(),
+        }
+    }
+
+    /// Adds a value and returns the extremum of the window it completes, or
+    /// `None` until `period` values have been seen.
+    pub fn push(&mut self, value: f64) -> Option<f64> {
+        let dominated = |kept: f64| {
+            if self.max {
+                kept <= value
+            } else {
+                kept >= value
+            }
+        };
+        while self
+            .candidates
+            .back()
+            .is_some_and(|(_, kept)| dominated(*kept))
+        {
+            self.candidates.pop_back();
+        }
+        self.candidates.push_back((self.index, value));
+        self.index += 1;
+        while self
+            .candidates
+            .front()
+            .is_some_and(|(i, _)| i + self.period < self.index)
+        {
+            self.candidates.pop_front();
+        }
+        (self.index >= self.period).then(|| self.candidates[0].1)
+    }
+}
+
+/// Means, variances and covariance of the last `period` (x, y) pairs, for
+/// rolling regressions and correlations.
+#[derive(Debug, Clone)]
+pub struct RollingCovariance {
+    period: usize,
+    window: VecDeque<(f64, f64)>,
+    mean_x: f64,
+    mean_y: f64,
+    m2_x: f64,
+    m2_y: f64,
+    co_moment: f64,
+    evictions: usize,
+}
+
+impl RollingCovariance {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period: period.max(1),
+            window: VecDeque::with_capacity(period.max(1) + 1),
+            mean_x: 0.0,
+            mean_y: 0.0,
+            m2_x: 0.0,
+            m2_y: 0.0,
+            co_moment: 0.0,
+            evictions: 0,
+        }
+    }
+
+    pub fn push(&mut self, x: f64, y: f64) {
+        self.window.push_back((x, y));
+        self.update(x, y, 1.0);
+        if self.window.len() > self.period {
+            let (old_x, old_y) = self.window.pop_front().unwrap_or_default();
+            self.update(old_x, old_y, -1.0);
+            self.evictions += 1;
+            if self.evictions.is_multiple_of(RESYNC_EVERY * self.period) {
+                self.resync();
+            }
+        }
+    }
+
+    /// Welford step adding (`sign` = 1) or removing (`sign` = -1) a pair;
+    /// `window` already reflects the change.
+    fn update(&mut self, x: f64, y: f64, sign: f64) {
+        let n = self.window.len() as f64;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x += sign * dx / n;
+        self.mean_y += sign * dy / n;
+        self.m2_x = (self.m2_x + sign * dx * (x - self.mean_x)).max(0.0);
+        self.m2_y = (self.m2_y + sign * dy * (y - self.mean_y)).max(0.0);
+        self.co_moment += sign * dx * (y - self.mean_y);
+    }
+
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.window.len() == self.period
+    }
+
+    pub fn means(&self) -> (f64, f64) {
+        (self.mean_x, self.mean_y)
+    }
+
+    /// Population variances of x and y.
+    pub fn variances(&self) -> (f64, f64) {
+        let n = self.window.len().max(1) as f64;
+        (self.m2_x / n, self.m2_y / n)
+    }
+
+    /// Population covariance of x and y.
+    pub fn covariance(&self) -> f64 {
+        self.co_moment / self.window.len().max(1) as f64
+    }
+
+    /// Pearson correlation; 0.0 when either side is constant.
+    pub fn correlation(&self) -> f64 {
+        let denom = (self.m2_x * self.m2_y).sqrt();
+        if denom > 0.0 {
+            self.co_moment / denom
+        } else {
+            0.0
+        }
+    }
+
+    fn resync(&mut self) {
+        let n = self.window.len() as f64;
+        self.mean_x = self.window.iter().map(|(x, _)| x).sum::<f64>() / n;
+        self.mean_y = self.window.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (mut m2_x, mut m2_y, mut co_moment) = (0.0, 0.0, 0.0);
+        for (x, y) in &self.window {
+            let (dx, dy) = (x - self.mean_x, y - self.mean_y);
+            m2_x += dx * dx;
+            m2_y += dy * dy;
+            co_moment += dx * dy;
+        }
+        (self.m2_x, self.m2_y, self.co_moment) = (m2_x, m2_y, co_moment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics;
+
+    #[test]
+    fn rolling_accumulators_match_full_window_recomputation() {
+        let values: Vec<f64> = (0..500)
+            .map(|i| 1_000.0 + 50.0 * (i as f64 * 0.37).sin() + (i % 7) as f64)
+            .collect();
+        let period = 20;
+        let mut stats = RollingStats::new(period);
+        let mut highest = RollingExtremum::max(period);
+        let mut cov = RollingCovariance::new(period);
+        for (i, &v) in values.iter().enumerate() {
+            stats.push(v);
+            let max = highest.push(v);
+            cov.push(i as f64, v);
+            if i + 1 < period {
+                assert_eq!(max, None);
+                continue;
+            }
+            let window = &values[i + 1 - period..=i];
+            let mean = window.iter().sum::<f64>() / period as f64;
+            assert!((stats.mean().unwrap() - mean).abs() < 1e-9);
+            assert!((stats.std_dev().unwrap() - metrics::std_dev(window)).abs() < 1e-9);
+            assert_eq!(max, window.iter().copied().reduce(f64::max));
+            let xs: Vec<f64> = (i + 1 - period..=i).map(|k| k as f64).collect();
+            let expected = metrics::correlation(&xs, window);
+            assert!((cov.correlation() - expected).abs() < 1e-9);
+        }
+    }
+}
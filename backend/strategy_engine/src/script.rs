@@ -0,0 +1,1237 @@
+//! `script` strategies: user-written signals in a small sandboxed language.
+//!
+//! The syntax is Rhai's expression subset: `let`, assignment, `if` / `else`
+//! expressions, `return`, arithmetic, comparisons and `&&` / `||`, with `//`
+//! comments. There are no loops, strings or host functions beyond the
+//! helpers below, so a script can only read the bars it is given.
+//!
+//! The script runs once per bar and its value is the target position for that
+//! bar: a number (clamped to [-1, 1], NaN counts as flat), a bool (long or
+//! flat) or nothing, which keeps the previous position. Variables:
+//! `close`, `open`, `high`, `low`, `volume` of the current bar, `bar` (its
+//! index) and `position` (the previous target). Helpers never look ahead:
+//!
+//! - `price(n)`: close `n` bars ago; `change(n)`: return over the last `n` bars
+//! - `sma(n)`, `ema(n)`, `std(n)`, `rsi(n)`, `atr(n)`, `highest(n)`, `lowest(n)`:
+//!   indicators over the trailing `n` bars, NaN until enough bars exist
+//! - `abs`, `sqrt`, `ln`, `exp`, `min`, `max`, `clamp(x, lo, hi)` and
+//!   `ready(x)` (false while `x` is NaN)
+//!
+//! Each evaluated node costs one operation and each indicator series costs
+//! one per bar when first computed; runs stop after [`Limits::max_operations`]
+//! or once [`Limits::timeout`] of wall time has passed.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::{indicators, PricePoint};
+
+/// Longest accepted source, in bytes.
+pub const MAX_SOURCE_LEN: usize = 16 * 1024;
+/// Upper bound for `max_operations`.
+pub const MAX_OPERATIONS: u64 = 50_000_000;
+/// Upper bound for `timeout_ms`.
+pub const MAX_TIMEOUT_MS: u64 = 10_000;
+/// Longest indicator window a script may ask for.
+pub const MAX_WINDOW: usize = 10_000;
+/// Deepest parenthesis and block nesting the parser accepts.
+const MAX_DEPTH: usize = 64;
+/// Tallest expression tree accepted, so evaluation cannot exhaust the stack
+/// (long operator chains nest without parentheses).
+const MAX_HEIGHT: usize = 256;
+/// Operations between wall-clock checks.
+const CLOCK_EVERY: u64 = 1_024;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ScriptError {
+    #[error("line {line}:{col}: {message}")]
+    At {
+        line: usize,
+        col: usize,
+        message: String,
+    },
+    #[error("script exceeded {0} operations")]
+    OperationLimit(u64),
+    #[error("script ran longer than {0} ms")]
+    Timeout(u128),
+}
+
+/// CPU and wall-time budget for one run over a price series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub max_operations: u64,
+    pub timeout: Duration,
+}
+
+/// Positions a script produced and what it cost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptRun {
+    pub positions: Vec<f64>,
+    pub operations: u64,
+}
+
+/// A parsed script, ready to run on any series.
+#[derive(Debug, Clone)]
+pub struct Script {
+    body: Block,
+}
+
+impl Script {
+    pub fn compile(source: &str) -> Result<Self, ScriptError> {
+        let tokens = lex(source)?;
+        let mut parser = Parser {
+            tokens,
+            at: 0,
+            depth: 0,
+        };
+        let body = parser.block_body(None)?;
+        Ok(Self { body })
+    }
+
+    /// Target position on every bar, evaluated oldest first.
+    pub fn positions(
+        &self,
+        bars: &[PricePoint],
+        limits: &Limits,
+    ) -> Result<ScriptRun, ScriptError> {
+        let mut machine = Machine {
+            bars,
+            closes: indicators::closes(bars),
+            series: HashMap::new(),
+            vars: Vec::new(),
+            bar: 0,
+            position: 0.0,
+            operations: 0,
+            limits: *limits,
+            started: Instant::now(),
+        };
+        let mut positions = Vec::with_capacity(bars.len());
+        for bar in 0..bars.len() {
+            machine.bar = bar;
+            machine.vars.clear();
+            let value = match machine.block(&self.body) {
+                Ok(value) | Err(Exit::Return(value)) => value,
+                Err(Exit::Fail(err)) => return Err(err),
+            };
+            machine.position = match value {
+                Value::Num(x) if x.is_nan() => 0.0,
+                Value::Num(x) => x.clamp(-1.0, 1.0),
+                Value::Bool(long) => {
+                    if long {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                Value::Unit => machine.position,
+            };
+            positions.push(machine.position);
+        }
+        Ok(ScriptRun {
+            positions,
+            operations: machine.operations,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pos {
+    line: usize,
+    col: usize,
+}
+
+impl Pos {
+    fn error(self, message: impl Into<String>) -> ScriptError {
+        ScriptError::At {
+            line: self.line,
+            col: self.col,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Num(f64),
+    Ident(String),
+    Sym(&'static str),
+    End,
+}
+
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tok::Num(n) => write!(f, "{n}"),
+            Tok::Ident(name) => write!(f, "'{name}'"),
+            Tok::Sym(sym) => write!(f, "'{sym}'"),
+            Tok::End => f.write_str("end of script"),
+        }
+    }
+}
+
+/// Two-character symbols first so they win over their prefixes.
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "=", "(", ")", "{",
+    "}", ",", ";",
+];
+
+const KEYWORDS: &[&str] = &["let", "return", "if", "else", "true", "false"];
+
+fn lex(source: &str) -> Result<Vec<(Tok, Pos)>, ScriptError> {
+    let chars: Vec<char> = source.chars().collect();
+    let (mut i, mut line, mut col) = (0, 1, 1);
+    let mut tokens = Vec::new();
+    while i < chars.len() {
+        let c = chars[i];
+        let pos = Pos { line, col };
+        let start = i;
+        if c == '\n' {
+            i += 1;
+            line += 1;
+            col = 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit()))
+        {
+            while i < chars.len() && (chars[i].is_ascii_digit() || matches!(chars[i], '.' | '_')) {
+                i += 1;
+            }
+            if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && matches!(chars[j], '+' | '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+            let value = text
+                .parse::<f64>()
+                .map_err(|_| pos.error(format!("invalid number {text}")))?;
+            tokens.push((Tok::Num(value), pos));
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Tok::Ident(chars[start..i].iter().collect()), pos));
+        } else {
+            let rest: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let Some(sym) = SYMBOLS.iter().find(|sym| rest.starts_with(**sym)) else {
+                return Err(pos.error(format!("unexpected character '{c}'")));
+            };
+            i += sym.len();
+            tokens.push((Tok::Sym(sym), pos));
+        }
+        col += i - start;
+    }
+    tokens.push((Tok::End, Pos { line, col }));
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+struct Block {
+    stmts: Vec<Stmt>,
+    /// Trailing expression without a semicolon: the block's value.
+    value: Option<Box<Expr>>,
+    height: usize,
+}
+
+impl Block {
+    fn new(stmts: Vec<Stmt>, value: Option<Expr>) -> Self {
+        let height = stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Let(_, expr) | Stmt::Assign(_, expr, _) | Stmt::Expr(expr) => Some(expr),
+                Stmt::Return(expr) => expr.as_ref(),
+            })
+            .chain(value.as_ref())
+            .map(|expr| expr.height())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        Self {
+            stmts,
+            value: value.map(Box::new),
+            height,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(String, Expr, Pos),
+    Expr(Expr),
+    Return(Option<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinOp {
+    fn from_sym(sym: &str) -> Option<Self> {
+        Some(match sym {
+            "||" => BinOp::Or,
+            "&&" => BinOp::And,
+            "==" => BinOp::Eq,
+            "!=" => BinOp::Ne,
+            "<" => BinOp::Lt,
+            "<=" => BinOp::Le,
+            ">" => BinOp::Gt,
+            ">=" => BinOp::Ge,
+            "+" => BinOp::Add,
+            "-" => BinOp::Sub,
+            "*" => BinOp::Mul,
+            "/" => BinOp::Div,
+            "%" => BinOp::Rem,
+            _ => return None,
+        })
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            BinOp::Or => "||",
+            BinOp::And => "&&",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+        }
+    }
+}
+
+/// Binary operators from loosest to tightest binding.
+const PRECEDENCE: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["==", "!=", "<", "<=", ">", ">="],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+/// Comparisons do not chain: `a < b < c` is a syntax error.
+const COMPARISON_LEVEL: usize = 2;
+
+/// Expressions; the trailing `usize` of compound nodes is the tree height.
+#[derive(Debug, Clone)]
+enum Expr {
+    Num(f64),
+    Bool(bool),
+    Var(String, Pos),
+    Neg(Box<Expr>, Pos, usize),
+    Not(Box<Expr>, Pos, usize),
+    Binary(BinOp, Box<Expr>, Box<Expr>, Pos, usize),
+    Call(String, Vec<Expr>, Pos, usize),
+    If(Box<Expr>, Block, Option<Block>, Pos, usize),
+}
+
+impl Expr {
+    fn height(&self) -> usize {
+        match self {
+            Expr::Num(_) | Expr::Bool(_) | Expr::Var(..) => 1,
+            Expr::Neg(.., h)
+            | Expr::Not(.., h)
+            | Expr::Binary(.., h)
+            | Expr::Call(.., h)
+            | Expr::If(.., h) => *h,
+        }
+    }
+
+    fn pos(&self) -> Option<Pos> {
+        match self {
+            Expr::Num(_) | Expr::Bool(_) => None,
+            Expr::Var(_, pos)
+            | Expr::Neg(_, pos, _)
+            | Expr::Not(_, pos, _)
+            | Expr::Binary(_, _, _, pos, _)
+            | Expr::Call(_, _, pos, _)
+            | Expr::If(_, _, _, pos, _) => Some(*pos),
+        }
+    }
+}
+
+/// Rejects `expr` if it grew taller than [`MAX_HEIGHT`].
+fn bounded(expr: Expr) -> Result<Expr, ScriptError> {
+    if expr.height() > MAX_HEIGHT {
+        let pos = expr.pos().unwrap_or(Pos { line: 1, col: 1 });
+        return Err(pos.error("expression nested too deeply"));
+    }
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<(Tok, Pos)>,
+    at: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Tok {
+        &self.tokens[self.at].0
+    }
+
+    fn pos(&self) -> Pos {
+        self.tokens[self.at].1
+    }
+
+    fn bump(&mut self) -> (Tok, Pos) {
+        let token = self.tokens[self.at].clone();
+        if self.at + 1 < self.tokens.len() {
+            self.at += 1;
+        }
+        token
+    }
+
+    fn is_sym(&self, sym: &str) -> bool {
+        matches!(self.peek(), Tok::Sym(s) if *s == sym)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Tok::Ident(name) if name == keyword)
+    }
+
+    fn eat(&mut self, sym: &str) -> bool {
+        let found = self.is_sym(sym);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect(&mut self, sym: &str) -> Result<(), ScriptError> {
+        if self.eat(sym) {
+            Ok(())
+        } else {
+            Err(self
+                .pos()
+                .error(format!("expected '{sym}', found {}", self.peek())))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ScriptError> {
+        match self.bump() {
+            (Tok::Ident(name), pos) if KEYWORDS.contains(&name.as_str()) => {
+                Err(pos.error(format!("'{name}' is a keyword")))
+            }
+            (Tok::Ident(name), _) => Ok(name),
+            (tok, pos) => Err(pos.error(format!("expected a name, found {tok}"))),
+        }
+    }
+
+    fn at_block_end(&self, close: Option<&str>) -> bool {
+        match close {
+            Some(sym) => self.is_sym(sym) || *self.peek() == Tok::End,
+            None => *self.peek() == Tok::End,
+        }
+    }
+
+    /// Statements up to `close` (or the end of the script), consuming `close`.
+    fn block_body(&mut self, close: Option<&'static str>) -> Result<Block, ScriptError> {
+        let mut stmts = Vec::new();
+        let mut value = None;
+        while !self.at_block_end(close) {
+            if self.eat(";") {
+                continue;
+            }
+            match self.statement()? {
+                Stmt::Expr(expr) => {
+                    if self.eat(";") {
+                        stmts.push(Stmt::Expr(expr));
+                    } else if self.at_block_end(close) {
+                        value = Some(expr);
+                    } else if matches!(expr, Expr::If(..)) {
+                        stmts.push(Stmt::Expr(expr));
+                    } else {
+                        return Err(self
+                            .pos()
+                            .error(format!("expected ';', found {}", self.peek())));
+                    }
+                }
+                stmt => {
+                    if !self.at_block_end(close) {
+                        self.expect(";")?;
+                    }
+                    stmts.push(stmt);
+                }
+            }
+        }
+        if let Some(sym) = close {
+            self.expect(sym)?;
+        }
+        Ok(Block::new(stmts, value))
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ScriptError> {
+        if self.is_keyword("let") {
+            self.bump();
+            let name = self.ident()?;
+            self.expect("=")?;
+            return Ok(Stmt::Let(name, self.expr()?));
+        }
+        if self.is_keyword("return") {
+            self.bump();
+            if self.is_sym(";") || self.is_sym("}") || *self.peek() == Tok::End {
+                return Ok(Stmt::Return(None));
+            }
+            return Ok(Stmt::Return(Some(self.expr()?)));
+        }
+        if self.is_keyword("if") {
+            // A statement-level `if` ends at its closing brace, so a following
+            // `-x` starts a new statement instead of subtracting.
+            return Ok(Stmt::Expr(self.if_expr()?));
+        }
+        if let Tok::Ident(name) = self.peek() {
+            let assigns = matches!(self.tokens.get(self.at + 1), Some((Tok::Sym("="), _)));
+            if assigns && !KEYWORDS.contains(&name.as_str()) {
+                let name = name.clone();
+                let pos = self.pos();
+                self.bump();
+                self.bump();
+                return Ok(Stmt::Assign(name, self.expr()?, pos));
+            }
+        }
+        Ok(Stmt::Expr(self.expr()?))
+    }
+
+    fn expr(&mut self) -> Result<Expr, ScriptError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.pos().error("expression nested too deeply"));
+        }
+        let expr = self.binary(0);
+        self.depth -= 1;
+        expr
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, ScriptError> {
+        let Some(ops) = PRECEDENCE.get(level) else {
+            return self.unary();
+        };
+        let mut lhs = self.binary(level + 1)?;
+        while let Tok::Sym(sym) = self.peek() {
+            if !ops.contains(sym) {
+                break;
+            }
+            let op = BinOp::from_sym(sym).expect("precedence table only lists operators");
+            let (_, pos) = self.bump();
+            let rhs = self.binary(level + 1)?;
+            let height = lhs.height().max(rhs.height()) + 1;
+            lhs = bounded(Expr::Binary(op, Box::new(lhs), Box::new(rhs), pos, height))?;
+            if level == COMPARISON_LEVEL {
+                break;
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScriptError> {
+        let mut ops = Vec::new();
+        while self.is_sym("-") || self.is_sym("!") {
+            ops.push(self.bump());
+        }
+        let mut expr = self.primary()?;
+        for (op, pos) in ops.into_iter().rev() {
+            let height = expr.height() + 1;
+            expr = bounded(match op {
+                Tok::Sym("-") => Expr::Neg(Box::new(expr), pos, height),
+                _ => Expr::Not(Box::new(expr), pos, height),
+            })?;
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ScriptError> {
+        let pos = self.pos();
+        match self.peek().clone() {
+            Tok::Num(value) => {
+                self.bump();
+                Ok(Expr::Num(value))
+            }
+            Tok::Sym("(") => {
+                self.bump();
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Tok::Ident(name) => match name.as_str() {
+                "true" | "false" => {
+                    self.bump();
+                    Ok(Expr::Bool(name == "true"))
+                }
+                "if" => self.if_expr(),
+                _ => {
+                    let name = self.ident()?;
+                    if !self.eat("(") {
+                        return Ok(Expr::Var(name, pos));
+                    }
+                    let mut args = Vec::new();
+                    while !self.is_sym(")") {
+                        args.push(self.expr()?);
+                        if !self.eat(",") {
+                            break;
+                        }
+                    }
+                    self.expect(")")?;
+                    let height = args.iter().map(Expr::height).max().unwrap_or(0) + 1;
+                    bounded(Expr::Call(name, args, pos, height))
+                }
+            },
+            tok => Err(pos.error(format!("expected an expression, found {tok}"))),
+        }
+    }
+
+    /// A braced block; statement-level `if`s nest without going through
+    /// [`Parser::expr`], so blocks count towards [`MAX_DEPTH`] themselves.
+    fn block(&mut self) -> Result<Block, ScriptError> {
+        self.expect("{")?;
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.pos().error("block nested too deeply"));
+        }
+        let block = self.block_body(Some("}"));
+        self.depth -= 1;
+        block
+    }
+
+    fn if_expr(&mut self) -> Result<Expr, ScriptError> {
+        let (_, pos) = self.bump();
+        let cond = self.expr()?;
+        let then = self.block()?;
+        let otherwise = if self.is_keyword("else") {
+            self.bump();
+            if self.is_keyword("if") {
+                Some(Block::new(Vec::new(), Some(self.expr()?)))
+            } else {
+                Some(self.block()?)
+            }
+        } else {
+            None
+        };
+        let height = cond
+            .height()
+            .max(then.height)
+            .max(otherwise.as_ref().map_or(0, |b| b.height))
+            + 1;
+        bounded(Expr::If(Box::new(cond), then, otherwise, pos, height))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Num(f64),
+    Bool(bool),
+    Unit,
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Num(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Unit => "nothing",
+        }
+    }
+}
+
+/// Why evaluation left a block early.
+enum Exit {
+    Return(Value),
+    Fail(ScriptError),
+}
+
+impl From<ScriptError> for Exit {
+    fn from(err: ScriptError) -> Self {
+        Exit::Fail(err)
+    }
+}
+
+/// Indicator series a script can ask for, cached per window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Series {
+    Sma,
+    Ema,
+    Std,
+    Rsi,
+    Atr,
+    Highest,
+    Lowest,
+}
+
+impl Series {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "sma" => Series::Sma,
+            "ema" => Series::Ema,
+            "std" => Series::Std,
+            "rsi" => Series::Rsi,
+            "atr" => Series::Atr,
+            "highest" => Series::Highest,
+            "lowest" => Series::Lowest,
+            _ => return None,
+        })
+    }
+
+    fn compute(self, bars: &[PricePoint], closes: &[f64], window: usize) -> Vec<f64> {
+        let nan = |values: Vec<Option<f64>>| -> Vec<f64> {
+            values.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect()
+        };
+        match self {
+            Series::Sma => nan(indicators::sma(closes, window)),
+            Series::Ema => indicators::ema(closes, window)
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| if i + 1 < window { f64::NAN } else { v })
+                .collect(),
+            Series::Std => nan(indicators::rolling_std(closes, window)),
+            Series::Rsi => nan(crate::rsi(closes, window)),
+            Series::Atr => nan(indicators::atr(bars, window)),
+            Series::Highest | Series::Lowest => indicators::donchian(bars, window)
+                .into_iter()
+                .map(|band| match (band, self) {
+                    (Some(band), Series::Highest) => band.upper,
+                    (Some(band), _) => band.lower,
+                    (None, _) => f64::NAN,
+                })
+                .collect(),
+        }
+    }
+}
+
+struct Machine<'a> {
+    bars: &'a [PricePoint],
+    closes: Vec<f64>,
+    series: HashMap<(Series, usize), Vec<f64>>,
+    vars: Vec<(String, Value)>,
+    bar: usize,
+    position: f64,
+    operations: u64,
+    limits: Limits,
+    started: Instant,
+}
+
+impl Machine<'_> {
+    fn tick(&mut self, cost: u64) -> Result<(), ScriptError> {
+        let before = self.operations;
+        self.operations = self.operations.saturating_add(cost);
+        if self.operations > self.limits.max_operations {
+            return Err(ScriptError::OperationLimit(self.limits.max_operations));
+        }
+        if before / CLOCK_EVERY != self.operations / CLOCK_EVERY
+            && self.started.elapsed() > self.limits.timeout
+        {
+            return Err(ScriptError::Timeout(self.limits.timeout.as_millis()));
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<Value, Exit> {
+        let scope = self.vars.len();
+        let value = self.block_inner(block);
+        self.vars.truncate(scope);
+        value
+    }
+
+    fn block_inner(&mut self, block: &Block) -> Result<Value, Exit> {
+        for stmt in &block.stmts {
+            self.exec(stmt)?;
+        }
+        match &block.value {
+            Some(expr) => self.eval(expr),
+            None => Ok(Value::Unit),
+        }
+    }
+
+    fn exec(&mut self, stmt: &Stmt) -> Result<(), Exit> {
+        match stmt {
+            Stmt::Let(name, expr) => {
+                let value = self.eval(expr)?;
+                self.vars.push((name.clone(), value));
+            }
+            Stmt::Assign(name, expr, pos) => {
+                let value = self.eval(expr)?;
+                let Some(slot) = self.vars.iter_mut().rev().find(|(n, _)| n == name) else {
+                    return Err(pos.error(format!("unknown variable '{name}'")).into());
+                };
+                slot.1 = value;
+            }
+            Stmt::Expr(expr) => {
+                self.eval(expr)?;
+            }
+            Stmt::Return(expr) => {
+                let value = match expr {
+                    Some(expr) => self.eval(expr)?,
+                    None => Value::Unit,
+                };
+                return Err(Exit::Return(value));
+            }
+        }
+        Ok(())
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value, Exit> {
+        self.tick(1)?;
+        Ok(match expr {
+            Expr::Num(value) => Value::Num(*value),
+            Expr::Bool(value) => Value::Bool(*value),
+            Expr::Var(name, pos) => self.var(name, *pos)?,
+            Expr::Neg(inner, pos, _) => Value::Num(-self.num(inner, *pos)?),
+            Expr::Not(inner, pos, _) => Value::Bool(!self.bool(inner, *pos)?),
+            Expr::Binary(op, lhs, rhs, pos, _) => self.binary(*op, lhs, rhs, *pos)?,
+            Expr::Call(name, args, pos, _) => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.num(arg, *pos)?);
+                }
+                self.call(name, &values, *pos)?
+            }
+            Expr::If(cond, then, otherwise, pos, _) => {
+                if self.bool(cond, *pos)? {
+                    self.block(then)?
+                } else if let Some(otherwise) = otherwise {
+                    self.block(otherwise)?
+                } else {
+                    Value::Unit
+                }
+            }
+        })
+    }
+
+    fn num(&mut self, expr: &Expr, pos: Pos) -> Result<f64, Exit> {
+        match self.eval(expr)? {
+            Value::Num(value) => Ok(value),
+            other => Err(pos
+                .error(format!("expected a number, found {}", other.type_name()))
+                .into()),
+        }
+    }
+
+    fn bool(&mut self, expr: &Expr, pos: Pos) -> Result<bool, Exit> {
+        match self.eval(expr)? {
+            Value::Bool(value) => Ok(value),
+            other => Err(pos
+                .error(format!("expected a bool, found {}", other.type_name()))
+                .into()),
+        }
+    }
+
+    fn var(&self, name: &str, pos: Pos) -> Result<Value, ScriptError> {
+        if let Some((_, value)) = self.vars.iter().rev().find(|(n, _)| n == name) {
+            return Ok(*value);
+        }
+        let bar = &self.bars[self.bar];
+        Ok(Value::Num(match name {
+            "close" => bar.price,
+            "open" => bar.open(),
+            "high" => bar.high(),
+            "low" => bar.low(),
+            "volume" => bar.volume.unwrap_or(f64::NAN),
+            "bar" => self.bar as f64,
+            "position" => self.position,
+            _ => return Err(pos.error(format!("unknown variable '{name}'"))),
+        }))
+    }
+
+    fn binary(&mut self, op: BinOp, lhs: &Expr, rhs: &Expr, pos: Pos) -> Result<Value, Exit> {
+        match op {
+            BinOp::Or => return Ok(Value::Bool(self.bool(lhs, pos)? || self.bool(rhs, pos)?)),
+            BinOp::And => return Ok(Value::Bool(self.bool(lhs, pos)? && self.bool(rhs, pos)?)),
+            _ => {}
+        }
+        let (a, b) = (self.eval(lhs)?, self.eval(rhs)?);
+        if let (Value::Bool(a), Value::Bool(b), BinOp::Eq | BinOp::Ne) = (a, b, op) {
+            return Ok(Value::Bool((a == b) == (op == BinOp::Eq)));
+        }
+        let (Value::Num(a), Value::Num(b)) = (a, b) else {
+            return Err(pos
+                .error(format!(
+                    "cannot apply '{}' to {} and {}",
+                    op.as_str(),
+                    a.type_name(),
+                    b.type_name()
+                ))
+                .into());
+        };
+        Ok(match op {
+            BinOp::Eq => Value::Bool(a == b),
+            BinOp::Ne => Value::Bool(a != b),
+            BinOp::Lt => Value::Bool(a < b),
+            BinOp::Le => Value::Bool(a <= b),
+            BinOp::Gt => Value::Bool(a > b),
+            BinOp::Ge => Value::Bool(a >= b),
+            BinOp::Add => Value::Num(a + b),
+            BinOp::Sub => Value::Num(a - b),
+            BinOp::Mul => Value::Num(a * b),
+            BinOp::Div => Value::Num(a / b),
+            BinOp::Rem => Value::Num(a % b),
+            BinOp::Or | BinOp::And => unreachable!("handled above"),
+        })
+    }
+
+    fn call(&mut self, name: &str, args: &[f64], pos: Pos) -> Result<Value, ScriptError> {
+        let arity = |n: usize| {
+            if args.len() == n {
+                Ok(())
+            } else {
+                Err(pos.error(format!("{name} takes {n} argument(s), got {}", args.len())))
+            }
+        };
+        if let Some(series) = Series::from_name(name) {
+            arity(1)?;
+            let window = window(args[0], 1, pos)?;
+            if !self.series.contains_key(&(series, window)) {
+                self.tick(self.closes.len() as u64)?;
+                let values = series.compute(self.bars, &self.closes, window);
+                self.series.insert((series, window), values);
+            }
+            return Ok(Value::Num(self.series[&(series, window)][self.bar]));
+        }
+        let back = |n: usize| self.bar.checked_sub(n).map_or(f64::NAN, |i| self.closes[i]);
+        Ok(match name {
+            "price" => {
+                arity(1)?;
+                Value::Num(back(window(args[0], 0, pos)?))
+            }
+            "change" => {
+                arity(1)?;
+                Value::Num(self.closes[self.bar] / back(window(args[0], 1, pos)?) - 1.0)
+            }
+            "abs" | "sqrt" | "ln" | "exp" => {
+                arity(1)?;
+                Value::Num(match name {
+                    "abs" => args[0].abs(),
+                    "sqrt" => args[0].sqrt(),
+                    "ln" => args[0].ln(),
+                    _ => args[0].exp(),
+                })
+            }
+            "min" | "max" => {
+                arity(2)?;
+                Value::Num(if name == "min" {
+                    args[0].min(args[1])
+                } else {
+                    args[0].max(args[1])
+                })
+            }
+            "clamp" => {
+                arity(3)?;
+                if args[1].is_nan() || args[2].is_nan() || args[1] > args[2] {
+                    return Err(pos.error("clamp needs lo <= hi"));
+                }
+                Value::Num(args[0].clamp(args[1], args[2]))
+            }
+            "ready" => {
+                arity(1)?;
+                Value::Bool(!args[0].is_nan())
+            }
+            _ => return Err(pos.error(format!("unknown function '{name}'"))),
+        })
+    }
+}
+
+/// A bar count argument: a whole number between `min` and [`MAX_WINDOW`].
+fn window(value: f64, min: usize, pos: Pos) -> Result<usize, ScriptError> {
+    if value.fract() == 0.0 && value >= min as f64 && value <= MAX_WINDOW as f64 {
+        Ok(value as usize)
+    } else {
+        Err(pos.error(format!(
+            "bar count must be a whole number from {min} to {MAX_WINDOW}, got {value}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kinds::ScriptParams;
+    use crate::tests::strategy;
+    use crate::{InMemoryStrategyService, StrategyKind, StrategyService};
+    use chrono::{Duration as ChronoDuration, Utc};
+
+    const LIMITS: Limits = Limits {
+        max_operations: MAX_OPERATIONS,
+        timeout: Duration::from_millis(MAX_TIMEOUT_MS),
+    };
+
+    fn bars(closes: &[f64]) -> Vec<PricePoint> {
+        let start = Utc::now() - ChronoDuration::days(closes.len() as i64);
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| PricePoint::new(start + ChronoDuration::days(i as i64), *p))
+            .collect()
+    }
+
+    fn positions(source: &str, closes: &[f64]) -> Vec<f64> {
+        Script::compile(source)
+            .unwrap()
+            .positions(&bars(closes), &LIMITS)
+            .unwrap()
+            .positions
+    }
+
+    fn compile_error(source: &str) -> String {
+        Script::compile(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn lexer_reads_number_forms_and_rejects_stray_characters() {
+        let tokens: Vec<Tok> = lex("1_000 1e-3 .5 2E+2 x1")
+            .unwrap()
+            .into_iter()
+            .map(|(tok, _)| tok)
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                Tok::Num(1000.0),
+                Tok::Num(0.001),
+                Tok::Num(0.5),
+                Tok::Num(200.0),
+                Tok::Ident("x1".to_string()),
+                Tok::End,
+            ]
+        );
+        // An exponent without digits is not part of the number.
+        let tokens = lex("2e").unwrap();
+        assert_eq!(tokens[0].0, Tok::Num(2.0));
+        assert_eq!(tokens[1].0, Tok::Ident("e".to_string()));
+
+        assert_eq!(
+            compile_error("let x = 1;\nclose # 2"),
+            "line 2:7: unexpected character '#'"
+        );
+        assert_eq!(compile_error("1.2.3"), "line 1:1: invalid number 1.2.3");
+    }
+
+    #[test]
+    fn operators_bind_by_precedence_and_comparisons_do_not_chain() {
+        assert_eq!(positions("(1 + 2 * 3) / 10", &[1.0]), [0.7]);
+        assert_eq!(positions("(10 - 4 - 3) / 10", &[1.0]), [0.3]);
+        assert_eq!(positions("(7 % 4 - -1) / 10", &[1.0]), [0.4]);
+        assert_eq!(positions("1 + 1 == 2 && 2 < 1 || !false", &[1.0]), [1.0]);
+        assert_eq!(positions("false || 1 < 2 && 3 > 4", &[1.0]), [0.0]);
+
+        assert_eq!(
+            compile_error("1 < 2 < 3"),
+            "line 1:7: expected ';', found '<'"
+        );
+        assert_eq!(
+            compile_error("true == 1 < 2"),
+            "line 1:11: expected ';', found '<'"
+        );
+        assert_eq!(positions("true == (1 < 2)", &[1.0]), [1.0]);
+    }
+
+    #[test]
+    fn statement_if_ends_at_its_brace() {
+        // `-0.5` is the script's value, not subtracted from the `if`.
+        assert_eq!(
+            positions("if bar == 0 { 1 } -0.5", &[1.0, 1.0]),
+            [-0.5, -0.5]
+        );
+        // Inside an expression the `if` is an operand like any other.
+        assert_eq!(
+            positions("let x = if bar == 0 { 1 } else { 2 } - 1.5; x", &[1.0, 1.0]),
+            [-0.5, 0.5]
+        );
+    }
+
+    #[test]
+    fn return_without_value_keeps_the_previous_position() {
+        assert_eq!(
+            positions(
+                "if bar == 2 { return; } 0.1 * (bar + 1)",
+                &[1.0, 1.0, 1.0, 1.0]
+            ),
+            [0.1, 0.2, 0.2, 0.4]
+        );
+        assert_eq!(positions("return 0.5; 1", &[1.0]), [0.5]);
+        assert_eq!(positions("if bar > 0 { return; } 1", &[1.0; 3]), [1.0; 3]);
+        // Nothing to keep on the first bar: flat.
+        assert_eq!(positions("return;", &[1.0, 1.0]), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn values_become_clamped_positions_and_nan_is_flat() {
+        assert_eq!(
+            positions("sma(2) - 100", &[100.0, 96.0, 104.0, 110.0]),
+            [0.0, -1.0, 0.0, 1.0]
+        );
+        // NaN is flat rather than keeping the previous position.
+        assert_eq!(
+            positions("if bar == 0 { 1 } else { 0 / 0 }", &[1.0, 1.0]),
+            [1.0, 0.0]
+        );
+        assert_eq!(positions("close > 100", &[99.0, 101.0]), [0.0, 1.0]);
+        assert_eq!(positions("-3", &[1.0]), [-1.0]);
+    }
+
+    #[test]
+    fn runs_stop_at_the_operation_and_time_limits() {
+        let script = Script::compile("close + 1").unwrap();
+        let closes = [1.0; 2_000];
+        let run = script.positions(&bars(&closes), &LIMITS).unwrap();
+        assert_eq!(run.operations, 3 * 2_000);
+
+        let tight = Limits {
+            max_operations: 10,
+            ..LIMITS
+        };
+        assert_eq!(
+            script.positions(&bars(&closes), &tight),
+            Err(ScriptError::OperationLimit(10))
+        );
+        // The clock is only read every `CLOCK_EVERY` operations.
+        let expired = Limits {
+            timeout: Duration::ZERO,
+            ..LIMITS
+        };
+        assert_eq!(
+            script.positions(&bars(&closes), &expired),
+            Err(ScriptError::Timeout(0))
+        );
+        assert!(script.positions(&bars(&[1.0]), &expired).is_ok());
+    }
+
+    #[test]
+    fn nesting_is_bounded_by_depth_and_height() {
+        let parens = |n: usize| format!("{}1{}", "(".repeat(n), ")".repeat(n));
+        assert!(Script::compile(&parens(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(
+            compile_error(&parens(MAX_DEPTH)),
+            format!("line 1:{}: expression nested too deeply", MAX_DEPTH + 1)
+        );
+
+        let chain = |n: usize| format!("{}1", "1 + ".repeat(n));
+        assert!(Script::compile(&chain(MAX_HEIGHT - 1)).is_ok());
+        assert!(Script::compile(&chain(MAX_HEIGHT))
+            .unwrap_err()
+            .to_string()
+            .ends_with("expression nested too deeply"));
+        let negations = format!("{}1", "-".repeat(MAX_HEIGHT));
+        assert!(Script::compile(&negations).is_err());
+        // Statement-level blocks count towards the depth like parentheses.
+        let blocks = |n: usize| format!("{}1{}", "if true { ".repeat(n), " }".repeat(n));
+        assert!(Script::compile(&blocks(MAX_DEPTH - 1)).is_ok());
+        assert!(compile_error(&blocks(MAX_DEPTH)).ends_with("nested too deeply"));
+        assert!(Script::compile(&blocks(1_600)).is_err());
+    }
+
+    #[tokio::test]
+    async fn script_strategies_trade_like_builtin_kinds() {
+        let prices = bars(
+            &(0..120)
+                .map(|i| 100.0 + 15.0 * (i as f64 / 6.0).sin() + (i % 5) as f64)
+                .collect::<Vec<_>>(),
+        );
+        let builtin = InMemoryStrategyService
+            .backtest(&strategy("rsi", serde_json::json!({})), &prices)
+            .await
+            .unwrap();
+        // No value on a bar keeps the previous position, like the RSI bands.
+        let source = "
+            let r = rsi(14); // NaN while warming up
+            if ready(r) {
+                if r < 30 { 1 } else if r > 70 { 0 }
+            }
+        ";
+        let scripted = InMemoryStrategyService
+            .backtest(
+                &strategy("script", serde_json::json!({ "source": source })),
+                &prices,
+            )
+            .await
+            .unwrap();
+        assert!(!builtin.trades.is_empty());
+        assert_eq!(scripted.trades.len(), builtin.trades.len());
+        assert_eq!(scripted.equity_curve, builtin.equity_curve);
+        assert_eq!(scripted.metrics["type"], "script");
+        assert!(scripted.metrics["script_operations"].as_u64().unwrap() > 0);
+
+        let bars = bars(&[100.0, 200.0, 100.0, 100.0]);
+        let script = Script::compile("if bar == 0 { return; } -change(1) * 5").unwrap();
+        let limits = ScriptParams::default().limits();
+        let run = script.positions(&bars, &limits).unwrap();
+        // Values are clamped to [-1, 1].
+        assert_eq!(run.positions, vec![0.0, -1.0, 1.0, 0.0]);
+    }
+
+    #[tokio::test]
+    async fn script_errors_and_limits_are_reported_on_source() {
+        let errors = StrategyKind::Script
+            .validate(&serde_json::json!({ "source": "let x = 1;\nx + * 2" }))
+            .unwrap_err();
+        assert_eq!(errors[0].field, "source");
+        assert_eq!(
+            errors[0].message,
+            "line 2:5: expected an expression, found '*'"
+        );
+        let errors = StrategyKind::Script
+            .validate(&serde_json::json!({ "source": "", "timeout_ms": 60000 }))
+            .unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["source", "timeout_ms"]);
+        let deep = format!("{}1", "1 + ".repeat(300));
+        assert!(Script::compile(&deep).is_err());
+
+        let prices = bars(&(0..50).map(|i| 100.0 + i as f64).collect::<Vec<_>>());
+        let params = serde_json::json!({
+            "source": "let a = sma(10); let b = sma(20); a > b",
+            "max_operations": 100
+        });
+        let err = InMemoryStrategyService
+            .backtest(&strategy("script", params), &prices)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("exceeded 100 operations"));
+
+        // Type errors only show up once the script runs.
+        let err = InMemoryStrategyService
+            .backtest(
+                &strategy(
+                    "script",
+                    serde_json::json!({ "source": "close > 1 + true" }),
+                ),
+                &prices,
+            )
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("line 1:11: cannot apply '+' to number and bool"));
+    }
+}
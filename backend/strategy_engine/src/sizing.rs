@@ -0,0 +1,203 @@
+//! Position sizing: turns a directional signal into an exposure, so kinds can
+//! trade fractions or multiples of equity instead of all-or-nothing.
+
+use crate::metrics::{self, Curve};
+use crate::rolling::RollingStats;
+use crate::PricePoint;
+
+/// Scales exposure so the position's annualized volatility tracks
+/// `target_vol`, estimated from the realized volatility of the last
+/// `lookback` bar returns and capped at `max_leverage`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolTarget {
+    pub target_vol: f64,
+    pub lookback: usize,
+    pub max_leverage: f64,
+}
+
+impl Default for VolTarget {
+    fn default() -> Self {
+        Self {
+            target_vol: 0.2,
+            lookback: 20,
+            max_leverage: 1.0,
+        }
+    }
+}
+
+impl VolTarget {
+    /// Reads `target_vol`, `sizing_lookback` and `max_leverage`, keeping the
+    /// defaults (20% annualized, 20 bars, no leverage) for missing keys.
+    pub fn from_params(params: &serde_json::Value) -> Self {
+        let defaults = Self::default();
+        let positive = |key: &str| {
+            params
+                .get(key)
+                .and_then(|v| v.as_f64())
+                .filter(|v| *v > 0.0)
+        };
+        Self {
+            target_vol: positive("target_vol").unwrap_or(defaults.target_vol),
+            lookback: params
+                .get("sizing_lookback")
+                .and_then(|v| v.as_u64())
+                .map(|v| (v as usize).max(2))
+                .unwrap_or(defaults.lookback),
+            max_leverage: positive("max_leverage").unwrap_or(defaults.max_leverage),
+        }
+    }
+
+    /// `Some` when the params select `sizing: "vol_target"`.
+    pub fn from_sizing_params(params: &serde_json::Value) -> Option<Self> {
+        params
+            .get("sizing")
+            .and_then(|v| v.as_str())
+            .filter(|mode| mode.eq_ignore_ascii_case("vol_target"))
+            .map(|_| Self::from_params(params))
+    }
+
+    /// Exposure per bar, decided at the close from the returns observed so
+    /// far. Zero until `lookback` returns exist, or when the window shows no
+    /// volatility.
+    pub fn exposures(&self, prices: &[PricePoint], periods_per_year: f64) -> Vec<f64> {
+        let mut window = RollingStats::new(self.lookback);
+        let annualize = periods_per_year.sqrt();
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                // The window holds the returns into bars i - lookback + 1..=i.
+                if i > 0 {
+                    let prev = prices[i - 1].price;
+                    window.push(if prev > 0.0 {
+                        point.price / prev - 1.0
+                    } else {
+                        0.0
+                    });
+                }
+                if !window.is_full() {
+                    return 0.0;
+                }
+                let vol = window.std_dev().unwrap_or_default() * annualize;
+                if vol > 0.0 {
+                    (self.target_vol / vol).min(self.max_leverage)
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Scales each raw position by the exposure of its bar.
+    pub fn apply(&self, prices: &[PricePoint], raw: &[f64], periods_per_year: f64) -> Vec<f64> {
+        self.exposures(prices, periods_per_year)
+            .into_iter()
+            .zip(raw)
+            .map(|(exposure, position)| exposure * position)
+            .collect()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "mode": "vol_target",
+            "target_vol": self.target_vol,
+            "lookback": self.lookback,
+            "max_leverage": self.max_leverage
+        })
+    }
+}
+
+/// Annualized volatility of the equity curve from the first bar holding a
+/// position onwards, so a flat warm-up does not dilute it.
+pub fn realized_vol(equity_curve: &Curve, positions: &[f64], periods_per_year: f64) -> f64 {
+    let Some(first) = positions.iter().position(|p| p.abs() > f64::EPSILON) else {
+        return 0.0;
+    };
+    let returns = metrics::simple_returns(&equity_curve[first.min(equity_curve.len())..]);
+    metrics::std_dev(&returns) * periods_per_year.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{series, strategy};
+    use crate::{InMemoryStrategyService, StrategyService};
+
+    #[tokio::test]
+    async fn vol_targeting_scales_exposure() {
+        // Alternating +-1% then +-4% daily moves.
+        let mut price = 100.0;
+        let mut prices = vec![price];
+        for i in 0..120 {
+            let step = if i < 60 { 0.01 } else { 0.04 };
+            price *= if i % 2 == 0 { 1.0 + step } else { 1.0 - step };
+            prices.push(price);
+        }
+        let points = series(&prices);
+        let sizing = VolTarget {
+            target_vol: 0.3,
+            lookback: 10,
+            max_leverage: 2.0,
+        };
+        let exposures = sizing.exposures(&points, 365.0);
+        assert_eq!(exposures[9], 0.0);
+        // Calm regime: 1% daily is ~19% annualized, so exposure is levered up.
+        assert!((exposures[50] - 1.57).abs() < 0.01);
+        // Volatile regime: ~76% annualized, scaled down to ~0.39x.
+        assert!((exposures[110] - 0.39).abs() < 0.01);
+        assert_eq!(
+            VolTarget {
+                max_leverage: 1.0,
+                ..sizing.clone()
+            }
+            .exposures(&points, 365.0)[50],
+            1.0
+        );
+
+        let params = serde_json::json!({
+            "lookback": 10,
+            "target_vol": 0.3,
+            "max_leverage": 2.0,
+            "periods_per_year": 365
+        });
+        let result = InMemoryStrategyService
+            .backtest(&strategy("volatility", params), &points)
+            .await
+            .unwrap();
+        let realized = result.metrics["realized_vol"].as_f64().unwrap();
+        assert!((realized - 0.3).abs() < 0.05, "realized {realized}");
+        assert_eq!(result.metrics["target_vol"], 0.3);
+        let max_exposure = result.metrics["max_exposure"].as_f64().unwrap();
+        assert!(max_exposure > 1.5 && max_exposure <= 2.0);
+
+        // A zero print is skipped instead of turning asset_vol into inf/NaN.
+        let mut gappy: Vec<f64> = (0..30).map(|i| 100.0 + (i % 3) as f64).collect();
+        gappy[25] = 0.0;
+        let result = InMemoryStrategyService
+            .backtest(
+                &strategy("volatility", serde_json::json!({ "lookback": 10 })),
+                &series(&gappy),
+            )
+            .await
+            .unwrap();
+        assert!(result.metrics["asset_vol"]
+            .as_f64()
+            .is_some_and(f64::is_finite));
+
+        // Any position kind can opt into the same sizing.
+        let params = serde_json::json!({
+            "short_window": 2,
+            "long_window": 5,
+            "sizing": "vol_target",
+            "target_vol": 0.1,
+            "sizing_lookback": 10
+        });
+        let result = InMemoryStrategyService
+            .backtest(&strategy("ma_cross", params), &points)
+            .await
+            .unwrap();
+        assert_eq!(result.metrics["sizing"]["mode"], "vol_target");
+        assert!(result.metrics["max_exposure"].as_f64().unwrap() < 1.0);
+        assert!(result.trades.iter().all(|t| t.size < 1.0));
+    }
+}
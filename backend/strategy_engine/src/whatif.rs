@@ -0,0 +1,174 @@
+//! What-if replays over a wallet's tracked holdings. A strategy's backtest
+//! decides what happens to part of the wallet's real starting positions, the
+//! rest is held as it was, and the hypothetical value is compared with the
+//! wallet's recorded daily value.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use domain::{BacktestResult, DailySnapshot, Position};
+
+use crate::benchmark::{benchmark_curve, relative_stats};
+use crate::metrics::{self, simple_returns, Curve, MetricsConfig};
+use crate::portfolio::PriceSeries;
+use crate::PricePoint;
+
+/// Timestamp a daily snapshot is charted at: midnight UTC of its day.
+pub fn snapshot_time(day: NaiveDate) -> DateTime<Utc> {
+    day.and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc()
+}
+
+/// Unit price of every held asset implied by the snapshots (`usd_value /
+/// amount`), keyed by upper-case symbol. Days an asset was not held leave a
+/// gap in its series.
+pub fn implied_prices(days: &[DailySnapshot]) -> PriceSeries {
+    let mut series = PriceSeries::new();
+    for day in days {
+        let timestamp = snapshot_time(day.day);
+        for position in &day.positions {
+            if position.amount > 0.0 && position.usd_value > 0.0 {
+                series
+                    .entry(position.asset_symbol.to_uppercase())
+                    .or_default()
+                    .push(PricePoint::new(
+                        timestamp,
+                        position.usd_value / position.amount,
+                    ));
+            }
+        }
+    }
+    series
+}
+
+/// Positions with a value on the first day, merged per upper-case symbol.
+pub fn starting_positions(first: &DailySnapshot) -> Vec<Position> {
+    let mut merged: BTreeMap<String, Position> = BTreeMap::new();
+    for position in &first.positions {
+        if position.amount <= 0.0 || position.usd_value <= 0.0 {
+            continue;
+        }
+        let symbol = position.asset_symbol.to_uppercase();
+        let entry = merged.entry(symbol.clone()).or_insert(Position {
+            asset_symbol: symbol,
+            amount: 0.0,
+            usd_value: 0.0,
+        });
+        entry.amount += position.amount;
+        entry.usd_value += position.usd_value;
+    }
+    merged.into_values().collect()
+}
+
+/// Hypothetical and actual wallet value over the snapshot days.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub hypothetical_curve: Vec<(DateTime<Utc>, f64)>,
+    pub actual_curve: Vec<(DateTime<Utc>, f64)>,
+    pub hypothetical_metrics: serde_json::Value,
+    pub actual_metrics: serde_json::Value,
+    pub comparison: serde_json::Value,
+}
+
+/// Values the wallet as if, from the first snapshot on, the starting value of
+/// `managed` had followed `policy`'s equity curve and every other starting
+/// position had been held. Prices between observations carry forward.
+///
+/// The actual curve is the recorded `total_usd_value`, so deposits and
+/// withdrawals show up as returns there but not in the hypothetical curve.
+pub fn replay(
+    days: &[DailySnapshot],
+    managed: &[String],
+    policy: &BacktestResult,
+    config: &MetricsConfig,
+) -> Replay {
+    let timestamps: Vec<DateTime<Utc>> = days.iter().map(|d| snapshot_time(d.day)).collect();
+    let actual_curve: Vec<(DateTime<Utc>, f64)> = days
+        .iter()
+        .zip(&timestamps)
+        .map(|(day, ts)| (*ts, day.total_usd_value))
+        .collect();
+
+    let prices = implied_prices(days);
+    let start = days.first().map(starting_positions).unwrap_or_default();
+    let mut hypothetical = vec![0.0; timestamps.len()];
+    let mut add_sleeve = |value: f64, growth: Vec<(DateTime<Utc>, f64)>| {
+        for (total, (_, ratio)) in hypothetical.iter_mut().zip(growth) {
+            *total += value * ratio;
+        }
+    };
+    let managed_value: f64 = start
+        .iter()
+        .filter(|p| managed.contains(&p.asset_symbol))
+        .map(|p| p.usd_value)
+        .sum();
+    let policy_points: Vec<PricePoint> = policy
+        .equity_curve
+        .iter()
+        .map(|(ts, equity)| PricePoint::new(*ts, *equity))
+        .collect();
+    if managed_value > 0.0 {
+        add_sleeve(managed_value, benchmark_curve(&timestamps, &policy_points));
+    }
+    for position in start.iter().filter(|p| !managed.contains(&p.asset_symbol)) {
+        let series = prices
+            .get(&position.asset_symbol)
+            .map_or(&[][..], Vec::as_slice);
+        let growth = benchmark_curve(&timestamps, series);
+        if growth.is_empty() {
+            add_sleeve(
+                position.usd_value,
+                timestamps.iter().map(|ts| (*ts, 1.0)).collect(),
+            );
+        } else {
+            add_sleeve(position.usd_value, growth);
+        }
+    }
+    let hypothetical_curve: Vec<(DateTime<Utc>, f64)> =
+        timestamps.iter().copied().zip(hypothetical).collect();
+
+    let hypothetical_metrics = metrics::build_metrics(
+        &hypothetical_curve,
+        serde_json::json!({
+            "managed_symbols": managed,
+            "managed_value": managed_value
+        }),
+        config,
+    );
+    let actual_metrics = metrics::build_metrics(&actual_curve, serde_json::json!({}), config);
+    let comparison = compare(&hypothetical_curve, &actual_curve, config);
+    Replay {
+        hypothetical_curve,
+        actual_curve,
+        hypothetical_metrics,
+        actual_metrics,
+        comparison,
+    }
+}
+
+fn compare(hypothetical: &Curve, actual: &Curve, config: &MetricsConfig) -> serde_json::Value {
+    let total_return = |curve: &Curve| match (curve.first(), curve.last()) {
+        (Some((_, first)), Some((_, last))) if *first > 0.0 => last / first - 1.0,
+        _ => 0.0,
+    };
+    let final_value = |curve: &Curve| curve.last().map_or(0.0, |(_, v)| *v);
+    let periods_per_year = config
+        .annualization(hypothetical.iter().map(|(ts, _)| *ts))
+        .periods_per_year;
+    let stats = relative_stats(
+        &simple_returns(hypothetical),
+        &simple_returns(actual),
+        periods_per_year,
+    );
+    serde_json::json!({
+        "hypothetical_return": total_return(hypothetical),
+        "actual_return": total_return(actual),
+        "excess_return": total_return(hypothetical) - total_return(actual),
+        "final_difference_usd": final_value(hypothetical) - final_value(actual),
+        "alpha": stats.alpha,
+        "beta": stats.beta,
+        "tracking_error": stats.tracking_error,
+        "information_ratio": stats.information_ratio
+    })
+}
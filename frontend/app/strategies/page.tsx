@@ -32,9 +32,11 @@ import {
 import { ChangeEvent, FormEvent, MouseEvent, useEffect, useMemo, useState } from "react";
 import { useProfile } from "../../lib/auth-context";
 import {
-  backtestStrategy,
+  cancelBacktestJob,
   createStrategy,
   deleteStrategy,
+  enqueueBacktestJob,
+  fetchBacktestJob,
   fetchStrategies,
   fetchStrategyBacktests,
 } from "../../lib/api";
@@ -54,9 +56,22 @@ type BacktestResult = {
   strategy_id: string;
   equity_curve: [string, number][];
   metrics: Record<string, unknown>;
+  benchmark_curve?: [string, number][];
+  weights_history?: { timestamp: string; weights: Record<string, number>; rebalanced: boolean }[];
   completed_at?: string | null;
 };
 
+type BacktestJob = {
+  id: string;
+  strategy_id: string;
+  status: "queued" | "running" | "succeeded" | "failed" | "cancelled";
+  progress: number;
+  error?: string | null;
+  result?: BacktestResult;
+};
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
 export default function StrategiesPage() {
   const { profile, loading } = useProfile();
   const router = useRouter();
@@ -69,15 +84,32 @@ export default function StrategiesPage() {
     short: 5,
     long: 20,
     lookback: 20,
-    lag: 5,
+    targetVol: 0.2,
+    maxLeverage: 1,
+    period: 14,
+    overbought: 70,
+    oversold: 30,
+    fast: 12,
+    slow: 26,
+    signal: 9,
+    symbols: "ETH,BTC",
+    weighting: "equal",
+    rebalanceEvery: 20,
+    pairSymbols: "ETH,WBTC",
+    entryZ: 2,
+    exitZ: 0.5,
+    bbPeriod: 20,
+    numStd: 2,
     symbol: "ETH",
     days: 30,
+    benchmark: "",
   });
   const [backtestResult, setBacktestResult] = useState<BacktestResult | null>(null);
   const [backtestHistory, setBacktestHistory] = useState<BacktestResult[]>([]);
   const [historyLoading, setHistoryLoading] = useState(false);
   const [selectedStrategyId, setSelectedStrategyId] = useState<string | null>(null);
   const [runningId, setRunningId] = useState<string | null>(null);
+  const [activeJob, setActiveJob] = useState<BacktestJob | null>(null);
   const [csvPrices, setCsvPrices] = useState<{ timestamp: string; price: number }[] | null>(null);
   const [guideOpen, setGuideOpen] = useState(true);
   const presetSymbols = ["ETH", "WETH", "USDC"];
@@ -144,7 +176,28 @@ export default function StrategiesPage() {
       short: Number(strategy.params?.short_window ?? prev.short) || 5,
       long: Number(strategy.params?.long_window ?? prev.long) || 20,
       lookback: Number(strategy.params?.lookback ?? prev.lookback) || 20,
-      lag: Number(strategy.params?.lag ?? prev.lag) || 5,
+      targetVol: Number(strategy.params?.target_vol ?? prev.targetVol) || 0.2,
+      maxLeverage: Number(strategy.params?.max_leverage ?? prev.maxLeverage) || 1,
+      period: Number(strategy.params?.period ?? prev.period) || 14,
+      overbought: Number(strategy.params?.overbought ?? prev.overbought) || 70,
+      oversold: Number(strategy.params?.oversold ?? prev.oversold) || 30,
+      fast: Number(strategy.params?.fast ?? prev.fast) || 12,
+      slow: Number(strategy.params?.slow ?? prev.slow) || 26,
+      signal: Number(strategy.params?.signal ?? prev.signal) || 9,
+      symbols: Array.isArray(strategy.params?.symbols)
+        ? (strategy.params.symbols as string[]).join(",")
+        : prev.symbols,
+      weighting: String(strategy.params?.weighting ?? prev.weighting),
+      rebalanceEvery: Number(strategy.params?.rebalance_every ?? prev.rebalanceEvery) || 20,
+      pairSymbols:
+        type === "PAIRS" && Array.isArray(strategy.params?.symbols)
+          ? (strategy.params.symbols as string[]).join(",")
+          : prev.pairSymbols,
+      entryZ: Number(strategy.params?.entry_z ?? prev.entryZ) || 2,
+      exitZ: Number(strategy.params?.exit_z ?? prev.exitZ),
+      bbPeriod:
+        type === "BOLLINGER" ? Number(strategy.params?.period ?? prev.bbPeriod) || 20 : prev.bbPeriod,
+      numStd: Number(strategy.params?.num_std ?? prev.numStd) || 2,
     }));
   };
 
@@ -155,13 +208,16 @@ export default function StrategiesPage() {
       const res = await createStrategy({
         name: form.name,
         type: form.type,
-        params: {
-          short_window: form.short,
-          long_window: form.long,
-          lookback: form.lookback,
-          lag: form.lag,
-        },
+        params: paramsForType(form),
       });
+      if (res.status === 422) {
+        // 後端回傳欄位層級錯誤：{ error, fields: [{ field, message }] }
+        const body = await res.json().catch(() => null);
+        const details = (body?.fields ?? [])
+          .map((f: { field: string; message: string }) => `${f.field}: ${f.message}`)
+          .join("；");
+        throw new Error(`參數驗證失敗${details ? `：${details}` : ""}`);
+      }
       if (!res.ok) throw new Error(`建立策略失敗 (${res.status})`);
       const data = await res.json();
       setStrategies((prev) => [data, ...prev]);
@@ -174,9 +230,15 @@ export default function StrategiesPage() {
         short: 5,
         long: 20,
         lookback: 20,
-        lag: 5,
+        period: 14,
+        overbought: 70,
+        oversold: 30,
+        fast: 12,
+        slow: 26,
+        signal: 9,
         symbol: "ETH",
         days: 30,
+        benchmark: "",
       });
     } catch (err) {
       setError(err instanceof Error ? err.message : "未知錯誤");
@@ -221,6 +283,9 @@ export default function StrategiesPage() {
         payload.symbol = form.symbol;
         payload.days = form.days;
       }
+      if (form.benchmark) {
+        payload.benchmark_symbol = form.benchmark;
+      }
       const type = strategy.type.toUpperCase();
       payload.type = type;
       if (type === "MA_CROSS") {
@@ -230,18 +295,35 @@ export default function StrategiesPage() {
       if (type === "VOLATILITY") {
         payload.lookback = form.lookback;
       }
-      if (type === "CORRELATION") {
-        payload.lag = form.lag;
-      }
-      const res = await backtestStrategy(strategyId, payload);
+      const res = await enqueueBacktestJob(strategyId, payload);
       if (!res.ok) throw new Error(`回測失敗 (${res.status})`);
-      const data = await res.json();
-      setBacktestResult(data);
-      loadBacktests(strategyId);
+      let job: BacktestJob = await res.json();
+      setActiveJob(job);
+      while (job.status === "queued" || job.status === "running") {
+        await sleep(1000);
+        const poll = await fetchBacktestJob(job.id);
+        if (!poll.ok) throw new Error(`查詢回測進度失敗 (${poll.status})`);
+        job = await poll.json();
+        setActiveJob(job);
+      }
+      if (job.status === "failed") throw new Error(`回測失敗：${job.error ?? "未知錯誤"}`);
+      if (job.status === "succeeded" && job.result) {
+        setBacktestResult(job.result);
+        loadBacktests(strategyId);
+      }
     } catch (err) {
       setError(err instanceof Error ? err.message : "未知錯誤");
     } finally {
       setRunningId(null);
+      setActiveJob(null);
+    }
+  };
+
+  const cancelBacktest = async () => {
+    if (!activeJob) return;
+    const res = await cancelBacktestJob(activeJob.id);
+    if (!res.ok && res.status !== 409) {
+      setError(`取消回測失敗 (${res.status})`);
     }
   };
 
@@ -283,7 +365,7 @@ export default function StrategiesPage() {
                 使用手冊
               </Typography>
               <Typography variant="body2" color="text.secondary" sx={{ whiteSpace: "pre-line" }}>
-                1) 建立策略：選擇類型並填入參數。MA 需短/長均線；Vol 需 lookback；Corr 需 lag。
+                1) 建立策略：選擇類型並填入參數。MA 需短/長均線；Vol 需 lookback；Pairs 需兩個幣種。
 {"\n"}2) 回測：可選預置資產+天數抓實價，或匯入 CSV（timestamp,price）。
 {"\n"}3) 結果：查看總報酬、年化、最大回撤、Sharpe；右側 JSON 為後端指標。
 {"\n"}4) 如果抓不到價格：檢查後端 price oracle/Coingecko；CSV 需有效時間格式。
@@ -346,7 +428,11 @@ export default function StrategiesPage() {
                   >
                     <MenuItem value="MA_CROSS">MA Cross</MenuItem>
                     <MenuItem value="VOLATILITY">Volatility</MenuItem>
-                    <MenuItem value="CORRELATION">Correlation</MenuItem>
+                    <MenuItem value="RSI">RSI</MenuItem>
+                    <MenuItem value="MACD">MACD</MenuItem>
+                    <MenuItem value="REBALANCE">Rebalance（多資產）</MenuItem>
+                    <MenuItem value="PAIRS">Pairs（配對交易）</MenuItem>
+                    <MenuItem value="BOLLINGER">Bollinger（通道回歸）</MenuItem>
                   </TextField>
                   {form.type === "MA_CROSS" && (
                     <>
@@ -367,24 +453,169 @@ export default function StrategiesPage() {
                     </>
                   )}
                   {form.type === "VOLATILITY" && (
-                    <TextField
-                      label="Lookback"
-                      type="number"
-                      value={form.lookback}
-                      onChange={(e) => setForm((f) => ({ ...f, lookback: Number(e.target.value) }))}
-                      fullWidth
-                      sx={{ gridColumn: { xs: "span 1", sm: "span 2" } }}
-                    />
+                    <>
+                      <TextField
+                        label="Lookback"
+                        type="number"
+                        value={form.lookback}
+                        onChange={(e) => setForm((f) => ({ ...f, lookback: Number(e.target.value) }))}
+                        fullWidth
+                        sx={{ gridColumn: { xs: "span 1", sm: "span 2" } }}
+                      />
+                      <TextField
+                        label="Target vol（年化）"
+                        type="number"
+                        inputProps={{ step: 0.05 }}
+                        value={form.targetVol}
+                        onChange={(e) => setForm((f) => ({ ...f, targetVol: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                      <TextField
+                        label="Max leverage"
+                        type="number"
+                        inputProps={{ step: 0.5 }}
+                        value={form.maxLeverage}
+                        onChange={(e) => setForm((f) => ({ ...f, maxLeverage: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                    </>
                   )}
-                  {form.type === "CORRELATION" && (
-                    <TextField
-                      label="Lag"
-                      type="number"
-                      value={form.lag}
-                      onChange={(e) => setForm((f) => ({ ...f, lag: Number(e.target.value) }))}
-                      fullWidth
-                      sx={{ gridColumn: { xs: "span 1", sm: "span 2" } }}
-                    />
+                  {form.type === "RSI" && (
+                    <>
+                      <TextField
+                        label="Period"
+                        type="number"
+                        value={form.period}
+                        onChange={(e) => setForm((f) => ({ ...f, period: Number(e.target.value) }))}
+                        fullWidth
+                        sx={{ gridColumn: { xs: "span 1", sm: "span 2" } }}
+                      />
+                      <TextField
+                        label="Oversold"
+                        type="number"
+                        value={form.oversold}
+                        onChange={(e) => setForm((f) => ({ ...f, oversold: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                      <TextField
+                        label="Overbought"
+                        type="number"
+                        value={form.overbought}
+                        onChange={(e) => setForm((f) => ({ ...f, overbought: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                    </>
+                  )}
+                  {form.type === "MACD" && (
+                    <>
+                      <TextField
+                        label="Fast EMA"
+                        type="number"
+                        value={form.fast}
+                        onChange={(e) => setForm((f) => ({ ...f, fast: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                      <TextField
+                        label="Slow EMA"
+                        type="number"
+                        value={form.slow}
+                        onChange={(e) => setForm((f) => ({ ...f, slow: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                      <TextField
+                        label="Signal EMA"
+                        type="number"
+                        value={form.signal}
+                        onChange={(e) => setForm((f) => ({ ...f, signal: Number(e.target.value) }))}
+                        fullWidth
+                        sx={{ gridColumn: { xs: "span 1", sm: "span 2" } }}
+                      />
+                    </>
+                  )}
+                  {form.type === "REBALANCE" && (
+                    <>
+                      <TextField
+                        label="Symbols（逗號分隔）"
+                        value={form.symbols}
+                        onChange={(e) => setForm((f) => ({ ...f, symbols: e.target.value }))}
+                        fullWidth
+                        sx={{ gridColumn: { xs: "span 1", sm: "span 2" } }}
+                      />
+                      <TextField
+                        select
+                        label="Weighting"
+                        value={form.weighting}
+                        onChange={(e) => setForm((f) => ({ ...f, weighting: e.target.value }))}
+                        fullWidth
+                      >
+                        <MenuItem value="equal">Equal</MenuItem>
+                        <MenuItem value="inverse_vol">Inverse vol</MenuItem>
+                        <MenuItem value="risk_parity">Risk parity</MenuItem>
+                      </TextField>
+                      <TextField
+                        label="Rebalance every (bars)"
+                        type="number"
+                        value={form.rebalanceEvery}
+                        onChange={(e) =>
+                          setForm((f) => ({ ...f, rebalanceEvery: Number(e.target.value) }))
+                        }
+                        fullWidth
+                      />
+                    </>
+                  )}
+                  {form.type === "PAIRS" && (
+                    <>
+                      <TextField
+                        label="Pair（交易腿,避險腿）"
+                        value={form.pairSymbols}
+                        onChange={(e) => setForm((f) => ({ ...f, pairSymbols: e.target.value }))}
+                        fullWidth
+                        sx={{ gridColumn: { xs: "span 1", sm: "span 2" } }}
+                      />
+                      <TextField
+                        label="Lookback"
+                        type="number"
+                        value={form.lookback}
+                        onChange={(e) => setForm((f) => ({ ...f, lookback: Number(e.target.value) }))}
+                        fullWidth
+                        sx={{ gridColumn: { xs: "span 1", sm: "span 2" } }}
+                      />
+                      <TextField
+                        label="Entry z"
+                        type="number"
+                        inputProps={{ step: 0.1 }}
+                        value={form.entryZ}
+                        onChange={(e) => setForm((f) => ({ ...f, entryZ: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                      <TextField
+                        label="Exit z"
+                        type="number"
+                        inputProps={{ step: 0.1 }}
+                        value={form.exitZ}
+                        onChange={(e) => setForm((f) => ({ ...f, exitZ: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                    </>
+                  )}
+                  {form.type === "BOLLINGER" && (
+                    <>
+                      <TextField
+                        label="Period"
+                        type="number"
+                        value={form.bbPeriod}
+                        onChange={(e) => setForm((f) => ({ ...f, bbPeriod: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                      <TextField
+                        label="標準差倍數"
+                        type="number"
+                        inputProps={{ step: 0.1 }}
+                        value={form.numStd}
+                        onChange={(e) => setForm((f) => ({ ...f, numStd: Number(e.target.value) }))}
+                        fullWidth
+                      />
+                    </>
                   )}
                 </Box>
 
@@ -418,6 +649,19 @@ export default function StrategiesPage() {
                     value={form.days}
                     onChange={(e) => setForm((f) => ({ ...f, days: Number(e.target.value) }))}
                   />
+                  <TextField
+                    select
+                    label="基準"
+                    value={form.benchmark}
+                    onChange={(e) => setForm((f) => ({ ...f, benchmark: e.target.value }))}
+                  >
+                    <MenuItem value="">Buy & Hold（同資產）</MenuItem>
+                    {presetSymbols.map((s) => (
+                      <MenuItem key={s} value={s}>
+                        {s}
+                      </MenuItem>
+                    ))}
+                  </TextField>
                   <Button
                     component="label"
                     variant="outlined"
@@ -484,11 +728,21 @@ export default function StrategiesPage() {
                     >
                       <TableCell>{s.name}</TableCell>
                       <TableCell>
-                        {s.type === "MA_CROSS"
+                        {s.type.toUpperCase() === "MA_CROSS"
                           ? `MA(${(s.params?.short_window as number) ?? "-"}, ${(s.params?.long_window as number) ?? "-"})`
-                          : s.type === "VOLATILITY"
-                            ? `Vol lookback ${(s.params?.lookback as number) ?? "-"}`
-                            : `Corr lag ${(s.params?.lag as number) ?? "-"}`}
+                          : s.type.toUpperCase() === "VOLATILITY"
+                            ? `Vol target ${(s.params?.target_vol as number) ?? 0.2} / ${(s.params?.lookback as number) ?? "-"}`
+                            : s.type.toUpperCase() === "RSI"
+                              ? `RSI(${(s.params?.period as number) ?? "-"}) ${(s.params?.oversold as number) ?? "-"}/${(s.params?.overbought as number) ?? "-"}`
+                              : s.type.toUpperCase() === "MACD"
+                                ? `MACD(${(s.params?.fast as number) ?? "-"}, ${(s.params?.slow as number) ?? "-"}, ${(s.params?.signal as number) ?? "-"})`
+                                : s.type.toUpperCase() === "BOLLINGER"
+                                  ? `BB(${(s.params?.period as number) ?? 20}, ${(s.params?.num_std as number) ?? 2})`
+                                  : s.type.toUpperCase() === "PAIRS"
+                                    ? `Pairs ${((s.params?.symbols as string[]) ?? []).join("/")} z±${(s.params?.entry_z as number) ?? 2}`
+                                    : s.type.toUpperCase() === "REBALANCE"
+                                      ? `${((s.params?.symbols as string[]) ?? []).join("/")} ${(s.params?.weighting as string) ?? "equal"}`
+                                      : s.type}
                       </TableCell>
                       <TableCell align="right">
                         <Button
@@ -500,8 +754,25 @@ export default function StrategiesPage() {
                           }}
                           disabled={runningId === s.id}
                         >
-                          {runningId === s.id ? t("strategies.running") : t("strategies.run")}
+                          {runningId === s.id
+                            ? `${t("strategies.running")}${
+                                activeJob ? ` ${Math.round(activeJob.progress * 100)}%` : ""
+                              }`
+                            : t("strategies.run")}
                         </Button>
+                        {runningId === s.id && activeJob && (
+                          <Button
+                            size="small"
+                            color="warning"
+                            sx={{ ml: 1 }}
+                            onClick={(e: MouseEvent) => {
+                              e.stopPropagation();
+                              cancelBacktest();
+                            }}
+                          >
+                            取消
+                          </Button>
+                        )}
                         <Button
                           size="small"
                           color="secondary"
@@ -607,10 +878,25 @@ export default function StrategiesPage() {
                   </CardContent>
                 </Card>
                 {backtestResult ? (
-                  <EquityCurveChart
-                    equityCurve={backtestResult.equity_curve}
-                    metrics={backtestResult.metrics}
-                  />
+                  <>
+                    <EquityCurveChart
+                      equityCurve={backtestResult.equity_curve}
+                      benchmarkCurve={backtestResult.benchmark_curve}
+                      metrics={backtestResult.metrics}
+                    />
+                    {backtestResult.weights_history && backtestResult.weights_history.length > 0 && (
+                      <Typography variant="body2" color="text.secondary">
+                        期末權重：
+                        {Object.entries(
+                          backtestResult.weights_history[backtestResult.weights_history.length - 1]
+                            .weights,
+                        )
+                          .map(([symbol, w]) => `${symbol} ${(w * 100).toFixed(1)}%`)
+                          .join(" / ")}
+                        （再平衡 {backtestResult.weights_history.filter((p) => p.rebalanced).length} 次）
+                      </Typography>
+                    )}
+                  </>
                 ) : (
                   <Typography variant="body2" color="text.secondary">
                     尚未回測，從列表點「回測」開始。
@@ -625,6 +911,64 @@ export default function StrategiesPage() {
   );
 }
 
+// 只送出該策略類型的參數；後端會拒絕未知欄位（422）。
+function paramsForType(form: {
+  type: string;
+  short: number;
+  long: number;
+  lookback: number;
+  targetVol: number;
+  maxLeverage: number;
+  period: number;
+  overbought: number;
+  oversold: number;
+  fast: number;
+  slow: number;
+  signal: number;
+  symbols: string;
+  weighting: string;
+  rebalanceEvery: number;
+  pairSymbols: string;
+  entryZ: number;
+  exitZ: number;
+  bbPeriod: number;
+  numStd: number;
+}): Record<string, unknown> {
+  switch (form.type) {
+    case "MA_CROSS":
+      return { short_window: form.short, long_window: form.long };
+    case "VOLATILITY":
+      return { lookback: form.lookback, target_vol: form.targetVol, max_leverage: form.maxLeverage };
+    case "RSI":
+      return { period: form.period, overbought: form.overbought, oversold: form.oversold };
+    case "MACD":
+      return { fast: form.fast, slow: form.slow, signal: form.signal };
+    case "REBALANCE":
+      return {
+        symbols: form.symbols
+          .split(",")
+          .map((s) => s.trim())
+          .filter(Boolean),
+        weighting: form.weighting,
+        rebalance_every: form.rebalanceEvery,
+      };
+    case "PAIRS":
+      return {
+        symbols: form.pairSymbols
+          .split(",")
+          .map((s) => s.trim())
+          .filter(Boolean),
+        lookback: form.lookback,
+        entry_z: form.entryZ,
+        exit_z: form.exitZ,
+      };
+    case "BOLLINGER":
+      return { period: form.bbPeriod, num_std: form.numStd };
+    default:
+      return {};
+  }
+}
+
 function summarizeResult(result: BacktestResult) {
   const curve = result.equity_curve
     .map(([ts, v]) => ({ ts: new Date(ts), v: Number(v) }))
@@ -6,10 +6,11 @@ import { GlowingAreaChart } from "./GlowingAreaChart";
 
 type Props = {
   equityCurve: [string, number][];
+  benchmarkCurve?: [string, number][];
   metrics: Record<string, unknown>;
 };
 
-export function EquityCurveChart({ equityCurve, metrics }: Props) {
+export function EquityCurveChart({ equityCurve, benchmarkCurve, metrics }: Props) {
   const sorted = useMemo(
     () =>
       equityCurve
@@ -19,6 +20,15 @@ export function EquityCurveChart({ equityCurve, metrics }: Props) {
     [equityCurve]
   );
 
+  // Benchmark starts at 1.0; rescale to the strategy's starting equity so both lines share an axis.
+  const benchmark = useMemo(() => {
+    const base = sorted[0]?.value ?? 1;
+    return (benchmarkCurve ?? [])
+      .map(([ts, v]) => ({ ts: new Date(ts), value: Number(v) * base }))
+      .filter((p) => Number.isFinite(p.value))
+      .sort((a, b) => a.ts.getTime() - b.ts.getTime());
+  }, [benchmarkCurve, sorted]);
+
   const values = sorted.map((p) => p.value);
   const start = values[0] ?? 0;
   const end = values[values.length - 1] ?? 0;
@@ -65,8 +75,36 @@ export function EquityCurveChart({ equityCurve, metrics }: Props) {
     sharpe: "Sharpe",
     cagr: "CAGR",
     max_drawdown: "最大回撤",
+    benchmark: "基準",
+    benchmark_total_return: "基準報酬",
+    alpha: "Alpha",
+    beta: "Beta",
+    tracking_error: "追蹤誤差",
+    information_ratio: "資訊比率",
+    sortino: "Sortino",
+    calmar: "Calmar",
+    max_drawdown_duration_secs: "最長回撤期",
+    time_to_recovery_secs: "回復時間",
+    tail_risk: "VaR / CVaR",
+    annualization: "年化基準",
   };
 
+  const formatDuration = (secs: unknown) =>
+    typeof secs === "number" ? `${(secs / 86400).toFixed(1)} 天` : "未回復";
+
+  // tail_risk: [{ confidence, historical_var, historical_cvar, parametric_var, parametric_cvar }]
+  const formatTailRisk = (raw: unknown) =>
+    Array.isArray(raw)
+      ? raw
+          .map(
+            (r: any) =>
+              `${(r.confidence * 100).toFixed(0)}%: ${(r.historical_var * 100).toFixed(2)}% / ${(
+                r.historical_cvar * 100
+              ).toFixed(2)}%`
+          )
+          .join("，")
+      : String(raw);
+
   const metricEntries = Object.entries(metrics ?? {}).filter(([key]) => key !== "equity_curve");
 
   const startLabel = sorted[0]?.ts.toLocaleDateString("zh-TW") ?? "";
@@ -81,6 +119,8 @@ export function EquityCurveChart({ equityCurve, metrics }: Props) {
           value: p.value,
           label: p.ts.toLocaleString("zh-TW"),
         }))}
+        overlay={benchmark.map((p) => ({ value: p.value, label: p.ts.toLocaleString("zh-TW") }))}
+        overlayLabel={`基準 ${String(metrics?.benchmark ?? "")}`.trim()}
         unit=""
         baseline={start}
         height={300}
@@ -124,11 +164,19 @@ export function EquityCurveChart({ equityCurve, metrics }: Props) {
           {metricEntries.map(([key, raw]) => {
             const label = labelMap[key] ?? key;
             const value =
-              typeof raw === "number"
-                ? Math.abs(raw) < 1 && raw !== 0
-                  ? raw.toPrecision(3)
-                  : raw.toFixed(2)
-                : String(raw);
+              key === "tail_risk"
+                ? formatTailRisk(raw)
+                : key === "annualization" && raw && typeof raw === "object"
+                  ? `${Number((raw as any).periods_per_year).toFixed(0)} 期/年（${(raw as any).source}）`
+                : key.endsWith("_secs")
+                  ? formatDuration(raw)
+                  : typeof raw === "number"
+                    ? Math.abs(raw) < 1 && raw !== 0
+                      ? raw.toPrecision(3)
+                      : raw.toFixed(2)
+                    : raw !== null && typeof raw === "object"
+                      ? JSON.stringify(raw)
+                      : String(raw);
             return (
               <Grid item xs={12} sm={6} md={4} key={key}>
                 <Paper
@@ -30,6 +30,10 @@ type Props = {
   title: string;
   subtitle?: string;
   points: ChartPoint[];
+  /** Secondary series drawn as a dashed line, index-aligned with `points`. */
+  overlay?: ChartPoint[];
+  overlayLabel?: string;
+  overlayColor?: string;
   unit?: string;
   accent?: string;
   height?: number;
@@ -45,6 +49,9 @@ export function GlowingAreaChart({
   title,
   subtitle,
   points,
+  overlay,
+  overlayLabel,
+  overlayColor = "#ffb74d",
   unit = "",
   accent = "#10d7ff",
   height = 260,
@@ -60,6 +67,10 @@ export function GlowingAreaChart({
   const safePoints = points.filter((p) => Number.isFinite(p.value));
   const values = safePoints.map((p) => p.value);
   const hasData = values.length >= 2;
+  const overlayValues = (overlay ?? [])
+    .slice(0, values.length)
+    .map((p) => p.value)
+    .filter((v) => Number.isFinite(v));
 
   const stats = useMemo(() => {
     if (!hasData) {
@@ -80,13 +91,15 @@ export function GlowingAreaChart({
 
   const yRange = useMemo(() => {
     if (!hasData) return { min: 0, max: 1 };
-    const [min, max] = [stats.min, stats.max];
+    const [min, max] = overlayValues.length
+      ? [Math.min(stats.min, ...overlayValues), Math.max(stats.max, ...overlayValues)]
+      : [stats.min, stats.max];
     if (min === max) {
       return { min: min - 0.5, max: max + 0.5 };
     }
     const padding = (max - min) * 0.08;
     return { min: min - padding, max: max + padding };
-  }, [hasData, stats.max, stats.min]);
+  }, [hasData, overlayValues, stats.max, stats.min]);
 
   const toX = (idx: number) =>
     pad.left + (Math.max(idx, 0) / Math.max(values.length - 1, 1)) * innerW;
@@ -103,6 +116,13 @@ export function GlowingAreaChart({
       .join(" ");
   }, [hasData, toX, toY, values]);
 
+  const overlayPath = useMemo(() => {
+    if (!hasData || overlayValues.length < 2) return "";
+    return overlayValues
+      .map((v, idx) => `${idx === 0 ? "M" : "L"} ${toX(idx)} ${toY(v)}`)
+      .join(" ");
+  }, [hasData, overlayValues, toX, toY]);
+
   const areaPath = useMemo(() => {
     if (!hasData) return "";
     const startPath = `M ${pad.left} ${pad.top + innerH}`;
@@ -288,6 +308,17 @@ export function GlowingAreaChart({
             strokeLinecap="round"
             style={{ filter: "drop-shadow(0px 9px 18px rgba(16,215,255,0.25))" }}
           />
+          {overlayPath && (
+            <path
+              d={overlayPath}
+              fill="none"
+              stroke={overlayColor}
+              strokeWidth="1.6"
+              strokeDasharray="5 3"
+              strokeLinejoin="round"
+              strokeLinecap="round"
+            />
+          )}
 
           {hoveredX != null && hoveredY != null && (
             <>
@@ -352,6 +383,11 @@ export function GlowingAreaChart({
                 {tooltip.meta}
               </Typography>
             )}
+            {hoveredIdx != null && overlayValues[hoveredIdx] != null && (
+              <Typography variant="caption" display="block" sx={{ color: overlayColor }}>
+                {overlayLabel ?? "Overlay"} {displayValue(overlayValues[hoveredIdx])}
+              </Typography>
+            )}
           </Box>
         )}
       </Box>
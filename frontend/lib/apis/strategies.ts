@@ -19,6 +19,18 @@ export function backtestStrategy(
   return apiPostJson(`/api/strategies/${id}/backtest`, payload);
 }
 
+export function enqueueBacktestJob(id: string, payload: Record<string, unknown>): Promise<Response> {
+  return apiPostJson(`/api/strategies/${id}/backtest-jobs`, payload);
+}
+
+export function fetchBacktestJob(jobId: string): Promise<Response> {
+  return apiFetch(`/api/backtest-jobs/${jobId}`);
+}
+
+export function cancelBacktestJob(jobId: string): Promise<Response> {
+  return apiFetch(`/api/backtest-jobs/${jobId}/cancel`, { method: "POST" });
+}
+
 export function deleteStrategy(id: string): Promise<Response> {
   return apiFetch(`/api/strategies/${id}`, { method: "DELETE" });
 }